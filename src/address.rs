@@ -0,0 +1,140 @@
+/// Error returned when a port-expander is constructed with an I2C address that its datasheet does
+/// not allow.
+///
+/// If the address is correct nonetheless (e.g. because of a module which straps the chip in an
+/// unusual way), use the `with_address_unchecked()` constructor of the device instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InvalidAddress {
+    address: u8,
+}
+
+impl InvalidAddress {
+    /// The address which was rejected.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+}
+
+/// Error of the `with_address()` constructors of port-expanders which access the chip while
+/// they are constructed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressError<E> {
+    /// The address is not allowed by the datasheet.
+    InvalidAddress(InvalidAddress),
+    /// The bus reported an error while setting up the chip.
+    Bus(E),
+}
+
+impl<E> From<InvalidAddress> for AddressError<E> {
+    fn from(value: InvalidAddress) -> Self {
+        Self::InvalidAddress(value)
+    }
+}
+
+/// Whether the I2C address of a port-expander was validated against the datasheet.
+///
+/// The `with_address()` constructors of the devices only accept the addresses documented in the
+/// datasheet.  Some modules strap the chip to an address outside of that range, for those the
+/// `with_address_unchecked()` constructors skip the validation.  The `address_check()` method of
+/// the device then reports [`Unchecked`](AddressCheck::Unchecked).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressCheck {
+    /// The address is one of the addresses documented in the datasheet.
+    Checked,
+    /// The address was passed to `with_address_unchecked()` and may lie outside of the documented
+    /// range.
+    Unchecked,
+}
+
+pub(crate) fn validate(
+    address: u8,
+    valid: core::ops::RangeInclusive<u8>,
+) -> Result<u8, InvalidAddress> {
+    if valid.contains(&address) {
+        Ok(address)
+    } else {
+        Err(InvalidAddress { address })
+    }
+}
+
+/// Define the typed I2C address of a device module.
+///
+/// The address is `base` plus each address pin shifted to its bit.  Pins are `bool` unless a type
+/// is given, which must convert to the value of the pin with `as u8`.  Only addresses in `range`
+/// are accepted by `custom()`.
+#[allow(unused_macros)] // without any device features
+macro_rules! addr_type {
+    (@pin) => { bool };
+    (@pin $ty:ty) => { $ty };
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident: $base:expr, [$($pin:ident $(: $ty:ty)? = $bit:literal),*], $range:expr;
+    ) => {
+        $(#[$meta])*
+        ///
+        /// Construct it from the levels of the address pins with `from_pins()`, or from a raw
+        /// address with `custom()`, which only accepts addresses documented in the datasheet.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+        pub struct $name(u8);
+
+        impl $name {
+            /// All addresses documented in the datasheet.
+            pub const RANGE: core::ops::RangeInclusive<u8> = $range;
+
+            /// Address selected by the levels of the address pins.
+            pub const fn from_pins($($pin: crate::address::addr_type!(@pin $($ty)?)),*) -> Self {
+                Self($base $(+ (($pin as u8) << $bit))*)
+            }
+
+            /// Validate a raw address against [`RANGE`](Self::RANGE).
+            pub fn custom(addr: u8) -> Result<Self, crate::InvalidAddress> {
+                crate::address::validate(addr, Self::RANGE).map(Self)
+            }
+
+            /// The raw 7-bit address.
+            pub const fn get(self) -> u8 {
+                self.0
+            }
+        }
+
+        impl From<$name> for u8 {
+            fn from(addr: $name) -> u8 {
+                addr.0
+            }
+        }
+
+        impl TryFrom<u8> for $name {
+            type Error = crate::InvalidAddress;
+
+            fn try_from(addr: u8) -> Result<Self, Self::Error> {
+                Self::custom(addr)
+            }
+        }
+    };
+}
+// the `CH422` and `CH423` have fixed addresses and are the only expanders without `Addr`
+#[cfg(any(
+    feature = "aw9523b",
+    feature = "max7300",
+    feature = "max7321",
+    feature = "mcp23016",
+    feature = "mcp23x17",
+    feature = "pca9536",
+    feature = "pca9538",
+    feature = "pca9554",
+    feature = "pca9555",
+    feature = "pca9575",
+    feature = "pcal6408a",
+    feature = "pcal6416a",
+    feature = "pcf8574",
+    feature = "pcf8575",
+    feature = "pi4ioe5v6408",
+    feature = "tca6408a",
+    feature = "tca6424a",
+    feature = "xra1201",
+))]
+pub(crate) use addr_type;
@@ -0,0 +1,369 @@
+use embedded_hal::digital::{self as hal_digital};
+
+/// A pin of any port-expander, independent of the chip, bus, mutex, and pin mode.
+///
+/// [`Pin`](crate::Pin) carries the chip, the bus, the mutex, and the pin mode in its type.  This
+/// is what makes it zero-cost, but it also means that code which should work with pins of
+/// arbitrary port-expanders has to be generic over all of them.  `AnyPin` erases all of this
+/// behind trait objects so libraries can accept "some expander pin" as a plain type:
+///
+/// ```
+/// use port_expander::{AnyPin, AnyPinError, Direction};
+///
+/// fn pulse(pin: &mut AnyPin<'_>) -> Result<(), AnyPinError> {
+///     pin.set_direction(Direction::Output, false)?;
+///     pin.set_high()?;
+///     pin.set_low()
+/// }
+/// # let expectations = [
+/// #     embedded_hal_mock::eh1::i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+/// #     embedded_hal_mock::eh1::i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+/// #     embedded_hal_mock::eh1::i2c::Transaction::write(0x20, vec![0x06, 0xfe]),
+/// #     embedded_hal_mock::eh1::i2c::Transaction::write(0x20, vec![0x02, 0xff]),
+/// #     embedded_hal_mock::eh1::i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+/// # ];
+/// # let mut i2c = embedded_hal_mock::eh1::i2c::Mock::new(&expectations);
+///
+/// let mut pca9555 = port_expander::Pca9555::new(i2c.clone(), false, false, false);
+/// let pins = pca9555.split();
+///
+/// let mut io0_0 = pins.io0_0.into_any();
+/// pulse(&mut io0_0).unwrap();
+/// # i2c.done();
+/// ```
+///
+/// An `AnyPin` is created from a pin using `into_any()`.  It is always possible to read the input
+/// state and to set the output state of an `AnyPin`, as there is no type-level mode.  On chips
+/// with totem-pole outputs, the output state of an input pin is latched and only driven once the
+/// pin is made an output with [`set_direction()`](AnyPin::set_direction).  On chips with
+/// quasi-bidirectional pins (e.g. the `PCF8574`), `set_direction()` is emulated by setting the
+/// pin HIGH for [`Direction::Input`](crate::Direction::Input).
+///
+/// Errors from the underlying driver are reported as [`AnyPinError::Driver`], the error itself is
+/// not preserved.
+#[must_use = "a dropped pin cannot be accessed again"]
+pub struct AnyPin<'a> {
+    pin_mask: u32,
+    port: &'a dyn ErasedPort,
+    directions: Directions<'a>,
+    /// Last direction the pin was configured for, `None` for quasi-bidirectional pins which were
+    /// never configured through this `AnyPin`.
+    direction: Option<crate::Direction>,
+}
+
+enum Directions<'a> {
+    TotemPole(&'a dyn ErasedTotemPole),
+    QuasiBidirectional,
+}
+
+/// Error type for [`AnyPin`] which implements [`embedded_hal::digital::Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum AnyPinError {
+    /// The port driver reported an error.
+    Driver,
+}
+
+impl hal_digital::Error for AnyPinError {
+    fn kind(&self) -> hal_digital::ErrorKind {
+        hal_digital::ErrorKind::Other
+    }
+}
+
+trait ErasedPort {
+    fn set(&self, mask_high: u32, mask_low: u32) -> Result<(), AnyPinError>;
+    fn is_set(&self, mask_high: u32, mask_low: u32) -> Result<u32, AnyPinError>;
+    fn get(&self, mask_high: u32, mask_low: u32) -> Result<u32, AnyPinError>;
+    fn toggle(&self, mask: u32) -> Result<(), AnyPinError>;
+}
+
+impl<MUTEX, PD> ErasedPort for MUTEX
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn set(&self, mask_high: u32, mask_low: u32) -> Result<(), AnyPinError> {
+        self.lock(|drv| drv.set(mask_high, mask_low))
+            .map_err(|_| AnyPinError::Driver)
+    }
+
+    fn is_set(&self, mask_high: u32, mask_low: u32) -> Result<u32, AnyPinError> {
+        self.lock(|drv| drv.is_set(mask_high, mask_low))
+            .map_err(|_| AnyPinError::Driver)
+    }
+
+    fn get(&self, mask_high: u32, mask_low: u32) -> Result<u32, AnyPinError> {
+        self.lock(|drv| drv.get(mask_high, mask_low))
+            .map_err(|_| AnyPinError::Driver)
+    }
+
+    fn toggle(&self, mask: u32) -> Result<(), AnyPinError> {
+        self.lock(|drv| drv.toggle(mask))
+            .map_err(|_| AnyPinError::Driver)
+    }
+}
+
+trait ErasedTotemPole {
+    fn set_direction(
+        &self,
+        mask: u32,
+        dir: crate::Direction,
+        state: bool,
+    ) -> Result<(), AnyPinError>;
+}
+
+impl<MUTEX, PD> ErasedTotemPole for MUTEX
+where
+    PD: crate::PortDriver + crate::PortDriverTotemPole,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn set_direction(
+        &self,
+        mask: u32,
+        dir: crate::Direction,
+        state: bool,
+    ) -> Result<(), AnyPinError> {
+        self.lock(|drv| drv.set_direction(mask, dir, state))
+            .map_err(|_| AnyPinError::Driver)
+    }
+}
+
+impl<'a> AnyPin<'a> {
+    pub fn pin_mask(&self) -> u32 {
+        self.pin_mask
+    }
+
+    /// Number of this pin on its port-expander, see [`Pin::pin_index()`](crate::Pin::pin_index).
+    pub fn pin_index(&self) -> u8 {
+        self.pin_mask.trailing_zeros() as u8
+    }
+
+    /// Whether `self` and `other` are pins of the same port-expander.
+    pub(crate) fn same_chip(&self, other: &AnyPin<'_>) -> bool {
+        core::ptr::eq(
+            self.port as *const dyn ErasedPort as *const (),
+            other.port as *const dyn ErasedPort as *const (),
+        )
+    }
+
+    /// Set pins of this pin's port-expander in a single operation.
+    pub(crate) fn set_port(&self, mask_high: u32, mask_low: u32) -> Result<(), AnyPinError> {
+        self.port.set(mask_high, mask_low)
+    }
+
+    /// Output states of pins of this pin's port-expander in a single operation.
+    pub(crate) fn is_set_port(&self, mask_high: u32, mask_low: u32) -> Result<u32, AnyPinError> {
+        self.port.is_set(mask_high, mask_low)
+    }
+
+    /// Configure the direction of pins of this pin's port-expander in a single operation.
+    ///
+    /// Only meaningful for totem-pole pins.  The directions of the `AnyPin`s of the other pins in
+    /// `mask` are not updated, use [`set_direction()`](AnyPin::set_direction) for that.
+    pub(crate) fn set_port_direction(
+        &self,
+        mask: u32,
+        dir: crate::Direction,
+        state: bool,
+    ) -> Result<(), AnyPinError> {
+        match self.directions {
+            Directions::TotemPole(port) => port.set_direction(mask, dir, state),
+            Directions::QuasiBidirectional => Ok(()),
+        }
+    }
+
+    /// The direction this pin was last configured for.
+    ///
+    /// Quasi-bidirectional pins report `None` until [`set_direction()`](AnyPin::set_direction)
+    /// was called, as they do not have a direction of their own.
+    pub fn direction(&self) -> Option<crate::Direction> {
+        self.direction
+    }
+
+    /// Configure the direction of this pin.
+    ///
+    /// When making the pin an output, `state` is the initial output state.  It is, as long as the
+    /// port-expander chip allows this, entered without any electrical glitch.  For inputs, `state`
+    /// is ignored.
+    pub fn set_direction(&mut self, dir: crate::Direction, state: bool) -> Result<(), AnyPinError> {
+        match self.directions {
+            Directions::TotemPole(port) => port.set_direction(self.pin_mask, dir, state)?,
+            Directions::QuasiBidirectional => match (dir, state) {
+                (crate::Direction::Output, false) => self.port.set(0, self.pin_mask)?,
+                _ => self.port.set(self.pin_mask, 0)?,
+            },
+        }
+        self.direction = Some(dir);
+        Ok(())
+    }
+
+    /// Read the pin's input state and return `true` if it is HIGH.
+    pub fn is_high(&self) -> Result<bool, AnyPinError> {
+        Ok(self.port.get(self.pin_mask, 0)? == self.pin_mask)
+    }
+
+    /// Read the pin's input state and return `true` if it is LOW.
+    pub fn is_low(&self) -> Result<bool, AnyPinError> {
+        Ok(self.port.get(0, self.pin_mask)? == self.pin_mask)
+    }
+
+    /// Set the pin's output state to HIGH.
+    pub fn set_high(&mut self) -> Result<(), AnyPinError> {
+        self.port.set(self.pin_mask, 0)
+    }
+
+    /// Set the pin's output state to LOW.
+    pub fn set_low(&mut self) -> Result<(), AnyPinError> {
+        self.port.set(0, self.pin_mask)
+    }
+
+    /// Return `true` if the pin's output state is HIGH.
+    ///
+    /// This method does **not** read the pin's electrical state.
+    pub fn is_set_high(&self) -> Result<bool, AnyPinError> {
+        Ok(self.port.is_set(self.pin_mask, 0)? == self.pin_mask)
+    }
+
+    /// Return `true` if the pin's output state is LOW.
+    ///
+    /// This method does **not** read the pin's electrical state.
+    pub fn is_set_low(&self) -> Result<bool, AnyPinError> {
+        Ok(self.port.is_set(0, self.pin_mask)? == self.pin_mask)
+    }
+
+    /// Toggle the pin's output state.
+    pub fn toggle(&mut self) -> Result<(), AnyPinError> {
+        self.port.toggle(self.pin_mask)
+    }
+}
+
+impl<'a, MUTEX, PD> crate::Pin<'a, crate::mode::Input, MUTEX>
+where
+    PD: crate::PortDriver + crate::PortDriverTotemPole,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Erase the type of this pin.  See [`AnyPin`].
+    pub fn into_any(self) -> AnyPin<'a> {
+        let port = self.port_driver();
+        AnyPin {
+            pin_mask: self.pin_mask(),
+            port,
+            directions: Directions::TotemPole(port),
+            direction: Some(crate::Direction::Input),
+        }
+    }
+}
+
+impl<'a, MUTEX, PD> crate::Pin<'a, crate::mode::Output, MUTEX>
+where
+    PD: crate::PortDriver + crate::PortDriverTotemPole,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Erase the type of this pin.  See [`AnyPin`].
+    pub fn into_any(self) -> AnyPin<'a> {
+        let port = self.port_driver();
+        AnyPin {
+            pin_mask: self.pin_mask(),
+            port,
+            directions: Directions::TotemPole(port),
+            direction: Some(crate::Direction::Output),
+        }
+    }
+}
+
+impl<'a, MUTEX, PD> crate::Pin<'a, crate::mode::QuasiBidirectional, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Erase the type of this pin.  See [`AnyPin`].
+    pub fn into_any(self) -> AnyPin<'a> {
+        AnyPin {
+            pin_mask: self.pin_mask(),
+            port: self.port_driver(),
+            directions: Directions::QuasiBidirectional,
+            direction: None,
+        }
+    }
+}
+
+impl hal_digital::ErrorType for AnyPin<'_> {
+    type Error = AnyPinError;
+}
+
+impl hal_digital::InputPin for AnyPin<'_> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        AnyPin::is_high(self)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        AnyPin::is_low(self)
+    }
+}
+
+impl hal_digital::OutputPin for AnyPin<'_> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        AnyPin::set_low(self)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        AnyPin::set_high(self)
+    }
+}
+
+impl hal_digital::StatefulOutputPin for AnyPin<'_> {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        AnyPin::is_set_high(self)
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        AnyPin::is_set_low(self)
+    }
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        AnyPin::toggle(self)
+    }
+}
+
+#[cfg(all(test, feature = "pca9536", feature = "pcf8574"))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    fn blink(pin: &mut super::AnyPin<'_>) -> Result<bool, super::AnyPinError> {
+        pin.set_direction(crate::Direction::Output, true)?;
+        pin.toggle()?;
+        pin.is_set_low()
+    }
+
+    #[test]
+    fn any_pin_mixed_chips() {
+        let expectations = [
+            // pca9536 io1: output high
+            mock_i2c::Transaction::write_read(0x41, vec![0x03], vec![0xff]),
+            mock_i2c::Transaction::write(0x41, vec![0x03, 0xfd]),
+            // pca9536 io1: toggle
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xfd]),
+            // pcf8574 p2: output high
+            mock_i2c::Transaction::write(0x21, vec![0xff]),
+            // pcf8574 p2: toggle
+            mock_i2c::Transaction::write(0x21, vec![0xfb]),
+            // pcf8574 p2: input read
+            mock_i2c::Transaction::read(0x21, vec![0x04]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = crate::Pca9536::new(bus.clone());
+        let pca_pins = pca.split();
+        let mut pcf = crate::Pcf8574::new(bus.clone(), true, false, false);
+        let pcf_pins = pcf.split();
+
+        let mut pins = [pca_pins.io1.into_any(), pcf_pins.p2.into_any()];
+        for pin in pins.iter_mut() {
+            assert!(blink(pin).unwrap());
+        }
+        assert!(pins[1].is_high().unwrap());
+
+        bus.done();
+    }
+}
@@ -0,0 +1,176 @@
+use core::marker::PhantomData;
+use embedded_hal::digital::{self as hal_digital};
+use std::sync::Arc;
+
+/// A port-expander pin which owns a reference count of its device instead of borrowing it.
+///
+/// [`Pin`](crate::Pin)s borrow their port-expander, so they cannot be moved into threads or tasks
+/// which outlive the scope of the device.  An `ArcPin` holds an [`Arc`] of the device's mutex
+/// instead.  All pins of a device are created at once by consuming it, e.g. with
+/// [`Pca9555::into_arc_pins()`](crate::Pca9555::into_arc_pins).  With a mutex like
+/// `std::sync::Mutex`, the pins are `Send` and can be handed to different threads.
+///
+/// `ArcPin` implements the `embedded-hal` digital traits.  For everything else,
+/// [`as_pin()`](ArcPin::as_pin) gives access to a [`Pin`](crate::Pin) for the duration of a
+/// borrow.
+///
+/// Only available with the `std` feature.
+///
+/// ## Example
+/// ```
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[
+/// #     embedded_hal_mock::eh1::i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+/// #     embedded_hal_mock::eh1::i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+/// #     embedded_hal_mock::eh1::i2c::Transaction::write(0x20, vec![0x06, 0xfe]),
+/// #     embedded_hal_mock::eh1::i2c::Transaction::write(0x20, vec![0x02, 0xff]),
+/// # ]);
+/// # let mut i2c_ = i2c.clone();
+/// let pca = port_expander::Pca9555::new_std(i2c, false, false, false);
+/// let [led, ..] = pca.into_arc_pins();
+/// let mut led = led.into_output().unwrap();
+///
+/// std::thread::spawn(move || {
+///     led.as_pin().set_high().unwrap();
+/// })
+/// .join()
+/// .unwrap();
+/// # i2c_.done();
+/// ```
+#[must_use = "a dropped pin cannot be accessed again"]
+pub struct ArcPin<MODE, MUTEX> {
+    pin_mask: u32,
+    port_driver: Arc<MUTEX>,
+    _m: PhantomData<MODE>,
+}
+
+impl<MODE, MUTEX, PD> ArcPin<MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    cfg_drivers! {
+        pub(crate) fn new(pin_number: u8, port_driver: Arc<MUTEX>) -> Self {
+            assert!(pin_number < 32);
+            Self {
+                pin_mask: 1 << pin_number,
+                port_driver,
+                _m: PhantomData,
+            }
+        }
+    }
+
+    pub fn pin_mask(&self) -> u32 {
+        self.pin_mask
+    }
+
+    /// Number of this pin on its port-expander, see [`Pin::pin_index()`](crate::Pin::pin_index).
+    pub fn pin_index(&self) -> u8 {
+        self.pin_mask.trailing_zeros() as u8
+    }
+
+    /// Access this pin as a [`Pin`](crate::Pin).
+    pub fn as_pin(&mut self) -> crate::Pin<'_, MODE, MUTEX> {
+        crate::Pin::new(self.pin_mask.trailing_zeros() as u8, &self.port_driver)
+    }
+
+    fn into_mode<NEWMODE>(self) -> ArcPin<NEWMODE, MUTEX> {
+        ArcPin {
+            pin_mask: self.pin_mask,
+            port_driver: self.port_driver,
+            _m: PhantomData,
+        }
+    }
+}
+
+impl<MODE: crate::mode::Configurable, MUTEX, PD> ArcPin<MODE, MUTEX>
+where
+    PD: crate::PortDriver + crate::PortDriverTotemPole,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Configure this pin as an input, see [`Pin::into_input()`](crate::Pin::into_input).
+    pub fn into_input(
+        self,
+    ) -> Result<ArcPin<crate::mode::Input, MUTEX>, crate::pin::PinError<PD::Error>> {
+        self.port_driver
+            .lock(|drv| drv.set_direction(self.pin_mask, crate::Direction::Input, false))?;
+        Ok(self.into_mode())
+    }
+
+    /// Configure this pin as an output with an initial LOW state, see
+    /// [`Pin::into_output()`](crate::Pin::into_output).
+    pub fn into_output(
+        self,
+    ) -> Result<ArcPin<crate::mode::Output, MUTEX>, crate::pin::PinError<PD::Error>> {
+        self.port_driver
+            .lock(|drv| drv.set_direction(self.pin_mask, crate::Direction::Output, false))?;
+        Ok(self.into_mode())
+    }
+
+    /// Configure this pin as an output with an initial HIGH state, see
+    /// [`Pin::into_output_high()`](crate::Pin::into_output_high).
+    pub fn into_output_high(
+        self,
+    ) -> Result<ArcPin<crate::mode::Output, MUTEX>, crate::pin::PinError<PD::Error>> {
+        self.port_driver
+            .lock(|drv| drv.set_direction(self.pin_mask, crate::Direction::Output, true))?;
+        Ok(self.into_mode())
+    }
+}
+
+impl<MODE, MUTEX, PD> hal_digital::ErrorType for ArcPin<MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    PD::Error: core::fmt::Debug,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    type Error = crate::pin::PinError<PD::Error>;
+}
+
+impl<MODE: crate::mode::HasInput, MUTEX, PD> hal_digital::InputPin for ArcPin<MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    PD::Error: core::fmt::Debug,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.as_pin().is_high()
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.as_pin().is_low()
+    }
+}
+
+impl<MODE: crate::mode::HasOutput, MUTEX, PD> hal_digital::OutputPin for ArcPin<MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    PD::Error: core::fmt::Debug,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.as_pin().set_low()
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.as_pin().set_high()
+    }
+}
+
+impl<MODE: crate::mode::HasOutput, MUTEX, PD> hal_digital::StatefulOutputPin for ArcPin<MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    PD::Error: core::fmt::Debug,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        self.as_pin().is_set_high()
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        self.as_pin().is_set_low()
+    }
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        self.as_pin().toggle()
+    }
+}
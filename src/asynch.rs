@@ -0,0 +1,817 @@
+// The `RefMut` of the driver is the lock of an `AsyncPort`, so it is held across the awaited bus
+// transfers on purpose.  Other tasks wait for it in `lock()` instead of panicking.
+#![allow(clippy::await_holding_refcell_ref)]
+
+use core::cell::{Cell, RefCell, RefMut};
+use core::marker::PhantomData;
+use core::task::{Poll, Waker};
+
+/// Async counterpart of the blocking `PortDriver`, implemented by drivers on top of an
+/// `embedded_hal_async::i2c::I2c` bus.
+///
+/// The methods have the same meaning as those of the blocking driver trait, but each bus transfer
+/// is awaited instead of blocking the executor.
+#[allow(async_fn_in_trait)]
+pub trait AsyncPortDriver {
+    type Error;
+
+    /// Mask of all pins of the chip.
+    const PINS: u32;
+
+    /// Set all pins in `mask_high` to HIGH and all pins in `mask_low` to LOW.
+    async fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error>;
+
+    /// Check whether pins in `mask_high` were set HIGH and pins in `mask_low` were set LOW.
+    async fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error>;
+
+    /// Check whether pins in `mask_high` are driven HIGH and pins in `mask_low` are driven LOW.
+    async fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error>;
+
+    /// Invert the output state of all pins in `mask`.
+    async fn toggle(&mut self, mask: u32) -> Result<(), Self::Error> {
+        // all pins which are currently low become high, the others become low
+        let mask_high = self.is_set(0, mask).await?;
+        self.set(mask_high, mask & !mask_high).await
+    }
+}
+
+/// Async counterpart of the blocking `PortDriverTotemPole`.
+#[allow(async_fn_in_trait)]
+pub trait AsyncPortDriverTotemPole: AsyncPortDriver {
+    /// Set the direction for all pins in `mask` to direction `dir`, with the initial output
+    /// `state` for outputs.
+    async fn set_direction(
+        &mut self,
+        mask: u32,
+        dir: crate::Direction,
+        state: bool,
+    ) -> Result<(), Self::Error> {
+        // set state before switching direction to prevent glitch
+        if dir == crate::Direction::Output {
+            if state {
+                self.set(mask, 0).await?;
+            } else {
+                self.set(0, mask).await?;
+            }
+        }
+
+        match dir {
+            crate::Direction::Input => self.set_directions(mask, 0).await,
+            crate::Direction::Output => self.set_directions(0, mask).await,
+        }
+    }
+
+    /// Make all pins in `mask_input` inputs and all pins in `mask_output` outputs.
+    async fn set_directions(
+        &mut self,
+        mask_input: u32,
+        mask_output: u32,
+    ) -> Result<(), Self::Error>;
+}
+
+/// A port-expander on an async I2C bus.
+///
+/// The blocking devices like [`Pca9555`](crate::Pca9555) perform their bus transfers
+/// synchronously, even when they are used from async code.  An `AsyncPort` wraps a driver for an
+/// `embedded_hal_async::i2c::I2c` bus instead, and its [`AsyncPin`]s await every transfer, so the
+/// executor can run other tasks in the meantime.
+///
+/// The driver is locked for the duration of each pin operation.  A pin which finds it locked by
+/// another task yields and tries again on its next poll.  The port is not `Sync`, it is meant for
+/// single-threaded executors where all tasks using its pins run on the same thread.
+///
+/// Waiting for input changes needs someone to read the chip when its inputs change: call
+/// [`handle_interrupt()`](AsyncPort::handle_interrupt) whenever the interrupt line of the chip is
+/// asserted.  It compares the inputs to the previous read and wakes the pins waiting for one of
+/// the edges.  Edges are latched for each pin until a wait consumes them, so an edge which
+/// happens while the waiting task is busy elsewhere is reported by its next wait.  When the
+/// interrupt line is handled by an ISR instead of an async input, let the ISR raise an
+/// [`InterruptSignal`] and pass it to [`handle_interrupts()`](AsyncPort::handle_interrupts).
+/// Without a connected interrupt line, [`poll_every()`](AsyncPort::poll_every) reads the chip
+/// periodically instead.
+///
+/// Currently, the drivers of the `PCA9555` family, the `PCAL6416A`, the `MCP23017` and the
+/// `PCF8575` support async buses.  Only available with the `async` feature.
+///
+/// ## Example
+/// ```no_run
+/// # async fn example() {
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// use port_expander::dev::pca9555;
+///
+/// // `i2c` implements `embedded_hal_async::i2c::I2c`
+/// let port = port_expander::AsyncPort::new(pca9555::Driver::new(i2c, false, false, false));
+/// let mut led = port.take_pin(0).unwrap().into_output().await.unwrap();
+/// let button = port.take_pin(8).unwrap();
+///
+/// if button.is_low().await.unwrap() {
+///     led.set_high().await.unwrap();
+/// }
+/// # }
+/// ```
+///
+/// With the interrupt line of the chip on `int`, an input of the microcontroller which implements
+/// `embedded_hal_async::digital::Wait`, one task services the interrupt while others wait for
+/// their pins:
+/// ```no_run
+/// # async fn example() {
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut int = embedded_hal_mock::eh1::digital::Mock::new(&[]);
+/// use embedded_hal_async::digital::Wait;
+/// use port_expander::dev::pca9555;
+///
+/// let port = port_expander::AsyncPort::new(pca9555::Driver::new(i2c, false, false, false));
+/// let mut button = port.take_pin(8).unwrap();
+///
+/// let interrupt = async {
+///     loop {
+///         int.wait_for_low().await.unwrap();
+///         port.handle_interrupt().await.unwrap();
+///     }
+/// };
+/// let presses = async {
+///     loop {
+///         button.wait_for_falling_edge().await.unwrap();
+///         // ...
+///     }
+/// };
+/// # let _ = (interrupt, presses);
+/// // run both futures concurrently, e.g. with `embassy_futures::join::join()`
+/// # }
+/// ```
+pub struct AsyncPort<PD> {
+    driver: RefCell<PD>,
+    claimed: Cell<u32>,
+    /// Input state of the last read, `None` before the first one.
+    inputs: Cell<Option<u32>>,
+    /// Edges which were found by `handle_interrupt()` and not yet consumed by a wait.
+    rising: Cell<u32>,
+    falling: Cell<u32>,
+    /// Waker of the task waiting for an edge of each pin.
+    wakers: [Cell<Option<Waker>>; 32],
+}
+
+impl<PD: AsyncPortDriver> AsyncPort<PD> {
+    pub fn new(driver: PD) -> Self {
+        Self {
+            driver: RefCell::new(driver),
+            claimed: Cell::new(0),
+            inputs: Cell::new(None),
+            rising: Cell::new(0),
+            falling: Cell::new(0),
+            wakers: core::array::from_fn(|_| Cell::new(None)),
+        }
+    }
+
+    /// Read all inputs of the chip and wake the pins waiting for an edge which happened.
+    ///
+    /// Call this whenever the interrupt line of the chip is asserted.  On most chips, reading the
+    /// inputs also clears the interrupt.  Returns the mask of the pins which changed since the
+    /// previous read.  The first read only records the input state.
+    pub async fn handle_interrupt(&self) -> Result<u32, PD::Error> {
+        let state = self.lock().await.get(PD::PINS, 0).await?;
+        let changed = match self.inputs.replace(Some(state)) {
+            Some(last) => last ^ state,
+            None => 0,
+        };
+        self.rising.set(self.rising.get() | (changed & state));
+        self.falling.set(self.falling.get() | (changed & !state));
+        for (i, waker) in self.wakers.iter().enumerate() {
+            if changed & (1 << i) != 0 {
+                if let Some(waker) = waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Call [`handle_interrupt()`](AsyncPort::handle_interrupt) for every notification of
+    /// `signal`.
+    ///
+    /// This is the task side of an [`InterruptSignal`] which is raised by an interrupt service
+    /// routine.  It only returns if reading the chip fails.
+    #[cfg(feature = "critical-section")]
+    pub async fn handle_interrupts(&self, signal: &InterruptSignal) -> Result<(), PD::Error> {
+        loop {
+            signal.wait().await;
+            self.handle_interrupt().await?;
+        }
+    }
+
+    /// Call [`handle_interrupt()`](AsyncPort::handle_interrupt) every `interval_ms`
+    /// milliseconds.
+    ///
+    /// Polling fallback for boards where the interrupt line of the chip is not connected, the
+    /// pins' waits then resolve after the next poll.  Edges which are undone between two polls
+    /// can't be seen.  It only returns if reading the chip fails.
+    pub async fn poll_every<D: embedded_hal_async::delay::DelayNs>(
+        &self,
+        mut delay: D,
+        interval_ms: u32,
+    ) -> Result<(), PD::Error> {
+        loop {
+            self.handle_interrupt().await?;
+            delay.delay_ms(interval_ms).await;
+        }
+    }
+
+    /// Claim pin number `n` of the chip.
+    ///
+    /// Pins are numbered like the bits of the port masks, e.g. `8` is `IO1_0` of a `PCA9555`.
+    /// Each pin can only be taken once: `None` is returned if `n` is not a pin of the chip or
+    /// the pin was taken before, even if that pin was dropped in the meantime.
+    pub fn take_pin(&self, n: u8) -> Option<AsyncPin<'_, crate::mode::Input, PD>> {
+        let mask = 1u32.checked_shl(n.into())? & PD::PINS;
+        if mask == 0 || self.claimed.get() & mask != 0 {
+            return None;
+        }
+        self.claimed.set(self.claimed.get() | mask);
+        self.clear_edges(mask);
+        Some(AsyncPin {
+            pin_mask: mask,
+            port: self,
+            _m: PhantomData,
+        })
+    }
+
+    /// Release the driver.  Consuming the port requires that all of its pins were dropped.
+    pub fn into_inner(self) -> PD {
+        self.driver.into_inner()
+    }
+
+    /// Make sure edges can be found, by reading the initial input state if there was no read yet.
+    async fn init_inputs(&self) -> Result<(), PD::Error> {
+        if self.inputs.get().is_none() {
+            let state = self.lock().await.get(PD::PINS, 0).await?;
+            // a concurrent `handle_interrupt()` may have been first
+            if self.inputs.get().is_none() {
+                self.inputs.set(Some(state));
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget the latched edges of the pins in `mask`.
+    fn clear_edges(&self, mask: u32) {
+        self.rising.set(self.rising.get() & !mask);
+        self.falling.set(self.falling.get() & !mask);
+    }
+
+    /// Wait until `handle_interrupt()` latches a rising or falling edge of the pin `mask` and
+    /// consume it.
+    async fn edge(&self, mask: u32, rising: bool, falling: bool) {
+        core::future::poll_fn(|cx| {
+            let rising = if rising { mask } else { 0 };
+            let falling = if falling { mask } else { 0 };
+            if (self.rising.get() & rising) | (self.falling.get() & falling) != 0 {
+                self.rising.set(self.rising.get() & !rising);
+                self.falling.set(self.falling.get() & !falling);
+                Poll::Ready(())
+            } else {
+                self.wakers[mask.trailing_zeros() as usize].set(Some(cx.waker().clone()));
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    async fn lock(&self) -> RefMut<'_, PD> {
+        core::future::poll_fn(|cx| match self.driver.try_borrow_mut() {
+            Ok(driver) => Poll::Ready(driver),
+            Err(_) => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+/// Interrupt notification which can be raised from an interrupt service routine.
+///
+/// An [`AsyncPort`] must not be touched by an ISR: it is only guarded against other tasks of the
+/// same executor, and reading the chip needs awaited bus transfers anyway.  Instead, put an
+/// `InterruptSignal` into a `static`, [`signal()`](InterruptSignal::signal) it from the ISR of
+/// the interrupt line and pass it to [`AsyncPort::handle_interrupts()`] in a task, which then
+/// reads the chip.  Signals which are raised before the task gets to run are merged into one
+/// read.
+///
+/// Only available with the `async` and `critical-section` features.
+///
+/// ## Example
+/// ```no_run
+/// # async fn example() {
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// use port_expander::dev::pca9555;
+///
+/// static EXPANDER_INT: port_expander::InterruptSignal = port_expander::InterruptSignal::new();
+///
+/// // in the interrupt handler of the pin connected to the INT line of the chip
+/// fn on_interrupt() {
+///     EXPANDER_INT.signal();
+/// }
+///
+/// let port = port_expander::AsyncPort::new(pca9555::Driver::new(i2c, false, false, false));
+/// let mut button = port.take_pin(8).unwrap();
+///
+/// let interrupts = port.handle_interrupts(&EXPANDER_INT);
+/// let presses = async {
+///     loop {
+///         button.wait_for_falling_edge().await.unwrap();
+///         // ...
+///     }
+/// };
+/// # let _ = (interrupts, presses);
+/// // run both futures concurrently, e.g. with `embassy_futures::join::join()`
+/// # }
+/// ```
+#[cfg(feature = "critical-section")]
+pub struct InterruptSignal {
+    pending: critical_section::Mutex<Cell<bool>>,
+    waker: critical_section::Mutex<Cell<Option<Waker>>>,
+}
+
+#[cfg(feature = "critical-section")]
+impl InterruptSignal {
+    pub const fn new() -> Self {
+        Self {
+            pending: critical_section::Mutex::new(Cell::new(false)),
+            waker: critical_section::Mutex::new(Cell::new(None)),
+        }
+    }
+
+    /// Notify the waiting task that the interrupt line was asserted.  Safe to call from an ISR.
+    pub fn signal(&self) {
+        let waker = critical_section::with(|cs| {
+            self.pending.borrow(cs).set(true);
+            self.waker.borrow(cs).take()
+        });
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Wait for the next [`signal()`](InterruptSignal::signal), or return at once if one is
+    /// pending.
+    pub async fn wait(&self) {
+        core::future::poll_fn(|cx| {
+            critical_section::with(|cs| {
+                if self.pending.borrow(cs).replace(false) {
+                    Poll::Ready(())
+                } else {
+                    self.waker.borrow(cs).set(Some(cx.waker().clone()));
+                    Poll::Pending
+                }
+            })
+        })
+        .await
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl Default for InterruptSignal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A pin of an [`AsyncPort`].
+///
+/// This is the async counterpart of [`Pin`](crate::Pin), with the same type-state for the pin
+/// mode.  Only available with the `async` feature.
+#[must_use = "a dropped pin cannot be taken again"]
+pub struct AsyncPin<'a, MODE, PD> {
+    pin_mask: u32,
+    port: &'a AsyncPort<PD>,
+    _m: PhantomData<MODE>,
+}
+
+impl<'a, MODE, PD: AsyncPortDriver> AsyncPin<'a, MODE, PD> {
+    pub fn pin_mask(&self) -> u32 {
+        self.pin_mask
+    }
+
+    /// Forget the edges which were latched for this pin so far.
+    #[cfg(feature = "async-button")]
+    pub(crate) fn clear_edges(&self) {
+        self.port.clear_edges(self.pin_mask);
+    }
+
+    /// Number of this pin on its port-expander, see [`Pin::pin_index()`](crate::Pin::pin_index).
+    pub fn pin_index(&self) -> u8 {
+        self.pin_mask.trailing_zeros() as u8
+    }
+
+    fn into_mode<NEWMODE>(self) -> AsyncPin<'a, NEWMODE, PD> {
+        AsyncPin {
+            pin_mask: self.pin_mask,
+            port: self.port,
+            _m: PhantomData,
+        }
+    }
+}
+
+impl<'a, MODE, PD> AsyncPin<'a, MODE, PD>
+where
+    MODE: crate::mode::Configurable,
+    PD: AsyncPortDriverTotemPole,
+{
+    /// Configure this pin as an input, see [`Pin::into_input()`](crate::Pin::into_input).
+    pub async fn into_input(
+        self,
+    ) -> Result<AsyncPin<'a, crate::mode::Input, PD>, crate::pin::PinError<PD::Error>> {
+        let mut drv = self.port.lock().await;
+        drv.set_direction(self.pin_mask, crate::Direction::Input, false)
+            .await?;
+        drop(drv);
+        Ok(self.into_mode())
+    }
+
+    /// Configure this pin as an output with an initial LOW state, see
+    /// [`Pin::into_output()`](crate::Pin::into_output).
+    pub async fn into_output(
+        self,
+    ) -> Result<AsyncPin<'a, crate::mode::Output, PD>, crate::pin::PinError<PD::Error>> {
+        let mut drv = self.port.lock().await;
+        drv.set_direction(self.pin_mask, crate::Direction::Output, false)
+            .await?;
+        drop(drv);
+        Ok(self.into_mode())
+    }
+
+    /// Configure this pin as an output with an initial HIGH state, see
+    /// [`Pin::into_output_high()`](crate::Pin::into_output_high).
+    pub async fn into_output_high(
+        self,
+    ) -> Result<AsyncPin<'a, crate::mode::Output, PD>, crate::pin::PinError<PD::Error>> {
+        let mut drv = self.port.lock().await;
+        drv.set_direction(self.pin_mask, crate::Direction::Output, true)
+            .await?;
+        drop(drv);
+        Ok(self.into_mode())
+    }
+}
+
+impl<MODE: crate::mode::HasInput, PD: AsyncPortDriver> AsyncPin<'_, MODE, PD> {
+    /// Read the pin's input state and return `true` if it is HIGH.
+    pub async fn is_high(&self) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        let mut drv = self.port.lock().await;
+        Ok(drv.get(self.pin_mask, 0).await? == self.pin_mask)
+    }
+
+    /// Read the pin's input state and return `true` if it is LOW.
+    pub async fn is_low(&self) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        let mut drv = self.port.lock().await;
+        Ok(drv.get(0, self.pin_mask).await? == self.pin_mask)
+    }
+}
+
+/// Waits for input changes, which are found by [`AsyncPort::handle_interrupt()`].
+///
+/// Edges are latched from the moment the pin was taken, so an edge which happens while no wait is
+/// running is reported by the next wait for it.  A wait consumes the edges it reports, a wait for a
+/// level forgets all earlier edges of the pin.  Changes
+/// which are undone before the next `handle_interrupt()` can't be seen.
+impl<MODE: crate::mode::HasInput, PD: AsyncPortDriver> AsyncPin<'_, MODE, PD> {
+    /// Wait until the pin is HIGH.
+    pub async fn wait_for_high(&mut self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        self.wait_for_level(true).await
+    }
+
+    /// Wait until the pin is LOW.
+    pub async fn wait_for_low(&mut self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        self.wait_for_level(false).await
+    }
+
+    /// Wait for the pin to change from LOW to HIGH.
+    pub async fn wait_for_rising_edge(&mut self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        self.wait_for_edge(true, false).await
+    }
+
+    /// Wait for the pin to change from HIGH to LOW.
+    pub async fn wait_for_falling_edge(&mut self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        self.wait_for_edge(false, true).await
+    }
+
+    /// Wait for the pin to change in either direction.
+    pub async fn wait_for_any_edge(&mut self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        self.wait_for_edge(true, true).await
+    }
+
+    async fn wait_for_level(&mut self, high: bool) -> Result<(), crate::pin::PinError<PD::Error>> {
+        self.port.init_inputs().await?;
+        // older edges don't matter for the level, one during the read below is latched
+        self.port.clear_edges(self.pin_mask);
+        if self.is_high().await? == high {
+            return Ok(());
+        }
+        self.port.edge(self.pin_mask, high, !high).await;
+        Ok(())
+    }
+
+    async fn wait_for_edge(
+        &mut self,
+        rising: bool,
+        falling: bool,
+    ) -> Result<(), crate::pin::PinError<PD::Error>> {
+        self.port.init_inputs().await?;
+        self.port.edge(self.pin_mask, rising, falling).await;
+        Ok(())
+    }
+}
+
+impl<MODE, PD> embedded_hal::digital::ErrorType for AsyncPin<'_, MODE, PD>
+where
+    PD: AsyncPortDriver,
+    PD::Error: core::fmt::Debug,
+{
+    type Error = crate::pin::PinError<PD::Error>;
+}
+
+impl<MODE: crate::mode::HasInput, PD> embedded_hal_async::digital::Wait for AsyncPin<'_, MODE, PD>
+where
+    PD: AsyncPortDriver,
+    PD::Error: core::fmt::Debug,
+{
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        AsyncPin::wait_for_high(self).await
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        AsyncPin::wait_for_low(self).await
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        AsyncPin::wait_for_rising_edge(self).await
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        AsyncPin::wait_for_falling_edge(self).await
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        AsyncPin::wait_for_any_edge(self).await
+    }
+}
+
+impl<MODE: crate::mode::HasOutput, PD: AsyncPortDriver> AsyncPin<'_, MODE, PD> {
+    /// Set the pin's output state to HIGH.
+    pub async fn set_high(&mut self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        let mut drv = self.port.lock().await;
+        drv.set(self.pin_mask, 0).await?;
+        Ok(())
+    }
+
+    /// Set the pin's output state to LOW.
+    pub async fn set_low(&mut self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        let mut drv = self.port.lock().await;
+        drv.set(0, self.pin_mask).await?;
+        Ok(())
+    }
+
+    /// Return `true` if the pin's output state is HIGH.
+    pub async fn is_set_high(&self) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        let mut drv = self.port.lock().await;
+        Ok(drv.is_set(self.pin_mask, 0).await? == self.pin_mask)
+    }
+
+    /// Return `true` if the pin's output state is LOW.
+    pub async fn is_set_low(&self) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        let mut drv = self.port.lock().await;
+        Ok(drv.is_set(0, self.pin_mask).await? == self.pin_mask)
+    }
+
+    /// Toggle the pin's output state.
+    pub async fn toggle(&mut self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        let mut drv = self.port.lock().await;
+        drv.toggle(self.pin_mask).await?;
+        Ok(())
+    }
+}
+
+#[cfg(all(
+    test,
+    any(feature = "mcp23x17", feature = "pca9555", feature = "pcf8575")
+))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    fn block_on<F: core::future::Future>(f: F) -> F::Output {
+        let mut f = core::pin::pin!(f);
+        let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+        loop {
+            if let core::task::Poll::Ready(v) = f.as_mut().poll(&mut cx) {
+                return v;
+            }
+        }
+    }
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn pca9555_async() {
+        let expectations = [
+            // pin setup io0_0
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0xfe]),
+            // io0_0 writes
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+            // input io1_0
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0x01]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let driver = crate::dev::pca9555::Driver::new(bus.clone(), false, false, false);
+        let port = super::AsyncPort::new(driver);
+        let io1_0 = port.take_pin(8).unwrap();
+        assert!(port.take_pin(8).is_none());
+        assert!(port.take_pin(16).is_none());
+
+        block_on(async {
+            let mut io0_0 = port.take_pin(0).unwrap().into_output().await.unwrap();
+            io0_0.set_high().await.unwrap();
+            io0_0.toggle().await.unwrap();
+            assert!(io0_0.is_set_low().await.unwrap());
+            assert!(io1_0.is_high().await.unwrap());
+        });
+
+        bus.done();
+    }
+
+    #[cfg(feature = "pca9555")]
+    fn poll_once<F: core::future::Future>(
+        f: core::pin::Pin<&mut F>,
+    ) -> core::task::Poll<F::Output> {
+        let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+        f.poll(&mut cx)
+    }
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn pca9555_async_wait() {
+        let expectations = [
+            // initial state of both ports
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xff]),
+            // interrupt, io1_0 falls
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xfe]),
+            // interrupt, io1_0 rises while nobody waits
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xff]),
+            // level of io1_0
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xff]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let driver = crate::dev::pca9555::Driver::new(bus.clone(), false, false, false);
+        let port = super::AsyncPort::new(driver);
+        let mut io1_0 = port.take_pin(8).unwrap();
+
+        {
+            let mut wait = core::pin::pin!(io1_0.wait_for_falling_edge());
+            assert!(poll_once(wait.as_mut()).is_pending());
+            assert!(poll_once(wait.as_mut()).is_pending());
+            assert_eq!(block_on(port.handle_interrupt()).unwrap(), 0x0100);
+            assert!(poll_once(wait.as_mut()).is_ready());
+        }
+
+        // the rising edge is latched until the next wait
+        assert_eq!(block_on(port.handle_interrupt()).unwrap(), 0x0100);
+        block_on(io1_0.wait_for_rising_edge()).unwrap();
+        block_on(io1_0.wait_for_high()).unwrap();
+
+        bus.done();
+    }
+
+    #[cfg(all(feature = "critical-section", feature = "pca9555"))]
+    #[test]
+    fn pca9555_interrupt_signal() {
+        static SIGNAL: super::InterruptSignal = super::InterruptSignal::new();
+
+        let expectations = [
+            // initial state of both ports
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xff]),
+            // interrupt, io0_3 falls
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xf7]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xff]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let driver = crate::dev::pca9555::Driver::new(bus.clone(), false, false, false);
+        let port = super::AsyncPort::new(driver);
+        let mut io0_3 = port.take_pin(3).unwrap();
+
+        let mut interrupts = core::pin::pin!(port.handle_interrupts(&SIGNAL));
+        let mut wait = core::pin::pin!(io0_3.wait_for_falling_edge());
+        assert!(poll_once(wait.as_mut()).is_pending());
+        assert!(poll_once(interrupts.as_mut()).is_pending());
+
+        // two signals before the task runs cause a single read
+        SIGNAL.signal();
+        SIGNAL.signal();
+        assert!(poll_once(interrupts.as_mut()).is_pending());
+        assert!(poll_once(wait.as_mut()).is_ready());
+
+        bus.done();
+    }
+
+    /// Delay which returns on its second poll.
+    #[cfg(feature = "pca9555")]
+    struct YieldDelay;
+
+    #[cfg(feature = "pca9555")]
+    impl embedded_hal_async::delay::DelayNs for YieldDelay {
+        async fn delay_ns(&mut self, _ns: u32) {
+            let mut yielded = false;
+            core::future::poll_fn(|_| {
+                if yielded {
+                    core::task::Poll::Ready(())
+                } else {
+                    yielded = true;
+                    core::task::Poll::Pending
+                }
+            })
+            .await
+        }
+    }
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn pca9555_poll_every() {
+        let expectations = [
+            // first poll records the state
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xff]),
+            // second poll, io1_7 falls
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0x7f]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let driver = crate::dev::pca9555::Driver::new(bus.clone(), false, false, false);
+        let port = super::AsyncPort::new(driver);
+        let mut io1_7 = port.take_pin(15).unwrap();
+
+        let mut poller = core::pin::pin!(port.poll_every(YieldDelay, 10));
+        assert!(poll_once(poller.as_mut()).is_pending());
+        let mut wait = core::pin::pin!(io1_7.wait_for_falling_edge());
+        assert!(poll_once(wait.as_mut()).is_pending());
+        assert!(poll_once(poller.as_mut()).is_pending());
+        assert!(poll_once(wait.as_mut()).is_ready());
+
+        bus.done();
+    }
+
+    #[cfg(feature = "mcp23x17")]
+    #[test]
+    fn mcp23017_async() {
+        let expectations = [
+            // into_output() of gpb0
+            mock_i2c::Transaction::write(0x20, vec![0x15, 0x00]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x01, 0xfe]),
+            mock_i2c::Transaction::write(0x20, vec![0x15, 0x01]),
+            // gpa0 is read
+            mock_i2c::Transaction::write_read(0x20, vec![0x12], vec![0x01]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let driver = crate::dev::mcp23x17::Driver::new(bus.clone(), false, false, false);
+        let port = super::AsyncPort::new(driver);
+        let gpa0 = port.take_pin(0).unwrap();
+
+        block_on(async {
+            let mut gpb0 = port.take_pin(8).unwrap().into_output().await.unwrap();
+            gpb0.set_high().await.unwrap();
+            assert!(gpa0.is_high().await.unwrap());
+        });
+
+        bus.done();
+    }
+
+    #[cfg(feature = "pcf8575")]
+    #[test]
+    fn pcf8575_async() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0xfe, 0xff]),
+            mock_i2c::Transaction::read(0x20, vec![0xfe, 0x7f]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let driver = crate::dev::pcf8575::Driver::new(bus.clone(), false, false, false);
+        let port = super::AsyncPort::new(driver);
+        let p17 = port.take_pin(15).unwrap();
+
+        block_on(async {
+            let p00 = port.take_pin(0).unwrap().into_output().await.unwrap();
+            assert!(p00.is_set_low().await.unwrap());
+            assert!(p17.is_low().await.unwrap());
+        });
+
+        bus.done();
+    }
+}
@@ -0,0 +1,251 @@
+/// Pins of several port-expanders behind one index space, like a single wide port.
+///
+/// Boards often combine multiple identical port-expanders, e.g. four `PCA9555` for 64 channels of
+/// digital I/O.  A `GpioBank` owns pins of all of them and numbers them from 0 in the order they
+/// were passed in.  Its methods take masks over these indices, bit `n` being the `n`-th pin.  Each
+/// operation is split into one mask per chip, so every chip is only locked and accessed once, with
+/// the same bus transactions as for [`write_multiple()`](crate::write_multiple) or
+/// [`read_multiple()`](crate::read_multiple) on that chip.
+///
+/// A bank holds up to 64 pins.  All pins must have the same mode and their port-expanders the
+/// same mutex type, which is the case for several chips of the same kind.
+///
+/// The chips are accessed one after another, so the pins of different chips do not change at
+/// exactly the same time.  If accessing a chip fails, the chips before it were already accessed.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// use port_expander::{GpioBank, Pca9555};
+///
+/// let mut pca0 = Pca9555::new(i2c.clone(), false, false, false);
+/// let mut pca1 = Pca9555::new(i2c, true, false, false);
+///
+/// // 32 outputs, 0..16 on the first chip and 16..32 on the second one
+/// let bank = GpioBank::from_chips([pca0.pins(), pca1.pins()]);
+/// let mut bank: GpioBank<_, _, 32> = bank.into_outputs().unwrap();
+/// bank.write_all(0x8000_0001).unwrap();
+/// ```
+#[must_use = "a dropped pin cannot be accessed again"]
+pub struct GpioBank<'a, MODE, MUTEX, const N: usize> {
+    pins: [crate::Pin<'a, MODE, MUTEX>; N],
+}
+
+impl<'a, MODE, MUTEX, PD, const N: usize> GpioBank<'a, MODE, MUTEX, N>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Create a bank from `pins`, element `n` becomes index `n` of the bank.
+    ///
+    /// # Panics
+    /// A bank can hold at most 64 pins.
+    pub fn new(pins: [crate::Pin<'a, MODE, MUTEX>; N]) -> Self {
+        assert!(N <= 64);
+        Self { pins }
+    }
+
+    /// Create a bank from the pins of several chips, e.g. from their `pins()`.
+    ///
+    /// The pins of the first chip are numbered first, then those of the second chip, and so on.
+    ///
+    /// # Panics
+    /// `N` must be the total number of pins, `C * P`, and at most 64.
+    pub fn from_chips<const C: usize, const P: usize>(
+        chips: [[crate::Pin<'a, MODE, MUTEX>; P]; C],
+    ) -> Self {
+        assert_eq!(C * P, N);
+        let mut pins = chips.into_iter().flatten();
+        Self::new(core::array::from_fn(|_| pins.next().unwrap()))
+    }
+
+    /// Release the pins of this bank.
+    pub fn into_pins(self) -> [crate::Pin<'a, MODE, MUTEX>; N] {
+        self.pins
+    }
+
+    /// Access the pin with index `index`, or `None` if it is out of range.
+    pub fn pin(&mut self, index: usize) -> Option<&mut crate::Pin<'a, MODE, MUTEX>> {
+        self.pins.get_mut(index)
+    }
+
+    /// Mask of all indices of this bank.
+    pub fn mask(&self) -> u64 {
+        match N {
+            0 => 0,
+            _ => u64::MAX >> (64 - N),
+        }
+    }
+
+    /// Split the indices in `mask` by chip.
+    ///
+    /// Yields the mutex of each chip with the indices of its pins.
+    fn chips(&self, mask: u64) -> impl Iterator<Item = (&'a MUTEX, u64)> + '_ {
+        let mut remaining = mask & self.mask();
+        core::iter::from_fn(move || {
+            if remaining == 0 {
+                return None;
+            }
+            let port_driver = self.pins[remaining.trailing_zeros() as usize].port_driver();
+            let indices = (0..N)
+                .filter(|&i| remaining & (1 << i) != 0)
+                .filter(|&i| core::ptr::eq(self.pins[i].port_driver(), port_driver))
+                .fold(0, |indices, i| indices | (1 << i));
+            remaining &= !indices;
+            Some((port_driver, indices))
+        })
+    }
+
+    /// Port mask of the pins with the indices in `indices`.
+    fn pin_mask(&self, indices: u64) -> u32 {
+        (0..N)
+            .filter(|&i| indices & (1 << i) != 0)
+            .fold(0, |mask, i| mask | self.pins[i].pin_mask())
+    }
+
+    fn into_mode<NEWMODE>(self) -> GpioBank<'a, NEWMODE, MUTEX, N> {
+        GpioBank {
+            pins: self.pins.map(crate::Pin::into_mode),
+        }
+    }
+}
+
+impl<'a, MODE: crate::mode::Configurable, MUTEX, PD, const N: usize> GpioBank<'a, MODE, MUTEX, N>
+where
+    PD: crate::PortDriver + crate::PortDriverTotemPole,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Configure all pins as inputs, with one access per chip.
+    pub fn into_inputs(
+        self,
+    ) -> Result<GpioBank<'a, crate::mode::Input, MUTEX, N>, crate::pin::PinError<PD::Error>> {
+        for (port_driver, indices) in self.chips(u64::MAX) {
+            let mask = self.pin_mask(indices);
+            port_driver.lock(|drv| drv.set_direction(mask, crate::Direction::Input, false))?;
+        }
+        Ok(self.into_mode())
+    }
+
+    /// Configure all pins as outputs with an initial LOW state, with one access per chip.
+    pub fn into_outputs(
+        self,
+    ) -> Result<GpioBank<'a, crate::mode::Output, MUTEX, N>, crate::pin::PinError<PD::Error>> {
+        for (port_driver, indices) in self.chips(u64::MAX) {
+            let mask = self.pin_mask(indices);
+            port_driver.lock(|drv| drv.set_direction(mask, crate::Direction::Output, false))?;
+        }
+        Ok(self.into_mode())
+    }
+}
+
+impl<'a, MODE: crate::mode::HasOutput, MUTEX, PD, const N: usize> GpioBank<'a, MODE, MUTEX, N>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Set the pins in `mask_high` HIGH and the pins in `mask_low` LOW.
+    ///
+    /// Pins in neither mask keep their state.
+    pub fn write(&mut self, mask_high: u64, mask_low: u64) -> Result<(), PD::Error> {
+        for (port_driver, indices) in self.chips(mask_high | mask_low) {
+            let chip_high = self.pin_mask(indices & mask_high);
+            let chip_low = self.pin_mask(indices & mask_low & !mask_high);
+            port_driver.lock(|drv| drv.set(chip_high, chip_low))?;
+        }
+        Ok(())
+    }
+
+    /// Write `value` to all pins, bit `n` is the state of the pin with index `n`.
+    pub fn write_all(&mut self, value: u64) -> Result<(), PD::Error> {
+        self.write(value & self.mask(), !value & self.mask())
+    }
+}
+
+impl<'a, MODE: crate::mode::HasInput, MUTEX, PD, const N: usize> GpioBank<'a, MODE, MUTEX, N>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Read the pins in `mask` and return the mask of those which are HIGH.
+    pub fn read(&self, mask: u64) -> Result<u64, PD::Error> {
+        let mut high = 0x00;
+        for (port_driver, indices) in self.chips(mask) {
+            let chip_high = port_driver.lock(|drv| drv.get(self.pin_mask(indices), 0))?;
+            high |= (0..N)
+                .filter(|&i| indices & (1 << i) != 0)
+                .filter(|&i| chip_high & self.pins[i].pin_mask() != 0)
+                .fold(0, |high, i| high | (1 << i));
+        }
+        Ok(high)
+    }
+
+    /// Read all pins, bit `n` of the result is the state of the pin with index `n`.
+    pub fn read_all(&self) -> Result<u64, PD::Error> {
+        self.read(self.mask())
+    }
+}
+
+#[cfg(all(test, any(feature = "pca9555", feature = "pcf8574")))]
+mod tests {
+    use super::GpioBank;
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn pca9555_bank() {
+        let expectations = [
+            // outputs, one configuration per chip
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0x00]),
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0x00]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0x00]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x07], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x07, 0x00]),
+            mock_i2c::Transaction::write(0x21, vec![0x02, 0x00]),
+            mock_i2c::Transaction::write(0x21, vec![0x03, 0x00]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x21, vec![0x06, 0x00]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x07], vec![0xff]),
+            mock_i2c::Transaction::write(0x21, vec![0x07, 0x00]),
+            // index 0 and 31 HIGH
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0x01]),
+            mock_i2c::Transaction::write(0x21, vec![0x03, 0x80]),
+            // only the first chip is touched
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0x10]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca0 = crate::Pca9555::new(bus.clone(), false, false, false);
+        let mut pca1 = crate::Pca9555::new(bus.clone(), true, false, false);
+        let bank = GpioBank::from_chips([pca0.pins(), pca1.pins()]);
+        let mut bank: GpioBank<_, _, 32> = bank.into_outputs().unwrap();
+
+        assert_eq!(bank.mask(), 0xffff_ffff);
+        bank.write(0x8000_0001, 0x0000_0000).unwrap();
+        bank.write(1 << 12, 0).unwrap();
+
+        bus.done();
+    }
+
+    #[cfg(feature = "pcf8574")]
+    #[test]
+    fn pcf8574_bank_read() {
+        let expectations = [
+            // chips in the order of their first pin
+            mock_i2c::Transaction::read(0x21, vec![0b1000_0000]),
+            mock_i2c::Transaction::read(0x20, vec![0b0000_0101]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf0 = crate::Pcf8574::new(bus.clone(), false, false, false);
+        let mut pcf1 = crate::Pcf8574::new(bus.clone(), true, false, false);
+        let [p0, p1, p2, ..] = pcf0.pins();
+        let [.., p7] = pcf1.pins();
+
+        // pins of both chips in a custom order
+        let bank = GpioBank::new([p7, p2, p1, p0]);
+        assert_eq!(bank.read_all().unwrap(), 0b1011);
+
+        bus.done();
+    }
+}
@@ -0,0 +1,107 @@
+/// Define a struct of named, pre-configured pins for a board.
+///
+/// Board support crates usually give the pins of a port-expander names which describe their
+/// function on the board and put them into the mode they are used in.  `board_pins!()` defines
+/// such a struct for a device module and a bus type.  Each field names the mode of the pin, the
+/// pin of the device's `Parts` and optionally the mode changes to apply.  Each of these method
+/// calls must return a `Result`, errors are passed on by the generated `new()` constructor.
+///
+/// The struct has a lifetime parameter and a mutex type parameter which defaults to
+/// [`RefCell`](core::cell::RefCell), like the device itself.  Pins of the `Parts` which are not
+/// listed are dropped.
+///
+/// ## Example
+/// ```no_run
+/// # type I2c = embedded_hal_mock::eh1::i2c::Mock;
+/// port_expander::board_pins! {
+///     /// Pins of the relay board.
+///     pub struct RelayBoard: pca9555<I2c> {
+///         /// Status LED, initially off.
+///         pub led_status: Output = io0_0.into_output_high(),
+///         pub relay1: Output = io0_1.into_output(),
+///         pub button: Input = io1_0.into_inverted(),
+///     }
+/// }
+///
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// let mut pca9555 = port_expander::Pca9555::new(i2c, false, false, false);
+/// let mut board = RelayBoard::new(pca9555.split()).unwrap();
+/// board.led_status.set_low().unwrap();
+/// ```
+#[macro_export]
+macro_rules! board_pins {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident : $dev:ident < $bus:ty > {
+            $(
+                $(#[$fmeta:meta])*
+                $fvis:vis $field:ident : $mode:ident = $pin:ident $(. $method:ident ( $($arg:expr),* ))*
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name<'a, M = core::cell::RefCell<$crate::dev::$dev::Driver<$bus>>> {
+            $(
+                $(#[$fmeta])*
+                $fvis $field: $crate::Pin<'a, $crate::mode::$mode, M>,
+            )*
+        }
+
+        impl<'a, M> $name<'a, M>
+        where
+            M: $crate::PortMutex<Port = $crate::dev::$dev::Driver<$bus>>,
+        {
+            /// Configure the pins of `parts` and give them their board names.
+            $vis fn new(
+                parts: $crate::dev::$dev::Parts<'a, $bus, M>,
+            ) -> Result<
+                Self,
+                $crate::__macro::PinError<
+                    <$crate::dev::$dev::Driver<$bus> as $crate::__macro::PortDriver>::Error,
+                >,
+            > {
+                Ok(Self {
+                    $(
+                        $field: parts.$pin $(.$method($($arg),*)?)*,
+                    )*
+                })
+            }
+        }
+    };
+}
+
+#[cfg(all(test, feature = "pca9555"))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    type I2c = mock_i2c::Mock;
+
+    crate::board_pins! {
+        struct Board: pca9555<I2c> {
+            led: Output = io0_0.into_output_high(),
+            button: Input = io1_7,
+        }
+    }
+
+    #[test]
+    fn pca9555_board_pins() {
+        let expectations = [
+            // led output HIGH
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0xfe]),
+            // led LOW
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+            // button
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0x80]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = crate::Pca9555::new(bus.clone(), false, false, false);
+        let mut board = Board::new(pca.split()).unwrap();
+        board.led.set_low().unwrap();
+        assert!(board.button.is_high().unwrap());
+
+        bus.done();
+    }
+}
@@ -1,4 +1,12 @@
-use embedded_hal::{i2c as hal_i2c, spi as hal_spi};
+use embedded_hal::i2c as hal_i2c;
+#[cfg(any(
+    feature = "max7300",
+    feature = "mcp23x17",
+    feature = "pca9702",
+    feature = "sn74hc165",
+    feature = "sn74hc595",
+))]
+use embedded_hal::spi as hal_spi;
 
 /// Blanket trait for types implementing `i2c::I2c
 pub trait I2cBus: hal_i2c::I2c {
@@ -12,58 +20,424 @@ where
     type BusError = E;
 }
 
-pub(crate) trait I2cExt {
-    type Error;
+/// I2C bus adapter for port-expanders behind a 10-bit address.
+///
+/// The drivers address their chip with the usual 7-bit address (e.g. `0x20` to `0x27` for a
+/// `PCA9555`).  `TenBitI2c` wraps a bus implementing `I2c<TenBitAddress>` and places this address
+/// in the 10-bit address range starting at `base`, so the chip is accessed at `base | address`.
+///
+/// The base is a [`TenBitBase`], so `new()` cannot fail.  `with_base()` accepts a raw 10-bit
+/// address instead and rejects bases which are not aligned to a block of 128 addresses.
+///
+/// ## Example
+/// ```no_run
+/// # use embedded_hal::i2c::{ErrorType, I2c, Operation, TenBitAddress};
+/// # struct Bus;
+/// # impl ErrorType for Bus { type Error = core::convert::Infallible; }
+/// # impl I2c<TenBitAddress> for Bus {
+/// #     fn transaction(&mut self, _: u16, _: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # let i2c = Bus;
+/// // PCA9555 with A0..A2 low at the 10-bit address 0x320
+/// let base = port_expander::TenBitBase::from_bits(true, true, false);
+/// let bus = port_expander::TenBitI2c::new(i2c, base);
+/// let mut pca9555 = port_expander::Pca9555::new(bus, false, false, false);
+/// ```
+pub struct TenBitI2c<I2C> {
+    i2c: I2C,
+    base: hal_i2c::TenBitAddress,
+}
+
+impl<I2C> TenBitI2c<I2C> {
+    /// Wrap `i2c` to access the chips in the 10-bit address range starting at `base`.
+    pub fn new(i2c: I2C, base: TenBitBase) -> Self {
+        Self {
+            i2c,
+            base: base.get(),
+        }
+    }
+
+    /// Wrap `i2c` to access the chips in the 10-bit address range starting at the raw address
+    /// `base`, which must be a multiple of `0x80` between `0x000` and `0x380`.
+    pub fn with_base(i2c: I2C, base: hal_i2c::TenBitAddress) -> Result<Self, InvalidTenBitBase> {
+        Ok(Self::new(i2c, TenBitBase::custom(base)?))
+    }
+
+    /// Get the wrapped bus back.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    fn address(&self, address: hal_i2c::SevenBitAddress) -> hal_i2c::TenBitAddress {
+        self.base | (address & 0x7f) as hal_i2c::TenBitAddress
+    }
+}
+
+/// Start of a block of 128 addresses in the 10-bit I2C address space, for [`TenBitI2c`].
+///
+/// Construct it from the upper three address bits with `from_bits()`, or from a raw 10-bit address
+/// with `custom()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TenBitBase(hal_i2c::TenBitAddress);
+
+impl TenBitBase {
+    /// Base address selected by the address bits `A9`, `A8` and `A7`.
+    pub const fn from_bits(a9: bool, a8: bool, a7: bool) -> Self {
+        Self(((a9 as u16) << 9) | ((a8 as u16) << 8) | ((a7 as u16) << 7))
+    }
+
+    /// Validate a raw 10-bit base address, which must be a multiple of `0x80` between `0x000`
+    /// and `0x380`.
+    pub fn custom(base: hal_i2c::TenBitAddress) -> Result<Self, InvalidTenBitBase> {
+        if base & 0x7f == 0 && base <= 0x380 {
+            Ok(Self(base))
+        } else {
+            Err(InvalidTenBitBase { base })
+        }
+    }
+
+    /// The raw 10-bit base address.
+    pub const fn get(self) -> hal_i2c::TenBitAddress {
+        self.0
+    }
+}
+
+impl From<TenBitBase> for hal_i2c::TenBitAddress {
+    fn from(base: TenBitBase) -> hal_i2c::TenBitAddress {
+        base.0
+    }
+}
+
+impl TryFrom<hal_i2c::TenBitAddress> for TenBitBase {
+    type Error = InvalidTenBitBase;
+
+    fn try_from(base: hal_i2c::TenBitAddress) -> Result<Self, Self::Error> {
+        Self::custom(base)
+    }
+}
+
+/// Error returned when a [`TenBitBase`] is constructed from an address which does not start a
+/// block of 128 addresses in the 10-bit address space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InvalidTenBitBase {
+    base: hal_i2c::TenBitAddress,
+}
+
+impl InvalidTenBitBase {
+    /// The base address which was rejected.
+    pub fn base(&self) -> hal_i2c::TenBitAddress {
+        self.base
+    }
+}
+
+impl<I2C: hal_i2c::ErrorType> hal_i2c::ErrorType for TenBitI2c<I2C> {
+    type Error = I2C::Error;
+}
+
+impl<I2C: hal_i2c::I2c<hal_i2c::TenBitAddress>> hal_i2c::I2c for TenBitI2c<I2C> {
+    fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        let address = self.address(address);
+        self.i2c.read(address, read)
+    }
+
+    fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        let address = self.address(address);
+        self.i2c.write(address, write)
+    }
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let address = self.address(address);
+        self.i2c.write_read(address, write, read)
+    }
+
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [hal_i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let address = self.address(address);
+        self.i2c.transaction(address, operations)
+    }
+}
+
+/// Register access to a port-expander, independent of the bus it is connected to.
+///
+/// Drivers are written against this trait so they work with any transport the chip offers.  It is
+/// implemented for all I2C buses and for SPI buses wrapped in `SpiRegisters`, which frames
+/// each access with the device's opcode.
+///
+/// With the `defmt` feature, every successful register access is logged at the `trace` level,
+/// e.g. with `DEFMT_LOG=port_expander=trace`.
+pub trait RegisterBus {
+    type BusError;
+
+    /// Number of bus transactions needed by [`read_reg()`](RegisterBus::read_reg).
+    const READ_COST: u32 = 1;
+
+    fn write_reg<R: Into<u8>>(&mut self, addr: u8, reg: R, value: u8)
+        -> Result<(), Self::BusError>;
+    fn read_reg<R: Into<u8>>(&mut self, addr: u8, reg: R) -> Result<u8, Self::BusError>;
+
+    /// Like [`write_reg()`](RegisterBus::write_reg), for registers which only read back the bits
+    /// in `mask` as written, e.g. because the other bits reflect the levels of input pins.
+    ///
+    /// Only wrappers which read back the written registers, like
+    /// [`VerifyingI2c`](crate::VerifyingI2c), need to handle `mask`.
+    fn write_reg_masked<R: Into<u8>>(
+        &mut self,
+        addr: u8,
+        reg: R,
+        value: u8,
+        _mask: u8,
+    ) -> Result<(), Self::BusError> {
+        self.write_reg(addr, reg, value)
+    }
 
-    fn write_reg<R: Into<u8>>(&mut self, addr: u8, reg: R, value: u8) -> Result<(), Self::Error>;
     fn update_reg<R: Into<u8>>(
         &mut self,
         addr: u8,
         reg: R,
         mask_set: u8,
         mask_clear: u8,
-    ) -> Result<(), Self::Error>;
-    fn read_reg<R: Into<u8>>(&mut self, addr: u8, reg: R) -> Result<u8, Self::Error>;
+    ) -> Result<(), Self::BusError> {
+        let reg = reg.into();
+        let mut val = self.read_reg(addr, reg)?;
+        val |= mask_set;
+        val &= !mask_clear;
+        self.write_reg(addr, reg, val)?;
+        Ok(())
+    }
 }
 
-impl<I2C: I2cBus> I2cExt for I2C {
-    type Error = I2C::BusError;
+impl<I2C: I2cBus> RegisterBus for I2C {
+    type BusError = I2C::BusError;
 
-    fn write_reg<R: Into<u8>>(&mut self, addr: u8, reg: R, value: u8) -> Result<(), Self::Error> {
-        self.write(addr, &[reg.into(), value])?;
+    fn write_reg<R: Into<u8>>(
+        &mut self,
+        addr: u8,
+        reg: R,
+        value: u8,
+    ) -> Result<(), Self::BusError> {
+        let reg = reg.into();
+        self.write(addr, &[reg, value])?;
+        trace_write(addr, reg, value);
         Ok(())
     }
 
-    fn update_reg<R: Into<u8>>(
+    fn read_reg<R: Into<u8>>(&mut self, addr: u8, reg: R) -> Result<u8, Self::BusError> {
+        let reg = reg.into();
+        let mut buf = [0x00];
+        self.write_read(addr, &[reg], &mut buf)?;
+        trace_read(addr, reg, buf[0]);
+        Ok(buf[0])
+    }
+}
+
+/// Async counterpart of [`RegisterBus::write_reg()`] for `embedded_hal_async::i2c::I2c` buses.
+#[cfg(feature = "async")]
+pub(crate) async fn write_reg_async<I2C: embedded_hal_async::i2c::I2c>(
+    i2c: &mut I2C,
+    addr: u8,
+    reg: u8,
+    value: u8,
+) -> Result<(), I2C::Error> {
+    i2c.write(addr, &[reg, value]).await?;
+    trace_write(addr, reg, value);
+    Ok(())
+}
+
+/// Async counterpart of [`RegisterBus::read_reg()`] for `embedded_hal_async::i2c::I2c` buses.
+#[cfg(feature = "async")]
+pub(crate) async fn read_reg_async<I2C: embedded_hal_async::i2c::I2c>(
+    i2c: &mut I2C,
+    addr: u8,
+    reg: u8,
+) -> Result<u8, I2C::Error> {
+    let mut buf = [0x00];
+    i2c.write_read(addr, &[reg], &mut buf).await?;
+    trace_read(addr, reg, buf[0]);
+    Ok(buf[0])
+}
+
+/// Async counterpart of [`RegisterBus::update_reg()`] for `embedded_hal_async::i2c::I2c` buses.
+#[cfg(feature = "async")]
+pub(crate) async fn update_reg_async<I2C: embedded_hal_async::i2c::I2c>(
+    i2c: &mut I2C,
+    addr: u8,
+    reg: u8,
+    mask_set: u8,
+    mask_clear: u8,
+) -> Result<(), I2C::Error> {
+    let mut val = read_reg_async(i2c, addr, reg).await?;
+    val |= mask_set;
+    val &= !mask_clear;
+    write_reg_async(i2c, addr, reg, val).await
+}
+
+#[cfg_attr(not(feature = "defmt"), allow(unused_variables))]
+pub(crate) fn trace_write(addr: u8, reg: u8, value: u8) {
+    #[cfg(feature = "defmt")]
+    defmt::trace!(
+        "port-expander {=u8:#04x}: write {=u8:#04x} <- {=u8:#010b}",
+        addr,
+        reg,
+        value
+    );
+}
+
+#[cfg_attr(not(feature = "defmt"), allow(unused_variables))]
+pub(crate) fn trace_read(addr: u8, reg: u8, value: u8) {
+    #[cfg(feature = "defmt")]
+    defmt::trace!(
+        "port-expander {=u8:#04x}: read {=u8:#04x} -> {=u8:#010b}",
+        addr,
+        reg,
+        value
+    );
+}
+
+/// Address framing of an SPI port-expander.
+///
+/// SPI port-expanders which share a chip-select line are addressed by an opcode byte which is
+/// sent before the register address.
+#[cfg(feature = "mcp23x17")]
+pub trait SpiFraming {
+    /// Opcode for accessing the device at `addr`, for a read if `read` is `true`.
+    fn opcode(addr: u8, read: bool) -> u8;
+}
+
+/// SPI bus with the address framing `F`, for use as a [`RegisterBus`].
+#[cfg(feature = "mcp23x17")]
+pub struct SpiRegisters<SPI, F> {
+    spi: SPI,
+    _framing: core::marker::PhantomData<F>,
+}
+
+#[cfg(feature = "mcp23x17")]
+impl<SPI, F> SpiRegisters<SPI, F> {
+    pub(crate) fn new(spi: SPI) -> Self {
+        Self {
+            spi,
+            _framing: core::marker::PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "mcp23x17")]
+impl<SPI: SpiBus, F: SpiFraming> RegisterBus for SpiRegisters<SPI, F> {
+    type BusError = SPI::BusError;
+
+    fn write_reg<R: Into<u8>>(
         &mut self,
         addr: u8,
         reg: R,
-        mask_set: u8,
-        mask_clear: u8,
-    ) -> Result<(), Self::Error> {
+        value: u8,
+    ) -> Result<(), Self::BusError> {
         let reg = reg.into();
-        let mut buf = [0x00];
-        self.write_read(addr, &[reg], &mut buf)?;
-        buf[0] |= mask_set;
-        buf[0] &= !mask_clear;
-        self.write(addr, &[reg, buf[0]])?;
+        self.spi.write(&[F::opcode(addr, false), reg, value])?;
+        trace_write(addr, reg, value);
         Ok(())
     }
 
-    fn read_reg<R: Into<u8>>(&mut self, addr: u8, reg: R) -> Result<u8, Self::Error> {
-        let mut buf = [0x00];
-        self.write_read(addr, &[reg.into()], &mut buf)?;
-        Ok(buf[0])
+    fn read_reg<R: Into<u8>>(&mut self, addr: u8, reg: R) -> Result<u8, Self::BusError> {
+        let reg = reg.into();
+        let mut val = [0x00];
+        let write = [F::opcode(addr, true), reg];
+        let mut tx = [
+            hal_spi::Operation::Write(&write),
+            hal_spi::Operation::Read(&mut val),
+        ];
+        self.spi.transaction(&mut tx)?;
+        trace_read(addr, reg, val[0]);
+        Ok(val[0])
     }
 }
 
+#[cfg(any(
+    feature = "max7300",
+    feature = "mcp23x17",
+    feature = "pca9702",
+    feature = "sn74hc165",
+    feature = "sn74hc595",
+))]
 pub trait SpiBus: hal_spi::SpiDevice {
     type BusError: From<<Self as hal_spi::ErrorType>::Error>;
 }
 
+#[cfg(any(
+    feature = "max7300",
+    feature = "mcp23x17",
+    feature = "pca9702",
+    feature = "sn74hc165",
+    feature = "sn74hc595",
+))]
 impl<T, E> SpiBus for T
 where
     T: hal_spi::SpiDevice<Error = E>,
 {
     type BusError = E;
 }
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal::i2c as hal_i2c;
+
+    /// Records the addresses of all accesses and reads back `0xff`.
+    struct TenBitBus(std::rc::Rc<core::cell::RefCell<Vec<u16>>>);
+
+    impl hal_i2c::ErrorType for TenBitBus {
+        type Error = core::convert::Infallible;
+    }
+
+    impl hal_i2c::I2c<hal_i2c::TenBitAddress> for TenBitBus {
+        fn transaction(
+            &mut self,
+            address: u16,
+            operations: &mut [hal_i2c::Operation<'_>],
+        ) -> Result<(), Self::Error> {
+            self.0.borrow_mut().push(address);
+            for op in operations {
+                if let hal_i2c::Operation::Read(buf) = op {
+                    buf.fill(0xff);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn pca9555_ten_bit_address() {
+        let addresses = std::rc::Rc::default();
+        let base = super::TenBitBase::from_bits(true, true, false);
+        assert_eq!(base.get(), 0x300);
+        let bus = super::TenBitI2c::new(TenBitBus(std::rc::Rc::clone(&addresses)), base);
+        let mut pca = crate::Pca9555::new(bus, true, false, false);
+        let pca_pins = pca.split();
+        assert!(pca_pins.io0_0.is_high().unwrap());
+
+        assert_eq!(*addresses.borrow(), [0x321]);
+    }
+
+    #[test]
+    fn ten_bit_base() {
+        let addresses = std::rc::Rc::default();
+        for base in [0x301, 0x400, 0x7ff] {
+            let err = super::TenBitI2c::with_base(TenBitBus(std::rc::Rc::clone(&addresses)), base)
+                .err()
+                .unwrap();
+            assert_eq!(err.base(), base);
+        }
+        assert!(super::TenBitI2c::with_base(TenBitBus(addresses), 0x380).is_ok());
+        assert_eq!(super::TenBitBase::try_from(0x080).map(u16::from), Ok(0x080));
+    }
+}
@@ -0,0 +1,252 @@
+use core::future::Future;
+use core::task::Poll;
+use embedded_hal_async::delay::DelayNs;
+
+/// Event of an [`AsyncButton`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ButtonEvent {
+    /// The button was pressed.
+    Pressed,
+    /// The button was released.
+    Released,
+    /// The button has been pressed for the hold time and is still pressed.  Reported once per
+    /// press, between `Pressed` and `Released`.
+    Held,
+}
+
+/// Timing of an [`AsyncButton`], all values in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ButtonTiming {
+    /// Time after an edge until the level is read, bounces within this time are ignored.
+    pub debounce_ms: u32,
+    /// Time after `Pressed` until `Held` is reported.
+    pub hold_ms: u32,
+}
+
+impl Default for ButtonTiming {
+    fn default() -> Self {
+        Self {
+            debounce_ms: 20,
+            hold_ms: 1000,
+        }
+    }
+}
+
+/// Debounced button events of an input pin, for async firmware.
+///
+/// The button waits for edges of an [`AsyncPin`](crate::AsyncPin), so its port must be read by
+/// [`AsyncPort::handle_interrupt()`](crate::AsyncPort::handle_interrupt) or one of the loops
+/// around it.  After an edge, [`next()`](AsyncButton::next) waits `debounce_ms` with the async
+/// `delay` and then reads the level, an event is reported if it differs from the last one.  The
+/// port latches the edges of the pin while `next()` is not running, so a press which happens
+/// while the caller handles the previous event is reported by the next call.
+///
+/// `Held` is reported if the button is still pressed `hold_ms` after the call of `next()` which
+/// follows `Pressed`.  The first call only records the initial level, a button which is already
+/// pressed then is not reported.  Available with the `async-button` feature.
+///
+/// ## Example
+/// ```no_run
+/// # async fn example() {
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # struct Delay;
+/// # impl embedded_hal_async::delay::DelayNs for Delay {
+/// #     async fn delay_ns(&mut self, _ns: u32) {}
+/// # }
+/// # let delay = Delay;
+/// use port_expander::dev::pca9555;
+/// use port_expander::{AsyncButton, ButtonEvent, ButtonTiming};
+///
+/// let port = port_expander::AsyncPort::new(pca9555::Driver::new(i2c, false, false, false));
+///
+/// // button connects io1_0 to GND, `port.handle_interrupt()` is called by another task
+/// let mut button = AsyncButton::new(port.take_pin(8).unwrap(), delay, true, ButtonTiming::default());
+/// loop {
+///     match button.next().await.unwrap() {
+///         ButtonEvent::Pressed => { /* ... */ }
+///         ButtonEvent::Released => { /* ... */ }
+///         ButtonEvent::Held => { /* ... */ }
+///     }
+/// }
+/// # }
+/// ```
+pub struct AsyncButton<'a, MODE, PD, D> {
+    pin: crate::AsyncPin<'a, MODE, PD>,
+    delay: D,
+    active_low: bool,
+    timing: ButtonTiming,
+    /// Debounced state, `None` before the first read.
+    pressed: Option<bool>,
+    /// Whether `Held` is still to be reported for the current press.
+    hold_pending: bool,
+}
+
+impl<'a, MODE, PD, D> AsyncButton<'a, MODE, PD, D>
+where
+    MODE: crate::mode::HasInput,
+    PD: crate::AsyncPortDriver,
+    D: DelayNs,
+{
+    /// Create the button for `pin`.  With `active_low`, the button is pressed when the pin is LOW.
+    pub fn new(
+        pin: crate::AsyncPin<'a, MODE, PD>,
+        delay: D,
+        active_low: bool,
+        timing: ButtonTiming,
+    ) -> Self {
+        Self {
+            pin,
+            delay,
+            active_low,
+            timing,
+            pressed: None,
+            hold_pending: false,
+        }
+    }
+
+    /// Wait for the next button event.
+    pub async fn next(&mut self) -> Result<ButtonEvent, crate::pin::PinError<PD::Error>> {
+        let pressed = match self.pressed {
+            Some(pressed) => pressed,
+            None => {
+                let pressed = self.read_pressed().await?;
+                self.pressed = Some(pressed);
+                pressed
+            }
+        };
+
+        loop {
+            if pressed && self.hold_pending {
+                if !self.wait_for_edge_or_hold().await? {
+                    self.hold_pending = false;
+                    return Ok(ButtonEvent::Held);
+                }
+            } else {
+                self.pin.wait_for_any_edge().await?;
+            }
+
+            self.delay.delay_ms(self.timing.debounce_ms).await;
+            let level = self.read_pressed().await?;
+            if level != pressed {
+                self.pressed = Some(level);
+                self.hold_pending = level;
+                return Ok(match level {
+                    true => ButtonEvent::Pressed,
+                    false => ButtonEvent::Released,
+                });
+            }
+        }
+    }
+
+    /// Release the pin and the delay.
+    pub fn release(self) -> (crate::AsyncPin<'a, MODE, PD>, D) {
+        (self.pin, self.delay)
+    }
+
+    /// Read the level after forgetting the edges which led to it.
+    async fn read_pressed(&mut self) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        self.pin.clear_edges();
+        Ok(self.pin.is_high().await? != self.active_low)
+    }
+
+    /// Wait for an edge of the pin or for the hold time, return `false` if the latter was first.
+    async fn wait_for_edge_or_hold(&mut self) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        let mut edge = core::pin::pin!(self.pin.wait_for_any_edge());
+        let mut hold = core::pin::pin!(self.delay.delay_ms(self.timing.hold_ms));
+        core::future::poll_fn(|cx| {
+            if let Poll::Ready(result) = edge.as_mut().poll(cx) {
+                return Poll::Ready(result.map(|()| true));
+            }
+            if hold.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(Ok(false));
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+#[cfg(all(test, feature = "pca9555"))]
+mod tests {
+    use super::{AsyncButton, ButtonEvent, ButtonTiming};
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    struct CountingDelay(u32);
+
+    impl embedded_hal_async::delay::DelayNs for CountingDelay {
+        async fn delay_ns(&mut self, ns: u32) {
+            self.0 += ns / 1_000_000;
+        }
+    }
+
+    fn block_on<F: core::future::Future>(f: F) -> F::Output {
+        let mut f = core::pin::pin!(f);
+        let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+        loop {
+            if let core::task::Poll::Ready(v) = f.as_mut().poll(&mut cx) {
+                return v;
+            }
+        }
+    }
+
+    fn poll_once<F: core::future::Future>(
+        f: core::pin::Pin<&mut F>,
+    ) -> core::task::Poll<F::Output> {
+        let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+        f.poll(&mut cx)
+    }
+
+    #[test]
+    fn pca9555_async_button() {
+        let expectations = [
+            // initial level of io1_0 and of all inputs
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xff]),
+            // interrupt, io1_0 falls
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xfe]),
+            // level after debouncing
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xfe]),
+            // interrupt, io1_0 rises while nobody waits
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xff]),
+            // level after debouncing
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xff]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let driver = crate::dev::pca9555::Driver::new(bus.clone(), false, false, false);
+        let port = crate::AsyncPort::new(driver);
+
+        let timing = ButtonTiming {
+            debounce_ms: 20,
+            hold_ms: 30,
+        };
+        let mut button =
+            AsyncButton::new(port.take_pin(8).unwrap(), CountingDelay(0), true, timing);
+
+        {
+            let mut next = core::pin::pin!(button.next());
+            assert!(poll_once(next.as_mut()).is_pending());
+            block_on(port.handle_interrupt()).unwrap();
+            match poll_once(next.as_mut()) {
+                core::task::Poll::Ready(event) => assert_eq!(event.unwrap(), ButtonEvent::Pressed),
+                core::task::Poll::Pending => panic!("press not reported"),
+            }
+        }
+        // the delay returns at once, so the hold time passes before any edge
+        assert_eq!(block_on(button.next()).unwrap(), ButtonEvent::Held);
+
+        // the release is latched until the next call
+        block_on(port.handle_interrupt()).unwrap();
+        assert_eq!(block_on(button.next()).unwrap(), ButtonEvent::Released);
+
+        let (_, delay) = button.release();
+        assert_eq!(delay.0, 70);
+
+        bus.done();
+    }
+}
@@ -0,0 +1,114 @@
+/// Answer all input reads of a port-expander from a single snapshot while the returned guard is
+/// alive.
+///
+/// In a tight loop, checking multiple pins of the same chip one after the other
+///
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pcf = port_expander::Pcf8574::new(i2c, false, false, false);
+/// # let p = pcf.split();
+/// # let io0 = p.p0;
+/// # let io1 = p.p1;
+/// loop {
+///     let a = io0.is_high().unwrap();
+///     let b = io1.is_high().unwrap();
+///     // ...
+/// }
+/// ```
+///
+/// does a separate bus transaction for each read.  `cached_inputs_for()` takes any pin of the
+/// port-expander and returns a guard.  While the guard is alive, the first read takes a snapshot of
+/// all inputs of the chip and all following reads of any of its pins are answered from this
+/// snapshot without touching the bus.  Dropping the guard discards the snapshot.
+///
+/// Input changes which happen while the guard is alive are thus not visible.  Keep the scope of
+/// the guard as short as possible, usually a single iteration of the loop.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pcf = port_expander::Pcf8574::new(i2c, false, false, false);
+/// # let p = pcf.split();
+/// # let io0 = p.p0;
+/// # let io1 = p.p1;
+/// loop {
+///     let _cache = port_expander::cached_inputs_for(&io0);
+///     let a = io0.is_high().unwrap();
+///     let b = io1.is_high().unwrap();
+///     // ...
+/// }
+/// ```
+pub fn cached_inputs_for<'a, MODE, MUTEX, PD>(
+    pin: &crate::Pin<'a, MODE, MUTEX>,
+) -> CachedInputs<'a, MUTEX>
+where
+    PD: crate::PortDriver + crate::PortDriverInputState,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    let port_driver = pin.port_driver();
+    port_driver.lock(|drv| drv.input_state().enable_cache());
+    CachedInputs { port_driver }
+}
+
+/// Guard returned by [`cached_inputs_for()`].
+///
+/// Input reads are answered from a snapshot until this guard is dropped.
+#[must_use = "inputs are only cached while the guard is alive"]
+pub struct CachedInputs<'a, MUTEX>
+where
+    MUTEX: crate::PortMutex,
+    MUTEX::Port: crate::PortDriverInputState,
+{
+    port_driver: &'a MUTEX,
+}
+
+impl<'a, MUTEX> Drop for CachedInputs<'a, MUTEX>
+where
+    MUTEX: crate::PortMutex,
+    MUTEX::Port: crate::PortDriverInputState,
+{
+    fn drop(&mut self) {
+        self.port_driver
+            .lock(|drv| crate::PortDriverInputState::input_state(drv).disable_cache());
+    }
+}
+
+#[cfg(all(test, feature = "pca9555"))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pca9555_cached_inputs() {
+        let expectations = [
+            // uncached reads only touch the port of the pin
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x01]),
+            // snapshot of both ports
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x01]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0x80]),
+            // fresh reads after the guard was dropped
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0x00]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = crate::Pca9555::new(bus.clone(), false, false, false);
+        let pca_pins = pca.split();
+
+        assert!(pca_pins.io0_0.is_high().unwrap());
+
+        {
+            let _cache = super::cached_inputs_for(&pca_pins.io0_0);
+            assert!(pca_pins.io0_0.is_high().unwrap());
+            assert!(pca_pins.io0_1.is_low().unwrap());
+            assert!(pca_pins.io1_7.is_high().unwrap());
+
+            // nested guards keep the snapshot alive
+            let nested = super::cached_inputs_for(&pca_pins.io1_0);
+            drop(nested);
+            assert!(pca_pins.io1_7.is_high().unwrap());
+        }
+
+        assert!(pca_pins.io1_7.is_low().unwrap());
+
+        bus.done();
+    }
+}
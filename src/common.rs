@@ -22,61 +22,567 @@ pub trait PortDriver {
     /// If a bit is set in both `mask_high` and `mask_low`, the resulting bit must be 1.
     fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error>;
 
+    /// Invert the output state of all pins in `mask`.
+    ///
+    /// This is done with a single call to [`is_set()`](PortDriver::is_set) and a single
+    /// [`set()`](PortDriver::set), so all pins change state at the same time.
     fn toggle(&mut self, mask: u32) -> Result<(), Self::Error> {
-        // for all pins which are currently low, make them high.
+        // all pins which are currently low become high, the others become low
         let mask_high = self.is_set(0, mask)?;
-        // for all pins which are currently high, make them low.
-        let mask_low = self.is_set(mask, 0)?;
-        self.set(mask_high, mask_low)
+        self.set(mask_high, mask & !mask_high)
+    }
+
+    /// Return which pins in `mask` have their input polarity inverted in hardware.
+    ///
+    /// Drivers for chips with a polarity inversion register must read it here, the default is for
+    /// chips which can't invert inputs.
+    fn get_polarity(&mut self, mask: u32) -> Result<u32, Self::Error> {
+        let _ = mask;
+        Ok(0)
     }
 }
 
+#[diagnostic::on_unimplemented(
+    message = "the port-expander driver `{Self}` does not support switching pins between input and output",
+    label = "this chip has no direction register"
+)]
 pub trait PortDriverTotemPole: PortDriver {
     /// Set the direction for all pins in `mask` to direction `dir`.
     ///
     /// To prevent electrical glitches, when making pins outputs, the `state` can be either `true`
     /// or `false` to immediately put the pin HIGH or LOW upon switching.
-    fn set_direction(&mut self, mask: u32, dir: Direction, state: bool) -> Result<(), Self::Error>;
+    fn set_direction(&mut self, mask: u32, dir: Direction, state: bool) -> Result<(), Self::Error> {
+        // set state before switching direction to prevent glitch
+        if dir == Direction::Output {
+            if state {
+                self.set(mask, 0)?;
+            } else {
+                self.set(0, mask)?;
+            }
+        }
+
+        match dir {
+            Direction::Input => self.set_directions(mask, 0),
+            Direction::Output => self.set_directions(0, mask),
+        }
+    }
+
+    /// Make all pins in `mask_input` inputs and all pins in `mask_output` outputs.
+    ///
+    /// Unlike [`set_direction()`][PortDriverTotemPole::set_direction], this does not touch the
+    /// output state of any pin.  The driver should implement this with as few bus transactions
+    /// as possible.
+    fn set_directions(&mut self, mask_input: u32, mask_output: u32) -> Result<(), Self::Error>;
 }
 
+/// Direction of a pin.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Direction {
     Input,
     Output,
 }
 
+/// Output stage of all outputs of a port-expander, see e.g.
+/// [`Pcal6408a::set_output_mode()`](crate::Pcal6408a::set_output_mode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OutputMode {
+    /// Outputs actively drive both HIGH and LOW.
+    PushPull,
+    /// Outputs only drive LOW and are high-impedance when HIGH, e.g. for shared lines with an
+    /// external pull-up.
+    OpenDrain,
+}
+
+#[diagnostic::on_unimplemented(
+    message = "the port-expander driver `{Self}` does not support input polarity inversion",
+    label = "this chip has no input polarity inversion"
+)]
 pub trait PortDriverPolarity: PortDriver {
     /// Set the polarity of all pins in `mask` either `inverted` or not.
     fn set_polarity(&mut self, mask: u32, inverted: bool) -> Result<(), Self::Error>;
 }
 
+/// Pull resistor of an input pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pull {
+    /// Floating input
+    None,
+    Up,
+    Down,
+}
+
+/// Error type of [`Pin::into_input_with_pull()`](crate::Pin::into_input_with_pull).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PullError<E> {
+    /// The port driver reported an error.
+    Driver(E),
+    /// The chip has no such pull resistor.  Nothing was written.
+    Unsupported(Pull),
+}
+
+impl<E> From<E> for PullError<E> {
+    fn from(value: E) -> Self {
+        Self::Driver(value)
+    }
+}
+
+#[diagnostic::on_unimplemented(
+    message = "the port-expander driver `{Self}` does not support pull-down resistors",
+    label = "this chip has no pull-down resistors"
+)]
 pub trait PortDriverPullDown: PortDriver {
     /// Enable pull-downs for pins in mask or set the pin to floating if enable is false.
     fn set_pull_down(&mut self, mask: u32, enable: bool) -> Result<(), Self::Error>;
+
+    /// Make all pins in `mask` inputs with their pull-downs enabled.
+    ///
+    /// The pull-downs are enabled before the direction is changed, so the pins never float.
+    fn set_input_pull_down(&mut self, mask: u32) -> Result<(), Self::Error>
+    where
+        Self: PortDriverTotemPole,
+    {
+        self.set_pull_down(mask, true)?;
+        self.set_directions(mask, 0)
+    }
 }
 
+#[diagnostic::on_unimplemented(
+    message = "the port-expander driver `{Self}` does not support pull-up resistors",
+    label = "this chip has no pull-up resistors"
+)]
 pub trait PortDriverPullUp: PortDriver {
     /// Enable pull-ups for pins in mask or set the pin to floating if enable is false.
     fn set_pull_up(&mut self, mask: u32, enable: bool) -> Result<(), Self::Error>;
+
+    /// Make all pins in `mask` inputs with their pull-ups enabled.
+    ///
+    /// The pull-ups are enabled before the direction is changed, so the pins never float.
+    /// Drivers which keep direction and pull-up in the same register override this to configure
+    /// both at once.
+    fn set_input_pull_up(&mut self, mask: u32) -> Result<(), Self::Error>
+    where
+        Self: PortDriverTotemPole,
+    {
+        self.set_pull_up(mask, true)?;
+        self.set_directions(mask, 0)
+    }
+
+    /// Make all pins in `mask` inputs with the pull resistor `pull`.
+    ///
+    /// The pull resistors are configured before the direction is changed, so an input never
+    /// floats when it shouldn't.  [`Pull::Down`] returns [`PullError::Unsupported`], drivers which
+    /// also implement [`PortDriverPullDown`] override this to support it.
+    fn set_input_pull(&mut self, mask: u32, pull: Pull) -> Result<(), PullError<Self::Error>>
+    where
+        Self: PortDriverTotemPole,
+    {
+        match pull {
+            Pull::None => {
+                self.set_pull_up(mask, false)?;
+                self.set_directions(mask, 0)?;
+            }
+            Pull::Up => self.set_input_pull_up(mask)?,
+            Pull::Down => return Err(PullError::Unsupported(pull)),
+        }
+        Ok(())
+    }
+}
+
+/// [`set_input_pull()`](PortDriverPullUp::set_input_pull) for drivers with pull-up and pull-down
+/// resistors.
+#[cfg(any(feature = "pca9575", feature = "pi4ioe5v6408", feature = "test-util"))]
+pub(crate) fn set_input_pull_up_down<D>(
+    drv: &mut D,
+    mask: u32,
+    pull: Pull,
+) -> Result<(), PullError<D::Error>>
+where
+    D: PortDriverTotemPole + PortDriverPullUp + PortDriverPullDown,
+{
+    match pull {
+        Pull::None => {
+            drv.set_pull_up(mask, false)?;
+            drv.set_pull_down(mask, false)?;
+            drv.set_directions(mask, 0)?;
+        }
+        Pull::Up => drv.set_input_pull_up(mask)?,
+        Pull::Down => drv.set_input_pull_down(mask)?,
+    }
+    Ok(())
+}
+
+#[diagnostic::on_unimplemented(
+    message = "the port-expander driver `{Self}` does not support reading status flags",
+    label = "this chip has no status flags"
+)]
+pub trait PortDriverStatus: PortDriver {
+    /// Read the status flags of the device.
+    fn status(&mut self) -> Result<crate::Status, Self::Error>;
+}
+
+#[diagnostic::on_unimplemented(
+    message = "the port-expander driver `{Self}` does not support configuring interrupts",
+    label = "this chip has no configurable interrupts"
+)]
+pub trait PortDriverInterrupt: PortDriver {
+    /// Configure when the inputs in `mask` raise an interrupt.
+    fn set_interrupt(&mut self, mask: u32, mode: crate::InterruptMode) -> Result<(), Self::Error>;
+}
+
+#[diagnostic::on_unimplemented(
+    message = "the port-expander driver `{Self}` does not support reading the interrupt cause",
+    label = "this chip has no interrupt flag or capture registers"
+)]
+pub trait PortDriverIrqState: PortDriver {
+    /// Read the pins which caused the pending interrupt and the inputs captured with it.
+    fn irq_state(&mut self) -> Result<crate::IrqState, Self::Error>;
+}
+
+pub trait PortDriverResync: PortDriver {
+    /// Bring the cached output state and the device back in line.
+    ///
+    /// Drivers which can read back the output register reload their cache from it.  Drivers for
+    /// chips without a readable output register write the cached state to the device instead.
+    fn resync(&mut self) -> Result<(), Self::Error>;
+
+    /// Flag which is set when a write of the output state failed.
+    ///
+    /// The device may then hold a different output state than the cache.  Drivers set the flag
+    /// in [`PortDriver::set()`] and clear it once a later write brought all outputs of the device
+    /// in line again.
+    fn dirty(&mut self) -> &mut bool;
+}
+
+#[diagnostic::on_unimplemented(
+    message = "the port-expander driver `{Self}` does not support high-impedance outputs",
+    label = "this chip cannot tri-state single outputs"
+)]
+pub trait PortDriverHighImpedance: PortDriver {
+    /// Put the outputs in `mask` into high-impedance state, or let them drive again.
+    fn set_high_impedance(&mut self, mask: u32, enable: bool) -> Result<(), Self::Error>;
+}
+
+#[cfg(any(
+    feature = "ch422",
+    feature = "ch423",
+    feature = "pcal6408a",
+    feature = "pcal6416a"
+))]
+#[diagnostic::on_unimplemented(
+    message = "the port-expander driver `{Self}` does not support switching the output mode",
+    label = "this chip has no configurable output stage"
+)]
+pub trait PortDriverOutputMode: PortDriver {
+    /// Switch the output stage of all outputs of the device to `mode`.
+    fn set_output_mode(&mut self, mode: crate::OutputMode) -> Result<(), Self::Error>;
+}
+
+#[cfg(any(feature = "ch422", feature = "ch423", feature = "max7300"))]
+#[diagnostic::on_unimplemented(
+    message = "the port-expander driver `{Self}` does not support a low-power mode",
+    label = "this chip has no standby mode"
+)]
+pub trait PortDriverPower: PortDriver {
+    /// Put the device into its low-power mode.
+    fn sleep(&mut self) -> Result<(), Self::Error>;
+
+    /// Bring the device back from its low-power mode into normal operation.
+    fn wake(&mut self) -> Result<(), Self::Error>;
+}
+
+cfg_expanders! {
+    #[diagnostic::on_unimplemented(
+        message = "the port-expander driver `{Self}` does not support restoring its power-on defaults",
+        label = "this chip cannot be reset to its power-on configuration"
+    )]
+    pub trait PortDriverRestoreDefaults: PortDriver {
+        /// Put the device back into its power-on configuration.
+        fn restore_defaults(&mut self) -> Result<(), Self::Error>;
+    }
+}
+
+pub trait PortDriverSnapshot: PortDriver {
+    /// Collect the state of the device and the driver for diagnostics.
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error>;
+}
+
+pub trait PortDriverRefresh: PortDriver {
+    /// Write the cached output state of all registers containing pins in `mask` to the device
+    /// again, even if nothing changed.
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error>;
+}
+
+pub trait PortDriverCost: PortDriver {
+    /// Number of bus transactions which `op` needs in the current state of the driver.
+    fn cost(&self, op: crate::Operation) -> u32;
+}
+
+pub trait PortDriverInputState: PortDriver {
+    /// Access the input state of this driver.
+    fn input_state(&mut self) -> &mut InputState;
+}
+
+/// Input-side state which is shared by all drivers.
+///
+/// All drivers pass the values they read from the input registers through this type.  It
+/// implements
+///
+/// - **Snapshot caching**: While caching is enabled, the first read takes a snapshot of all inputs
+///   and all following reads are answered from it.  Caching can be enabled multiple times (e.g. by
+///   nested scopes), the snapshot is only dropped once it was disabled as often as it was enabled.
+/// - **Forced inputs** (with the `force-input` feature): Pins can be forced to report a fixed
+///   state, regardless of their physical input.
+#[derive(Debug, Default)]
+pub struct InputState {
+    depth: usize,
+    snapshot: Option<u32>,
+    #[cfg(feature = "force-input")]
+    forced_mask: u32,
+    #[cfg(feature = "force-input")]
+    forced_high: u32,
+}
+
+impl InputState {
+    pub fn enable_cache(&mut self) {
+        self.depth += 1;
+    }
+
+    pub fn disable_cache(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+        if self.depth == 0 {
+            self.snapshot = None;
+        }
+    }
+
+    /// Drop the current snapshot.  While caching is enabled, the next read takes a new one.
+    pub fn invalidate(&mut self) {
+        self.snapshot = None;
+    }
+
+    /// Force all pins in `mask` to read as `state`, or stop forcing them if `state` is `None`.
+    #[cfg(feature = "force-input")]
+    pub fn force(&mut self, mask: u32, state: Option<bool>) {
+        match state {
+            Some(high) => {
+                self.forced_mask |= mask;
+                if high {
+                    self.forced_high |= mask;
+                } else {
+                    self.forced_high &= !mask;
+                }
+            }
+            None => {
+                self.forced_mask &= !mask;
+                self.forced_high &= !mask;
+            }
+        }
+    }
+
+    #[cfg(feature = "force-input")]
+    fn apply_forced(&self, value: u32) -> u32 {
+        (value & !self.forced_mask) | (self.forced_high & self.forced_mask)
+    }
+
+    #[cfg(not(feature = "force-input"))]
+    fn apply_forced(&self, value: u32) -> u32 {
+        value
+    }
+
+    /// Mask of pins the driver has to read from the device for a read of `mask`.
+    ///
+    /// Returns `None` if the read is answered from the snapshot.
+    pub fn read_mask(&self, mask: u32) -> Option<u32> {
+        if self.depth == 0 {
+            Some(mask)
+        } else if self.snapshot.is_none() {
+            Some(u32::MAX)
+        } else {
+            None
+        }
+    }
+
+    /// Read the input states of the pins in `mask` using `read`.
+    ///
+    /// `read` receives the mask of pins it needs to read.  While caching is enabled, this is the
+    /// mask of all pins and `read` is only called once.
+    pub fn read<E, F>(&mut self, mask: u32, read: F) -> Result<u32, E>
+    where
+        F: FnOnce(u32) -> Result<u32, E>,
+    {
+        let value = if self.depth == 0 {
+            read(mask)?
+        } else {
+            match self.snapshot {
+                Some(snapshot) => snapshot,
+                None => {
+                    let snapshot = read(u32::MAX)?;
+                    self.snapshot = Some(snapshot);
+                    snapshot
+                }
+            }
+        };
+        Ok(self.apply_forced(value))
+    }
+}
+
+cfg_drivers! {
+    pub trait PortDriverClaims: PortDriver {
+        /// Access the pins of this driver which were handed out at runtime.
+        fn claims(&mut self) -> &mut PinClaims;
+    }
+
+    /// Pins which were handed out at runtime, e.g. by
+    /// [`Pca9555::take_pin()`](crate::Pca9555::take_pin).
+    #[derive(Debug, Default)]
+    pub struct PinClaims {
+        claimed: u32,
+    }
+
+    impl PinClaims {
+        /// Claim all pins in `mask`.
+        ///
+        /// Returns `false` without claiming anything if one of them was already claimed.
+        pub fn claim(&mut self, mask: u32) -> bool {
+            if self.claimed & mask != 0 {
+                return false;
+            }
+            self.claimed |= mask;
+            true
+        }
+    }
+}
+
+pub trait PortDriverOutputInversion: PortDriver {
+    /// Access the output inversion of this driver.
+    fn output_inversion(&mut self) -> &mut OutputInversion;
+}
+
+/// Output inversion which is shared by all drivers.
+///
+/// For pins with inverted outputs, the meaning of HIGH and LOW is swapped in
+/// [`PortDriver::set()`] and [`PortDriver::is_set()`].  Drivers apply this before touching their
+/// output state.
+#[derive(Debug, Default)]
+pub struct OutputInversion {
+    inverted: u32,
+}
+
+impl OutputInversion {
+    /// Invert the outputs of all pins in `mask` or not.
+    pub fn set(&mut self, mask: u32, inverted: bool) {
+        if inverted {
+            self.inverted |= mask;
+        } else {
+            self.inverted &= !mask;
+        }
+    }
+
+    /// Translate logical `(mask_high, mask_low)` into electrical `(mask_high, mask_low)`.
+    pub fn apply(&self, mask_high: u32, mask_low: u32) -> (u32, u32) {
+        (
+            (mask_high & !self.inverted) | (mask_low & self.inverted),
+            (mask_low & !self.inverted) | (mask_high & self.inverted),
+        )
+    }
 }
 
 /// Pin Modes
+///
+/// The mode traits are sealed, only the modes of this crate implement them.  Using a pin in a way
+/// its mode or its chip does not allow is rejected at compile time, e.g. enabling the pull-down of
+/// a `PCA9555` pin which has no pull-down resistor:
+///
+/// ```compile_fail
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// let mut pca = port_expander::Pca9555::new(i2c, false, false, false);
+/// let pca_pins = pca.split();
+/// let io0_0 = pca_pins.io0_0.into_pull_down();
+/// ```
 pub mod mode {
+    pub(crate) mod sealed {
+        pub trait Sealed {}
+    }
+
     /// Trait for pin-modes which can be used to set a logic level.
-    pub trait HasOutput {}
+    #[diagnostic::on_unimplemented(
+        message = "pins in mode `{Self}` cannot set a logic level",
+        label = "this pin is not an output",
+        note = "turn the pin into an output first, e.g. with `into_output()`"
+    )]
+    pub trait HasOutput: sealed::Sealed {}
     /// Trait for pin-modes which can be used to read a logic level.
-    pub trait HasInput {}
+    #[diagnostic::on_unimplemented(
+        message = "pins in mode `{Self}` cannot read a logic level",
+        label = "this pin is not an input",
+        note = "turn the pin into an input first, e.g. with `into_input()`"
+    )]
+    pub trait HasInput: sealed::Sealed {}
+    /// Trait for pin-modes which may change the configuration of their pin, e.g. its direction or
+    /// pull resistors.
+    #[diagnostic::on_unimplemented(
+        message = "pins in mode `{Self}` cannot change their configuration",
+        label = "this pin is a read-only view",
+        note = "only the pin returned by `split()` can be reconfigured"
+    )]
+    pub trait Configurable: sealed::Sealed {}
 
     /// Pin configured as an input.
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     pub struct Input;
+    impl sealed::Sealed for Input {}
     impl HasInput for Input {}
+    impl Configurable for Input {}
 
     /// Pin configured as an output.
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     pub struct Output;
+    impl sealed::Sealed for Output {}
     impl HasOutput for Output {}
+    impl Configurable for Output {}
+
+    /// Pin of a device which only has inputs.
+    ///
+    /// Unlike [`Input`], pins in this mode can never be turned into outputs.
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct InputOnly;
+    impl sealed::Sealed for InputOnly {}
+    impl HasInput for InputOnly {}
+    impl Configurable for InputOnly {}
+
+    /// Pin of a device which only has outputs.
+    ///
+    /// Unlike [`Output`], pins in this mode can never be turned into inputs.
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct OutputOnly;
+    impl sealed::Sealed for OutputOnly {}
+    impl HasOutput for OutputOnly {}
+    impl Configurable for OutputOnly {}
 
     /// Pin configured as a quasi-bidirectional input/output.
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     pub struct QuasiBidirectional;
+    impl sealed::Sealed for QuasiBidirectional {}
     impl HasInput for QuasiBidirectional {}
     impl HasOutput for QuasiBidirectional {}
+    impl Configurable for QuasiBidirectional {}
+
+    /// Read-only view of a pin, see [`Pin::read_only()`](crate::Pin::read_only).
+    ///
+    /// Pins in this mode can only read their input state.  They cannot change the direction,
+    /// output state, or any other configuration of the pin:
+    ///
+    /// ```compile_fail
+    /// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+    /// let pca = port_expander::Pca9555::new(i2c, false, false, false);
+    /// let [p0, ..] = pca.split_ref();
+    /// let p0 = p0.into_output();
+    /// ```
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct ReadOnly;
+    impl sealed::Sealed for ReadOnly {}
+    impl HasInput for ReadOnly {}
 }
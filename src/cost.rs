@@ -0,0 +1,104 @@
+/// An operation on the pins of a port-expander, see [`cost_of()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Operation {
+    /// Drive the pins in `mask_high` HIGH and the pins in `mask_low` LOW, e.g. with
+    /// [`write_multiple()`](crate::write_multiple) or [`Pin::set_high()`](crate::Pin::set_high).
+    Set { mask_high: u32, mask_low: u32 },
+    /// Read the input state of the pins in `mask`, e.g. with
+    /// [`read_multiple()`](crate::read_multiple) or [`Pin::is_high()`](crate::Pin::is_high).
+    Get { mask: u32 },
+    /// Make the pins in `mask_input` inputs and the pins in `mask_output` outputs, without
+    /// touching their output state.
+    SetDirections { mask_input: u32, mask_output: u32 },
+}
+
+/// Number of bus transactions an operation on a port-expander will need.
+///
+/// This allows real-time schedulers to budget bus time before accessing a port-expander.  The
+/// result takes the current state of the driver into account, e.g. an output register which still
+/// needs to be read before its first write, outputs which are already in the requested state, or
+/// inputs which are answered from a [`cached_inputs_for()`](crate::cached_inputs_for) snapshot.
+/// It is only valid until the next access to the port-expander.
+///
+/// A transaction is one I2C transfer (which can be a combined write and read) or one SPI
+/// transaction.  Direction changes on chips without direction registers need no transactions.
+///
+/// `pin` can be any pin of the port-expander, it is only used to find the chip.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pca9555 = port_expander::Pca9555::new(i2c, false, false, false);
+/// # let p = pca9555.split();
+/// use port_expander::Operation;
+///
+/// // reading both banks needs two transactions
+/// let mask = p.io0_0.pin_mask() | p.io1_0.pin_mask();
+/// assert_eq!(port_expander::cost_of(&p.io0_0, Operation::Get { mask }), 2);
+/// ```
+pub fn cost_of<PD, MUTEX, MODE>(pin: &crate::Pin<'_, MODE, MUTEX>, op: Operation) -> u32
+where
+    PD: crate::PortDriver + crate::PortDriverCost,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    pin.port_driver().lock(|drv| drv.cost(op))
+}
+
+/// Number of 8-bit banks of a 16-bit port-expander which have pins in `mask`.
+pub(crate) fn banks16(mask: u32) -> u32 {
+    (mask & 0x00ff != 0) as u32 + (mask & 0xff00 != 0) as u32
+}
+
+#[cfg(all(test, feature = "pcal6416a"))]
+mod tests {
+    use super::Operation;
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pcal6416a_cost() {
+        let expectations = [
+            // output register is read before the first write
+            mock_i2c::Transaction::write_read(0x20, vec![0x02], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x03], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0xfe]),
+            // cached inputs, read both banks once
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x00]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0x00]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcal = crate::Pcal6416a::new(bus.clone(), false);
+        let pcal_pins = pcal.split();
+        let io0_0 = pcal_pins.io0_0.pin_mask();
+        let io1_0 = pcal_pins.io1_0.pin_mask();
+
+        let set = Operation::Set {
+            mask_high: 0,
+            mask_low: io0_0,
+        };
+        assert_eq!(crate::cost_of(&pcal_pins.io1_0, set), 3);
+        let _io0_0 = pcal_pins.io0_0.into_output().unwrap();
+        assert_eq!(crate::cost_of(&pcal_pins.io1_0, set), 1);
+
+        let dirs = Operation::SetDirections {
+            mask_input: io0_0,
+            mask_output: io1_0,
+        };
+        assert_eq!(crate::cost_of(&pcal_pins.io1_0, dirs), 4);
+
+        let get = Operation::Get { mask: io1_0 };
+        assert_eq!(crate::cost_of(&pcal_pins.io1_0, get), 1);
+        {
+            let _cache = crate::cached_inputs_for(&pcal_pins.io1_0);
+            assert_eq!(crate::cost_of(&pcal_pins.io1_0, get), 2);
+            assert!(pcal_pins.io1_0.is_low().unwrap());
+            assert_eq!(crate::cost_of(&pcal_pins.io1_0, get), 0);
+        }
+
+        bus.done();
+    }
+}
@@ -0,0 +1,225 @@
+/// Define a driver module for a 16-bit port-expander with a `PCA9555`-style register map.
+///
+/// Many port-expanders only differ from the chips supported by this crate in their register
+/// addresses and I2C address range.  `define_expander!()` generates a device module for such a
+/// chip outside of this crate, like the ones in [`dev`](crate::dev): the device struct with
+/// `new()`, `with_mutex()`, `with_address()`, `with_address_unchecked()`, `split()` and `pins()`,
+/// the `Parts` struct with the given pin names, and a `Driver` which implements the port driver
+/// traits, so the pins support everything pins of a `PCA9555` do.
+///
+/// The chip must have an input, an output, a polarity inversion and a configuration register,
+/// each with one address for port 0 and one for port 1.  A 1 bit in the configuration register
+/// makes a pin an input.  Exactly 16 pin names are needed, the first one is bit 0 of port 0.
+///
+/// The I2C address is the base address with the address pins added as bits, the first address
+/// pin being bit 0.  These pins become the `bool` parameters of `new()`, `with_address()` accepts
+/// the resulting range of addresses.  `output_reset` is the content of the output register after
+/// power-up, or `None` to read it from the chip before the first write.
+///
+/// ## Example
+/// ```
+/// # use embedded_hal_mock::eh1::i2c as mock_i2c;
+/// port_expander::define_expander! {
+///     /// Support for the `XYZ1616` port-expander.
+///     pub mod xyz1616 {
+///         /// `XYZ1616` "16-bit I/O expander"
+///         struct Xyz1616;
+///
+///         address: 0x40, [a0, a1];
+///         registers: {
+///             input: [0x00, 0x01],
+///             output: [0x02, 0x03],
+///             polarity_inversion: [0x04, 0x05],
+///             configuration: [0x06, 0x07],
+///             output_reset: Some(0x0000),
+///         }
+///         pins: [
+///             p0_0, p0_1, p0_2, p0_3, p0_4, p0_5, p0_6, p0_7,
+///             p1_0, p1_1, p1_2, p1_3, p1_4, p1_5, p1_6, p1_7,
+///         ];
+///     }
+/// }
+///
+/// # let i2c = mock_i2c::Mock::new(&[
+/// #     mock_i2c::Transaction::write(0x41, vec![0x03, 0x01]),
+/// #     mock_i2c::Transaction::write_read(0x41, vec![0x07], vec![0xff]),
+/// #     mock_i2c::Transaction::write(0x41, vec![0x07, 0xfe]),
+/// # ]);
+/// # let mut i2c_ = i2c.clone();
+/// let mut xyz = xyz1616::Xyz1616::new(i2c, true, false);
+/// let xyz_pins = xyz.split();
+/// let led = xyz_pins.p1_0.into_output_high().unwrap();
+/// # i2c_.done();
+/// ```
+#[macro_export]
+macro_rules! define_expander {
+    (
+        $(#[$meta:meta])*
+        $vis:vis mod $module:ident {
+            $(#[$dmeta:meta])*
+            struct $name:ident;
+
+            address: $base:literal, [$($abit:ident),* $(,)?];
+            registers: {
+                input: [$in0:expr, $in1:expr],
+                output: [$out0:expr, $out1:expr],
+                polarity_inversion: [$pol0:expr, $pol1:expr],
+                configuration: [$cfg0:expr, $cfg1:expr],
+                output_reset: $reset:expr $(,)?
+            }
+            pins: [$($pin:ident),* $(,)?];
+        }
+    ) => {
+        $(#[$meta])*
+        $vis mod $module {
+            /// Register map of the device.
+            pub struct Registers;
+
+            impl $crate::__macro::Regs16 for Registers {
+                const INPUT: [u8; 2] = [$in0, $in1];
+                const OUTPUT: [u8; 2] = [$out0, $out1];
+                const POLARITY_INVERSION: [u8; 2] = [$pol0, $pol1];
+                const CONFIGURATION: [u8; 2] = [$cfg0, $cfg1];
+                const OUTPUT_RESET: Option<u16> = $reset;
+            }
+
+            /// Driver of the device.
+            pub type Driver<I2C> = $crate::__macro::Driver16<I2C, Registers>;
+
+            /// Addresses of the device, with all address pins LOW and HIGH.
+            const ADDRESSES: core::ops::RangeInclusive<u8> =
+                $base..=$base + ((1 << [$(stringify!($abit)),*].len()) - 1);
+
+            $(#[$dmeta])*
+            pub struct $name<M>(M);
+
+            impl<I2C> $name<core::cell::RefCell<Driver<I2C>>>
+            where
+                I2C: $crate::__macro::RegisterBus,
+            {
+                /// Create a new instance, with the levels of the address pins.
+                pub fn new(i2c: I2C, $($abit: bool),*) -> Self {
+                    Self::with_mutex(i2c, $($abit),*)
+                }
+            }
+
+            impl<I2C, M> $name<M>
+            where
+                I2C: $crate::__macro::RegisterBus,
+                M: $crate::PortMutex<Port = Driver<I2C>>,
+            {
+                /// Same as `new()`, but with the mutex type `M`.
+                pub fn with_mutex(i2c: I2C, $($abit: bool),*) -> Self {
+                    let addr = [$($abit),*]
+                        .iter()
+                        .enumerate()
+                        .fold($base, |addr, (i, &bit)| addr | (bit as u8) << i);
+                    Self($crate::PortMutex::create($crate::__macro::driver16(
+                        i2c,
+                        addr,
+                        $crate::AddressCheck::Checked,
+                    )))
+                }
+
+                /// Create a new instance with an explicit I2C address.
+                ///
+                /// Returns an error if `addr` is not one of the addresses selectable with the
+                /// address pins.
+                pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, $crate::InvalidAddress> {
+                    let addr = $crate::__macro::validate_address(addr, ADDRESSES)?;
+                    Ok(Self($crate::PortMutex::create($crate::__macro::driver16(
+                        i2c,
+                        addr,
+                        $crate::AddressCheck::Checked,
+                    ))))
+                }
+
+                /// Create a new instance with an explicit I2C address which is not validated.
+                pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+                    Self($crate::PortMutex::create($crate::__macro::driver16(
+                        i2c,
+                        addr,
+                        $crate::AddressCheck::Unchecked,
+                    )))
+                }
+
+                /// Split the device into its pins.
+                pub fn split(&mut self) -> Parts<'_, I2C, M> {
+                    let [$($pin),*] = self.pins();
+                    Parts { $($pin),* }
+                }
+
+                /// Get access to all pins as an array instead of named fields.
+                ///
+                /// Element `n` of the array is the same pin as the `n`-th field of `Parts`.
+                pub fn pins(&mut self) -> [$crate::Pin<'_, $crate::mode::Input, M>; 16] {
+                    core::array::from_fn(|i| $crate::__macro::pin(i as u8, &self.0))
+                }
+            }
+
+            /// Pins of the device.
+            pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
+            where
+                I2C: $crate::__macro::RegisterBus,
+                M: $crate::PortMutex<Port = Driver<I2C>>,
+            {
+                $(
+                    #[allow(missing_docs)]
+                    pub $pin: $crate::Pin<'a, $crate::mode::Input, M>,
+                )*
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    // the register map of the `PCA9555` at a different address range
+    crate::define_expander! {
+        #[allow(dead_code)]
+        mod custom {
+            struct Custom;
+
+            address: 0x30, [a0, a1, a2];
+            registers: {
+                input: [0x00, 0x01],
+                output: [0x02, 0x03],
+                polarity_inversion: [0x04, 0x05],
+                configuration: [0x06, 0x07],
+                output_reset: None,
+            }
+            pins: [
+                io0_0, io0_1, io0_2, io0_3, io0_4, io0_5, io0_6, io0_7,
+                io1_0, io1_1, io1_2, io1_3, io1_4, io1_5, io1_6, io1_7,
+            ];
+        }
+    }
+
+    #[test]
+    fn custom_expander() {
+        let expectations = [
+            // output register is read before the first write
+            mock_i2c::Transaction::write_read(0x35, vec![0x02], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x35, vec![0x03], vec![0xff]),
+            mock_i2c::Transaction::write(0x35, vec![0x03, 0x7f]),
+            mock_i2c::Transaction::write_read(0x35, vec![0x07], vec![0xff]),
+            mock_i2c::Transaction::write(0x35, vec![0x07, 0x7f]),
+            mock_i2c::Transaction::write_read(0x35, vec![0x00], vec![0x04]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut dev = custom::Custom::new(bus.clone(), true, false, true);
+        let pins = dev.split();
+
+        let _io1_7 = pins.io1_7.into_output().unwrap();
+        assert!(pins.io0_2.is_high().unwrap());
+
+        type Custom<I2C> = custom::Custom<core::cell::RefCell<custom::Driver<I2C>>>;
+        assert!(Custom::with_address(bus.clone(), 0x37).is_ok());
+        assert!(Custom::with_address(bus.clone(), 0x38).is_err());
+
+        bus.done();
+    }
+}
@@ -0,0 +1,831 @@
+//! Support for the `AW9523B` "16 multi-function LED driver and GPIO controller with I2C interface"
+//!
+//! Each of the 16 pins can either be used as a GPIO or as a constant-current LED driver.  In GPIO
+//! mode, the pins work like on any other totem-pole port-expander.  Pins can be switched into LED
+//! mode using [`Pin::into_led()`](crate::Pin::into_led) after which their current can be set with
+//! [`Pin::set_led_current()`](crate::Pin::set_led_current).  Switching the direction of a pin in
+//! LED mode puts it back into GPIO mode.
+//!
+//! Note that port 0 is configured as open-drain after reset.  Use
+//! [`Driver::set_port0_push_pull()`] to switch it to push-pull outputs.
+//!
+//! ## Board presets
+//! Some boards use the `AW9523B` to drive reset and enable lines which must never float.  The
+//! generic constructors briefly turn all pins into inputs, so these boards have their own
+//! constructors which bring the chip into the board's configuration without such a glitch:
+//!
+//! - M5Stack CoreS3: [`Aw9523b::new_m5stack_cores3()`] and
+//!   [`split_m5stack_cores3()`](Aw9523b::split_m5stack_cores3)
+
+/// `AW9523B` "16 multi-function LED driver and GPIO controller with I2C interface"
+pub struct Aw9523b<M>(M);
+
+impl<I2C> Aw9523b<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Create a new driver for the `AW9523B`.
+    ///
+    /// All pins will be configured as GPIO inputs with interrupts disabled.
+    pub fn new(i2c: I2C, ad0: bool, ad1: bool) -> Result<Self, I2C::BusError> {
+        Self::with_mutex(i2c, ad0, ad1)
+    }
+
+    /// Create a new driver for the `AW9523B` on the M5Stack CoreS3.
+    ///
+    /// See [`Driver::new_m5stack_cores3()`] for the configuration which is applied.  Use
+    /// [`split_m5stack_cores3()`](Aw9523b::split_m5stack_cores3) to get the pins in their
+    /// matching modes.
+    pub fn new_m5stack_cores3(i2c: I2C) -> Result<Self, I2C::BusError> {
+        Self::with_mutex_m5stack_cores3(i2c)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Aw9523b<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Aw9523b::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, ad0: bool, ad1: bool) -> Result<Self, I2C::BusError> {
+        Self::with_mutex(i2c, ad0, ad1)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Aw9523b<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Aw9523b::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, ad0: bool, ad1: bool) -> Result<Self, I2C::BusError> {
+        Self::with_mutex(i2c, ad0, ad1)
+    }
+}
+
+impl<I2C, M> Aw9523b<M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    /// Create a new driver for the `AW9523B` with a mutex.
+    ///
+    /// All pins will be configured as GPIO inputs with interrupts disabled.
+    pub fn with_mutex(i2c: I2C, ad0: bool, ad1: bool) -> Result<Self, I2C::BusError> {
+        Ok(Self(crate::PortMutex::create(Driver::new(i2c, ad0, ad1)?)))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Result<Self, I2C::BusError> {
+        let driver = Driver::with_address(i2c, addr.get(), crate::AddressCheck::Checked)?;
+        Ok(Self(crate::PortMutex::create(driver)))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x58` to `0x5b`)
+    /// or if setting up the chip fails.
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::AddressError<I2C::BusError>> {
+        let addr = Addr::custom(addr)?;
+        Self::from_addr(i2c, addr).map_err(crate::AddressError::Bus)
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Result<Self, I2C::BusError> {
+        Ok(Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )?)))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
+    /// Same as [`new_m5stack_cores3()`](Aw9523b::new_m5stack_cores3), but with a mutex.
+    pub fn with_mutex_m5stack_cores3(i2c: I2C) -> Result<Self, I2C::BusError> {
+        Ok(Self(crate::PortMutex::create(Driver::new_m5stack_cores3(
+            i2c,
+        )?)))
+    }
+
+    /// Split the pins of a chip created with
+    /// [`new_m5stack_cores3()`](Aw9523b::new_m5stack_cores3).
+    ///
+    /// Unlike [`split()`](Aw9523b::split), the pins are handed out in the modes the board preset
+    /// configured them for.
+    pub fn split_m5stack_cores3(&mut self) -> M5StackCoreS3Parts<'_, I2C, M> {
+        let p = self.split();
+        M5StackCoreS3Parts {
+            touch_rst: p.io0_0.into_mode(),
+            bus_out_en: p.io0_1.into_mode(),
+            aw88298_rst: p.io0_2.into_mode(),
+            io0_3: p.io0_3,
+            io0_4: p.io0_4,
+            io0_5: p.io0_5.into_mode(),
+            io0_6: p.io0_6.into_mode(),
+            io0_7: p.io0_7.into_mode(),
+            cam_rst: p.io1_0.into_mode(),
+            lcd_rst: p.io1_1.into_mode(),
+            touch_int: p.io1_2,
+            aw88298_int: p.io1_3,
+            io1_4: p.io1_4.into_mode(),
+            io1_5: p.io1_5.into_mode(),
+            io1_6: p.io1_6.into_mode(),
+            boost_en: p.io1_7.into_mode(),
+        }
+    }
+
+    pub fn split(&mut self) -> Parts<'_, I2C, M> {
+        Parts {
+            io0_0: crate::Pin::new(0, &self.0),
+            io0_1: crate::Pin::new(1, &self.0),
+            io0_2: crate::Pin::new(2, &self.0),
+            io0_3: crate::Pin::new(3, &self.0),
+            io0_4: crate::Pin::new(4, &self.0),
+            io0_5: crate::Pin::new(5, &self.0),
+            io0_6: crate::Pin::new(6, &self.0),
+            io0_7: crate::Pin::new(7, &self.0),
+            io1_0: crate::Pin::new(8, &self.0),
+            io1_1: crate::Pin::new(9, &self.0),
+            io1_2: crate::Pin::new(10, &self.0),
+            io1_3: crate::Pin::new(11, &self.0),
+            io1_4: crate::Pin::new(12, &self.0),
+            io1_5: crate::Pin::new(13, &self.0),
+            io1_6: crate::Pin::new(14, &self.0),
+            io1_7: crate::Pin::new(15, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 16,
+        error: I2C::BusError,
+    }
+
+    /// Reset the chip, turn all pins into inputs and release it.
+    ///
+    /// This issues a software reset and then makes all pins GPIO inputs with interrupts disabled,
+    /// the same state `new()` leaves the chip in.  LED mode and currents and the port 0
+    /// push-pull setting are reset as well.  Consuming the device requires that all of its pins
+    /// were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub io0_0: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_1: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_2: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_3: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_4: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_5: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_6: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_7: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_0: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_1: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_2: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_3: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_4: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_5: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_6: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_7: crate::Pin<'a, crate::mode::Input, M>,
+}
+
+/// Pins of the `AW9523B` on the M5Stack CoreS3, as configured by
+/// [`new_m5stack_cores3()`](Aw9523b::new_m5stack_cores3).
+///
+/// Pins with a known function on the board are named after it.  The others keep the names used
+/// in [`Parts`].
+pub struct M5StackCoreS3Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    /// `P0_0`: Reset of the touch controller (active low)
+    pub touch_rst: crate::Pin<'a, crate::mode::Output, M>,
+    /// `P0_1`: Enables the 5V output on the bus connectors
+    pub bus_out_en: crate::Pin<'a, crate::mode::Output, M>,
+    /// `P0_2`: Reset of the `AW88298` speaker amplifier (active low)
+    pub aw88298_rst: crate::Pin<'a, crate::mode::Output, M>,
+    pub io0_3: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_4: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_5: crate::Pin<'a, crate::mode::Output, M>,
+    pub io0_6: crate::Pin<'a, crate::mode::Output, M>,
+    pub io0_7: crate::Pin<'a, crate::mode::Output, M>,
+    /// `P1_0`: Reset of the camera (active low)
+    pub cam_rst: crate::Pin<'a, crate::mode::Output, M>,
+    /// `P1_1`: Reset of the LCD controller (active low)
+    pub lcd_rst: crate::Pin<'a, crate::mode::Output, M>,
+    /// `P1_2`: Interrupt of the touch controller
+    pub touch_int: crate::Pin<'a, crate::mode::Input, M>,
+    /// `P1_3`: Interrupt of the `AW88298` speaker amplifier
+    pub aw88298_int: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_4: crate::Pin<'a, crate::mode::Output, M>,
+    pub io1_5: crate::Pin<'a, crate::mode::Output, M>,
+    pub io1_6: crate::Pin<'a, crate::mode::Output, M>,
+    /// `P1_7`: Enables the boost converter for the 5V rail
+    pub boost_en: crate::Pin<'a, crate::mode::Output, M>,
+}
+
+/// Pin configured as a constant-current LED driver.
+pub struct Led;
+impl crate::mode::sealed::Sealed for Led {}
+impl crate::mode::Configurable for Led {}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Regs {
+    InputPort0 = 0x00,
+    InputPort1 = 0x01,
+    OutputPort0 = 0x02,
+    OutputPort1 = 0x03,
+    Configuration0 = 0x04,
+    Configuration1 = 0x05,
+    InterruptEnable0 = 0x06,
+    InterruptEnable1 = 0x07,
+    Id = 0x10,
+    /// Bit 4: Port 0 open-drain (0) or push-pull (1), Bits 1-0: LED current range
+    Control = 0x11,
+    /// 0: LED mode, 1: GPIO mode
+    LedModeSwitch0 = 0x12,
+    LedModeSwitch1 = 0x13,
+    /// First of the 16 LED current control registers (`DIM0` .. `DIM15`)
+    Dim0 = 0x20,
+    SoftwareReset = 0x7f,
+}
+
+impl From<Regs> for u8 {
+    fn from(r: Regs) -> u8 {
+        r as u8
+    }
+}
+
+const DEVICE_ID: u8 = 0x23;
+
+crate::address::addr_type! {
+    /// I2C address of the `AW9523B`.
+    pub struct Addr: 0x58, [ad0 = 0, ad1 = 1], 0x58..=0x5b;
+}
+
+pub struct Driver<I2C> {
+    i2c: I2C,
+    addr: u8,
+    address_check: crate::AddressCheck,
+    out: Option<u16>,
+    dirty: bool,
+    led: u16,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
+}
+
+impl<I2C: crate::RegisterBus> Driver<I2C> {
+    pub fn new(i2c: I2C, ad0: bool, ad1: bool) -> Result<Self, I2C::BusError> {
+        let addr = Addr::from_pins(ad0, ad1).get();
+        Self::with_address(i2c, addr, crate::AddressCheck::Checked)
+    }
+
+    pub(crate) fn with_address(
+        mut i2c: I2C,
+        addr: u8,
+        address_check: crate::AddressCheck,
+    ) -> Result<Self, I2C::BusError> {
+        Self::check_id(&mut i2c, addr)?;
+
+        // After reset, all pins are GPIO outputs with interrupts enabled.  Turn them into inputs
+        // and disable the interrupts to match the state of all other port-expanders.
+        i2c.write_reg(addr, Regs::Configuration0, 0xff)?;
+        i2c.write_reg(addr, Regs::Configuration1, 0xff)?;
+        i2c.write_reg(addr, Regs::InterruptEnable0, 0xff)?;
+        i2c.write_reg(addr, Regs::InterruptEnable1, 0xff)?;
+
+        Ok(Self {
+            i2c,
+            addr,
+            address_check,
+            out: None,
+            dirty: false,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
+            led: 0x0000,
+        })
+    }
+
+    /// Create a driver for the `AW9523B` on the M5Stack CoreS3 (address `0x58`).
+    ///
+    /// The board uses most pins as reset and enable lines, so the chip is brought into the
+    /// board's configuration without ever letting an output float:
+    ///
+    /// 1. The output registers are written first: `P0_0`-`P0_2`, `P1_0`, `P1_1` and `P1_7` are
+    ///    HIGH, everything else is LOW.  This releases all resets and enables the bus and boost
+    ///    supplies.
+    /// 2. Port 0 is switched to push-pull outputs.
+    /// 3. All pins are put into GPIO mode.
+    /// 4. `P0_3`, `P0_4`, `P1_2` and `P1_3` become inputs, all other pins stay outputs.
+    /// 5. Interrupts are disabled.
+    pub fn new_m5stack_cores3(mut i2c: I2C) -> Result<Self, I2C::BusError> {
+        const OUTPUT: u16 = 0b10000011_00000111;
+        const CONFIGURATION: u16 = 0b00001100_00011000;
+
+        let addr = 0x58;
+        Self::check_id(&mut i2c, addr)?;
+
+        i2c.write_reg(addr, Regs::OutputPort0, OUTPUT as u8)?;
+        i2c.write_reg(addr, Regs::OutputPort1, (OUTPUT >> 8) as u8)?;
+        i2c.update_reg(addr, Regs::Control, 1 << 4, 0)?;
+        i2c.write_reg(addr, Regs::LedModeSwitch0, 0xff)?;
+        i2c.write_reg(addr, Regs::LedModeSwitch1, 0xff)?;
+        i2c.write_reg(addr, Regs::Configuration0, CONFIGURATION as u8)?;
+        i2c.write_reg(addr, Regs::Configuration1, (CONFIGURATION >> 8) as u8)?;
+        i2c.write_reg(addr, Regs::InterruptEnable0, 0xff)?;
+        i2c.write_reg(addr, Regs::InterruptEnable1, 0xff)?;
+
+        Ok(Self {
+            i2c,
+            addr,
+            address_check: crate::AddressCheck::Checked,
+            out: Some(OUTPUT),
+            dirty: false,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
+            led: 0x0000,
+        })
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
+
+    fn check_id(i2c: &mut I2C, addr: u8) -> Result<(), I2C::BusError> {
+        let device_id = i2c.read_reg(addr, Regs::Id)?;
+        assert_eq!(
+            device_id, DEVICE_ID,
+            "Unexpected Device ID for the AW9523B: 0x{:02x}",
+            device_id
+        );
+        Ok(())
+    }
+
+    fn get_out(&mut self) -> Result<u16, I2C::BusError> {
+        // The reset value of the output registers depends on the address pins, so read it once
+        // instead of assuming a value.
+        match self.out {
+            Some(out) => Ok(out),
+            None => {
+                let out_low = self.i2c.read_reg(self.addr, Regs::OutputPort0)? as u16;
+                let out_high = self.i2c.read_reg(self.addr, Regs::OutputPort1)? as u16;
+                let out = out_low | (out_high << 8);
+                self.out = Some(out);
+                Ok(out)
+            }
+        }
+    }
+
+    /// Switch all pins in `mask` into LED mode (`true`) or back into GPIO mode (`false`).
+    pub fn set_led_mode(&mut self, mask: u32, enable: bool) -> Result<(), I2C::BusError> {
+        // LedModeSwitch bits are 0 for LED mode and 1 for GPIO mode
+        let (mask_set, mask_clear) = match enable {
+            true => (0, mask as u16),
+            false => (mask as u16, 0),
+        };
+        if mask & 0x00FF != 0 {
+            self.i2c.update_reg(
+                self.addr,
+                Regs::LedModeSwitch0,
+                (mask_set & 0xFF) as u8,
+                (mask_clear & 0xFF) as u8,
+            )?;
+        }
+        if mask & 0xFF00 != 0 {
+            self.i2c.update_reg(
+                self.addr,
+                Regs::LedModeSwitch1,
+                (mask_set >> 8) as u8,
+                (mask_clear >> 8) as u8,
+            )?;
+        }
+        if enable {
+            self.led |= mask as u16;
+        } else {
+            self.led &= !mask as u16;
+        }
+        Ok(())
+    }
+
+    /// Set the LED current for pin number `pin` (0-15) in 256 steps.
+    pub fn set_led_current(&mut self, pin: u8, current: u8) -> Result<(), I2C::BusError> {
+        assert!(pin < 16);
+        // The DIM registers are ordered P1_0..P1_3, P0_0..P0_7, P1_4..P1_7.
+        let offset = match pin {
+            0..=7 => pin + 4,
+            8..=11 => pin - 8,
+            _ => pin,
+        };
+        self.i2c
+            .write_reg(self.addr, u8::from(Regs::Dim0) + offset, current)
+    }
+
+    /// Configure port 0 for push-pull (`true`) or open-drain (`false`) outputs.
+    ///
+    /// Port 1 always uses push-pull outputs.
+    pub fn set_port0_push_pull(&mut self, push_pull: bool) -> Result<(), I2C::BusError> {
+        let (mask_set, mask_clear) = match push_pull {
+            true => (1 << 4, 0),
+            false => (0, 1 << 4),
+        };
+        self.i2c
+            .update_reg(self.addr, Regs::Control, mask_set, mask_clear)
+    }
+
+    /// Perform a software reset, putting the chip back into its power-on state.
+    ///
+    /// Note that after the reset, all pins are outputs again.
+    pub fn software_reset(&mut self) -> Result<(), I2C::BusError> {
+        self.out = None;
+        self.led = 0x0000;
+        self.i2c.write_reg(self.addr, Regs::SoftwareReset, 0x00)
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriver for Driver<I2C> {
+    type Error = I2C::BusError;
+
+    fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let mut out = self.get_out()?;
+        out |= mask_high as u16;
+        out &= !mask_low as u16;
+        self.out = Some(out);
+        // a failed write leaves the device in an unknown state, so all banks are written until a
+        // write succeeds again
+        let mask = match self.dirty {
+            true => 0xFFFF,
+            false => mask_high | mask_low,
+        };
+        self.dirty = true;
+        if mask & 0x00FF != 0 {
+            self.i2c
+                .write_reg(self.addr, Regs::OutputPort0, (out & 0xFF) as u8)?;
+        }
+        if mask & 0xFF00 != 0 {
+            self.i2c
+                .write_reg(self.addr, Regs::OutputPort1, (out >> 8) as u8)?;
+        }
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let out = self.get_out()?;
+        Ok(((out as u32) & mask_high) | (!(out as u32) & mask_low))
+    }
+
+    fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let in_ = self.input_state.read(mask_high | mask_low, |mask| {
+            let io0 = if mask & 0x00FF != 0 {
+                self.i2c.read_reg(self.addr, Regs::InputPort0)?
+            } else {
+                0
+            };
+            let io1 = if mask & 0xFF00 != 0 {
+                self.i2c.read_reg(self.addr, Regs::InputPort1)?
+            } else {
+                0
+            };
+            Ok::<_, Self::Error>(((io1 as u32) << 8) | io0 as u32)
+        })?;
+        Ok((in_ & mask_high) | (!in_ & mask_low))
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverInputState for Driver<I2C> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverOutputInversion for Driver<I2C> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverClaims for Driver<I2C> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverCost for Driver<I2C> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set {
+                mask_high,
+                mask_low,
+            } => {
+                let read_out = if self.out.is_none() { 2 } else { 0 };
+                read_out + crate::cost::banks16(mask_high | mask_low)
+            }
+            crate::Operation::Get { mask } => self
+                .input_state
+                .read_mask(mask)
+                .map_or(0, crate::cost::banks16),
+            crate::Operation::SetDirections {
+                mask_input,
+                mask_output,
+            } => {
+                let mask = mask_input | mask_output;
+                let led = mask & self.led as u32;
+                2 * crate::cost::banks16(led) + 2 * crate::cost::banks16(mask)
+            }
+        }
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverResync for Driver<I2C> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        let out_low = self.i2c.read_reg(self.addr, Regs::OutputPort0)? as u16;
+        let out_high = self.i2c.read_reg(self.addr, Regs::OutputPort1)? as u16;
+        self.out = Some(out_low | (out_high << 8));
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverSnapshot for Driver<I2C> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        let config0 = self.i2c.read_reg(self.addr, Regs::Configuration0)?;
+        let config1 = self.i2c.read_reg(self.addr, Regs::Configuration1)?;
+        let config = ((config1 as u32) << 8) | config0 as u32;
+        Ok(crate::DriverState {
+            pins: 0xffff,
+            outputs: self.out.map(u32::from),
+            output_pins: Some(!config & 0xffff),
+            pull_ups: None,
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, 0xffff, 0)?,
+        })
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRestoreDefaults for Driver<I2C> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        // any write to the reset register restores all registers, which leaves all pins as
+        // outputs with interrupts enabled.  Turn them back into inputs like `new()` does.
+        self.i2c.write_reg(self.addr, Regs::SoftwareReset, 0x00)?;
+        self.i2c.write_reg(self.addr, Regs::Configuration0, 0xff)?;
+        self.i2c.write_reg(self.addr, Regs::Configuration1, 0xff)?;
+        self.i2c
+            .write_reg(self.addr, Regs::InterruptEnable0, 0xff)?;
+        self.i2c
+            .write_reg(self.addr, Regs::InterruptEnable1, 0xff)?;
+        self.out = None;
+        self.led = 0x0000;
+        Ok(())
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRefresh for Driver<I2C> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        let out = self.get_out()?;
+        if mask & 0x00FF != 0 {
+            self.i2c
+                .write_reg(self.addr, Regs::OutputPort0, (out & 0xFF) as u8)?;
+        }
+        if mask & 0xFF00 != 0 {
+            self.i2c
+                .write_reg(self.addr, Regs::OutputPort1, (out >> 8) as u8)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverTotemPole for Driver<I2C> {
+    fn set_directions(&mut self, mask_input: u32, mask_output: u32) -> Result<(), Self::Error> {
+        let mask = mask_input | mask_output;
+        // pins in LED mode go back to GPIO mode when their direction is set
+        if self.led & mask as u16 != 0 {
+            self.set_led_mode(mask & self.led as u32, false)?;
+        }
+
+        if mask & 0x00FF != 0 {
+            self.i2c.update_reg(
+                self.addr,
+                Regs::Configuration0,
+                (mask_input & 0xFF) as u8,
+                (mask_output & 0xFF) as u8,
+            )?;
+        }
+        if mask & 0xFF00 != 0 {
+            self.i2c.update_reg(
+                self.addr,
+                Regs::Configuration1,
+                (mask_input >> 8) as u8,
+                (mask_output >> 8) as u8,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, MODE: crate::mode::Configurable, I2C, M> crate::Pin<'a, MODE, M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    /// Switch this pin into constant-current LED mode.
+    ///
+    /// The current starts out at whatever value was last configured for this pin (0 after
+    /// reset).  Use [`set_led_current()`](crate::Pin::set_led_current) to change it.
+    pub fn into_led(self) -> Result<crate::Pin<'a, Led, M>, crate::pin::PinError<I2C::BusError>> {
+        self.access_port_driver(|drv| drv.set_led_mode(self.pin_mask(), true))?;
+        Ok(self.into_mode())
+    }
+}
+
+impl<'a, I2C, M> crate::Pin<'a, Led, M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    /// Set the LED current of this pin in 256 steps from 0 (off) to 255 (maximum current).
+    pub fn set_led_current(
+        &mut self,
+        current: u8,
+    ) -> Result<(), crate::pin::PinError<I2C::BusError>> {
+        let pin = self.pin_mask().trailing_zeros() as u8;
+        self.access_port_driver(|drv| drv.set_led_current(pin, current))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn aw9523b() {
+        let expectations = [
+            // driver setup
+            mock_i2c::Transaction::write_read(0x59, vec![0x10], vec![0x23]),
+            mock_i2c::Transaction::write(0x59, vec![0x04, 0xff]),
+            mock_i2c::Transaction::write(0x59, vec![0x05, 0xff]),
+            mock_i2c::Transaction::write(0x59, vec![0x06, 0xff]),
+            mock_i2c::Transaction::write(0x59, vec![0x07, 0xff]),
+            // pin setup io0_0
+            mock_i2c::Transaction::write_read(0x59, vec![0x02], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x59, vec![0x03], vec![0xff]),
+            mock_i2c::Transaction::write(0x59, vec![0x02, 0xfe]),
+            mock_i2c::Transaction::write_read(0x59, vec![0x04], vec![0xff]),
+            mock_i2c::Transaction::write(0x59, vec![0x04, 0xfe]),
+            // output io0_0
+            mock_i2c::Transaction::write(0x59, vec![0x02, 0xff]),
+            // input io1_1
+            mock_i2c::Transaction::write_read(0x59, vec![0x01], vec![0x02]),
+            mock_i2c::Transaction::write_read(0x59, vec![0x01], vec![0xfd]),
+            // io0_3 and io1_2 into LED mode
+            mock_i2c::Transaction::write_read(0x59, vec![0x12], vec![0xff]),
+            mock_i2c::Transaction::write(0x59, vec![0x12, 0xf7]),
+            mock_i2c::Transaction::write_read(0x59, vec![0x13], vec![0xff]),
+            mock_i2c::Transaction::write(0x59, vec![0x13, 0xfb]),
+            // LED currents
+            mock_i2c::Transaction::write(0x59, vec![0x27, 0x80]),
+            mock_i2c::Transaction::write(0x59, vec![0x22, 0xff]),
+            // io0_3 back to GPIO input
+            mock_i2c::Transaction::write_read(0x59, vec![0x12], vec![0xf7]),
+            mock_i2c::Transaction::write(0x59, vec![0x12, 0xff]),
+            mock_i2c::Transaction::write_read(0x59, vec![0x04], vec![0xfe]),
+            mock_i2c::Transaction::write(0x59, vec![0x04, 0xfe]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut aw = super::Aw9523b::new(bus.clone(), true, false).unwrap();
+        let aw_pins = aw.split();
+
+        let mut io0_0 = aw_pins.io0_0.into_output().unwrap();
+        io0_0.set_high().unwrap();
+
+        assert!(aw_pins.io1_1.is_high().unwrap());
+        assert!(aw_pins.io1_1.is_low().unwrap());
+
+        let mut io0_3 = aw_pins.io0_3.into_led().unwrap();
+        let mut io1_2 = aw_pins.io1_2.into_led().unwrap();
+        io0_3.set_led_current(0x80).unwrap();
+        io1_2.set_led_current(0xff).unwrap();
+
+        let _ = io0_3.into_input().unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn aw9523b_restore_defaults() {
+        let expectations = [
+            // driver setup
+            mock_i2c::Transaction::write_read(0x58, vec![0x10], vec![0x23]),
+            mock_i2c::Transaction::write(0x58, vec![0x04, 0xff]),
+            mock_i2c::Transaction::write(0x58, vec![0x05, 0xff]),
+            mock_i2c::Transaction::write(0x58, vec![0x06, 0xff]),
+            mock_i2c::Transaction::write(0x58, vec![0x07, 0xff]),
+            // software reset, then all pins back to inputs
+            mock_i2c::Transaction::write(0x58, vec![0x7f, 0x00]),
+            mock_i2c::Transaction::write(0x58, vec![0x04, 0xff]),
+            mock_i2c::Transaction::write(0x58, vec![0x05, 0xff]),
+            mock_i2c::Transaction::write(0x58, vec![0x06, 0xff]),
+            mock_i2c::Transaction::write(0x58, vec![0x07, 0xff]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let aw = super::Aw9523b::new(bus.clone(), false, false).unwrap();
+        aw.restore_defaults().unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn aw9523b_m5stack_cores3() {
+        let expectations = [
+            // board preset
+            mock_i2c::Transaction::write_read(0x58, vec![0x10], vec![0x23]),
+            mock_i2c::Transaction::write(0x58, vec![0x02, 0b00000111]),
+            mock_i2c::Transaction::write(0x58, vec![0x03, 0b10000011]),
+            mock_i2c::Transaction::write_read(0x58, vec![0x11], vec![0x00]),
+            mock_i2c::Transaction::write(0x58, vec![0x11, 0x10]),
+            mock_i2c::Transaction::write(0x58, vec![0x12, 0xff]),
+            mock_i2c::Transaction::write(0x58, vec![0x13, 0xff]),
+            mock_i2c::Transaction::write(0x58, vec![0x04, 0b00011000]),
+            mock_i2c::Transaction::write(0x58, vec![0x05, 0b00001100]),
+            mock_i2c::Transaction::write(0x58, vec![0x06, 0xff]),
+            mock_i2c::Transaction::write(0x58, vec![0x07, 0xff]),
+            // reset the LCD, no read of the output registers
+            mock_i2c::Transaction::write(0x58, vec![0x03, 0b10000001]),
+            mock_i2c::Transaction::write(0x58, vec![0x03, 0b10000011]),
+            // touch interrupt
+            mock_i2c::Transaction::write_read(0x58, vec![0x01], vec![0b11111011]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut aw = super::Aw9523b::new_m5stack_cores3(bus.clone()).unwrap();
+        let mut board = aw.split_m5stack_cores3();
+
+        assert!(board.lcd_rst.is_set_high().unwrap());
+        board.lcd_rst.set_low().unwrap();
+        board.lcd_rst.set_high().unwrap();
+        assert!(board.touch_int.is_low().unwrap());
+
+        bus.done();
+    }
+
+    #[test]
+    fn aw9523b_with_address() {
+        let expectations = [
+            // driver setup at 0x5b
+            mock_i2c::Transaction::write_read(0x5b, vec![0x10], vec![0x23]),
+            mock_i2c::Transaction::write(0x5b, vec![0x04, 0xff]),
+            mock_i2c::Transaction::write(0x5b, vec![0x05, 0xff]),
+            mock_i2c::Transaction::write(0x5b, vec![0x06, 0xff]),
+            mock_i2c::Transaction::write(0x5b, vec![0x07, 0xff]),
+            // driver setup at 0x60
+            mock_i2c::Transaction::write_read(0x60, vec![0x10], vec![0x23]),
+            mock_i2c::Transaction::write(0x60, vec![0x04, 0xff]),
+            mock_i2c::Transaction::write(0x60, vec![0x05, 0xff]),
+            mock_i2c::Transaction::write(0x60, vec![0x06, 0xff]),
+            mock_i2c::Transaction::write(0x60, vec![0x07, 0xff]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let err = super::Aw9523b::<core::cell::RefCell<_>>::with_address(bus.clone(), 0x5c)
+            .err()
+            .unwrap();
+        assert!(matches!(err, crate::AddressError::InvalidAddress(e) if e.address() == 0x5c));
+
+        let aw: super::Aw9523b<core::cell::RefCell<_>> =
+            super::Aw9523b::with_address(bus.clone(), 0x5b).unwrap();
+        assert_eq!(aw.address_check(), crate::AddressCheck::Checked);
+
+        let aw: super::Aw9523b<core::cell::RefCell<_>> =
+            super::Aw9523b::with_address_unchecked(bus.clone(), 0x60).unwrap();
+        assert_eq!(aw.address_check(), crate::AddressCheck::Unchecked);
+
+        bus.done();
+    }
+}
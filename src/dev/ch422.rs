@@ -0,0 +1,555 @@
+//! Support for the `CH422` "I/O expander with 8 bidirectional and 4 open-collector pins"
+//!
+//! The `CH422` is not register-based.  Each command has its own I2C address, and each write
+//! carries a single data byte.  Because of this the chip occupies a fixed set of addresses and
+//! cannot share the bus with a second `CH422`.
+//!
+//! The chip has two kinds of pins:
+//!
+//! - `io0` to `io7` are bidirectional, but they share a single direction.  So
+//!   [`split()`](Ch422::split) hands them out together as an [`IoBank`], which can only switch the
+//!   direction of all eight pins at once.  [`IoBank::split()`] then gives access to the single
+//!   pins, which keep the direction of the bank.
+//! - `o0` to `o3` are output-only, so they are in the [`OutputOnly`](crate::mode::OutputOnly)
+//!   mode.  They are push-pull by default and can be switched to open-drain with
+//!   [`set_output_mode()`](Ch422::set_output_mode).
+//!
+//! The outputs cannot be read back, the driver keeps them in a cache instead.  It assumes the
+//! power-on state, which has all `io` pins as inputs and all `o` pins HIGH.
+
+/// `CH422` "I/O expander with 8 bidirectional and 4 open-collector pins"
+pub struct Ch422<M>(M);
+
+impl<I2C> Ch422<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::I2cBus,
+{
+    pub fn new(i2c: I2C) -> Self {
+        Self::with_mutex(i2c)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Ch422<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new()`](Ch422::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C) -> Self {
+        Self::with_mutex(i2c)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Ch422<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new()`](Ch422::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C) -> Self {
+        Self::with_mutex(i2c)
+    }
+}
+
+impl<I2C, M> Ch422<M>
+where
+    I2C: crate::I2cBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub fn with_mutex(i2c: I2C) -> Self {
+        Self(crate::PortMutex::create(Driver::new(i2c)))
+    }
+
+    pub fn split(&mut self) -> Parts<'_, I2C, M> {
+        Parts {
+            io: IoBank::new(&self.0),
+            o0: crate::Pin::new(8, &self.0),
+            o1: crate::Pin::new(9, &self.0),
+            o2: crate::Pin::new(10, &self.0),
+            o3: crate::Pin::new(11, &self.0),
+        }
+    }
+
+    pin_methods! {
+        pins: 12,
+        error: I2C::BusError,
+        bits: "Bits 0 to 7 of the masks are `io0` to `io7`, bits 8 to 11 are `o0` to `o3`.  The \
+            `o` pins cannot be read, [`read_mask()`](Self::read_mask) takes their bits from the \
+            output cache.  This bypasses the pin types, so the `io` pins are not checked to be \
+            outputs.",
+        methods: [write_mask, read_mask, write_pins, toggle_pins],
+    }
+
+    /// Switch the output stage of the `o` pins.
+    ///
+    /// The chip starts up with push-pull outputs.  The `io` pins are always push-pull.
+    pub fn set_output_mode(&self, mode: crate::OutputMode) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverOutputMode::set_output_mode(drv, mode)
+        })
+    }
+
+    /// Put the chip into its low-power sleep mode.
+    ///
+    /// Any following access to the chip wakes it up again, so [`wake()`](Self::wake) is only
+    /// needed to wake it without touching a pin.
+    pub fn sleep(&self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, crate::PortDriverPower::sleep)
+    }
+
+    /// Wake the chip up from its low-power sleep mode.
+    pub fn wake(&self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, crate::PortDriverPower::wake)
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All `io` pins become inputs and the `o` pins are driven HIGH with push-pull outputs.
+    /// Consuming the device requires that all of its pins and the [`IoBank`] were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::I2cBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub io: IoBank<'a, crate::mode::Input, M>,
+    pub o0: crate::Pin<'a, crate::mode::OutputOnly, M>,
+    pub o1: crate::Pin<'a, crate::mode::OutputOnly, M>,
+    pub o2: crate::Pin<'a, crate::mode::OutputOnly, M>,
+    pub o3: crate::Pin<'a, crate::mode::OutputOnly, M>,
+}
+
+/// The pins `io0` to `io7`, which share a single direction.
+///
+/// The bank starts out as inputs.  [`into_output()`](IoBank::into_output) and
+/// [`into_input()`](IoBank::into_input) switch all eight pins together, so no pin can end up in a
+/// different direction than its type says.  After [`split()`](IoBank::split), the direction can
+/// no longer be changed:
+///
+/// ```compile_fail
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// let mut ch = port_expander::Ch422::new(i2c);
+/// let io_pins = ch.split().io.split();
+/// let io0 = io_pins.io0.into_output();
+/// ```
+#[must_use = "a dropped pin cannot be accessed again"]
+pub struct IoBank<'a, MODE, M> {
+    port_driver: &'a M,
+    _m: core::marker::PhantomData<MODE>,
+}
+
+impl<'a, MODE, I2C, M> IoBank<'a, MODE, M>
+where
+    I2C: crate::I2cBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    fn new(port_driver: &'a M) -> Self {
+        Self {
+            port_driver,
+            _m: core::marker::PhantomData,
+        }
+    }
+
+    /// Make all eight pins outputs, driving the levels which were last written to them.
+    ///
+    /// The pins are LOW unless they were written before, e.g. with
+    /// [`write_mask()`](Ch422::write_mask).
+    pub fn into_output(self) -> Result<IoBank<'a, crate::mode::Output, M>, I2C::BusError> {
+        crate::PortMutex::lock(self.port_driver, |drv| drv.set_io_direction(true))?;
+        Ok(IoBank::new(self.port_driver))
+    }
+
+    /// Make all eight pins outputs which drive `levels`, bit `n` being `io<n>`.
+    ///
+    /// The levels are written before the direction is switched, so the pins don't glitch.
+    pub fn into_output_with(
+        self,
+        levels: u8,
+    ) -> Result<IoBank<'a, crate::mode::Output, M>, I2C::BusError> {
+        crate::PortMutex::lock(self.port_driver, |drv| {
+            crate::PortDriver::set(drv, levels as u32, !levels as u32 & IO_PINS)
+        })?;
+        self.into_output()
+    }
+
+    /// Make all eight pins inputs.
+    pub fn into_input(self) -> Result<IoBank<'a, crate::mode::Input, M>, I2C::BusError> {
+        crate::PortMutex::lock(self.port_driver, |drv| drv.set_io_direction(false))?;
+        Ok(IoBank::new(self.port_driver))
+    }
+
+    /// Get access to the single pins, which keep the direction of the bank.
+    pub fn split(self) -> IoParts<'a, MODE, M> {
+        IoParts {
+            io0: crate::Pin::new(0, self.port_driver),
+            io1: crate::Pin::new(1, self.port_driver),
+            io2: crate::Pin::new(2, self.port_driver),
+            io3: crate::Pin::new(3, self.port_driver),
+            io4: crate::Pin::new(4, self.port_driver),
+            io5: crate::Pin::new(5, self.port_driver),
+            io6: crate::Pin::new(6, self.port_driver),
+            io7: crate::Pin::new(7, self.port_driver),
+        }
+    }
+}
+
+/// The single pins of an [`IoBank`].
+pub struct IoParts<'a, MODE, M> {
+    pub io0: crate::Pin<'a, MODE, M>,
+    pub io1: crate::Pin<'a, MODE, M>,
+    pub io2: crate::Pin<'a, MODE, M>,
+    pub io3: crate::Pin<'a, MODE, M>,
+    pub io4: crate::Pin<'a, MODE, M>,
+    pub io5: crate::Pin<'a, MODE, M>,
+    pub io6: crate::Pin<'a, MODE, M>,
+    pub io7: crate::Pin<'a, MODE, M>,
+}
+
+/// I2C addresses of the commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    SetSystem = 0x24,
+    WriteOc = 0x23,
+    WriteIo = 0x38,
+    ReadIo = 0x26,
+}
+
+impl Command {
+    fn addr(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Bits of the system parameter.
+mod system {
+    /// All `io` pins are outputs.
+    pub const IO_OE: u8 = 0x01;
+    /// The `o` pins are open-drain.
+    pub const OD_EN: u8 = 0x04;
+    /// Enter the low-power sleep mode, any following command wakes the chip up again.
+    pub const SLEEP: u8 = 0x08;
+}
+
+const IO_PINS: u32 = 0x00ff;
+const OC_PINS: u32 = 0x0f00;
+
+pub struct Driver<I2C> {
+    i2c: I2C,
+    system: u8,
+    io_out: u8,
+    oc_out: u8,
+    dirty: bool,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+}
+
+impl<I2C> Driver<I2C> {
+    pub fn new(i2c: I2C) -> Self {
+        Self {
+            i2c,
+            system: 0x00,
+            io_out: 0x00,
+            oc_out: 0x0f,
+            dirty: false,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+        }
+    }
+}
+
+impl<I2C: crate::I2cBus> Driver<I2C> {
+    fn write(&mut self, cmd: Command, value: u8) -> Result<(), I2C::BusError> {
+        self.i2c.write(cmd.addr(), &[value])?;
+        Ok(())
+    }
+
+    /// Switch all `io` pins to outputs or inputs, see [`IoBank`].
+    fn set_io_direction(&mut self, output: bool) -> Result<(), I2C::BusError> {
+        let system = match output {
+            true => self.system | system::IO_OE,
+            false => self.system & !system::IO_OE,
+        };
+        self.write(Command::SetSystem, system)?;
+        self.system = system;
+        Ok(())
+    }
+
+    fn outputs(&self) -> u32 {
+        self.io_out as u32 | (self.oc_out as u32) << 8
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriver for Driver<I2C> {
+    type Error = I2C::BusError;
+
+    fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let out = (self.outputs() | mask_high) & !mask_low;
+        self.io_out = out as u8;
+        self.oc_out = (out >> 8) as u8 & 0x0f;
+
+        // stays set if a write fails
+        self.dirty = true;
+        if (mask_high | mask_low) & IO_PINS != 0 {
+            self.write(Command::WriteIo, self.io_out)?;
+        }
+        if (mask_high | mask_low) & OC_PINS != 0 {
+            self.write(Command::WriteOc, self.oc_out)?;
+        }
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let out = self.outputs();
+        Ok((out & mask_high) | (!out & mask_low))
+    }
+
+    fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let mask = mask_high | mask_low;
+        let io = if mask & IO_PINS != 0 {
+            self.input_state.read(mask & IO_PINS, |_| {
+                let mut buf = [0x00];
+                self.i2c.read(Command::ReadIo.addr(), &mut buf)?;
+                Ok::<_, Self::Error>(buf[0] as u32)
+            })?
+        } else {
+            0
+        };
+        // the `o` pins have no input, report their output latch instead
+        let in_ = io | (self.oc_out as u32) << 8;
+
+        Ok((in_ & mask_high) | (!in_ & mask_low))
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverOutputMode for Driver<I2C> {
+    fn set_output_mode(&mut self, mode: crate::OutputMode) -> Result<(), Self::Error> {
+        let system = match mode {
+            crate::OutputMode::PushPull => self.system & !system::OD_EN,
+            crate::OutputMode::OpenDrain => self.system | system::OD_EN,
+        };
+        self.write(Command::SetSystem, system)?;
+        self.system = system;
+        Ok(())
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverPower for Driver<I2C> {
+    fn sleep(&mut self) -> Result<(), Self::Error> {
+        // the flag is not cached, the chip clears it on the next command
+        self.write(Command::SetSystem, self.system | system::SLEEP)
+    }
+
+    fn wake(&mut self) -> Result<(), Self::Error> {
+        self.write(Command::SetSystem, self.system)
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverInputState for Driver<I2C> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverOutputInversion for Driver<I2C> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverCost for Driver<I2C> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set {
+                mask_high,
+                mask_low,
+            } => {
+                let mask = mask_high | mask_low;
+                (mask & IO_PINS != 0) as u32 + (mask & OC_PINS != 0) as u32
+            }
+            crate::Operation::Get { mask } => {
+                self.input_state.read_mask(mask & IO_PINS).is_some() as u32
+            }
+            // single pins cannot change their direction, only the whole `IoBank`
+            crate::Operation::SetDirections { .. } => 0,
+        }
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverResync for Driver<I2C> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        // nothing can be read back, restore everything from the cache instead
+        self.write(Command::WriteIo, self.io_out)?;
+        self.write(Command::WriteOc, self.oc_out)?;
+        self.write(Command::SetSystem, self.system)?;
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverSnapshot for Driver<I2C> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        let output_pins = if self.system & system::IO_OE != 0 {
+            IO_PINS | OC_PINS
+        } else {
+            OC_PINS
+        };
+        Ok(crate::DriverState {
+            pins: IO_PINS | OC_PINS,
+            outputs: Some(self.outputs()),
+            output_pins: Some(output_pins),
+            pull_ups: None,
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, IO_PINS | OC_PINS, 0)?,
+        })
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverRestoreDefaults for Driver<I2C> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        // `io` pins are inputs and the `o` pins are push-pull HIGH after power-on
+        self.system = 0x00;
+        self.oc_out = 0x0f;
+        self.write(Command::SetSystem, self.system)?;
+        self.write(Command::WriteOc, self.oc_out)?;
+        Ok(())
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverRefresh for Driver<I2C> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        if mask & IO_PINS != 0 {
+            self.write(Command::WriteIo, self.io_out)?;
+        }
+        if mask & OC_PINS != 0 {
+            self.write(Command::WriteOc, self.oc_out)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn ch422() {
+        let expectations = [
+            // o pins
+            mock_i2c::Transaction::write(0x23, vec![0b00001110]),
+            mock_i2c::Transaction::write(0x23, vec![0b00000110]),
+            mock_i2c::Transaction::write(0x23, vec![0b00001110]),
+            // io pins as outputs
+            mock_i2c::Transaction::write(0x38, vec![0b00000100]),
+            mock_i2c::Transaction::write(0x24, vec![0b00000001]),
+            mock_i2c::Transaction::write(0x24, vec![0b00000000]),
+            mock_i2c::Transaction::write(0x24, vec![0b00000001]),
+            mock_i2c::Transaction::write(0x38, vec![0b00000000]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut ch = super::Ch422::new(bus.clone());
+        let mut ch_pins = ch.split();
+
+        let mut o3 = ch_pins.o3;
+        ch_pins.o0.set_low().unwrap();
+        o3.set_low().unwrap();
+        assert!(o3.is_set_low().unwrap());
+        o3.toggle().unwrap();
+
+        let io = ch_pins.io.into_output_with(0b00000100).unwrap();
+        let io = io.into_input().unwrap().into_output().unwrap();
+        let mut io_pins = io.split();
+        io_pins.io2.set_low().unwrap();
+        assert!(io_pins.io2.is_set_low().unwrap());
+
+        bus.done();
+    }
+
+    #[test]
+    fn ch422_inputs() {
+        let expectations = [
+            mock_i2c::Transaction::read(0x26, vec![0b10000000]),
+            mock_i2c::Transaction::read(0x26, vec![0b01111111]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut ch = super::Ch422::new(bus.clone());
+        let io_pins = ch.split().io.split();
+
+        assert!(io_pins.io7.is_high().unwrap());
+        assert!(io_pins.io7.is_low().unwrap());
+
+        bus.done();
+    }
+
+    #[test]
+    fn ch422_output_mode() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x24, vec![0b00000100]),
+            mock_i2c::Transaction::write(0x24, vec![0b00000000]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let ch = super::Ch422::new(bus.clone());
+        ch.set_output_mode(crate::OutputMode::OpenDrain).unwrap();
+        ch.set_output_mode(crate::OutputMode::PushPull).unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn ch422_restore_defaults() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x24, vec![0b00000001]),
+            mock_i2c::Transaction::write(0x24, vec![0b00000000]),
+            mock_i2c::Transaction::write(0x23, vec![0b00001111]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut ch = super::Ch422::new(bus.clone());
+        let ch_pins = ch.split();
+        drop(ch_pins.io.into_output().unwrap());
+        ch.restore_defaults().unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn ch422_sleep() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x24, vec![0b00001000]),
+            mock_i2c::Transaction::write(0x24, vec![0b00000000]),
+            // the system flags are kept while sleeping
+            mock_i2c::Transaction::write(0x24, vec![0b00000100]),
+            mock_i2c::Transaction::write(0x24, vec![0b00001100]),
+            mock_i2c::Transaction::read(0x26, vec![0b00000001]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut ch = super::Ch422::new(bus.clone());
+        ch.sleep().unwrap();
+        ch.wake().unwrap();
+
+        ch.set_output_mode(crate::OutputMode::OpenDrain).unwrap();
+        ch.sleep().unwrap();
+        // a read wakes the chip up, too
+        let io_pins = ch.split().io.split();
+        assert!(io_pins.io0.is_high().unwrap());
+
+        bus.done();
+    }
+}
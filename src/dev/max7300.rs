@@ -0,0 +1,747 @@
+//! Support for the `MAX7300` and `MAX7301` "2-Wire-Interfaced / 4-Wire-Interfaced, 2.5V to 5.5V,
+//! 20-Port or 28-Port I/O Expander"
+//!
+//! The `MAX7300` is connected via I2C, the `MAX7301` via SPI.  Both have the same register map.
+//! The ports are named `P4` to `P31` as in the datasheet and use the same bit positions in masks
+//! passed to this driver.  The 20-port packages only have `P12` to `P31`, the pins `p4` to `p11`
+//! must not be used with them.
+//!
+//! Unlike most other port-expanders, these chips do not have one bit per pin in a few registers.
+//! Each port is configured by two bits in one of the port configuration registers and its level is
+//! accessed through range registers which cover eight consecutive ports.  The driver caches the
+//! configuration, so no register ever needs to be read back before it is written.
+use crate::RegisterBus;
+
+/// `MAX7300`/`MAX7301` "20-Port or 28-Port I/O Expander" with I2C or SPI interface
+pub struct Max7300<M>(M);
+
+impl<I2C> Max7300<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Create a new instance of the MAX7300 with I2C interface
+    pub fn new_max7300(i2c: I2C, ad0: AddressPin, ad1: AddressPin) -> Self {
+        Self::with_mutex(i2c, ad0, ad1)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Max7300<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new_max7300()`](Max7300::new_max7300), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_max7300_critical_section(i2c: I2C, ad0: AddressPin, ad1: AddressPin) -> Self {
+        Self::with_mutex(i2c, ad0, ad1)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Max7300<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new_max7300()`](Max7300::new_max7300), but the device is wrapped in a
+    /// `std::sync::Mutex` so it can be shared between threads.
+    pub fn new_max7300_std(i2c: I2C, ad0: AddressPin, ad1: AddressPin) -> Self {
+        Self::with_mutex(i2c, ad0, ad1)
+    }
+}
+
+impl<SPI> Max7300<core::cell::RefCell<Driver<Max7301Bus<SPI>>>>
+where
+    SPI: crate::SpiBus,
+{
+    /// Create a new instance of the MAX7301 with SPI interface
+    pub fn new_max7301(spi: SPI) -> Self {
+        Self::with_mutex(Max7301Bus(spi), AddressPin::Gnd, AddressPin::Gnd)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<SPI> Max7300<critical_section::Mutex<core::cell::RefCell<Driver<Max7301Bus<SPI>>>>>
+where
+    SPI: crate::SpiBus,
+{
+    /// Same as [`new_max7301()`](Max7300::new_max7301), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_max7301_critical_section(spi: SPI) -> Self {
+        Self::with_mutex(Max7301Bus(spi), AddressPin::Gnd, AddressPin::Gnd)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<SPI> Max7300<std::sync::Mutex<Driver<Max7301Bus<SPI>>>>
+where
+    SPI: crate::SpiBus,
+{
+    /// Same as [`new_max7301()`](Max7300::new_max7301), but the device is wrapped in a
+    /// `std::sync::Mutex` so it can be shared between threads.
+    pub fn new_max7301_std(spi: SPI) -> Self {
+        Self::with_mutex(Max7301Bus(spi), AddressPin::Gnd, AddressPin::Gnd)
+    }
+}
+
+impl<B, M> Max7300<M>
+where
+    B: RegisterBus,
+    M: crate::PortMutex<Port = Driver<B>>,
+{
+    /// Create a new instance with a custom mutex.
+    ///
+    /// For the `MAX7301`, the address pins are ignored.
+    pub fn with_mutex(bus: B, ad0: AddressPin, ad1: AddressPin) -> Self {
+        Self(crate::PortMutex::create(Driver::new(bus, ad0, ad1)))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(bus: B, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            bus,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x40` to `0x4f`).
+    pub fn with_address(bus: B, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(bus, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(bus: B, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            bus,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
+    pub fn split<'a>(&'a mut self) -> Parts<'a, B, M> {
+        Parts {
+            p4: crate::Pin::new(4, &self.0),
+            p5: crate::Pin::new(5, &self.0),
+            p6: crate::Pin::new(6, &self.0),
+            p7: crate::Pin::new(7, &self.0),
+            p8: crate::Pin::new(8, &self.0),
+            p9: crate::Pin::new(9, &self.0),
+            p10: crate::Pin::new(10, &self.0),
+            p11: crate::Pin::new(11, &self.0),
+            p12: crate::Pin::new(12, &self.0),
+            p13: crate::Pin::new(13, &self.0),
+            p14: crate::Pin::new(14, &self.0),
+            p15: crate::Pin::new(15, &self.0),
+            p16: crate::Pin::new(16, &self.0),
+            p17: crate::Pin::new(17, &self.0),
+            p18: crate::Pin::new(18, &self.0),
+            p19: crate::Pin::new(19, &self.0),
+            p20: crate::Pin::new(20, &self.0),
+            p21: crate::Pin::new(21, &self.0),
+            p22: crate::Pin::new(22, &self.0),
+            p23: crate::Pin::new(23, &self.0),
+            p24: crate::Pin::new(24, &self.0),
+            p25: crate::Pin::new(25, &self.0),
+            p26: crate::Pin::new(26, &self.0),
+            p27: crate::Pin::new(27, &self.0),
+            p28: crate::Pin::new(28, &self.0),
+            p29: crate::Pin::new(29, &self.0),
+            p30: crate::Pin::new(30, &self.0),
+            p31: crate::Pin::new(31, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 28 + 4,
+        error: B::BusError,
+        bits: "Bit `n` of the masks is the pin `Pn`, bits 0 to 3 are ignored.  This bypasses the \
+            pin types, so the pins are not checked to be outputs.",
+    }
+
+    /// Put the chip into shutdown mode to save power.
+    ///
+    /// In shutdown, all ports are high-impedance inputs without pull-ups, but their configuration
+    /// and output state are kept.  The chip wakes up with [`wake()`](Self::wake) or with the next
+    /// access to one of its pins.
+    pub fn sleep(&self) -> Result<(), B::BusError> {
+        crate::PortMutex::lock(&self.0, crate::PortDriverPower::sleep)
+    }
+
+    /// Leave shutdown mode, restoring the configuration of all ports.
+    pub fn wake(&self) -> Result<(), B::BusError> {
+        crate::PortMutex::lock(&self.0, crate::PortDriverPower::wake)
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All ports become inputs without pull-ups, the transition detection mask is cleared and the
+    /// chip goes back into shutdown mode.  Consuming the device requires that all of its pins were
+    /// dropped.
+    pub fn restore_defaults(self) -> Result<(), B::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+pub struct Parts<'a, B, M = core::cell::RefCell<Driver<B>>>
+where
+    B: RegisterBus,
+    M: crate::PortMutex<Port = Driver<B>>,
+{
+    pub p4: crate::Pin<'a, crate::mode::Input, M>,
+    pub p5: crate::Pin<'a, crate::mode::Input, M>,
+    pub p6: crate::Pin<'a, crate::mode::Input, M>,
+    pub p7: crate::Pin<'a, crate::mode::Input, M>,
+    pub p8: crate::Pin<'a, crate::mode::Input, M>,
+    pub p9: crate::Pin<'a, crate::mode::Input, M>,
+    pub p10: crate::Pin<'a, crate::mode::Input, M>,
+    pub p11: crate::Pin<'a, crate::mode::Input, M>,
+    pub p12: crate::Pin<'a, crate::mode::Input, M>,
+    pub p13: crate::Pin<'a, crate::mode::Input, M>,
+    pub p14: crate::Pin<'a, crate::mode::Input, M>,
+    pub p15: crate::Pin<'a, crate::mode::Input, M>,
+    pub p16: crate::Pin<'a, crate::mode::Input, M>,
+    pub p17: crate::Pin<'a, crate::mode::Input, M>,
+    pub p18: crate::Pin<'a, crate::mode::Input, M>,
+    pub p19: crate::Pin<'a, crate::mode::Input, M>,
+    pub p20: crate::Pin<'a, crate::mode::Input, M>,
+    pub p21: crate::Pin<'a, crate::mode::Input, M>,
+    pub p22: crate::Pin<'a, crate::mode::Input, M>,
+    pub p23: crate::Pin<'a, crate::mode::Input, M>,
+    pub p24: crate::Pin<'a, crate::mode::Input, M>,
+    pub p25: crate::Pin<'a, crate::mode::Input, M>,
+    pub p26: crate::Pin<'a, crate::mode::Input, M>,
+    pub p27: crate::Pin<'a, crate::mode::Input, M>,
+    pub p28: crate::Pin<'a, crate::mode::Input, M>,
+    pub p29: crate::Pin<'a, crate::mode::Input, M>,
+    pub p30: crate::Pin<'a, crate::mode::Input, M>,
+    pub p31: crate::Pin<'a, crate::mode::Input, M>,
+}
+
+/// Connection of one of the `AD0`/`AD1` address pins of the `MAX7300`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressPin {
+    Gnd,
+    VPlus,
+    Sda,
+    Scl,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Regs {
+    NoOp = 0x00,
+    /// Bit 0 selects normal operation (1) or shutdown (0).
+    Configuration = 0x04,
+    TransitionDetectMask = 0x06,
+    /// First of seven registers which configure four ports each, starting at `P4`.
+    PortConfiguration = 0x09,
+    /// Level of port `Pn` in bit 0 of register `0x20 + n`.
+    Port = 0x20,
+    /// Levels of ports `Pn` to `P(n + 7)` in register `0x40 + n`.
+    PortRange = 0x40,
+}
+
+impl From<Regs> for u8 {
+    fn from(r: Regs) -> u8 {
+        r as u8
+    }
+}
+
+/// First ports of the range registers which cover `P4` to `P31`.
+const RANGES: [u8; 4] = [4, 12, 20, 28];
+
+/// Port configuration: push-pull output.
+const CONFIG_OUTPUT: u8 = 0b01;
+/// Port configuration: input without pull-up.
+const CONFIG_INPUT: u8 = 0b10;
+/// Port configuration: input with pull-up.
+const CONFIG_INPUT_PULL_UP: u8 = 0b11;
+
+crate::address::addr_type! {
+    /// I2C address of the `MAX7300`.
+    pub struct Addr: 0x40, [ad0: AddressPin = 0, ad1: AddressPin = 2], 0x40..=0x4f;
+}
+
+pub struct Driver<B> {
+    bus: B,
+    out: u32,
+    dirty: bool,
+    outputs: u32,
+    pull_ups: u32,
+    running: bool,
+    addr: u8,
+    address_check: crate::AddressCheck,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
+}
+
+impl<B> Driver<B> {
+    pub fn new(bus: B, ad0: AddressPin, ad1: AddressPin) -> Self {
+        let addr = Addr::from_pins(ad0, ad1).get();
+        Self::with_address(bus, addr, crate::AddressCheck::Checked)
+    }
+
+    pub(crate) fn with_address(bus: B, addr: u8, address_check: crate::AddressCheck) -> Self {
+        Self {
+            bus,
+            out: 0x00000000,
+            dirty: false,
+            outputs: 0x00000000,
+            pull_ups: 0x00000000,
+            running: false,
+            addr,
+            address_check,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
+        }
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
+}
+
+impl<B: RegisterBus> Driver<B> {
+    /// Leave shutdown mode, which the chip enters on power-up, before the first access.
+    fn ensure_running(&mut self) -> Result<(), B::BusError> {
+        if !self.running {
+            self.bus.write_reg(self.addr, Regs::Configuration, 0x01)?;
+            self.running = true;
+        }
+        Ok(())
+    }
+
+    /// Write the cached configuration of all ports in `mask` to the port configuration registers.
+    fn write_port_config(&mut self, mask: u32) -> Result<(), B::BusError> {
+        for reg in 0..7 {
+            let first = 4 + 4 * reg;
+            if mask & (0xf << first) == 0 {
+                continue;
+            }
+            let mut value = 0x00;
+            for i in 0..4 {
+                let port_mask = 1 << (first + i);
+                let config = if self.outputs & port_mask != 0 {
+                    CONFIG_OUTPUT
+                } else if self.pull_ups & port_mask != 0 {
+                    CONFIG_INPUT_PULL_UP
+                } else {
+                    CONFIG_INPUT
+                };
+                value |= config << (2 * i);
+            }
+            self.bus
+                .write_reg(self.addr, Regs::PortConfiguration as u8 + reg, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriver for Driver<B> {
+    type Error = B::BusError;
+
+    fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        self.ensure_running()?;
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        self.out |= mask_high;
+        self.out &= !mask_low;
+        // a failed write leaves the device in an unknown state, so all ports are written until a
+        // write succeeds again
+        let mask = match self.dirty {
+            true => 0xFFFFFFFF,
+            false => mask_high | mask_low,
+        };
+        self.dirty = true;
+        for first in RANGES {
+            if mask & (0xff << first) != 0 {
+                // inputs read back their level, not the written value
+                self.bus.write_reg_masked(
+                    self.addr,
+                    Regs::PortRange as u8 + first,
+                    (self.out >> first) as u8,
+                    (self.outputs >> first) as u8,
+                )?;
+            }
+        }
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        Ok((self.out & mask_high) | (!self.out & mask_low))
+    }
+
+    fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        self.ensure_running()?;
+        let in_ = self.input_state.read(mask_high | mask_low, |mask| {
+            let mut in_ = 0x00000000;
+            for first in RANGES {
+                if mask & (0xff << first) != 0 {
+                    let range = self
+                        .bus
+                        .read_reg(self.addr, Regs::PortRange as u8 + first)?;
+                    in_ |= (range as u32) << first;
+                }
+            }
+            Ok::<_, Self::Error>(in_)
+        })?;
+        Ok((in_ & mask_high) | (!in_ & mask_low))
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverInputState for Driver<B> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverOutputInversion for Driver<B> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverClaims for Driver<B> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverCost for Driver<B> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        let start = !self.running as u32;
+        let ranges = |mask: u32| {
+            RANGES
+                .iter()
+                .filter(|&&first| mask & (0xff << first) != 0)
+                .count() as u32
+        };
+        match op {
+            crate::Operation::Set {
+                mask_high,
+                mask_low,
+            } => start + ranges(mask_high | mask_low),
+            crate::Operation::Get { mask } => {
+                start
+                    + self
+                        .input_state
+                        .read_mask(mask)
+                        .map_or(0, |mask| B::READ_COST * ranges(mask))
+            }
+            crate::Operation::SetDirections {
+                mask_input,
+                mask_output,
+            } => {
+                let mask = mask_input | mask_output;
+                start
+                    + (0..7)
+                        .filter(|reg| mask & (0xf << (4 + 4 * reg)) != 0)
+                        .count() as u32
+            }
+        }
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverResync for Driver<B> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        self.ensure_running()?;
+        let mut out = 0x00000000;
+        for first in RANGES {
+            let range = self
+                .bus
+                .read_reg(self.addr, Regs::PortRange as u8 + first)?;
+            out |= (range as u32) << first;
+        }
+        self.out = out;
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverSnapshot for Driver<B> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        // the port configuration is only written, never read back
+        Ok(crate::DriverState {
+            pins: 0xfffffff0,
+            outputs: Some(self.out),
+            output_pins: Some(self.outputs),
+            pull_ups: Some(self.pull_ups),
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, 0xfffffff0, 0)?,
+        })
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverPower for Driver<B> {
+    fn sleep(&mut self) -> Result<(), Self::Error> {
+        self.bus.write_reg(self.addr, Regs::Configuration, 0x00)?;
+        self.running = false;
+        Ok(())
+    }
+
+    fn wake(&mut self) -> Result<(), Self::Error> {
+        self.ensure_running()
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverRestoreDefaults for Driver<B> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        self.outputs = 0x00000000;
+        self.pull_ups = 0x00000000;
+        self.write_port_config(0xfffffff0)?;
+        self.bus
+            .write_reg(self.addr, Regs::TransitionDetectMask, 0x00)?;
+        // back to shutdown mode
+        self.bus.write_reg(self.addr, Regs::Configuration, 0x00)?;
+        self.running = false;
+        Ok(())
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverRefresh for Driver<B> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        self.ensure_running()?;
+        for first in RANGES {
+            if mask & (0xff << first) != 0 {
+                self.bus.write_reg_masked(
+                    self.addr,
+                    Regs::PortRange as u8 + first,
+                    (self.out >> first) as u8,
+                    (self.outputs >> first) as u8,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverTotemPole for Driver<B> {
+    fn set_directions(&mut self, mask_input: u32, mask_output: u32) -> Result<(), Self::Error> {
+        self.ensure_running()?;
+        self.outputs |= mask_output;
+        self.outputs &= !mask_input;
+        self.write_port_config(mask_input | mask_output)
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverPullUp for Driver<B> {
+    fn set_pull_up(&mut self, mask: u32, enable: bool) -> Result<(), Self::Error> {
+        self.ensure_running()?;
+        if enable {
+            self.pull_ups |= mask;
+        } else {
+            self.pull_ups &= !mask;
+        }
+        self.write_port_config(mask)
+    }
+
+    fn set_input_pull_up(&mut self, mask: u32) -> Result<(), Self::Error> {
+        self.ensure_running()?;
+        self.outputs &= !mask;
+        self.pull_ups |= mask;
+        self.write_port_config(mask)
+    }
+}
+
+/// SPI bus of the MAX7301.
+///
+/// The MAX7301 uses 16-bit frames without a device address: a read/write bit, the register, and
+/// the data byte.  The result of a read is shifted out during the following frame, so this does
+/// not fit the opcode framing used by the `MCP23S17`.
+pub struct Max7301Bus<SPI>(SPI);
+
+impl<SPI: crate::SpiBus> RegisterBus for Max7301Bus<SPI> {
+    type BusError = SPI::BusError;
+
+    const READ_COST: u32 = 2;
+
+    fn write_reg<R: Into<u8>>(
+        &mut self,
+        _addr: u8,
+        reg: R,
+        value: u8,
+    ) -> Result<(), Self::BusError> {
+        self.0.write(&[reg.into(), value])?;
+        Ok(())
+    }
+
+    fn read_reg<R: Into<u8>>(&mut self, _addr: u8, reg: R) -> Result<u8, Self::BusError> {
+        self.0.write(&[0x80 | reg.into(), 0x00])?;
+        let mut buf = [0x00; 2];
+        self.0.transfer(&mut buf, &[Regs::NoOp as u8, 0x00])?;
+        Ok(buf[1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::{i2c as mock_i2c, spi as mock_spi};
+
+    #[test]
+    fn max7300() {
+        let expectations = [
+            // leave shutdown mode
+            mock_i2c::Transaction::write(0x46, vec![0x04, 0x01]),
+            // p4 output HIGH
+            mock_i2c::Transaction::write(0x46, vec![0x44, 0x01]),
+            mock_i2c::Transaction::write(0x46, vec![0x09, 0b10101001]),
+            // p31 output LOW
+            mock_i2c::Transaction::write(0x46, vec![0x5c, 0x00]),
+            mock_i2c::Transaction::write(0x46, vec![0x0f, 0b01101010]),
+            // p30 input with pull-up
+            mock_i2c::Transaction::write(0x46, vec![0x0f, 0b01111010]),
+            // p13 input
+            mock_i2c::Transaction::write_read(0x46, vec![0x4c], vec![0b00000010]),
+            mock_i2c::Transaction::write_read(0x46, vec![0x4c], vec![0b00000000]),
+            // p30 input
+            mock_i2c::Transaction::write_read(0x46, vec![0x5c], vec![0b00000100]),
+            // p4 toggle
+            mock_i2c::Transaction::write(0x46, vec![0x44, 0x00]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut max = super::Max7300::new_max7300(
+            bus.clone(),
+            super::AddressPin::Sda,
+            super::AddressPin::VPlus,
+        );
+        let max_pins = max.split();
+
+        let mut p4 = max_pins.p4.into_output_high().unwrap();
+        let p31 = max_pins.p31.into_output().unwrap();
+        let p30 = max_pins.p30.into_pull_up().unwrap();
+        assert!(max_pins.p13.is_high().unwrap());
+        assert!(max_pins.p13.is_low().unwrap());
+        assert!(p30.is_high().unwrap());
+        p4.toggle().unwrap();
+        assert!(p31.is_set_low().unwrap());
+
+        bus.done();
+    }
+
+    #[test]
+    fn max7300_pull_up_input() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x40, vec![0x04, 0x01]),
+            // p5 output LOW
+            mock_i2c::Transaction::write(0x40, vec![0x44, 0x00]),
+            mock_i2c::Transaction::write(0x40, vec![0x09, 0b10100110]),
+            // p5 input with pull-up in a single write
+            mock_i2c::Transaction::write(0x40, vec![0x09, 0b10101110]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut max = super::Max7300::new_max7300(
+            bus.clone(),
+            super::AddressPin::Gnd,
+            super::AddressPin::Gnd,
+        );
+        let max_pins = max.split();
+
+        let p5 = max_pins.p5.into_output().unwrap();
+        let _ = p5.into_pull_up_input().unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn max7300_sleep() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x40, vec![0x04, 0x01]),
+            mock_i2c::Transaction::write(0x40, vec![0x04, 0x00]),
+            mock_i2c::Transaction::write(0x40, vec![0x04, 0x01]),
+            // waking up again is not needed
+            mock_i2c::Transaction::write(0x40, vec![0x04, 0x00]),
+            // the next pin access wakes the chip
+            mock_i2c::Transaction::write(0x40, vec![0x04, 0x01]),
+            mock_i2c::Transaction::write(0x40, vec![0x44, 0x01]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let max = super::Max7300::new_max7300(
+            bus.clone(),
+            super::AddressPin::Gnd,
+            super::AddressPin::Gnd,
+        );
+        max.wake().unwrap();
+        max.sleep().unwrap();
+        max.wake().unwrap();
+        max.wake().unwrap();
+        max.sleep().unwrap();
+        max.write_mask(0x10, 0).unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn max7301() {
+        let expectations = [
+            // leave shutdown mode
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x04, 0x01]),
+            mock_spi::Transaction::transaction_end(),
+            // p12 output LOW
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x4c, 0x00]),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x0b, 0b10101001]),
+            mock_spi::Transaction::transaction_end(),
+            // p12 HIGH
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x4c, 0x01]),
+            mock_spi::Transaction::transaction_end(),
+            // p20 input
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0xd4, 0x00]),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::transfer(vec![0x00, 0x00], vec![0x00, 0x01]),
+            mock_spi::Transaction::transaction_end(),
+        ];
+        let mut bus = mock_spi::Mock::new(&expectations);
+
+        let mut max = super::Max7300::new_max7301(bus.clone());
+        let max_pins = max.split();
+
+        let mut p12 = max_pins.p12.into_output().unwrap();
+        p12.set_high().unwrap();
+        assert!(max_pins.p20.is_high().unwrap());
+
+        bus.done();
+    }
+
+    #[test]
+    fn max7300_addr() {
+        use super::{Addr, AddressPin};
+
+        assert_eq!(
+            Addr::from_pins(AddressPin::Gnd, AddressPin::Gnd).get(),
+            0x40
+        );
+        assert_eq!(
+            Addr::from_pins(AddressPin::Scl, AddressPin::Sda).get(),
+            0x4b
+        );
+        assert!(Addr::custom(0x50).is_err());
+    }
+}
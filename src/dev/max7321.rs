@@ -1,4 +1,14 @@
 //! Support for the Maxim 7321 I2C 8-Port Open Drain port expander
+//!
+//! The outputs are open-drain and the chip has no direction register.  So generic code can set up
+//! pins like on other port-expanders, [`into_input()`](crate::Pin::into_input) writes the pin
+//! HIGH, which turns the output off, and [`into_output()`](crate::Pin::into_output) and
+//! [`into_output_high()`](crate::Pin::into_output_high) only write the requested level.  As a
+//! HIGH output is high-impedance, making a pin an input does not cause a glitch on the line.
+//!
+//! The chip flags input transitions and asserts its `INT` line until they are read.
+//! [`irq_state()`](crate::irq_state) reads the inputs together with these flags, which also
+//! clears them.
 pub struct Max7321<M>(M);
 
 /// MAX7321 "I2C Port Expander with 8 Open-Drain I/Os"
@@ -9,6 +19,46 @@ where
     pub fn new(i2c: I2C, a3: bool, a2: bool, a1: bool, a0: bool) -> Self {
         Self::with_mutex(i2c, a3, a2, a1, a0)
     }
+
+    /// Create a new instance and immediately write `initial` to the port.
+    ///
+    /// The chip powers up with all pins HIGH.  Writing the initial state during construction keeps
+    /// the window short in which pins which should be LOW are still HIGH.  Bit `n` of `initial` is
+    /// the `n`-th pin of [`pins()`](Max7321::pins).
+    pub fn new_with_state(
+        i2c: I2C,
+        a3: bool,
+        a2: bool,
+        a1: bool,
+        a0: bool,
+        initial: u8,
+    ) -> Result<Self, I2C::BusError> {
+        Self::with_mutex_state(i2c, a3, a2, a1, a0, initial)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Max7321<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new()`](Max7321::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a3: bool, a2: bool, a1: bool, a0: bool) -> Self {
+        Self::with_mutex(i2c, a3, a2, a1, a0)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Max7321<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new()`](Max7321::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a3: bool, a2: bool, a1: bool, a0: bool) -> Self {
+        Self::with_mutex(i2c, a3, a2, a1, a0)
+    }
 }
 
 impl<I2C, M> Max7321<M>
@@ -20,6 +70,51 @@ where
         Self(crate::PortMutex::create(Driver::new(i2c, a3, a2, a1, a0)))
     }
 
+    /// Like [`new_with_state()`](Max7321::new_with_state), with a custom mutex.
+    pub fn with_mutex_state(
+        i2c: I2C,
+        a3: bool,
+        a2: bool,
+        a1: bool,
+        a0: bool,
+        initial: u8,
+    ) -> Result<Self, I2C::BusError> {
+        let mut driver = Driver::new(i2c, a3, a2, a1, a0);
+        crate::PortDriver::set(&mut driver, initial as u32, !(initial as u32))?;
+        Ok(Self(crate::PortMutex::create(driver)))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x60` to `0x6f`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
     pub fn split(&mut self) -> Parts<'_, I2C, M> {
         Parts {
             p0: crate::Pin::new(0, &self.0),
@@ -32,6 +127,23 @@ where
             p7: crate::Pin::new(7, &self.0),
         }
     }
+
+    pin_methods! {
+        mode: crate::mode::QuasiBidirectional,
+        pins: 8,
+        error: I2C::BusError,
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// The chip has no direction register: writing HIGH to all pins turns off the output drivers,
+    /// leaving every pin as an input with the weak pull-up, just like after power-on.  Consuming
+    /// the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
 }
 
 pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
@@ -49,45 +161,188 @@ where
     pub p7: crate::Pin<'a, crate::mode::QuasiBidirectional, M>,
 }
 
+crate::address::addr_type! {
+    /// I2C address of the `MAX7321`.
+    pub struct Addr: 0x60, [a0 = 0, a1 = 1, a2 = 2, a3 = 3], 0x60..=0x6f;
+}
+
 pub struct Driver<I2C> {
     i2c: I2C,
     out: u8,
+    dirty: bool,
     addr: u8,
+    address_check: crate::AddressCheck,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
+    last_input: u8,
 }
 
 impl<I2C> Driver<I2C> {
     pub fn new(i2c: I2C, a3: bool, a2: bool, a1: bool, a0: bool) -> Self {
-        let addr = 0x60 | ((a3 as u8) << 3) | ((a2 as u8) << 2) | ((a1 as u8) << 1) | (a0 as u8);
+        let addr = Addr::from_pins(a0, a1, a2, a3).get();
+        Self::with_address(i2c, addr, crate::AddressCheck::Checked)
+    }
+
+    pub(crate) fn with_address(i2c: I2C, addr: u8, address_check: crate::AddressCheck) -> Self {
         Self {
             i2c,
             out: 0xff,
+            dirty: false,
             addr,
+            address_check,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
+            last_input: 0xff,
         }
     }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
+}
+
+impl<I2C: crate::I2cBus> Driver<I2C> {
+    /// Read all pins and return `(changed_mask, value)`, like `pcf8574::Driver::read_changes()`.
+    pub fn read_changes(&mut self) -> Result<(u32, u32), I2C::BusError> {
+        let mut buf = [0x00];
+        self.i2c.read(self.addr, &mut buf)?;
+        let value = buf[0];
+        let changed = value ^ self.last_input;
+        self.last_input = value;
+        Ok((changed as u32, value as u32))
+    }
 }
 
 impl<I2C: crate::I2cBus> crate::PortDriver for Driver<I2C> {
     type Error = I2C::BusError;
 
     fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         self.out |= mask_high as u8;
         self.out &= !mask_low as u8;
+        // stays set if the write fails
+        self.dirty = true;
         self.i2c.write(self.addr, &[self.out])?;
+        self.dirty = false;
         Ok(())
     }
 
     fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         Ok(((self.out as u32) & mask_high) | (!(self.out as u32) & mask_low))
     }
 
     fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
-        let mut buf = [0x00];
-        self.i2c.read(self.addr, &mut buf)?;
-        let in_ = buf[0] as u32;
+        let in_ = self.input_state.read(mask_high | mask_low, |_| {
+            let mut buf = [0x00];
+            self.i2c.read(self.addr, &mut buf)?;
+            Ok::<_, Self::Error>(buf[0] as u32)
+        })?;
         Ok((in_ & mask_high) | (!in_ & mask_low))
     }
 }
 
+impl<I2C: crate::I2cBus> crate::PortDriverIrqState for Driver<I2C> {
+    fn irq_state(&mut self) -> Result<crate::IrqState, Self::Error> {
+        // the second byte holds the transition flags, which are cleared by this read
+        let mut buf = [0x00; 2];
+        self.i2c.read(self.addr, &mut buf)?;
+        self.last_input = buf[0];
+        Ok(crate::IrqState {
+            source: buf[1] as u32,
+            captured: buf[0] as u32,
+        })
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverTotemPole for Driver<I2C> {
+    fn set_directions(&mut self, mask_input: u32, _mask_output: u32) -> Result<(), Self::Error> {
+        // quasi-bidirectional pins have no direction: an input is a pin which is released HIGH,
+        // outputs keep their current state
+        if mask_input == 0 {
+            return Ok(());
+        }
+        self.out |= mask_input as u8;
+        self.i2c.write(self.addr, &[self.out])?;
+        Ok(())
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverInputState for Driver<I2C> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverOutputInversion for Driver<I2C> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverClaims for Driver<I2C> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverCost for Driver<I2C> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set { .. } => 1,
+            crate::Operation::Get { mask } => self.input_state.read_mask(mask).is_some() as u32,
+            // only inputs are written, see `set_directions()`
+            crate::Operation::SetDirections { mask_input, .. } => (mask_input != 0) as u32,
+        }
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverResync for Driver<I2C> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        // the output latch cannot be read back, restore it from the cache instead
+        self.i2c.write(self.addr, &[self.out])?;
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverSnapshot for Driver<I2C> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        Ok(crate::DriverState {
+            pins: 0xff,
+            outputs: Some(self.out as u32),
+            output_pins: None,
+            pull_ups: None,
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, 0xff, 0)?,
+        })
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverRestoreDefaults for Driver<I2C> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        // all pins HIGH is the power-on state, which makes them inputs
+        self.out = 0xff;
+        self.i2c.write(self.addr, &[self.out])?;
+        Ok(())
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverRefresh for Driver<I2C> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        if mask & 0xFF != 0 {
+            self.i2c.write(self.addr, &[self.out])?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use embedded_hal_mock::eh1::i2c as mock_i2c;
@@ -113,4 +368,22 @@ mod tests {
 
         bus.done();
     }
+
+    #[test]
+    fn max7321_irq_state() {
+        let expectations = [mock_i2c::Transaction::read(
+            0b01100000,
+            vec![0b11110111, 0b00001000],
+        )];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut max = super::Max7321::new(bus.clone(), false, false, false, false);
+        let max_pins = max.split();
+
+        let irq = crate::irq_state(&max_pins.p0).unwrap();
+        assert_eq!(irq.source, max_pins.p3.pin_mask());
+        assert_eq!(irq.captured, 0b11110111);
+
+        bus.done();
+    }
 }
@@ -0,0 +1,308 @@
+//! Support for the Microchip `MCP23016` "16-Bit I2C I/O Expander"
+//!
+//! The `MCP23016` is the predecessor of the [`MCP23017`](super::mcp23x17).  Its register map is a
+//! lot smaller and laid out like the one of the `PCA9555`, so it shares the generic 16-bit driver
+//! instead of the `MCP23x17` one.  Unlike its successor, it has no pull-up resistors and no
+//! per-pin interrupt configuration: every input change raises an interrupt.
+//!
+//! The chip is clocked by an external RC network on its `CLK` pin, which must be running before
+//! the chip answers on the bus.  Allow for the power-on reset and oscillator start-up time of the
+//! datasheet before creating the driver.  The outputs are LOW after power-up, so pins which are
+//! made outputs drive LOW until they are set otherwise.
+
+/// Microchip `MCP23016` "16-Bit I2C I/O Expander"
+pub struct Mcp23016<M>(M);
+
+impl<I2C> Mcp23016<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Mcp23016<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Mcp23016::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Mcp23016<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Mcp23016::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+impl<I2C, M> Mcp23016<M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub fn with_mutex(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self(crate::PortMutex::create(Driver::new(i2c, a0, a1, a2)))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x27`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
+    pub fn split(&mut self) -> Parts<'_, I2C, M> {
+        Parts {
+            gp0_0: crate::Pin::new(0, &self.0),
+            gp0_1: crate::Pin::new(1, &self.0),
+            gp0_2: crate::Pin::new(2, &self.0),
+            gp0_3: crate::Pin::new(3, &self.0),
+            gp0_4: crate::Pin::new(4, &self.0),
+            gp0_5: crate::Pin::new(5, &self.0),
+            gp0_6: crate::Pin::new(6, &self.0),
+            gp0_7: crate::Pin::new(7, &self.0),
+            gp1_0: crate::Pin::new(8, &self.0),
+            gp1_1: crate::Pin::new(9, &self.0),
+            gp1_2: crate::Pin::new(10, &self.0),
+            gp1_3: crate::Pin::new(11, &self.0),
+            gp1_4: crate::Pin::new(12, &self.0),
+            gp1_5: crate::Pin::new(13, &self.0),
+            gp1_6: crate::Pin::new(14, &self.0),
+            gp1_7: crate::Pin::new(15, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 16,
+        error: I2C::BusError,
+    }
+
+    /// Select the interrupt activity resolution (`IARES` in `IOCON0`).
+    ///
+    /// After power-up, the chip samples its inputs for interrupts every 32 ms.  With `fast`, it
+    /// samples them every 200 us instead, at the cost of a higher supply current.
+    pub fn set_fast_interrupts(&self, fast: bool) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| drv.set_fast_interrupts(fast))
+    }
+
+    /// Read the port state captured when the last interrupt was raised.
+    ///
+    /// Reading the capture registers (`INTCAP0` and `INTCAP1`) does not clear the interrupt, only
+    /// reading the port does.
+    pub fn interrupt_capture(&self) -> Result<u32, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| drv.interrupt_capture())
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xffff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output latches are cleared to LOW and input polarity inversion
+    /// is cleared.  Consuming the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub gp0_0: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp0_1: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp0_2: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp0_3: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp0_4: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp0_5: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp0_6: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp0_7: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp1_0: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp1_1: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp1_2: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp1_3: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp1_4: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp1_5: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp1_6: crate::Pin<'a, crate::mode::Input, M>,
+    pub gp1_7: crate::Pin<'a, crate::mode::Input, M>,
+}
+
+/// Registers of the chip.
+///
+/// Each register is a pair of one register per port.  The chip toggles between the two registers
+/// of a pair when more than one byte is transferred.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Regs {
+    /// GP: port register
+    Gp0 = 0x00,
+    Gp1 = 0x01,
+    /// OLAT: output latch register
+    Olat0 = 0x02,
+    Olat1 = 0x03,
+    /// IPOL: input polarity register
+    Ipol0 = 0x04,
+    Ipol1 = 0x05,
+    /// IODIR: direction register, 1: input, 0: output
+    Iodir0 = 0x06,
+    Iodir1 = 0x07,
+    /// INTCAP: interrupt captured value
+    Intcap0 = 0x08,
+    Intcap1 = 0x09,
+    /// IOCON: I/O expander control register
+    Iocon0 = 0x0a,
+    Iocon1 = 0x0b,
+}
+
+impl From<Regs> for u8 {
+    fn from(r: Regs) -> u8 {
+        r as u8
+    }
+}
+
+/// Interrupt activity resolution bit in `IOCON0`
+const IARES: u8 = 0x01;
+
+crate::address::addr_type! {
+    /// I2C address of the `MCP23016`.
+    pub struct Addr: 0x20, [a0 = 0, a1 = 1, a2 = 2], 0x20..=0x27;
+}
+
+/// Register map of the `MCP23016`.
+pub struct Registers;
+
+impl crate::driver::Regs16 for Registers {
+    const INPUT: [u8; 2] = [Regs::Gp0 as u8, Regs::Gp1 as u8];
+    const OUTPUT: [u8; 2] = [Regs::Olat0 as u8, Regs::Olat1 as u8];
+    const POLARITY_INVERSION: [u8; 2] = [Regs::Ipol0 as u8, Regs::Ipol1 as u8];
+    const CONFIGURATION: [u8; 2] = [Regs::Iodir0 as u8, Regs::Iodir1 as u8];
+    const OUTPUT_RESET: Option<u16> = Some(0x0000);
+}
+
+pub type Driver<I2C> = crate::driver::Driver16<I2C, Registers>;
+
+impl<I2C> Driver<I2C> {
+    pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        let addr = Addr::from_pins(a0, a1, a2).get();
+        Self::with_address(i2c, addr, crate::AddressCheck::Checked)
+    }
+}
+
+impl<I2C: crate::RegisterBus> Driver<I2C> {
+    /// Select the interrupt activity resolution, see
+    /// [`Mcp23016::set_fast_interrupts()`].
+    pub fn set_fast_interrupts(&mut self, fast: bool) -> Result<(), I2C::BusError> {
+        // only IARES is implemented, the other bits of IOCON0 read as 0
+        self.i2c
+            .write_reg(self.addr, Regs::Iocon0, if fast { IARES } else { 0x00 })
+    }
+
+    /// Read the port state captured when the last interrupt was raised.
+    pub fn interrupt_capture(&mut self) -> Result<u32, I2C::BusError> {
+        let intcap0 = self.i2c.read_reg(self.addr, Regs::Intcap0)?;
+        let intcap1 = self.i2c.read_reg(self.addr, Regs::Intcap1)?;
+        Ok(((intcap1 as u32) << 8) | intcap0 as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn mcp23016() {
+        let expectations = [
+            // pin setup gp0_0, the output latch is LOW after power-up
+            mock_i2c::Transaction::write(0x21, vec![0x02, 0x00]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x21, vec![0x06, 0xfe]),
+            // pin setup gp1_7
+            mock_i2c::Transaction::write(0x21, vec![0x03, 0x80]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x07], vec![0xff]),
+            mock_i2c::Transaction::write(0x21, vec![0x07, 0x7f]),
+            // output gp0_0
+            mock_i2c::Transaction::write(0x21, vec![0x02, 0x01]),
+            mock_i2c::Transaction::write(0x21, vec![0x02, 0x00]),
+            // input gp0_7
+            mock_i2c::Transaction::write_read(0x21, vec![0x00], vec![0x80]),
+            // polarity gp1_0
+            mock_i2c::Transaction::write_read(0x21, vec![0x05], vec![0x00]),
+            mock_i2c::Transaction::write(0x21, vec![0x05, 0x01]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut mcp = super::Mcp23016::new(bus.clone(), true, false, false);
+        let mcp_pins = mcp.split();
+
+        let mut gp0_0 = mcp_pins.gp0_0.into_output().unwrap();
+        let _ = mcp_pins.gp1_7.into_output_high().unwrap();
+
+        gp0_0.set_high().unwrap();
+        gp0_0.set_low().unwrap();
+        assert!(mcp_pins.gp0_7.is_high().unwrap());
+        let _ = mcp_pins.gp1_0.into_inverted().unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn mcp23016_interrupts() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0x0a, 0x01]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x08], vec![0x7f]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x09], vec![0x01]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mcp = super::Mcp23016::new(bus.clone(), false, false, false);
+        mcp.set_fast_interrupts(true).unwrap();
+        assert_eq!(mcp.interrupt_capture().unwrap(), 0x017f);
+
+        bus.done();
+    }
+}
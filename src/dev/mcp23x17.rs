@@ -6,11 +6,20 @@
 //! address pins, so eight devices can coexist on an I2C bus.
 //!
 //! Each port has an interrupt, which can be configured to work
-//! together or independently.
+//! together or independently with
+//! [`set_interrupt_output()`](Mcp23x17::set_interrupt_output).  Inputs raise interrupts as
+//! configured by [`Pin::set_interrupt()`](crate::Pin::set_interrupt), and
+//! [`fetch_interrupt_state()`](Mcp23x17::fetch_interrupt_state) reports which pins caused them.
+//! This works the same for the I2C and the SPI variant.
 //!
 //! When passing 16-bit values to this driver, the upper byte corresponds to port
 //! B (pins 7..0) and the lower byte corresponds to port A (pins 7..0).
-use crate::I2cExt;
+use crate::RegisterBus;
+
+/// Register bus of the MCP23x17.
+///
+/// This is the generic [`RegisterBus`] which all drivers use, kept under its old name.
+pub use crate::bus::RegisterBus as Mcp23x17Bus;
 
 /// `MCP23x17` "16-Bit I/O Expander with Serial Interface" with I2C or SPI interface
 pub struct Mcp23x17<M>(M);
@@ -25,25 +34,132 @@ where
     }
 }
 
+#[cfg(feature = "critical-section")]
+impl<I2C> Mcp23x17<critical_section::Mutex<core::cell::RefCell<Driver<Mcp23017Bus<I2C>>>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new_mcp23017()`](Mcp23x17::new_mcp23017), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_mcp23017_critical_section(bus: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(Mcp23017Bus(bus), a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Mcp23x17<std::sync::Mutex<Driver<Mcp23017Bus<I2C>>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new_mcp23017()`](Mcp23x17::new_mcp23017), but the device is wrapped in a
+    /// `std::sync::Mutex` so it can be shared between threads.
+    pub fn new_mcp23017_std(bus: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(Mcp23017Bus(bus), a0, a1, a2)
+    }
+}
+
 impl<SPI> Mcp23x17<core::cell::RefCell<Driver<Mcp23S17Bus<SPI>>>>
 where
     SPI: crate::SpiBus,
 {
     /// Create a new instance of the MCP23S17 with SPI interface
     pub fn new_mcp23s17(bus: SPI) -> Self {
-        Self::with_mutex(Mcp23S17Bus(bus), false, false, false)
+        Self::with_mutex(crate::SpiRegisters::new(bus), false, false, false)
+    }
+
+    /// Create a new instance of the MCP23S17 with SPI interface and hardware addressing enabled
+    ///
+    /// This sets the `HAEN` bit in `IOCON` so the chip only responds to the address selected by
+    /// its `A0`-`A2` pins.  Up to eight MCP23S17 can then share the same chip-select line.
+    pub fn new_mcp23s17_with_haen(
+        bus: SPI,
+        a0: bool,
+        a1: bool,
+        a2: bool,
+    ) -> Result<Self, SPI::BusError> {
+        Self::with_mutex_haen(bus, a0, a1, a2)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<SPI> Mcp23x17<critical_section::Mutex<core::cell::RefCell<Driver<Mcp23S17Bus<SPI>>>>>
+where
+    SPI: crate::SpiBus,
+{
+    /// Same as [`new_mcp23s17()`](Mcp23x17::new_mcp23s17), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_mcp23s17_critical_section(bus: SPI) -> Self {
+        Self::with_mutex(crate::SpiRegisters::new(bus), false, false, false)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<SPI> Mcp23x17<std::sync::Mutex<Driver<Mcp23S17Bus<SPI>>>>
+where
+    SPI: crate::SpiBus,
+{
+    /// Same as [`new_mcp23s17()`](Mcp23x17::new_mcp23s17), but the device is wrapped in a
+    /// `std::sync::Mutex` so it can be shared between threads.
+    pub fn new_mcp23s17_std(bus: SPI) -> Self {
+        Self::with_mutex(crate::SpiRegisters::new(bus), false, false, false)
+    }
+}
+
+impl<SPI, M> Mcp23x17<M>
+where
+    SPI: crate::SpiBus,
+    M: crate::PortMutex<Port = Driver<Mcp23S17Bus<SPI>>>,
+{
+    /// Like [`new_mcp23s17_with_haen()`](Mcp23x17::new_mcp23s17_with_haen), with a custom mutex.
+    pub fn with_mutex_haen(bus: SPI, a0: bool, a1: bool, a2: bool) -> Result<Self, SPI::BusError> {
+        let mut driver = Driver::new(crate::SpiRegisters::new(bus), a0, a1, a2);
+        driver.enable_haen()?;
+        Ok(Self(crate::PortMutex::create(driver)))
     }
 }
 
 impl<B, M> Mcp23x17<M>
 where
-    B: Mcp23x17Bus,
+    B: RegisterBus,
     M: crate::PortMutex<Port = Driver<B>>,
 {
     pub fn with_mutex(bus: B, a0: bool, a1: bool, a2: bool) -> Self {
         Self(crate::PortMutex::create(Driver::new(bus, a0, a1, a2)))
     }
 
+    /// Create a new instance with the address `addr`.
+    pub fn from_addr(bus: B, addr: Addr) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            bus,
+            addr.get(),
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit address.
+    ///
+    /// For the `MCP23S17`, this is the hardware address which is selected by the address pins
+    /// when `HAEN` is enabled, in the same format as for the `MCP23017`.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x27`).
+    pub fn with_address(bus: B, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(bus, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(bus: B, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            bus,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
     pub fn split<'a>(&'a mut self) -> Parts<'a, B, M> {
         Parts {
             gpa0: crate::Pin::new(0, &self.0),
@@ -64,11 +180,68 @@ where
             gpb7: crate::Pin::new(15, &self.0),
         }
     }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 16,
+        error: B::BusError,
+    }
+
+    /// Configure the `INTA`/`INTB` interrupt outputs.
+    ///
+    /// With `mirror`, both outputs are asserted for an interrupt of either port, so a single line
+    /// can be wired up.  Otherwise `INTA` belongs to port A and `INTB` to port B.
+    pub fn set_interrupt_output(
+        &self,
+        mirror: bool,
+        output: InterruptOutput,
+    ) -> Result<(), B::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| drv.set_interrupt_output(mirror, output))
+    }
+
+    /// Enable or disable the slew rate control of the `SDA` output (`DISSLW` in `IOCON`).
+    ///
+    /// Slew rate control is enabled after reset.  Disabling it can help with fast-mode I2C on
+    /// lightly loaded buses.  This has no effect on the `MCP23S17`.
+    ///
+    /// The interrupt output bits of `IOCON` are configured with
+    /// [`set_interrupt_output()`](Self::set_interrupt_output).  `BANK`, `SEQOP` and `HAEN` are
+    /// managed by the driver.
+    pub fn set_slew_rate_control(&self, enable: bool) -> Result<(), B::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| drv.set_slew_rate_control(enable))
+    }
+
+    /// Read which pins caused an interrupt and their input state at that time.
+    ///
+    /// This releases the interrupt outputs.
+    pub fn fetch_interrupt_state(&self) -> Result<InterruptState, B::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| drv.fetch_interrupt_state())
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, B::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xffff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output latches are cleared to LOW and input polarity inversion,
+    /// the pull-ups and the interrupt-on-change configuration are reset.  Consuming the device
+    /// requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), B::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
 }
 
 pub struct Parts<'a, B, M = core::cell::RefCell<Driver<B>>>
 where
-    B: Mcp23x17Bus,
+    B: RegisterBus,
     M: crate::PortMutex<Port = Driver<B>>,
 {
     pub gpa0: crate::Pin<'a, crate::mode::Input, M>,
@@ -89,7 +262,30 @@ where
     pub gpb7: crate::Pin<'a, crate::mode::Input, M>,
 }
 
-#[allow(dead_code)]
+/// Electrical configuration of the `INTA`/`INTB` outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InterruptOutput {
+    /// Push-pull output which is LOW while an interrupt is pending.  This is the reset state.
+    ActiveLow,
+    /// Push-pull output which is HIGH while an interrupt is pending.
+    ActiveHigh,
+    /// Open-drain output which pulls LOW while an interrupt is pending.
+    OpenDrain,
+}
+
+/// Interrupt flags and captured inputs, see
+/// [`fetch_interrupt_state()`](Mcp23x17::fetch_interrupt_state).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InterruptState {
+    /// Pins which caused the interrupt (`INTF`).
+    pub flags: u16,
+    /// Input state of all pins when the interrupt occurred (`INTCAP`).
+    pub captured: u16,
+}
+
+#[allow(dead_code, clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// N.B.: These values are for BANK=0, which is the reset state of
 /// the chip (and this driver does not change).
@@ -111,12 +307,12 @@ enum Regs {
     /// IOCON: configuration register
     /// - Pin 7: BANK (which driver assumes stays 0)
     /// - Pin 6: MIRROR: if enabled, INT{A,B} are logically ORed; an interrupt on either
-    ///          port will cause both pins to activate
+    ///   port will cause both pins to activate
     /// - Pin 5: SEQOP: controls the incrementing function of the address pointer
     /// - Pin 4: DISSLW: disables slew rate control on SDA
     /// - Pin 3: HAEN: no effect on MCP23017, enables address pins on MCP23S17
     /// - Pin 2: ODR: interrupt pins are 0=active-driver outputs (INTPOL sets polarity)
-    ///          or 1=open-drain outputs (overrides INTPOL)
+    ///   or 1=open-drain outputs (overrides INTPOL)
     /// - Pin 1: INTPOL: interrupt pin is 0=active-low or 1=active-high
     /// - Pin 0: unused
     IOCONA = 0x0a,
@@ -146,12 +342,12 @@ enum Regs {
     /// IOCON: configuration register
     /// - Pin 7: BANK (which driver assumes stays 0)
     /// - Pin 6: MIRROR: if enabled, INT{A,B} are logically ORed; an interrupt on either
-    ///          port will cause both pins to activate
+    ///   port will cause both pins to activate
     /// - Pin 5: SEQOP: controls the incrementing function of the address pointer
     /// - Pin 4: DISSLW: disables slew rate control on SDA
     /// - Pin 3: HAEN: no effect on MCP23017, enables address pins on MCP23S17
     /// - Pin 2: ODR: interrupt pins are 0=active-driver outputs (INTPOL sets polarity)
-    ///          or 1=open-drain outputs (overrides INTPOL)
+    ///   or 1=open-drain outputs (overrides INTPOL)
     /// - Pin 1: INTPOL: interrupt pin is 0=active-low or 1=active-high
     /// - Pin 0: unused
     IOCONB = 0x0b,
@@ -175,92 +371,486 @@ impl From<Regs> for u8 {
     }
 }
 
+crate::address::addr_type! {
+    /// I2C address of the `MCP23017`/`MCP23S17`.
+    pub struct Addr: 0x20, [a0 = 0, a1 = 1, a2 = 2], 0x20..=0x27;
+}
+
 pub struct Driver<B> {
     bus: B,
     out: u16,
+    dirty: bool,
     addr: u8,
+    address_check: crate::AddressCheck,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
 }
 
 impl<B> Driver<B> {
     pub fn new(bus: B, a0: bool, a1: bool, a2: bool) -> Self {
-        let addr = 0x20 | ((a2 as u8) << 2) | ((a1 as u8) << 1) | (a0 as u8);
+        let addr = Addr::from_pins(a0, a1, a2).get();
+        Self::with_address(bus, addr, crate::AddressCheck::Checked)
+    }
+
+    pub(crate) fn with_address(bus: B, addr: u8, address_check: crate::AddressCheck) -> Self {
         Self {
             bus,
             out: 0x0000,
+            dirty: false,
             addr,
+            address_check,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
+        }
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
+}
+
+impl<B: RegisterBus> Driver<B> {
+    fn enable_haen(&mut self) -> Result<(), B::BusError> {
+        // As long as HAEN is disabled, all chips respond to hardware address 0 regardless of
+        // their address pins.  This write thus reaches every chip sharing the chip-select line,
+        // which is harmless as it is identical for all of them.
+        self.bus.write_reg(0x20, Regs::IOCONA, 0x08)
+    }
+
+    /// Update the port A register `reg_a` and the port B register `reg_b`.
+    ///
+    /// Ports which are not touched by either mask are not accessed.
+    fn update_ports(
+        &mut self,
+        reg_a: Regs,
+        reg_b: Regs,
+        mask_set: u16,
+        mask_clear: u16,
+    ) -> Result<(), B::BusError> {
+        let mask = mask_set | mask_clear;
+        if mask & 0x00FF != 0 {
+            self.bus.update_reg(
+                self.addr,
+                reg_a,
+                (mask_set & 0xFF) as u8,
+                (mask_clear & 0xFF) as u8,
+            )?;
+        }
+        if mask & 0xFF00 != 0 {
+            self.bus.update_reg(
+                self.addr,
+                reg_b,
+                (mask_set >> 8) as u8,
+                (mask_clear >> 8) as u8,
+            )?;
         }
+        Ok(())
+    }
+
+    /// Configure when the inputs in `mask` raise an interrupt.
+    pub fn set_interrupt(
+        &mut self,
+        mask: u32,
+        interrupt: crate::InterruptMode,
+    ) -> Result<(), B::BusError> {
+        let mask = mask as u16;
+        let (compare, defval) = match interrupt {
+            crate::InterruptMode::Disabled => {
+                return self.update_ports(Regs::GPINTENA, Regs::GPINTENB, 0, mask)
+            }
+            crate::InterruptMode::OnChange => (false, false),
+            // the interrupt is raised while the input differs from DEFVAL
+            crate::InterruptMode::WhileHigh => (true, false),
+            crate::InterruptMode::WhileLow => (true, true),
+        };
+        if compare {
+            let (set, clear) = if defval { (mask, 0) } else { (0, mask) };
+            self.update_ports(Regs::DEFVALA, Regs::DEFVALB, set, clear)?;
+            self.update_ports(Regs::INTCONA, Regs::INTCONB, mask, 0)?;
+        } else {
+            self.update_ports(Regs::INTCONA, Regs::INTCONB, 0, mask)?;
+        }
+        self.update_ports(Regs::GPINTENA, Regs::GPINTENB, mask, 0)
+    }
+
+    /// Configure the `INTA`/`INTB` outputs, see
+    /// [`Mcp23x17::set_interrupt_output()`].
+    pub fn set_interrupt_output(
+        &mut self,
+        mirror: bool,
+        output: InterruptOutput,
+    ) -> Result<(), B::BusError> {
+        const MIRROR: u8 = 1 << 6;
+        const ODR: u8 = 1 << 2;
+        const INTPOL: u8 = 1 << 1;
+        let mut set = match output {
+            InterruptOutput::ActiveLow => 0,
+            InterruptOutput::ActiveHigh => INTPOL,
+            InterruptOutput::OpenDrain => ODR,
+        };
+        if mirror {
+            set |= MIRROR;
+        }
+        // IOCONA and IOCONB are the same register, the other bits (e.g. HAEN) are kept
+        self.bus
+            .update_reg(self.addr, Regs::IOCONA, set, (MIRROR | ODR | INTPOL) & !set)
+    }
+
+    /// Enable or disable the `SDA` slew rate control, see
+    /// [`Mcp23x17::set_slew_rate_control()`].
+    pub fn set_slew_rate_control(&mut self, enable: bool) -> Result<(), B::BusError> {
+        const DISSLW: u8 = 1 << 4;
+        let (set, clear) = if enable { (0, DISSLW) } else { (DISSLW, 0) };
+        self.bus.update_reg(self.addr, Regs::IOCONA, set, clear)
+    }
+
+    /// Read the interrupt flags and captured inputs, see
+    /// [`Mcp23x17::fetch_interrupt_state()`].
+    pub fn fetch_interrupt_state(&mut self) -> Result<InterruptState, B::BusError> {
+        let intfa = self.bus.read_reg(self.addr, Regs::INTFA)?;
+        let intfb = self.bus.read_reg(self.addr, Regs::INTFB)?;
+        // reading INTCAP clears the interrupt
+        let intcapa = self.bus.read_reg(self.addr, Regs::INTCAPA)?;
+        let intcapb = self.bus.read_reg(self.addr, Regs::INTCAPB)?;
+        Ok(InterruptState {
+            flags: ((intfb as u16) << 8) | intfa as u16,
+            captured: ((intcapb as u16) << 8) | intcapa as u16,
+        })
     }
 }
 
-impl<B: Mcp23x17Bus> crate::PortDriver for Driver<B> {
+impl<B: RegisterBus> crate::PortDriver for Driver<B> {
     type Error = B::BusError;
 
     fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         self.out |= mask_high as u16;
         self.out &= !mask_low as u16;
-        if (mask_high | mask_low) & 0x00FF != 0 {
+        // a failed write leaves the device in an unknown state, so all banks are written until a
+        // write succeeds again
+        let mask = match self.dirty {
+            true => 0xFFFF,
+            false => mask_high | mask_low,
+        };
+        self.dirty = true;
+        if mask & 0x00FF != 0 {
             self.bus
-                .write_reg(self.addr, Regs::GPIOA, (self.out & 0xFF) as u8)?;
+                .write_reg(self.addr, Regs::OLATA, (self.out & 0xFF) as u8)?;
         }
-        if (mask_high | mask_low) & 0xFF00 != 0 {
+        if mask & 0xFF00 != 0 {
             self.bus
-                .write_reg(self.addr, Regs::GPIOB, (self.out >> 8) as u8)?;
+                .write_reg(self.addr, Regs::OLATB, (self.out >> 8) as u8)?;
         }
+        self.dirty = false;
         Ok(())
     }
 
     fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         Ok(((self.out as u32) & mask_high) | (!(self.out as u32) & mask_low))
     }
 
     fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
-        let io0 = if (mask_high | mask_low) & 0x00FF != 0 {
-            self.bus.read_reg(self.addr, Regs::GPIOA)?
-        } else {
-            0
-        };
-        let io1 = if (mask_high | mask_low) & 0xFF00 != 0 {
-            self.bus.read_reg(self.addr, Regs::GPIOB)?
-        } else {
-            0
+        let in_ = self.input_state.read(mask_high | mask_low, |mask| {
+            let io0 = if mask & 0x00FF != 0 {
+                self.bus.read_reg(self.addr, Regs::GPIOA)?
+            } else {
+                0
+            };
+            let io1 = if mask & 0xFF00 != 0 {
+                self.bus.read_reg(self.addr, Regs::GPIOB)?
+            } else {
+                0
+            };
+            Ok::<_, Self::Error>(((io1 as u32) << 8) | io0 as u32)
+        })?;
+        Ok((in_ & mask_high) | (!in_ & mask_low))
+    }
+
+    fn get_polarity(&mut self, mask: u32) -> Result<u32, Self::Error> {
+        let mut inverted = 0;
+        if mask & 0x00FF != 0 {
+            inverted |= self.bus.read_reg(self.addr, Regs::IPOLA)? as u32;
+        }
+        if mask & 0xFF00 != 0 {
+            inverted |= (self.bus.read_reg(self.addr, Regs::IPOLB)? as u32) << 8;
+        }
+        Ok(inverted & mask)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<B: embedded_hal_async::i2c::I2c> Driver<B> {
+    /// Async counterpart of `update_ports()`.
+    async fn update_ports_async(
+        &mut self,
+        reg_a: Regs,
+        reg_b: Regs,
+        mask_set: u16,
+        mask_clear: u16,
+    ) -> Result<(), B::Error> {
+        let mask = mask_set | mask_clear;
+        for (port, reg) in [reg_a, reg_b].into_iter().enumerate() {
+            let shift = 8 * port;
+            if (mask >> shift) & 0xFF != 0 {
+                crate::bus::update_reg_async(
+                    &mut self.bus,
+                    self.addr,
+                    reg.into(),
+                    (mask_set >> shift) as u8,
+                    (mask_clear >> shift) as u8,
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The `MCP23017` on an async I2C bus, see [`AsyncPort`](crate::AsyncPort).
+#[cfg(feature = "async")]
+impl<B: embedded_hal_async::i2c::I2c> crate::AsyncPortDriver for Driver<B> {
+    type Error = B::Error;
+
+    const PINS: u32 = 0xffff;
+
+    async fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        self.out |= mask_high as u16;
+        self.out &= !mask_low as u16;
+        // see the blocking `set()`
+        let mask = match self.dirty {
+            true => 0xFFFF,
+            false => mask_high | mask_low,
         };
-        let in_ = ((io1 as u32) << 8) | io0 as u32;
+        self.dirty = true;
+        if mask & 0x00FF != 0 {
+            let out = (self.out & 0xFF) as u8;
+            crate::bus::write_reg_async(&mut self.bus, self.addr, Regs::OLATA.into(), out).await?;
+        }
+        if mask & 0xFF00 != 0 {
+            let out = (self.out >> 8) as u8;
+            crate::bus::write_reg_async(&mut self.bus, self.addr, Regs::OLATB.into(), out).await?;
+        }
+        self.dirty = false;
+        Ok(())
+    }
+
+    async fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        Ok(((self.out as u32) & mask_high) | (!(self.out as u32) & mask_low))
+    }
+
+    async fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        // the input state can't await the bus, so read what it needs up front
+        let mut raw = 0;
+        if let Some(mask) = self.input_state.read_mask(mask_high | mask_low) {
+            for (port, reg) in [Regs::GPIOA, Regs::GPIOB].into_iter().enumerate() {
+                if (mask >> (8 * port)) & 0xFF != 0 {
+                    let in_ =
+                        crate::bus::read_reg_async(&mut self.bus, self.addr, reg.into()).await?;
+                    raw |= (in_ as u32) << (8 * port);
+                }
+            }
+        }
+        let in_ = self
+            .input_state
+            .read(mask_high | mask_low, |_| Ok::<_, Self::Error>(raw))?;
         Ok((in_ & mask_high) | (!in_ & mask_low))
     }
 }
 
-impl<B: Mcp23x17Bus> crate::PortDriverTotemPole for Driver<B> {
+#[cfg(feature = "async")]
+impl<B: embedded_hal_async::i2c::I2c> crate::AsyncPortDriverTotemPole for Driver<B> {
+    async fn set_directions(
+        &mut self,
+        mask_input: u32,
+        mask_output: u32,
+    ) -> Result<(), Self::Error> {
+        self.update_ports_async(
+            Regs::IODIRA,
+            Regs::IODIRB,
+            mask_input as u16,
+            mask_output as u16,
+        )
+        .await
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverInputState for Driver<B> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverOutputInversion for Driver<B> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverClaims for Driver<B> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverCost for Driver<B> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set {
+                mask_high,
+                mask_low,
+            } => crate::cost::banks16(mask_high | mask_low),
+            crate::Operation::Get { mask } => self
+                .input_state
+                .read_mask(mask)
+                .map_or(0, |mask| B::READ_COST * crate::cost::banks16(mask)),
+            crate::Operation::SetDirections {
+                mask_input,
+                mask_output,
+            } => (B::READ_COST + 1) * crate::cost::banks16(mask_input | mask_output),
+        }
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverResync for Driver<B> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        let out_low = self.bus.read_reg(self.addr, Regs::OLATA)? as u16;
+        let out_high = self.bus.read_reg(self.addr, Regs::OLATB)? as u16;
+        self.out = out_low | (out_high << 8);
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverSnapshot for Driver<B> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        let iodir_a = self.bus.read_reg(self.addr, Regs::IODIRA)?;
+        let iodir_b = self.bus.read_reg(self.addr, Regs::IODIRB)?;
+        let gppu_a = self.bus.read_reg(self.addr, Regs::GPPUA)?;
+        let gppu_b = self.bus.read_reg(self.addr, Regs::GPPUB)?;
+        let iodir = ((iodir_b as u32) << 8) | iodir_a as u32;
+        Ok(crate::DriverState {
+            pins: 0xffff,
+            outputs: Some(self.out as u32),
+            output_pins: Some(!iodir & 0xffff),
+            pull_ups: Some(((gppu_b as u32) << 8) | gppu_a as u32),
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, 0xffff, 0)?,
+        })
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverRestoreDefaults for Driver<B> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        // inputs first, so the outputs stop driving before anything else changes
+        for (reg, value) in [
+            (Regs::IODIRA, 0xff),
+            (Regs::IODIRB, 0xff),
+            (Regs::IPOLA, 0x00),
+            (Regs::IPOLB, 0x00),
+            (Regs::GPINTENA, 0x00),
+            (Regs::GPINTENB, 0x00),
+            (Regs::DEFVALA, 0x00),
+            (Regs::DEFVALB, 0x00),
+            (Regs::INTCONA, 0x00),
+            (Regs::INTCONB, 0x00),
+            (Regs::GPPUA, 0x00),
+            (Regs::GPPUB, 0x00),
+            (Regs::OLATA, 0x00),
+            (Regs::OLATB, 0x00),
+        ] {
+            self.bus.write_reg(self.addr, reg, value)?;
+        }
+        self.out = 0x0000;
+        Ok(())
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverRefresh for Driver<B> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        if mask & 0x00FF != 0 {
+            self.bus
+                .write_reg(self.addr, Regs::OLATA, (self.out & 0xFF) as u8)?;
+        }
+        if mask & 0xFF00 != 0 {
+            self.bus
+                .write_reg(self.addr, Regs::OLATB, (self.out >> 8) as u8)?;
+        }
+        Ok(())
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverStatus for Driver<B> {
+    fn status(&mut self) -> Result<crate::Status, Self::Error> {
+        let intfa = self.bus.read_reg(self.addr, Regs::INTFA)?;
+        let intfb = self.bus.read_reg(self.addr, Regs::INTFB)?;
+        Ok(crate::Status {
+            reset: None,
+            interrupt_source: Some(((intfb as u32) << 8) | intfa as u32),
+        })
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverInterrupt for Driver<B> {
+    fn set_interrupt(&mut self, mask: u32, mode: crate::InterruptMode) -> Result<(), Self::Error> {
+        Driver::set_interrupt(self, mask, mode)
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverIrqState for Driver<B> {
+    fn irq_state(&mut self) -> Result<crate::IrqState, Self::Error> {
+        let state = self.fetch_interrupt_state()?;
+        Ok(crate::IrqState {
+            source: state.flags as u32,
+            captured: state.captured as u32,
+        })
+    }
+}
+
+impl<B: RegisterBus> crate::PortDriverTotemPole for Driver<B> {
     fn set_direction(
         &mut self,
         mask: u32,
         dir: crate::Direction,
         _state: bool,
     ) -> Result<(), Self::Error> {
-        let (mask_set, mask_clear) = match dir {
-            crate::Direction::Input => (mask as u16, 0),
-            crate::Direction::Output => (0, mask as u16),
-        };
+        match dir {
+            crate::Direction::Input => self.set_directions(mask, 0),
+            crate::Direction::Output => self.set_directions(0, mask),
+        }
+    }
+
+    fn set_directions(&mut self, mask_input: u32, mask_output: u32) -> Result<(), Self::Error> {
+        let mask = mask_input | mask_output;
         if mask & 0x00FF != 0 {
             self.bus.update_reg(
                 self.addr,
                 Regs::IODIRA,
-                (mask_set & 0xFF) as u8,
-                (mask_clear & 0xFF) as u8,
+                (mask_input & 0xFF) as u8,
+                (mask_output & 0xFF) as u8,
             )?;
         }
         if mask & 0xFF00 != 0 {
             self.bus.update_reg(
                 self.addr,
                 Regs::IODIRB,
-                (mask_set >> 8) as u8,
-                (mask_clear >> 8) as u8,
+                (mask_input >> 8) as u8,
+                (mask_output >> 8) as u8,
             )?;
         }
         Ok(())
     }
 }
 
-impl<B: Mcp23x17Bus> crate::PortDriverPullUp for Driver<B> {
+impl<B: RegisterBus> crate::PortDriverPullUp for Driver<B> {
     fn set_pull_up(&mut self, mask: u32, enable: bool) -> Result<(), Self::Error> {
         let (mask_set, mask_clear) = match enable {
             true => (mask as u16, 0),
@@ -286,7 +876,7 @@ impl<B: Mcp23x17Bus> crate::PortDriverPullUp for Driver<B> {
     }
 }
 
-impl<B: Mcp23x17Bus> crate::PortDriverPolarity for Driver<B> {
+impl<B: RegisterBus> crate::PortDriverPolarity for Driver<B> {
     fn set_polarity(&mut self, mask: u32, inverted: bool) -> Result<(), Self::Error> {
         let (mask_set, mask_clear) = match inverted {
             true => (mask as u16, 0),
@@ -312,64 +902,22 @@ impl<B: Mcp23x17Bus> crate::PortDriverPolarity for Driver<B> {
     }
 }
 
-// We need these newtype wrappers since we can't implement `Mcp23x17Bus` for both `I2cBus` and `SpiBus`
-// at the same time
+// We need this newtype wrapper since `RegisterBus` is already implemented for all `I2cBus`
 pub struct Mcp23017Bus<I2C>(I2C);
-pub struct Mcp23S17Bus<SPI>(SPI);
-
-/// Special -Bus trait for the Mcp23x17 since the SPI version is a bit special/weird in terms of writing
-/// SPI registers, which can't necessarily be generialized for other devices.
-pub trait Mcp23x17Bus {
-    type BusError;
-
-    fn write_reg<R: Into<u8>>(&mut self, addr: u8, reg: R, value: u8)
-        -> Result<(), Self::BusError>;
-    fn read_reg<R: Into<u8>>(&mut self, addr: u8, reg: R) -> Result<u8, Self::BusError>;
-
-    fn update_reg<R: Into<u8>>(
-        &mut self,
-        addr: u8,
-        reg: R,
-        mask_set: u8,
-        mask_clear: u8,
-    ) -> Result<(), Self::BusError> {
-        let reg = reg.into();
-        let mut val = self.read_reg(addr, reg)?;
-        val |= mask_set;
-        val &= !mask_clear;
-        self.write_reg(addr, reg, val)?;
-        Ok(())
-    }
-}
 
-impl<SPI: crate::SpiBus> Mcp23x17Bus for Mcp23S17Bus<SPI> {
-    type BusError = SPI::BusError;
+/// SPI bus of the MCP23S17, see [`Mcp23S17Framing`].
+pub type Mcp23S17Bus<SPI> = crate::SpiRegisters<SPI, Mcp23S17Framing>;
 
-    fn write_reg<R: Into<u8>>(
-        &mut self,
-        addr: u8,
-        reg: R,
-        value: u8,
-    ) -> Result<(), Self::BusError> {
-        self.0.write(&[0x40 | addr << 1, reg.into(), value])?;
+/// Opcode framing of the MCP23S17: `0b0100 A2 A1 A0 R/W`.
+pub struct Mcp23S17Framing;
 
-        Ok(())
-    }
-
-    fn read_reg<R: Into<u8>>(&mut self, addr: u8, reg: R) -> Result<u8, Self::BusError> {
-        let mut val = [0; 1];
-        let write = [0x40 | addr << 1 | 0x1, reg.into()];
-        let mut tx = [
-            embedded_hal::spi::Operation::Write(&write),
-            embedded_hal::spi::Operation::Read(&mut val),
-        ];
-        self.0.transaction(&mut tx)?;
-
-        Ok(val[0])
+impl crate::SpiFraming for Mcp23S17Framing {
+    fn opcode(addr: u8, read: bool) -> u8 {
+        0x40 | addr << 1 | read as u8
     }
 }
 
-impl<I2C: crate::I2cBus> Mcp23x17Bus for Mcp23017Bus<I2C> {
+impl<I2C: crate::I2cBus> RegisterBus for Mcp23017Bus<I2C> {
     type BusError = I2C::BusError;
 
     fn write_reg<R: Into<u8>>(
@@ -410,10 +958,10 @@ mod tests {
             mock_i2c::Transaction::write_read(0x22, vec![0x01], vec![0x7e]),
             mock_i2c::Transaction::write(0x22, vec![0x01, 0xfe]),
             // output gpa0, gpb0
-            mock_i2c::Transaction::write(0x22, vec![0x12, 0x01]),
-            mock_i2c::Transaction::write(0x22, vec![0x12, 0x00]),
-            mock_i2c::Transaction::write(0x22, vec![0x13, 0x01]),
-            mock_i2c::Transaction::write(0x22, vec![0x13, 0x00]),
+            mock_i2c::Transaction::write(0x22, vec![0x14, 0x01]),
+            mock_i2c::Transaction::write(0x22, vec![0x14, 0x00]),
+            mock_i2c::Transaction::write(0x22, vec![0x15, 0x01]),
+            mock_i2c::Transaction::write(0x22, vec![0x15, 0x00]),
             // input gpa7, gpb7
             mock_i2c::Transaction::write_read(0x22, vec![0x12], vec![0x80]),
             mock_i2c::Transaction::write_read(0x22, vec![0x12], vec![0x7f]),
@@ -498,16 +1046,16 @@ mod tests {
             mock_spi::Transaction::transaction_end(),
             // output gpa0, gpb0
             mock_spi::Transaction::transaction_start(),
-            mock_spi::Transaction::write_vec(vec![0x40, 0x12, 0x01]),
+            mock_spi::Transaction::write_vec(vec![0x40, 0x14, 0x01]),
             mock_spi::Transaction::transaction_end(),
             mock_spi::Transaction::transaction_start(),
-            mock_spi::Transaction::write_vec(vec![0x40, 0x12, 0x00]),
+            mock_spi::Transaction::write_vec(vec![0x40, 0x14, 0x00]),
             mock_spi::Transaction::transaction_end(),
             mock_spi::Transaction::transaction_start(),
-            mock_spi::Transaction::write_vec(vec![0x40, 0x13, 0x01]),
+            mock_spi::Transaction::write_vec(vec![0x40, 0x15, 0x01]),
             mock_spi::Transaction::transaction_end(),
             mock_spi::Transaction::transaction_start(),
-            mock_spi::Transaction::write_vec(vec![0x40, 0x13, 0x00]),
+            mock_spi::Transaction::write_vec(vec![0x40, 0x15, 0x00]),
             mock_spi::Transaction::transaction_end(),
             // input gpa7, gpb7
             mock_spi::Transaction::transaction_start(),
@@ -554,4 +1102,177 @@ mod tests {
 
         bus.done();
     }
+
+    #[test]
+    fn mcp23s17_interrupt() {
+        let expectations = [
+            // mirrored open-drain INT output
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x41, 0x0a]),
+            mock_spi::Transaction::read(0x08),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x40, 0x0a, 0x4c]),
+            mock_spi::Transaction::transaction_end(),
+            // gpb1 interrupt while LOW
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x41, 0x07]),
+            mock_spi::Transaction::read(0x00),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x40, 0x07, 0x02]),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x41, 0x09]),
+            mock_spi::Transaction::read(0x00),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x40, 0x09, 0x02]),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x41, 0x05]),
+            mock_spi::Transaction::read(0x00),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x40, 0x05, 0x02]),
+            mock_spi::Transaction::transaction_end(),
+            // interrupt state
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x41, 0x0e]),
+            mock_spi::Transaction::read(0x00),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x41, 0x0f]),
+            mock_spi::Transaction::read(0x02),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x41, 0x10]),
+            mock_spi::Transaction::read(0xff),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x41, 0x11]),
+            mock_spi::Transaction::read(0xfd),
+            mock_spi::Transaction::transaction_end(),
+        ];
+        let mut bus = mock_spi::Mock::new(&expectations);
+
+        let mut mcp = super::Mcp23x17::new_mcp23s17(bus.clone());
+        mcp.set_interrupt_output(true, super::InterruptOutput::OpenDrain)
+            .unwrap();
+        mcp.split()
+            .gpb1
+            .set_interrupt(crate::InterruptMode::WhileLow)
+            .unwrap();
+
+        let state = mcp.fetch_interrupt_state().unwrap();
+        assert_eq!(state.flags, 0x0200);
+        assert_eq!(state.captured, 0xfdff);
+
+        bus.done();
+    }
+
+    #[test]
+    fn mcp23017_input_with_pull() {
+        let expectations = [
+            // gpa1 pull-up off, then input
+            mock_i2c::Transaction::write_read(0x20, vec![0x0c], vec![0x02]),
+            mock_i2c::Transaction::write(0x20, vec![0x0c, 0x00]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xfd]),
+            mock_i2c::Transaction::write(0x20, vec![0x00, 0xff]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut mcp = super::Mcp23x17::new_mcp23017(bus.clone(), false, false, false);
+        let mcp_pins = mcp.split();
+
+        assert_eq!(
+            mcp_pins.gpa0.into_input_with_pull(crate::Pull::Down).err(),
+            Some(crate::PullError::Unsupported(crate::Pull::Down))
+        );
+        let _ = mcp_pins
+            .gpa1
+            .into_input_with_pull(crate::Pull::None)
+            .unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn mcp23017_iocon() {
+        let expectations = [
+            // mirrored, open-drain interrupt outputs
+            mock_i2c::Transaction::write_read(0x20, vec![0x0a], vec![0x02]),
+            mock_i2c::Transaction::write(0x20, vec![0x0a, 0x44]),
+            // slew rate control off and on again
+            mock_i2c::Transaction::write_read(0x20, vec![0x0a], vec![0x44]),
+            mock_i2c::Transaction::write(0x20, vec![0x0a, 0x54]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x0a], vec![0x54]),
+            mock_i2c::Transaction::write(0x20, vec![0x0a, 0x44]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mcp = super::Mcp23x17::new_mcp23017(bus.clone(), false, false, false);
+        mcp.set_interrupt_output(true, super::InterruptOutput::OpenDrain)
+            .unwrap();
+        mcp.set_slew_rate_control(false).unwrap();
+        mcp.set_slew_rate_control(true).unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn mcp23017_irq_state() {
+        let expectations = [
+            mock_i2c::Transaction::write_read(0x20, vec![0x0e], vec![0x08]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x0f], vec![0x00]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x10], vec![0xf7]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x11], vec![0xff]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut mcp = super::Mcp23x17::new_mcp23017(bus.clone(), false, false, false);
+        let mcp_pins = mcp.split();
+
+        let irq = crate::irq_state(&mcp_pins.gpa3).unwrap();
+        assert_eq!(irq.source, mcp_pins.gpa3.pin_mask());
+        assert_eq!(irq.captured, 0xfff7);
+
+        bus.done();
+    }
+
+    #[test]
+    fn mcp23s17_haen_shared_bus() {
+        let expectations = [
+            // enable HAEN through hardware address 0, once for each device
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x40, 0x0a, 0x08]),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x40, 0x0a, 0x08]),
+            mock_spi::Transaction::transaction_end(),
+            // input on the device with A0 set
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x43, 0x12]),
+            mock_spi::Transaction::read(0x01),
+            mock_spi::Transaction::transaction_end(),
+            // input on the device with A2 set
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0x49, 0x13]),
+            mock_spi::Transaction::read(0x00),
+            mock_spi::Transaction::transaction_end(),
+        ];
+        let mut bus = mock_spi::Mock::new(&expectations);
+
+        let mut mcp0 =
+            super::Mcp23x17::new_mcp23s17_with_haen(bus.clone(), true, false, false).unwrap();
+        let mut mcp1 =
+            super::Mcp23x17::new_mcp23s17_with_haen(bus.clone(), false, false, true).unwrap();
+        let mcp0_pins = mcp0.split();
+        let mcp1_pins = mcp1.split();
+
+        assert!(mcp0_pins.gpa0.is_high().unwrap());
+        assert!(mcp1_pins.gpb7.is_low().unwrap());
+
+        bus.done();
+    }
 }
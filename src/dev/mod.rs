@@ -3,15 +3,69 @@
 //! In most cases you will not need anything from here explicitly, the exposed types at the root of
 //! the crate should be enough.
 
+pub use crate::driver::{PcalRegisters16, RegisterMap16};
+
+#[cfg(feature = "aw9523b")]
+pub mod aw9523b;
+#[cfg(feature = "ch422")]
+pub mod ch422;
+#[cfg(feature = "ch423")]
+pub mod ch423;
+#[cfg(feature = "max7300")]
+pub mod max7300;
+#[cfg(feature = "max7321")]
 pub mod max7321;
+#[cfg(feature = "mcp23016")]
+pub mod mcp23016;
+#[cfg(feature = "mcp23x17")]
 pub mod mcp23x17;
+#[cfg(feature = "pca9535")]
+pub mod pca9535;
+#[cfg(feature = "pca9536")]
 pub mod pca9536;
+#[cfg(feature = "pca9538")]
 pub mod pca9538;
+#[cfg(feature = "pca9539")]
+pub mod pca9539;
+#[cfg(feature = "pca9554")]
 pub mod pca9554;
+#[cfg(feature = "pca9555")]
 pub mod pca9555;
+#[cfg(feature = "pca9574")]
+pub mod pca9574;
+#[cfg(feature = "pca9575")]
+pub mod pca9575;
+#[cfg(feature = "pca9702")]
+pub mod pca9702;
+#[cfg(feature = "pcal6408a")]
 pub mod pcal6408a;
+#[cfg(feature = "pcal6416a")]
 pub mod pcal6416a;
+#[cfg(feature = "pcf8574")]
 pub mod pcf8574;
+#[cfg(feature = "pcf8575")]
 pub mod pcf8575;
+#[cfg(feature = "pi4ioe5v6408")]
 pub mod pi4ioe5v6408;
+#[cfg(feature = "pi4ioe5v6416")]
+pub mod pi4ioe5v6416;
+#[cfg(feature = "pi4ioe5v9535")]
+pub mod pi4ioe5v9535;
+#[cfg(feature = "sn74hc165")]
+pub mod sn74hc165;
+#[cfg(feature = "sn74hc595")]
+pub mod sn74hc595;
+#[cfg(feature = "tca6408a")]
 pub mod tca6408a;
+#[cfg(feature = "tca6424a")]
+pub mod tca6424a;
+#[cfg(feature = "tca9534")]
+pub mod tca9534;
+#[cfg(feature = "tca9535")]
+pub mod tca9535;
+#[cfg(feature = "tca9538")]
+pub mod tca9538;
+#[cfg(feature = "xra1200")]
+pub mod xra1200;
+#[cfg(feature = "xra1201")]
+pub mod xra1201;
@@ -0,0 +1,305 @@
+//! Support for the `PCA9535` and `PCA9535A` "Low-voltage 16-bit I2C-bus and SMBus I/O port with
+//! interrupt"
+//!
+//! The `PCA9535` is register-compatible with the [`PCA9555`](super::pca9555), but it has no
+//! internal pull-up resistors on its I/O pins.  Inputs that are left open will thus float instead
+//! of reading HIGH.  The driver and the pin layout are shared with the `PCA9555`.
+pub use super::pca9555::{Addr, Driver, Parts};
+
+/// `PCA9535` "Low-voltage 16-bit I2C-bus and SMBus I/O port with interrupt"
+pub struct Pca9535<M>(M);
+/// `PCA9535A` "Low-voltage 16-bit I2C-bus and SMBus I/O port with interrupt"
+pub struct Pca9535A<M>(M);
+
+impl<I2C> Pca9535<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Pca9535<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9535::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pca9535<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9535::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+impl<I2C> Pca9535A<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Pca9535A<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9535A::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pca9535A<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9535A::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+impl<I2C, M> Pca9535<M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub fn with_mutex(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self(crate::PortMutex::create(Driver::new(i2c, a0, a1, a2)))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x27`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
+    pub fn split(&mut self) -> Parts<'_, I2C, M> {
+        Parts {
+            io0_0: crate::Pin::new(0, &self.0),
+            io0_1: crate::Pin::new(1, &self.0),
+            io0_2: crate::Pin::new(2, &self.0),
+            io0_3: crate::Pin::new(3, &self.0),
+            io0_4: crate::Pin::new(4, &self.0),
+            io0_5: crate::Pin::new(5, &self.0),
+            io0_6: crate::Pin::new(6, &self.0),
+            io0_7: crate::Pin::new(7, &self.0),
+            io1_0: crate::Pin::new(8, &self.0),
+            io1_1: crate::Pin::new(9, &self.0),
+            io1_2: crate::Pin::new(10, &self.0),
+            io1_3: crate::Pin::new(11, &self.0),
+            io1_4: crate::Pin::new(12, &self.0),
+            io1_5: crate::Pin::new(13, &self.0),
+            io1_6: crate::Pin::new(14, &self.0),
+            io1_7: crate::Pin::new(15, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 16,
+        error: I2C::BusError,
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xffff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH and input polarity
+    /// inversion is cleared.  Consuming the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+impl<I2C, M> Pca9535A<M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub fn with_mutex(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self(crate::PortMutex::create(Driver::new(i2c, a0, a1, a2)))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x27`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
+    pub fn split(&mut self) -> Parts<'_, I2C, M> {
+        Parts {
+            io0_0: crate::Pin::new(0, &self.0),
+            io0_1: crate::Pin::new(1, &self.0),
+            io0_2: crate::Pin::new(2, &self.0),
+            io0_3: crate::Pin::new(3, &self.0),
+            io0_4: crate::Pin::new(4, &self.0),
+            io0_5: crate::Pin::new(5, &self.0),
+            io0_6: crate::Pin::new(6, &self.0),
+            io0_7: crate::Pin::new(7, &self.0),
+            io1_0: crate::Pin::new(8, &self.0),
+            io1_1: crate::Pin::new(9, &self.0),
+            io1_2: crate::Pin::new(10, &self.0),
+            io1_3: crate::Pin::new(11, &self.0),
+            io1_4: crate::Pin::new(12, &self.0),
+            io1_5: crate::Pin::new(13, &self.0),
+            io1_6: crate::Pin::new(14, &self.0),
+            io1_7: crate::Pin::new(15, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 16,
+        error: I2C::BusError,
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xffff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH and input polarity
+    /// inversion is cleared.  Consuming the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pca9535() {
+        let expectations = [
+            // pin setup io0_1
+            mock_i2c::Transaction::write(0x25, vec![0x02, 0xfd]),
+            mock_i2c::Transaction::write_read(0x25, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x25, vec![0x06, 0xfd]),
+            // output io0_1
+            mock_i2c::Transaction::write(0x25, vec![0x02, 0xff]),
+            // input io1_2
+            mock_i2c::Transaction::write_read(0x25, vec![0x01], vec![0x04]),
+            mock_i2c::Transaction::write_read(0x25, vec![0x01], vec![0xfb]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = super::Pca9535::new(bus.clone(), true, false, true);
+        let pca_pins = pca.split();
+
+        let mut io0_1 = pca_pins.io0_1.into_output().unwrap();
+        io0_1.set_high().unwrap();
+
+        assert!(pca_pins.io1_2.is_high().unwrap());
+        assert!(pca_pins.io1_2.is_low().unwrap());
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9535a() {
+        let expectations = [
+            // pin setup io1_7
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0x7f]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x07], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x07, 0x7f]),
+            // input io0_0
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x01]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = super::Pca9535A::new(bus.clone(), false, false, false);
+        let pca_pins = pca.split();
+
+        let _ = pca_pins.io1_7.into_output().unwrap();
+        assert!(pca_pins.io0_0.is_high().unwrap());
+
+        bus.done();
+    }
+}
@@ -0,0 +1,560 @@
+//! Support for the `PCA9539` and `PCA9539A` "16-bit I2C-bus and SMBus low power I/O port with
+//! interrupt and reset"
+//!
+//! The `PCA9539` is register-compatible with the [`PCA9555`](super::pca9555), but it has a RESET
+//! input instead of the A2 address pin and uses the I2C addresses `0x74` to `0x77`.  Like the
+//! [`PCA9535`](super::pca9535), it has no internal pull-up resistors, open inputs will float.  The
+//! driver and the pin layout are shared with the `PCA9555`.
+//!
+//! To reset the chip at runtime, attach the pin which drives RESET with `with_reset_pin()` and call
+//! `reset()`.  To keep to the datasheet timing of the RESET pulse, also hand the device a delay
+//! with `with_reset_delay()`.
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital as hal_digital;
+
+pub use super::pca9555::{Driver, Parts};
+
+/// `PCA9539` "16-bit I2C-bus and SMBus low power I/O port with interrupt and reset"
+///
+/// `RST` is the pin driving the RESET input, see [`with_reset_pin()`](Pca9539::with_reset_pin).
+pub struct Pca9539<M, RST = ()>(M, RST);
+/// `PCA9539A` "Low-voltage 16-bit I2C-bus I/O port with interrupt and reset"
+///
+/// `RST` is the pin driving the RESET input, see [`with_reset_pin()`](Pca9539A::with_reset_pin).
+pub struct Pca9539A<M, RST = ()>(M, RST);
+
+/// Timing of the RESET pulse, see [`Pca9539::with_reset_delay()`].
+///
+/// The default keeps RESET LOW for 600 ns, the longest "time to reset" of the datasheets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResetTiming {
+    /// Time RESET is held LOW.
+    pub pulse_ns: u32,
+    /// Time after releasing RESET before the chip is accessed again.
+    pub recovery_ns: u32,
+}
+
+impl Default for ResetTiming {
+    fn default() -> Self {
+        Self {
+            pulse_ns: 600,
+            recovery_ns: 0,
+        }
+    }
+}
+
+/// RESET pin of a device together with the delay timing its pulse, see
+/// [`Pca9539::with_reset_delay()`].
+pub struct TimedReset<RST, D> {
+    pin: RST,
+    delay: D,
+    timing: ResetTiming,
+}
+
+impl<RST: hal_digital::OutputPin, D: DelayNs> TimedReset<RST, D> {
+    fn pulse(&mut self) -> Result<(), RST::Error> {
+        self.pin.set_low()?;
+        self.delay.delay_ns(self.timing.pulse_ns);
+        self.pin.set_high()?;
+        self.delay.delay_ns(self.timing.recovery_ns);
+        Ok(())
+    }
+}
+
+crate::address::addr_type! {
+    /// I2C address of the `PCA9539`.
+    pub struct Addr: 0x74, [a0 = 0, a1 = 1], 0x74..=0x77;
+}
+
+impl<I2C> Pca9539<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    pub fn new(i2c: I2C, a0: bool, a1: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Pca9539<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9539::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pca9539<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9539::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1)
+    }
+}
+
+impl<I2C, M> Pca9539<M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub fn with_mutex(i2c: I2C, a0: bool, a1: bool) -> Self {
+        Self::from_addr(i2c, Addr::from_pins(a0, a1))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(
+            crate::PortMutex::create(Driver::with_address(
+                i2c,
+                addr,
+                crate::AddressCheck::Checked,
+            )),
+            (),
+        )
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x74` to `0x77`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(
+            crate::PortMutex::create(Driver::with_address(
+                i2c,
+                addr,
+                crate::AddressCheck::Unchecked,
+            )),
+            (),
+        )
+    }
+
+    /// Attach the output pin which is connected to the RESET input of the chip.
+    ///
+    /// This enables [`reset()`](Pca9539::reset).  The pin is not touched until then, so it should
+    /// already be HIGH to keep the chip out of reset.
+    pub fn with_reset_pin<RST>(self, reset: RST) -> Pca9539<M, RST>
+    where
+        RST: hal_digital::OutputPin,
+    {
+        Pca9539(self.0, reset)
+    }
+}
+
+impl<I2C, M, RST> Pca9539<M, RST>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+    RST: hal_digital::OutputPin,
+{
+    /// Let [`reset()`](Pca9539::reset) time the RESET pulse with `delay`.
+    pub fn with_reset_delay<D: DelayNs>(
+        self,
+        delay: D,
+        timing: ResetTiming,
+    ) -> Pca9539<M, TimedReset<RST, D>> {
+        Pca9539(
+            self.0,
+            TimedReset {
+                pin: self.1,
+                delay,
+                timing,
+            },
+        )
+    }
+}
+
+impl<I2C, M, RST> Pca9539<M, RST>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
+    pub fn split(&mut self) -> Parts<'_, I2C, M> {
+        Parts {
+            io0_0: crate::Pin::new(0, &self.0),
+            io0_1: crate::Pin::new(1, &self.0),
+            io0_2: crate::Pin::new(2, &self.0),
+            io0_3: crate::Pin::new(3, &self.0),
+            io0_4: crate::Pin::new(4, &self.0),
+            io0_5: crate::Pin::new(5, &self.0),
+            io0_6: crate::Pin::new(6, &self.0),
+            io0_7: crate::Pin::new(7, &self.0),
+            io1_0: crate::Pin::new(8, &self.0),
+            io1_1: crate::Pin::new(9, &self.0),
+            io1_2: crate::Pin::new(10, &self.0),
+            io1_3: crate::Pin::new(11, &self.0),
+            io1_4: crate::Pin::new(12, &self.0),
+            io1_5: crate::Pin::new(13, &self.0),
+            io1_6: crate::Pin::new(14, &self.0),
+            io1_7: crate::Pin::new(15, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 16,
+        error: I2C::BusError,
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xffff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH and input polarity
+    /// inversion is cleared.  Consuming the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+impl<I2C, M, RST> Pca9539<M, RST>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+    RST: hal_digital::OutputPin,
+{
+    /// Reset the chip through its RESET pin and forget all cached state.
+    ///
+    /// Afterwards, the chip is in its power-on configuration: All pins are inputs, the output
+    /// register is all HIGH and the input polarity is not inverted.  Resetting needs a mutable
+    /// borrow of the device, so no pins can exist which still assume their old configuration.
+    /// The chip only needs a RESET pulse of a few nanoseconds, so the pin is just driven LOW and
+    /// HIGH again.
+    pub fn reset(&mut self) -> Result<(), RST::Error> {
+        self.1.set_low()?;
+        self.1.set_high()?;
+        crate::PortMutex::lock(&self.0, |drv| drv.invalidate());
+        Ok(())
+    }
+}
+
+impl<I2C, M, RST, D> Pca9539<M, TimedReset<RST, D>>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+    RST: hal_digital::OutputPin,
+    D: DelayNs,
+{
+    /// Reset the chip through its RESET pin and forget all cached state.
+    ///
+    /// Like the `reset()` of a device without a delay, but RESET is held LOW and the chip is left
+    /// to recover for the [`ResetTiming`] passed to
+    /// [`with_reset_delay()`](Pca9539::with_reset_delay).
+    pub fn reset(&mut self) -> Result<(), RST::Error> {
+        self.1.pulse()?;
+        crate::PortMutex::lock(&self.0, |drv| drv.invalidate());
+        Ok(())
+    }
+}
+
+impl<I2C> Pca9539A<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    pub fn new(i2c: I2C, a0: bool, a1: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Pca9539A<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9539A::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pca9539A<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9539A::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1)
+    }
+}
+
+impl<I2C, M> Pca9539A<M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub fn with_mutex(i2c: I2C, a0: bool, a1: bool) -> Self {
+        Self::from_addr(i2c, Addr::from_pins(a0, a1))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(
+            crate::PortMutex::create(Driver::with_address(
+                i2c,
+                addr,
+                crate::AddressCheck::Checked,
+            )),
+            (),
+        )
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x74` to `0x77`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(
+            crate::PortMutex::create(Driver::with_address(
+                i2c,
+                addr,
+                crate::AddressCheck::Unchecked,
+            )),
+            (),
+        )
+    }
+
+    /// Attach the output pin which is connected to the RESET input of the chip.
+    ///
+    /// This enables [`reset()`](Pca9539A::reset).  The pin is not touched until then, so it should
+    /// already be HIGH to keep the chip out of reset.
+    pub fn with_reset_pin<RST>(self, reset: RST) -> Pca9539A<M, RST>
+    where
+        RST: hal_digital::OutputPin,
+    {
+        Pca9539A(self.0, reset)
+    }
+}
+
+impl<I2C, M, RST> Pca9539A<M, RST>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+    RST: hal_digital::OutputPin,
+{
+    /// Let [`reset()`](Pca9539A::reset) time the RESET pulse with `delay`.
+    pub fn with_reset_delay<D: DelayNs>(
+        self,
+        delay: D,
+        timing: ResetTiming,
+    ) -> Pca9539A<M, TimedReset<RST, D>> {
+        Pca9539A(
+            self.0,
+            TimedReset {
+                pin: self.1,
+                delay,
+                timing,
+            },
+        )
+    }
+}
+
+impl<I2C, M, RST> Pca9539A<M, RST>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
+    pub fn split(&mut self) -> Parts<'_, I2C, M> {
+        Parts {
+            io0_0: crate::Pin::new(0, &self.0),
+            io0_1: crate::Pin::new(1, &self.0),
+            io0_2: crate::Pin::new(2, &self.0),
+            io0_3: crate::Pin::new(3, &self.0),
+            io0_4: crate::Pin::new(4, &self.0),
+            io0_5: crate::Pin::new(5, &self.0),
+            io0_6: crate::Pin::new(6, &self.0),
+            io0_7: crate::Pin::new(7, &self.0),
+            io1_0: crate::Pin::new(8, &self.0),
+            io1_1: crate::Pin::new(9, &self.0),
+            io1_2: crate::Pin::new(10, &self.0),
+            io1_3: crate::Pin::new(11, &self.0),
+            io1_4: crate::Pin::new(12, &self.0),
+            io1_5: crate::Pin::new(13, &self.0),
+            io1_6: crate::Pin::new(14, &self.0),
+            io1_7: crate::Pin::new(15, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 16,
+        error: I2C::BusError,
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xffff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH and input polarity
+    /// inversion is cleared.  Consuming the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+impl<I2C, M, RST> Pca9539A<M, RST>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+    RST: hal_digital::OutputPin,
+{
+    /// Reset the chip through its RESET pin and forget all cached state.
+    ///
+    /// Afterwards, the chip is in its power-on configuration: All pins are inputs, the output
+    /// register is all HIGH and the input polarity is not inverted.  Resetting needs a mutable
+    /// borrow of the device, so no pins can exist which still assume their old configuration.
+    /// The chip only needs a RESET pulse of a few nanoseconds, so the pin is just driven LOW and
+    /// HIGH again.
+    pub fn reset(&mut self) -> Result<(), RST::Error> {
+        self.1.set_low()?;
+        self.1.set_high()?;
+        crate::PortMutex::lock(&self.0, |drv| drv.invalidate());
+        Ok(())
+    }
+}
+
+impl<I2C, M, RST, D> Pca9539A<M, TimedReset<RST, D>>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+    RST: hal_digital::OutputPin,
+    D: DelayNs,
+{
+    /// Reset the chip through its RESET pin and forget all cached state.
+    ///
+    /// Like the `reset()` of a device without a delay, but RESET is held LOW and the chip is left
+    /// to recover for the [`ResetTiming`] passed to
+    /// [`with_reset_delay()`](Pca9539A::with_reset_delay).
+    pub fn reset(&mut self) -> Result<(), RST::Error> {
+        self.1.pulse()?;
+        crate::PortMutex::lock(&self.0, |drv| drv.invalidate());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::delay as mock_delay;
+    use embedded_hal_mock::eh1::digital as mock_digital;
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pca9539() {
+        let expectations = [
+            // pin setup io0_1
+            mock_i2c::Transaction::write(0x75, vec![0x02, 0xfd]),
+            mock_i2c::Transaction::write_read(0x75, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x75, vec![0x06, 0xfd]),
+            // input io1_2
+            mock_i2c::Transaction::write_read(0x75, vec![0x01], vec![0x04]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = super::Pca9539::new(bus.clone(), true, false);
+        let pca_pins = pca.split();
+
+        let _ = pca_pins.io0_1.into_output().unwrap();
+        assert!(pca_pins.io1_2.is_high().unwrap());
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9539a_reset() {
+        let expectations = [
+            // io0_0 LOW
+            mock_i2c::Transaction::write(0x76, vec![0x02, 0xfe]),
+            // after the reset, the output register is HIGH again
+            mock_i2c::Transaction::write(0x76, vec![0x02, 0xfd]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+        let reset_expectations = [
+            mock_digital::Transaction::set(mock_digital::State::Low),
+            mock_digital::Transaction::set(mock_digital::State::High),
+        ];
+        let mut reset_pin = mock_digital::Mock::new(&reset_expectations);
+
+        let mut pca =
+            super::Pca9539A::new(bus.clone(), false, true).with_reset_pin(reset_pin.clone());
+        pca.write_mask(0x0000, 0x0001).unwrap();
+        pca.reset().unwrap();
+        pca.write_mask(0x0000, 0x0002).unwrap();
+
+        bus.done();
+        reset_pin.done();
+    }
+
+    #[test]
+    fn pca9539_timed_reset() {
+        let mut bus = mock_i2c::Mock::new(&[]);
+        let reset_expectations = [
+            mock_digital::Transaction::set(mock_digital::State::Low),
+            mock_digital::Transaction::set(mock_digital::State::High),
+        ];
+        let mut reset_pin = mock_digital::Mock::new(&reset_expectations);
+        let delay_expectations = [
+            mock_delay::Transaction::delay_ns(1000),
+            mock_delay::Transaction::delay_ns(50),
+        ];
+        let mut delay = mock_delay::CheckedDelay::new(&delay_expectations);
+
+        let timing = super::ResetTiming {
+            pulse_ns: 1000,
+            recovery_ns: 50,
+        };
+        let mut pca = super::Pca9539::new(bus.clone(), false, false)
+            .with_reset_pin(reset_pin.clone())
+            .with_reset_delay(delay.clone(), timing);
+        pca.reset().unwrap();
+
+        bus.done();
+        reset_pin.done();
+        delay.done();
+    }
+}
@@ -1,5 +1,4 @@
 //! Support for the `PCA9554` and `PCA9554a` "8-bit I2C-bus and SMBus I/O port with interrupt"
-use crate::I2cExt;
 
 /// `PCA9554` "8-bit I2C-bus and SMBus I/O port with interrupt"
 pub struct Pca9554<M>(M);
@@ -8,25 +7,73 @@ pub struct Pca9554A<M>(M);
 
 impl<I2C> Pca9554<core::cell::RefCell<Driver<I2C>>>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
 {
     pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
         Self::with_mutex(i2c, a0, a1, a2)
     }
 }
 
+#[cfg(feature = "critical-section")]
+impl<I2C> Pca9554<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9554::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pca9554<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9554::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
 impl<I2C> Pca9554A<core::cell::RefCell<Driver<I2C>>>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
 {
     pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
         Self::with_mutex(i2c, a0, a1, a2)
     }
 }
 
+#[cfg(feature = "critical-section")]
+impl<I2C> Pca9554A<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9554A::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pca9554A<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9554A::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
 impl<I2C, M> Pca9554<M>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
     M: crate::PortMutex<Port = Driver<I2C>>,
 {
     pub fn with_mutex(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
@@ -35,6 +82,37 @@ where
         )))
     }
 
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x27`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
     pub fn split<'a>(&'a mut self) -> Parts<'a, I2C, M> {
         Parts {
             io0: crate::Pin::new(0, &self.0),
@@ -47,17 +125,73 @@ where
             io7: crate::Pin::new(7, &self.0),
         }
     }
+
+    pin_methods! {
+        mode: crate::mode::QuasiBidirectional,
+        pins: 8,
+        error: I2C::BusError,
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH and input polarity
+    /// inversion is cleared.  Consuming the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
 }
 
 impl<I2C, M> Pca9554A<M>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
     M: crate::PortMutex<Port = Driver<I2C>>,
 {
     pub fn with_mutex(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
         Self(crate::PortMutex::create(Driver::new(i2c, true, a0, a1, a2)))
     }
 
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: AddrA) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x38` to `0x3f`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, AddrA::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
     pub fn split(&mut self) -> Parts<'_, I2C, M> {
         Parts {
             io0: crate::Pin::new(0, &self.0),
@@ -70,11 +204,36 @@ where
             io7: crate::Pin::new(7, &self.0),
         }
     }
+
+    pin_methods! {
+        mode: crate::mode::QuasiBidirectional,
+        pins: 8,
+        error: I2C::BusError,
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH and input polarity
+    /// inversion is cleared.  Consuming the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
 }
 
 pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
     M: crate::PortMutex<Port = Driver<I2C>>,
 {
     pub io0: crate::Pin<'a, crate::mode::QuasiBidirectional, M>,
@@ -102,99 +261,192 @@ impl From<Regs> for u8 {
     }
 }
 
+crate::address::addr_type! {
+    /// I2C address of the `PCA9554`.
+    pub struct Addr: 0x20, [a0 = 0, a1 = 1, a2 = 2], 0x20..=0x27;
+}
+
+crate::address::addr_type! {
+    /// I2C address of the `PCA9554A`.
+    pub struct AddrA: 0x38, [a0 = 0, a1 = 1, a2 = 2], 0x38..=0x3f;
+}
+
 pub struct Driver<I2C> {
     i2c: I2C,
     out: u8,
+    dirty: bool,
     addr: u8,
+    address_check: crate::AddressCheck,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
 }
 
 impl<I2C> Driver<I2C> {
     pub fn new(i2c: I2C, is_a_variant: bool, a0: bool, a1: bool, a2: bool) -> Self {
         let addr = if is_a_variant {
-            0x38 | ((a2 as u8) << 2) | ((a1 as u8) << 1) | (a0 as u8)
+            AddrA::from_pins(a0, a1, a2).get()
         } else {
-            0x20 | ((a2 as u8) << 2) | ((a1 as u8) << 1) | (a0 as u8)
+            Addr::from_pins(a0, a1, a2).get()
         };
+        Self::with_address(i2c, addr, crate::AddressCheck::Checked)
+    }
+
+    pub(crate) fn with_address(i2c: I2C, addr: u8, address_check: crate::AddressCheck) -> Self {
         Self {
             i2c,
             out: 0xff,
+            dirty: false,
             addr,
+            address_check,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
         }
     }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriver for Driver<I2C> {
+impl<I2C: crate::RegisterBus> crate::PortDriver for Driver<I2C> {
     type Error = I2C::BusError;
 
     fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         self.out |= mask_high as u8;
         self.out &= !mask_low as u8;
-        self.i2c
-            .write_reg(self.addr, Regs::OutputPort0, (self.out & 0xFF) as u8)?;
+        // stays set if the write fails
+        self.dirty = true;
+        self.i2c.write_reg(self.addr, Regs::OutputPort0, self.out)?;
+        self.dirty = false;
         Ok(())
     }
 
     fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         Ok(((self.out as u32) & mask_high) | (!(self.out as u32) & mask_low))
     }
 
     fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
-        let io0 = if (mask_high | mask_low) & 0x00FF != 0 {
-            self.i2c.read_reg(self.addr, Regs::InputPort0)?
-        } else {
-            0
-        };
-        let in_ = io0 as u32;
+        let in_ = self.input_state.read(mask_high | mask_low, |mask| {
+            let io0 = if mask & 0x00FF != 0 {
+                self.i2c.read_reg(self.addr, Regs::InputPort0)?
+            } else {
+                0
+            };
+            Ok::<_, Self::Error>(io0 as u32)
+        })?;
         Ok((in_ & mask_high) | (!in_ & mask_low))
     }
+
+    fn get_polarity(&mut self, mask: u32) -> Result<u32, Self::Error> {
+        Ok(self.i2c.read_reg(self.addr, Regs::PolarityInversion0)? as u32 & mask)
+    }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriverTotemPole for Driver<I2C> {
-    fn set_direction(
-        &mut self,
-        mask: u32,
-        dir: crate::Direction,
-        state: bool,
-    ) -> Result<(), Self::Error> {
-        // set state before switching direction to prevent glitch
-        if dir == crate::Direction::Output {
-            use crate::PortDriver;
-            if state {
-                self.set(mask, 0)?;
-            } else {
-                self.set(0, mask)?;
-            }
+impl<I2C: crate::RegisterBus> crate::PortDriverInputState for Driver<I2C> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverOutputInversion for Driver<I2C> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverClaims for Driver<I2C> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverCost for Driver<I2C> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set { .. } => 1,
+            crate::Operation::Get { mask } => self
+                .input_state
+                .read_mask(mask)
+                .map_or(0, |mask| (mask & 0xff != 0) as u32),
+            crate::Operation::SetDirections { .. } => 2,
         }
+    }
+}
 
-        let (mask_set, mask_clear) = match dir {
-            crate::Direction::Input => (mask as u16, 0),
-            crate::Direction::Output => (0, mask as u16),
-        };
-        if mask & 0x00FF != 0 {
-            self.i2c.update_reg(
-                self.addr,
-                Regs::Configuration0,
-                (mask_set & 0xFF) as u8,
-                (mask_clear & 0xFF) as u8,
-            )?;
+impl<I2C: crate::RegisterBus> crate::PortDriverResync for Driver<I2C> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        self.out = self.i2c.read_reg(self.addr, Regs::OutputPort0)?;
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverSnapshot for Driver<I2C> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        let config = self.i2c.read_reg(self.addr, Regs::Configuration0)?;
+        Ok(crate::DriverState {
+            pins: 0xff,
+            outputs: Some(self.out as u32),
+            output_pins: Some(!config as u32 & 0xff),
+            pull_ups: None,
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, 0xff, 0)?,
+        })
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRestoreDefaults for Driver<I2C> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        // inputs first, so the outputs stop driving before anything else changes
+        self.i2c.write_reg(self.addr, Regs::Configuration0, 0xff)?;
+        self.i2c.write_reg(self.addr, Regs::OutputPort0, 0xff)?;
+        self.i2c
+            .write_reg(self.addr, Regs::PolarityInversion0, 0x00)?;
+        self.out = 0xff;
+        Ok(())
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRefresh for Driver<I2C> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        if mask & 0xFF != 0 {
+            self.i2c.write_reg(self.addr, Regs::OutputPort0, self.out)?;
         }
         Ok(())
     }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriverPolarity for Driver<I2C> {
+impl<I2C: crate::RegisterBus> crate::PortDriverTotemPole for Driver<I2C> {
+    fn set_directions(&mut self, mask_input: u32, mask_output: u32) -> Result<(), Self::Error> {
+        if (mask_input | mask_output) & 0xFF == 0 {
+            return Ok(());
+        }
+        self.i2c.update_reg(
+            self.addr,
+            Regs::Configuration0,
+            mask_input as u8,
+            mask_output as u8,
+        )
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverPolarity for Driver<I2C> {
     fn set_polarity(&mut self, mask: u32, inverted: bool) -> Result<(), Self::Error> {
         let (mask_set, mask_clear) = match inverted {
             false => (0, mask as u8),
             true => (mask as u8, 0),
         };
 
-        self.i2c.update_reg(
-            self.addr,
-            Regs::PolarityInversion0,
-            (mask_set & 0xFF) as u8,
-            (mask_clear & 0xFF) as u8,
-        )?;
+        self.i2c
+            .update_reg(self.addr, Regs::PolarityInversion0, mask_set, mask_clear)?;
         Ok(())
     }
 }
@@ -232,7 +484,7 @@ mod tests {
         assert!(pin0.is_high().unwrap());
         pin1.set_low().unwrap();
         assert!(pin1.is_low().unwrap());
-        pin2.into_output().unwrap();
+        let _ = pin2.into_output().unwrap();
 
         bus.done();
     }
@@ -266,7 +518,7 @@ mod tests {
         assert!(pin0.is_high().unwrap());
         pin1.set_low().unwrap();
         assert!(pin1.is_low().unwrap());
-        pin2.into_output().unwrap();
+        let _ = pin2.into_output().unwrap();
 
         bus.done();
     }
@@ -1,27 +1,104 @@
 //! Support for the `PCA9555` "16-bit I2C-bus and SMBus I/O port with interrupt"
-use crate::I2cExt;
 
 /// `PCA9555` "16-bit I2C-bus and SMBus I/O port with interrupt"
 pub struct Pca9555<M>(M);
 
 impl<I2C> Pca9555<core::cell::RefCell<Driver<I2C>>>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
 {
     pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
         Self::with_mutex(i2c, a0, a1, a2)
     }
+
+    /// Describe the initial configuration of the chip, which is then written by
+    /// [`Builder::build()`].
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+    /// // io1_x are outputs, io1_0 starts HIGH, io0_7 is an inverted input
+    /// let mut pca9555 = port_expander::Pca9555::builder()
+    ///     .directions(0xff00)
+    ///     .polarity(0x0080)
+    ///     .initial_output(0x0100)
+    ///     .build(i2c, false, false, false)
+    ///     .unwrap();
+    /// ```
+    pub fn builder() -> Builder<I2C> {
+        Builder {
+            outputs: 0x0000,
+            polarity: 0x0000,
+            initial_output: 0x0000,
+            _i2c: core::marker::PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Pca9555<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9555::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pca9555<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9555::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
 }
 
 impl<I2C, M> Pca9555<M>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
     M: crate::PortMutex<Port = Driver<I2C>>,
 {
     pub fn with_mutex(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
         Self(crate::PortMutex::create(Driver::new(i2c, a0, a1, a2)))
     }
 
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x27`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
     pub fn split(&mut self) -> Parts<'_, I2C, M> {
         Parts {
             io0_0: crate::Pin::new(0, &self.0),
@@ -42,11 +119,36 @@ where
             io1_7: crate::Pin::new(15, &self.0),
         }
     }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 16,
+        error: I2C::BusError,
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xffff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH and input polarity
+    /// inversion is cleared.  Consuming the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
 }
 
 pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
     M: crate::PortMutex<Port = Driver<I2C>>,
 {
     pub io0_0: crate::Pin<'a, crate::mode::Input, M>,
@@ -67,144 +169,88 @@ where
     pub io1_7: crate::Pin<'a, crate::mode::Input, M>,
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Regs {
-    InputPort0 = 0x00,
-    InputPort1 = 0x01,
-    OutputPort0 = 0x02,
-    OutputPort1 = 0x03,
-    PolarityInversion0 = 0x04,
-    PolarityInversion1 = 0x05,
-    Configuration0 = 0x06,
-    Configuration1 = 0x07,
+/// Initial configuration of a [`Pca9555`], see [`Pca9555::builder()`].
+///
+/// Bit `n` of each mask is the `n`-th pin of [`Pca9555::pins()`].  The whole configuration is
+/// written with one write per register and port, outputs first so no pin glitches when it becomes
+/// an output.  The pins handed out by [`split()`](Pca9555::split) are still typed as inputs, use
+/// e.g. [`write_mask()`](Pca9555::write_mask) to drive the configured outputs.
+pub struct Builder<I2C> {
+    outputs: u16,
+    polarity: u16,
+    initial_output: u16,
+    _i2c: core::marker::PhantomData<fn() -> I2C>,
 }
 
-impl From<Regs> for u8 {
-    fn from(r: Regs) -> u8 {
-        r as u8
+impl<I2C: crate::RegisterBus> Builder<I2C> {
+    /// Make the pins in `outputs` outputs, all other pins stay inputs.
+    pub fn directions(mut self, outputs: u16) -> Self {
+        self.outputs = outputs;
+        self
     }
-}
 
-pub struct Driver<I2C> {
-    i2c: I2C,
-    out: u16,
-    addr: u8,
-}
-
-impl<I2C> Driver<I2C> {
-    pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
-        let addr = 0x20 | ((a2 as u8) << 2) | ((a1 as u8) << 1) | (a0 as u8);
-        Self {
-            i2c,
-            out: 0xffff,
-            addr,
-        }
+    /// Invert the input polarity of the pins in `inverted`.
+    pub fn polarity(mut self, inverted: u16) -> Self {
+        self.polarity = inverted;
+        self
     }
-}
-
-impl<I2C: crate::I2cBus> crate::PortDriver for Driver<I2C> {
-    type Error = I2C::BusError;
 
-    fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
-        self.out |= mask_high as u16;
-        self.out &= !mask_low as u16;
-        if (mask_high | mask_low) & 0x00FF != 0 {
-            self.i2c
-                .write_reg(self.addr, Regs::OutputPort0, (self.out & 0xFF) as u8)?;
-        }
-        if (mask_high | mask_low) & 0xFF00 != 0 {
-            self.i2c
-                .write_reg(self.addr, Regs::OutputPort1, (self.out >> 8) as u8)?;
-        }
-        Ok(())
+    /// Drive the outputs in `high` HIGH, all other outputs LOW.
+    pub fn initial_output(mut self, high: u16) -> Self {
+        self.initial_output = high;
+        self
     }
 
-    fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
-        Ok(((self.out as u32) & mask_high) | (!(self.out as u32) & mask_low))
+    /// Create the device and write the configuration to the chip.
+    pub fn build(
+        self,
+        i2c: I2C,
+        a0: bool,
+        a1: bool,
+        a2: bool,
+    ) -> Result<Pca9555<core::cell::RefCell<Driver<I2C>>>, I2C::BusError> {
+        self.build_with_mutex(i2c, a0, a1, a2)
     }
 
-    fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
-        let io0 = if (mask_high | mask_low) & 0x00FF != 0 {
-            self.i2c.read_reg(self.addr, Regs::InputPort0)?
-        } else {
-            0
-        };
-        let io1 = if (mask_high | mask_low) & 0xFF00 != 0 {
-            self.i2c.read_reg(self.addr, Regs::InputPort1)?
-        } else {
-            0
-        };
-        let in_ = ((io1 as u32) << 8) | io0 as u32;
-        Ok((in_ & mask_high) | (!in_ & mask_low))
+    /// Same as [`build()`](Builder::build), but with a mutex.
+    pub fn build_with_mutex<M>(
+        self,
+        i2c: I2C,
+        a0: bool,
+        a1: bool,
+        a2: bool,
+    ) -> Result<Pca9555<M>, I2C::BusError>
+    where
+        M: crate::PortMutex<Port = Driver<I2C>>,
+    {
+        let mut driver = Driver::new(i2c, a0, a1, a2);
+        driver.write_config(self.initial_output, self.polarity, !self.outputs)?;
+        Ok(Pca9555(crate::PortMutex::create(driver)))
     }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriverTotemPole for Driver<I2C> {
-    fn set_direction(
-        &mut self,
-        mask: u32,
-        dir: crate::Direction,
-        state: bool,
-    ) -> Result<(), Self::Error> {
-        // set state before switching direction to prevent glitch
-        if dir == crate::Direction::Output {
-            use crate::PortDriver;
-            if state {
-                self.set(mask, 0)?;
-            } else {
-                self.set(0, mask)?;
-            }
-        }
+crate::address::addr_type! {
+    /// I2C address of the `PCA9555`.
+    pub struct Addr: 0x20, [a0 = 0, a1 = 1, a2 = 2], 0x20..=0x27;
+}
 
-        let (mask_set, mask_clear) = match dir {
-            crate::Direction::Input => (mask as u16, 0),
-            crate::Direction::Output => (0, mask as u16),
-        };
-        if mask & 0x00FF != 0 {
-            self.i2c.update_reg(
-                self.addr,
-                Regs::Configuration0,
-                (mask_set & 0xFF) as u8,
-                (mask_clear & 0xFF) as u8,
-            )?;
-        }
-        if mask & 0xFF00 != 0 {
-            self.i2c.update_reg(
-                self.addr,
-                Regs::Configuration1,
-                (mask_set >> 8) as u8,
-                (mask_clear >> 8) as u8,
-            )?;
-        }
-        Ok(())
-    }
+/// Register map of the `PCA9555`.
+pub struct Registers;
+
+impl crate::driver::Regs16 for Registers {
+    const INPUT: [u8; 2] = [0x00, 0x01];
+    const OUTPUT: [u8; 2] = [0x02, 0x03];
+    const POLARITY_INVERSION: [u8; 2] = [0x04, 0x05];
+    const CONFIGURATION: [u8; 2] = [0x06, 0x07];
+    const OUTPUT_RESET: Option<u16> = Some(0xffff);
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriverPolarity for Driver<I2C> {
-    fn set_polarity(&mut self, mask: u32, inverted: bool) -> Result<(), Self::Error> {
-        let (mask_set, mask_clear) = match inverted {
-            false => (0, mask as u16),
-            true => (mask as u16, 0),
-        };
+pub type Driver<I2C> = crate::driver::Driver16<I2C, Registers>;
 
-        if mask & 0x00FF != 0 {
-            self.i2c.update_reg(
-                self.addr,
-                Regs::PolarityInversion0,
-                (mask_set & 0xFF) as u8,
-                (mask_clear & 0xFF) as u8,
-            )?;
-        }
-        if mask & 0xFF00 != 0 {
-            self.i2c.update_reg(
-                self.addr,
-                Regs::PolarityInversion1,
-                (mask_set >> 8) as u8,
-                (mask_clear >> 8) as u8,
-            )?;
-        }
-        Ok(())
+impl<I2C> Driver<I2C> {
+    pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        let addr = Addr::from_pins(a0, a1, a2).get();
+        Self::with_address(i2c, addr, crate::AddressCheck::Checked)
     }
 }
 
@@ -287,4 +333,305 @@ mod tests {
 
         bus.done();
     }
+
+    #[test]
+    fn pca9555_with_address() {
+        let expectations = [
+            mock_i2c::Transaction::write_read(0x27, vec![0x00], vec![0x01]),
+            mock_i2c::Transaction::write_read(0x4a, vec![0x00], vec![0x01]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let err = super::Pca9555::<core::cell::RefCell<_>>::with_address(bus.clone(), 0x28)
+            .err()
+            .unwrap();
+        assert_eq!(err.address(), 0x28);
+
+        let mut pca: super::Pca9555<core::cell::RefCell<_>> =
+            super::Pca9555::with_address(bus.clone(), 0x27).unwrap();
+        assert_eq!(pca.address_check(), crate::AddressCheck::Checked);
+        assert!(pca.split().io0_0.is_high().unwrap());
+
+        let mut pca: super::Pca9555<core::cell::RefCell<_>> =
+            super::Pca9555::with_address_unchecked(bus.clone(), 0x4a);
+        assert_eq!(pca.address_check(), crate::AddressCheck::Unchecked);
+        assert!(pca.split().io0_0.is_high().unwrap());
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9555_addr() {
+        let expectations = [mock_i2c::Transaction::write_read(
+            0x25,
+            vec![0x00],
+            vec![0x01],
+        )];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let addr = super::Addr::from_pins(true, false, true);
+        assert_eq!(addr.get(), 0x25);
+        assert_eq!(super::Addr::custom(0x25), Ok(addr));
+        assert_eq!(super::Addr::try_from(0x28).unwrap_err().address(), 0x28);
+        assert_eq!(u8::from(super::Addr::from_pins(true, true, true)), 0x27);
+
+        let mut pca: super::Pca9555<core::cell::RefCell<_>> =
+            super::Pca9555::from_addr(bus.clone(), addr);
+        assert_eq!(pca.address_check(), crate::AddressCheck::Checked);
+        assert!(pca.split().io0_0.is_high().unwrap());
+
+        bus.done();
+    }
+
+    #[test]
+    #[cfg(feature = "force-input")]
+    fn pca9555_force_input() {
+        let expectations = [
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x00]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0x01]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x00]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = super::Pca9555::new(bus.clone(), false, false, false);
+        let mut pca_pins = pca.split();
+
+        pca_pins.io0_0.force_input(Some(true));
+        pca_pins.io1_0.force_input(Some(false));
+        assert!(pca_pins.io0_0.is_high().unwrap());
+        assert!(pca_pins.io1_0.is_low().unwrap());
+
+        pca_pins.io0_0.force_input(None);
+        assert!(pca_pins.io0_0.is_low().unwrap());
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9555_new_std() {
+        let expectations = [mock_i2c::Transaction::write_read(
+            0x21,
+            vec![0x01],
+            vec![0x80],
+        )];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = super::Pca9555::new_std(bus.clone(), true, false, false);
+        let pca_pins = pca.split();
+
+        std::thread::scope(|s| {
+            s.spawn(|| assert!(pca_pins.io1_7.is_high().unwrap()));
+        });
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9555_register_map() {
+        let expectations = [
+            // output register of the other revision is read before the first write
+            mock_i2c::Transaction::write_read(0x20, vec![0x12], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x13], vec![0x7f]),
+            mock_i2c::Transaction::write(0x20, vec![0x13, 0x7e]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x17], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x17, 0xfe]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = super::Pca9555::new(bus.clone(), false, false, false);
+        let pca_pins = pca.split();
+
+        let map = crate::dev::RegisterMap16 {
+            input: [0x10, 0x11],
+            output: [0x12, 0x13],
+            polarity_inversion: [0x14, 0x15],
+            configuration: [0x16, 0x17],
+            output_reset: None,
+            pcal: None,
+        };
+        pca_pins.io1_0.access_port_driver(|drv| {
+            assert_eq!(drv.register_map().output, [0x02, 0x03]);
+            drv.set_register_map(map);
+        });
+        let _ = pca_pins.io1_0.into_output().unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9555_mask() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0x7f]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0x81]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let pca = super::Pca9555::new(bus.clone(), false, false, false);
+        pca.write_mask(0x0000, 0x8001).unwrap();
+        assert_eq!(pca.read_mask(0xff00).unwrap(), 0x8100);
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9555_write_pins() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0xfe]),
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let pca = super::Pca9555::new(bus.clone(), false, false, false);
+        pca.write_pins(&[(3, true), (8, false)]).unwrap();
+        pca.toggle_pins(&[0]).unwrap();
+        assert_eq!(
+            pca.write_pins(&[(0, true), (16, false)]),
+            Err(crate::PinIndexError::OutOfRange(16))
+        );
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9555_read_only() {
+        let expectations = [
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0x80]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x00]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let pca = super::Pca9555::new(bus.clone(), false, false, false);
+        let pins = pca.split_ref();
+        assert!(pins[15].is_high().unwrap());
+        let view = pins[0].read_only();
+        assert!(view.is_low().unwrap());
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9555_restore_defaults() {
+        let expectations = [
+            // io0_0 output LOW, io1_7 inverted input
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x05], vec![0x00]),
+            mock_i2c::Transaction::write(0x20, vec![0x05, 0x80]),
+            // restore defaults
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x07, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x04, 0x00]),
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x05, 0x00]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = super::Pca9555::new(bus.clone(), false, false, false);
+        let pca_pins = pca.split();
+        let _ = pca_pins.io0_0.into_output().unwrap();
+        let _ = pca_pins.io1_7.into_inverted().unwrap();
+        pca.restore_defaults().unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9555_take_pin() {
+        let expectations = [mock_i2c::Transaction::write_read(
+            0x20,
+            vec![0x01],
+            vec![0x80],
+        )];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let pca = super::Pca9555::new(bus.clone(), false, false, false);
+        let io1_7 = pca.take_pin(15).unwrap();
+        let io0_0 = pca.take_pin(0).unwrap();
+        assert!(pca.take_pin(15).is_none());
+        assert!(pca.take_pin(16).is_none());
+        assert!(io1_7.is_high().unwrap());
+        drop(io0_0);
+        assert!(pca.take_pin(0).is_none());
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9555_pin_index() {
+        let expectations = [mock_i2c::Transaction::write_read(
+            0x20,
+            vec![0x01],
+            vec![0x40],
+        )];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = super::Pca9555::new(bus.clone(), false, false, false);
+        assert!(pca.pin(16).is_none());
+        let io1_6 = pca.pin(14).unwrap();
+        assert_eq!(io1_6.pin_index(), 14);
+        assert!(io1_6.is_high().unwrap());
+        drop(io1_6);
+        assert_eq!(pca.split().io0_3.pin_index(), 3);
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9555_builder() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x21, vec![0x02, 0x00]),
+            mock_i2c::Transaction::write(0x21, vec![0x03, 0x01]),
+            mock_i2c::Transaction::write(0x21, vec![0x04, 0x80]),
+            mock_i2c::Transaction::write(0x21, vec![0x05, 0x00]),
+            mock_i2c::Transaction::write(0x21, vec![0x06, 0xff]),
+            mock_i2c::Transaction::write(0x21, vec![0x07, 0x00]),
+            // the output state is known without reading it back
+            mock_i2c::Transaction::write(0x21, vec![0x03, 0x03]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let pca = super::Pca9555::builder()
+            .directions(0xff00)
+            .polarity(0x0080)
+            .initial_output(0x0100)
+            .build(bus.clone(), true, false, false)
+            .unwrap();
+        pca.write_mask(0x0200, 0x0000).unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9555_set_verified_inverted() {
+        let expectations = [
+            // pin setup io1_1
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0xfd]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x07], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x07, 0xfd]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x05], vec![0x00]),
+            mock_i2c::Transaction::write(0x20, vec![0x05, 0x02]),
+            // set HIGH, the inverted input reads back LOW
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x05], vec![0x02]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0xfd]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = super::Pca9555::new(bus.clone(), false, false, false);
+        let pca_pins = pca.split();
+
+        let mut io1_1 = pca_pins
+            .io1_1
+            .into_output()
+            .unwrap()
+            .into_inverted()
+            .unwrap();
+        io1_1.set_high_verified().unwrap();
+
+        bus.done();
+    }
 }
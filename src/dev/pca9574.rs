@@ -0,0 +1,175 @@
+//! Support for the NXP `PCA9574` "8-bit I2C-bus and SMBus, level translating, low voltage GPIO with
+//! interrupt and reset"
+//!
+//! The `PCA9574` is the 8-bit version of the [`PCA9575`](super::pca9575) and shares its driver,
+//! including the pull resistors and the interrupt mask.  All eight pins form one port, so a pull
+//! resistor enabled on one pin also pulls the others.
+pub use super::pca9575::{Addr, Driver};
+
+/// `PCA9574` "8-bit I2C-bus and SMBus, level translating, low voltage GPIO with interrupt and reset"
+pub struct Pca9574<M>(M);
+
+impl<I2C> Pca9574<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Pca9574<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9574::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pca9574<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9574::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+impl<I2C, M> Pca9574<M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub fn with_mutex(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self(crate::PortMutex::create(Driver::new_pca9574(
+            i2c, a0, a1, a2,
+        )))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            1,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x27`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            1,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
+    pub fn split(&mut self) -> Parts<'_, I2C, M> {
+        Parts {
+            p0: crate::Pin::new(0, &self.0),
+            p1: crate::Pin::new(1, &self.0),
+            p2: crate::Pin::new(2, &self.0),
+            p3: crate::Pin::new(3, &self.0),
+            p4: crate::Pin::new(4, &self.0),
+            p5: crate::Pin::new(5, &self.0),
+            p6: crate::Pin::new(6, &self.0),
+            p7: crate::Pin::new(7, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 8,
+        error: I2C::BusError,
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs with the output register cleared to LOW, input polarity inversion and
+    /// bus-hold/pull resistors are disabled and all interrupts are masked.  Consuming the device
+    /// requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub p0: crate::Pin<'a, crate::mode::Input, M>,
+    pub p1: crate::Pin<'a, crate::mode::Input, M>,
+    pub p2: crate::Pin<'a, crate::mode::Input, M>,
+    pub p3: crate::Pin<'a, crate::mode::Input, M>,
+    pub p4: crate::Pin<'a, crate::mode::Input, M>,
+    pub p5: crate::Pin<'a, crate::mode::Input, M>,
+    pub p6: crate::Pin<'a, crate::mode::Input, M>,
+    pub p7: crate::Pin<'a, crate::mode::Input, M>,
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pca9574() {
+        let expectations = [
+            // p3 output HIGH
+            mock_i2c::Transaction::write_read(0x24, vec![0x05], vec![0x00]),
+            mock_i2c::Transaction::write(0x24, vec![0x05, 0x08]),
+            mock_i2c::Transaction::write_read(0x24, vec![0x04], vec![0xff]),
+            mock_i2c::Transaction::write(0x24, vec![0x04, 0xf7]),
+            // p5 pull-up
+            mock_i2c::Transaction::write_read(0x24, vec![0x03], vec![0x00]),
+            mock_i2c::Transaction::write(0x24, vec![0x03, 0x20]),
+            mock_i2c::Transaction::write_read(0x24, vec![0x02], vec![0x00]),
+            mock_i2c::Transaction::write(0x24, vec![0x02, 0x02]),
+            // p5 input
+            mock_i2c::Transaction::write_read(0x24, vec![0x00], vec![0x20]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = super::Pca9574::new(bus.clone(), false, false, true);
+        let pca_pins = pca.split();
+
+        let _ = pca_pins.p3.into_output_high().unwrap();
+        let p5 = pca_pins.p5.into_pull_up().unwrap();
+        assert!(p5.is_high().unwrap());
+
+        bus.done();
+    }
+}
@@ -0,0 +1,617 @@
+//! Support for the NXP `PCA9575` "16-bit I2C-bus and SMBus, level translating, low voltage GPIO
+//! with interrupt and reset"
+//!
+//! Unlike the `PCA9555`, the `PCA9575` has built-in pull-up and pull-down resistors and an
+//! interrupt mask register.  The pull resistors are switched on per port of eight pins, each pin
+//! then selects whether it is pulled up or down.  So enabling a pull resistor on one pin also
+//! pulls the other pins of the same port, in the direction last selected for them (pull-up after
+//! reset).  Interrupts are masked after reset and are enabled per pin with
+//! [`Pin::enable_interrupt()`](crate::Pin::enable_interrupt).  The pins which caused an interrupt
+//! are reported by [`status()`](crate::status).
+//!
+//! The 8-bit [`PCA9574`](super::pca9574) shares this driver.
+
+/// `PCA9575` "16-bit I2C-bus and SMBus, level translating, low voltage GPIO with interrupt and reset"
+pub struct Pca9575<M>(M);
+
+impl<I2C> Pca9575<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Pca9575<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9575::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pca9575<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pca9575::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+impl<I2C, M> Pca9575<M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub fn with_mutex(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self(crate::PortMutex::create(Driver::new(i2c, a0, a1, a2)))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            2,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x27`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            2,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
+    pub fn split(&mut self) -> Parts<'_, I2C, M> {
+        Parts {
+            p0: crate::Pin::new(0, &self.0),
+            p1: crate::Pin::new(1, &self.0),
+            p2: crate::Pin::new(2, &self.0),
+            p3: crate::Pin::new(3, &self.0),
+            p4: crate::Pin::new(4, &self.0),
+            p5: crate::Pin::new(5, &self.0),
+            p6: crate::Pin::new(6, &self.0),
+            p7: crate::Pin::new(7, &self.0),
+            p8: crate::Pin::new(8, &self.0),
+            p9: crate::Pin::new(9, &self.0),
+            p10: crate::Pin::new(10, &self.0),
+            p11: crate::Pin::new(11, &self.0),
+            p12: crate::Pin::new(12, &self.0),
+            p13: crate::Pin::new(13, &self.0),
+            p14: crate::Pin::new(14, &self.0),
+            p15: crate::Pin::new(15, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 16,
+        error: I2C::BusError,
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xffff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs with the output register cleared to LOW, input polarity inversion and
+    /// bus-hold/pull resistors are disabled and all interrupts are masked.  Consuming the device
+    /// requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub p0: crate::Pin<'a, crate::mode::Input, M>,
+    pub p1: crate::Pin<'a, crate::mode::Input, M>,
+    pub p2: crate::Pin<'a, crate::mode::Input, M>,
+    pub p3: crate::Pin<'a, crate::mode::Input, M>,
+    pub p4: crate::Pin<'a, crate::mode::Input, M>,
+    pub p5: crate::Pin<'a, crate::mode::Input, M>,
+    pub p6: crate::Pin<'a, crate::mode::Input, M>,
+    pub p7: crate::Pin<'a, crate::mode::Input, M>,
+    pub p8: crate::Pin<'a, crate::mode::Input, M>,
+    pub p9: crate::Pin<'a, crate::mode::Input, M>,
+    pub p10: crate::Pin<'a, crate::mode::Input, M>,
+    pub p11: crate::Pin<'a, crate::mode::Input, M>,
+    pub p12: crate::Pin<'a, crate::mode::Input, M>,
+    pub p13: crate::Pin<'a, crate::mode::Input, M>,
+    pub p14: crate::Pin<'a, crate::mode::Input, M>,
+    pub p15: crate::Pin<'a, crate::mode::Input, M>,
+}
+
+/// Registers of the chip.
+///
+/// The `PCA9575` has one register per port for each of them, at `2 * reg + port`.  The `PCA9574`
+/// only has port 0, so its registers are at `reg`.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Regs {
+    InputPort = 0x00,
+    PolarityInversion = 0x01,
+    /// Bit 0: bus-hold enable, bit 1: pull-up/pull-down enable for the whole port
+    BusHoldPullEnable = 0x02,
+    /// 0: pull-down, 1: pull-up
+    PullSelection = 0x03,
+    /// 0: output, 1: input
+    Configuration = 0x04,
+    OutputPort = 0x05,
+    /// 0: interrupt enabled, 1: interrupt masked
+    InterruptMask = 0x06,
+    InterruptStatus = 0x07,
+}
+
+/// Pull-up/pull-down enable bit in the [`Regs::BusHoldPullEnable`] registers.
+const PULL_ENABLE: u8 = 0b10;
+
+crate::address::addr_type! {
+    /// I2C address of the `PCA9575`.
+    pub struct Addr: 0x20, [a0 = 0, a1 = 1, a2 = 2], 0x20..=0x27;
+}
+
+pub struct Driver<I2C> {
+    i2c: I2C,
+    addr: u8,
+    ports: u8,
+    out: Option<u16>,
+    dirty: bool,
+    /// Pins with an enabled pull resistor.  The chip only knows whether a port has pulls.
+    pulls: u16,
+    address_check: crate::AddressCheck,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
+}
+
+impl<I2C> Driver<I2C> {
+    /// Create a driver for the 16-bit `PCA9575`.
+    pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        let addr = Addr::from_pins(a0, a1, a2).get();
+        Self::with_address(i2c, addr, 2, crate::AddressCheck::Checked)
+    }
+
+    /// Create a driver for the 8-bit `PCA9574`.
+    pub fn new_pca9574(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        let addr = Addr::from_pins(a0, a1, a2).get();
+        Self::with_address(i2c, addr, 1, crate::AddressCheck::Checked)
+    }
+
+    pub(crate) fn with_address(
+        i2c: I2C,
+        addr: u8,
+        ports: u8,
+        address_check: crate::AddressCheck,
+    ) -> Self {
+        Self {
+            i2c,
+            addr,
+            ports,
+            // The output register is read from the chip before it is first written, so a driver
+            // restarted without the chip does not glitch its outputs.
+            out: None,
+            dirty: false,
+            pulls: 0x0000,
+            address_check,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
+        }
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
+
+    fn reg(&self, reg: Regs, port: u8) -> u8 {
+        reg as u8 * self.ports + port
+    }
+
+    /// Number of ports touched by `mask`.
+    fn banks(&self, mask: u32) -> u32 {
+        (0..self.ports)
+            .filter(|port| mask & (0xff << (8 * port)) != 0)
+            .count() as u32
+    }
+}
+
+impl<I2C: crate::RegisterBus> Driver<I2C> {
+    fn get_out(&mut self) -> Result<u16, I2C::BusError> {
+        match self.out {
+            Some(out) => Ok(out),
+            None => {
+                let mut out = 0x0000;
+                for port in 0..self.ports {
+                    let reg = self.reg(Regs::OutputPort, port);
+                    out |= (self.i2c.read_reg(self.addr, reg)? as u16) << (8 * port);
+                }
+                self.out = Some(out);
+                Ok(out)
+            }
+        }
+    }
+
+    fn write_out(&mut self, mask: u32, out: u16) -> Result<(), I2C::BusError> {
+        for port in 0..self.ports {
+            if mask & (0xff << (8 * port)) != 0 {
+                let reg = self.reg(Regs::OutputPort, port);
+                self.i2c
+                    .write_reg(self.addr, reg, (out >> (8 * port)) as u8)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the register `reg` of all ports.
+    fn read_regs(&mut self, reg: Regs) -> Result<u32, I2C::BusError> {
+        let mut value = 0x00000000;
+        for port in 0..self.ports {
+            let reg = self.reg(reg, port);
+            value |= (self.i2c.read_reg(self.addr, reg)? as u32) << (8 * port);
+        }
+        Ok(value)
+    }
+
+    /// Set the bits in `mask_set` and clear the bits in `mask_clear` of the register `reg`.
+    ///
+    /// Ports which are not touched by either mask are not accessed.
+    fn update_regs(
+        &mut self,
+        reg: Regs,
+        mask_set: u32,
+        mask_clear: u32,
+    ) -> Result<(), I2C::BusError> {
+        for port in 0..self.ports {
+            let shift = 8 * port;
+            if (mask_set | mask_clear) & (0xff << shift) != 0 {
+                let reg = self.reg(reg, port);
+                self.i2c.update_reg(
+                    self.addr,
+                    reg,
+                    (mask_set >> shift) as u8,
+                    (mask_clear >> shift) as u8,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Enable or disable the pull resistors of the pins in `mask`, pulling up if `up`.
+    fn set_pull(&mut self, mask: u32, enable: bool, up: bool) -> Result<(), I2C::BusError> {
+        if enable {
+            match up {
+                true => self.update_regs(Regs::PullSelection, mask, 0)?,
+                false => self.update_regs(Regs::PullSelection, 0, mask)?,
+            }
+            self.pulls |= mask as u16;
+        } else {
+            self.pulls &= !mask as u16;
+        }
+        // the pulls of a port stay enabled as long as one of its pins needs them
+        for port in 0..self.ports {
+            let shift = 8 * port;
+            if mask & (0xff << shift) != 0 {
+                let (set, clear) = match self.pulls & (0xff << shift) != 0 {
+                    true => (PULL_ENABLE, 0),
+                    false => (0, PULL_ENABLE),
+                };
+                let reg = self.reg(Regs::BusHoldPullEnable, port);
+                self.i2c.update_reg(self.addr, reg, set, clear)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Enable or disable the interrupts of the inputs in `mask`.
+    ///
+    /// All interrupts are disabled after reset.
+    pub fn set_interrupt(&mut self, mask: u32, enable: bool) -> Result<(), I2C::BusError> {
+        match enable {
+            true => self.update_regs(Regs::InterruptMask, 0, mask),
+            false => self.update_regs(Regs::InterruptMask, mask, 0),
+        }
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriver for Driver<I2C> {
+    type Error = I2C::BusError;
+
+    fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let mut out = self.get_out()?;
+        out |= mask_high as u16;
+        out &= !mask_low as u16;
+        self.out = Some(out);
+        // a failed write leaves the device in an unknown state, so all ports are written until a
+        // write succeeds again
+        let mask = match self.dirty {
+            true => 0xFFFF,
+            false => mask_high | mask_low,
+        };
+        self.dirty = true;
+        self.write_out(mask, out)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let out = self.get_out()?;
+        Ok(((out as u32) & mask_high) | (!(out as u32) & mask_low))
+    }
+
+    fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let ports = self.ports;
+        let in_ = self.input_state.read(mask_high | mask_low, |mask| {
+            let mut in_ = 0x00000000;
+            for port in 0..ports {
+                if mask & (0xff << (8 * port)) != 0 {
+                    let reg = Regs::InputPort as u8 * ports + port;
+                    in_ |= (self.i2c.read_reg(self.addr, reg)? as u32) << (8 * port);
+                }
+            }
+            Ok::<_, Self::Error>(in_)
+        })?;
+        Ok((in_ & mask_high) | (!in_ & mask_low))
+    }
+
+    fn get_polarity(&mut self, mask: u32) -> Result<u32, Self::Error> {
+        Ok(self.read_regs(Regs::PolarityInversion)? & mask)
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverInputState for Driver<I2C> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverOutputInversion for Driver<I2C> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverClaims for Driver<I2C> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverCost for Driver<I2C> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set {
+                mask_high,
+                mask_low,
+            } => {
+                let read_out = if self.out.is_none() {
+                    self.ports as u32
+                } else {
+                    0
+                };
+                read_out + self.banks(mask_high | mask_low)
+            }
+            crate::Operation::Get { mask } => self
+                .input_state
+                .read_mask(mask)
+                .map_or(0, |mask| self.banks(mask)),
+            crate::Operation::SetDirections {
+                mask_input,
+                mask_output,
+            } => 2 * self.banks(mask_input | mask_output),
+        }
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverResync for Driver<I2C> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        self.out = None;
+        self.get_out()?;
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverSnapshot for Driver<I2C> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        let pins = (1u32 << (8 * self.ports)) - 1;
+        let config = self.read_regs(Regs::Configuration)?;
+        let selection = self.read_regs(Regs::PullSelection)?;
+        let mut pulls = 0x00000000;
+        for port in 0..self.ports {
+            let reg = self.reg(Regs::BusHoldPullEnable, port);
+            if self.i2c.read_reg(self.addr, reg)? & PULL_ENABLE != 0 {
+                pulls |= 0xff << (8 * port);
+            }
+        }
+        Ok(crate::DriverState {
+            pins,
+            outputs: self.out.map(u32::from),
+            output_pins: Some(!config & pins),
+            pull_ups: Some(pulls & selection),
+            pull_downs: Some(pulls & !selection),
+            inputs: crate::PortDriver::get(self, pins, 0)?,
+        })
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRestoreDefaults for Driver<I2C> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        // inputs first, so the outputs stop driving before anything else changes
+        for (reg, value) in [
+            (Regs::Configuration, 0xff),
+            (Regs::OutputPort, 0x00),
+            (Regs::PolarityInversion, 0x00),
+            (Regs::BusHoldPullEnable, 0x00),
+            (Regs::PullSelection, 0xff),
+            (Regs::InterruptMask, 0xff),
+        ] {
+            for port in 0..self.ports {
+                let reg = self.reg(reg, port);
+                self.i2c.write_reg(self.addr, reg, value)?;
+            }
+        }
+        self.out = Some(0x0000);
+        self.pulls = 0x0000;
+        Ok(())
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRefresh for Driver<I2C> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        let out = self.get_out()?;
+        self.write_out(mask, out)
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverStatus for Driver<I2C> {
+    fn status(&mut self) -> Result<crate::Status, Self::Error> {
+        Ok(crate::Status {
+            reset: None,
+            interrupt_source: Some(self.read_regs(Regs::InterruptStatus)?),
+        })
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverTotemPole for Driver<I2C> {
+    fn set_directions(&mut self, mask_input: u32, mask_output: u32) -> Result<(), Self::Error> {
+        self.update_regs(Regs::Configuration, mask_input, mask_output)
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverPolarity for Driver<I2C> {
+    fn set_polarity(&mut self, mask: u32, inverted: bool) -> Result<(), Self::Error> {
+        match inverted {
+            true => self.update_regs(Regs::PolarityInversion, mask, 0),
+            false => self.update_regs(Regs::PolarityInversion, 0, mask),
+        }
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverPullUp for Driver<I2C> {
+    fn set_pull_up(&mut self, mask: u32, enable: bool) -> Result<(), Self::Error> {
+        self.set_pull(mask, enable, true)
+    }
+
+    fn set_input_pull(
+        &mut self,
+        mask: u32,
+        pull: crate::Pull,
+    ) -> Result<(), crate::PullError<Self::Error>> {
+        crate::common::set_input_pull_up_down(self, mask, pull)
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverPullDown for Driver<I2C> {
+    fn set_pull_down(&mut self, mask: u32, enable: bool) -> Result<(), Self::Error> {
+        self.set_pull(mask, enable, false)
+    }
+}
+
+impl<'a, MODE: crate::mode::HasInput + crate::mode::Configurable, I2C, M> crate::Pin<'a, MODE, M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    /// Enable or disable the interrupt of this input.
+    pub fn enable_interrupt(
+        &mut self,
+        enable: bool,
+    ) -> Result<(), crate::pin::PinError<I2C::BusError>> {
+        self.access_port_driver(|drv| drv.set_interrupt(self.pin_mask(), enable))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pca9575() {
+        let expectations = [
+            // p9 output LOW
+            mock_i2c::Transaction::write_read(0x21, vec![0x0a], vec![0x00]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x0b], vec![0x00]),
+            mock_i2c::Transaction::write(0x21, vec![0x0b, 0x00]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x09], vec![0xff]),
+            mock_i2c::Transaction::write(0x21, vec![0x09, 0xfd]),
+            // p0 pull-down
+            mock_i2c::Transaction::write_read(0x21, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x21, vec![0x06, 0xfe]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x04], vec![0x00]),
+            mock_i2c::Transaction::write(0x21, vec![0x04, 0x02]),
+            // p0 interrupt
+            mock_i2c::Transaction::write_read(0x21, vec![0x0c], vec![0xff]),
+            mock_i2c::Transaction::write(0x21, vec![0x0c, 0xfe]),
+            // p0 input
+            mock_i2c::Transaction::write_read(0x21, vec![0x00], vec![0x01]),
+            // interrupt status
+            mock_i2c::Transaction::write_read(0x21, vec![0x0e], vec![0x01]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x0f], vec![0x00]),
+            // p0 pull-down off, the port has no more pulls
+            mock_i2c::Transaction::write_read(0x21, vec![0x04], vec![0x02]),
+            mock_i2c::Transaction::write(0x21, vec![0x04, 0x00]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = super::Pca9575::new(bus.clone(), true, false, false);
+        let pca_pins = pca.split();
+
+        let _ = pca_pins.p9.into_output().unwrap();
+        let mut p0 = pca_pins.p0.into_pull_down().unwrap();
+        p0.enable_interrupt(true).unwrap();
+        assert!(p0.is_high().unwrap());
+
+        let status = crate::status(&p0).unwrap();
+        assert_eq!(status.interrupt_source, Some(0x0001));
+
+        p0.enable_pull_down(false).unwrap();
+
+        bus.done();
+    }
+}
@@ -0,0 +1,223 @@
+//! Support for the `PCA9702` "18 V tolerant SPI 8-bit input port with interrupt"
+//!
+//! The `PCA9702` only has inputs.  Its pins are in the [`InputOnly`](crate::mode::InputOnly)
+//! mode which cannot be converted into any other mode, so output methods can never be called on
+//! them.  Each read clocks out the state of all eight inputs in a single SPI transaction, `IN7`
+//! first.
+//!
+//! Should the driver still be asked to drive or query outputs, it fails with
+//! [`Error::UnsupportedOperation`] instead of panicking.
+
+/// `PCA9702` "18 V tolerant SPI 8-bit input port with interrupt"
+pub struct Pca9702<M>(M);
+
+impl<SPI> Pca9702<core::cell::RefCell<Driver<SPI>>>
+where
+    SPI: crate::SpiBus,
+{
+    pub fn new(spi: SPI) -> Self {
+        Self::with_mutex(spi)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<SPI> Pca9702<critical_section::Mutex<core::cell::RefCell<Driver<SPI>>>>
+where
+    SPI: crate::SpiBus,
+{
+    /// Same as [`new()`](Pca9702::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(spi: SPI) -> Self {
+        Self::with_mutex(spi)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<SPI> Pca9702<std::sync::Mutex<Driver<SPI>>>
+where
+    SPI: crate::SpiBus,
+{
+    /// Same as [`new()`](Pca9702::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(spi: SPI) -> Self {
+        Self::with_mutex(spi)
+    }
+}
+
+impl<SPI, M> Pca9702<M>
+where
+    SPI: crate::SpiBus,
+    M: crate::PortMutex<Port = Driver<SPI>>,
+{
+    pub fn with_mutex(spi: SPI) -> Self {
+        Self(crate::PortMutex::create(Driver::new(spi)))
+    }
+
+    pub fn split(&mut self) -> Parts<'_, SPI, M> {
+        Parts {
+            in0: crate::Pin::new(0, &self.0),
+            in1: crate::Pin::new(1, &self.0),
+            in2: crate::Pin::new(2, &self.0),
+            in3: crate::Pin::new(3, &self.0),
+            in4: crate::Pin::new(4, &self.0),
+            in5: crate::Pin::new(5, &self.0),
+            in6: crate::Pin::new(6, &self.0),
+            in7: crate::Pin::new(7, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::InputOnly,
+        pins: 8,
+        error: Error<SPI::BusError>,
+        methods: [pins, pin, take_pin, split_ref, into_arc_pins, write_mask, read_mask],
+    }
+}
+
+pub struct Parts<'a, SPI, M = core::cell::RefCell<Driver<SPI>>>
+where
+    SPI: crate::SpiBus,
+    M: crate::PortMutex<Port = Driver<SPI>>,
+{
+    pub in0: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub in1: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub in2: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub in3: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub in4: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub in5: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub in6: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub in7: crate::Pin<'a, crate::mode::InputOnly, M>,
+}
+
+/// Errors of the `PCA9702` driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// The SPI bus reported an error.
+    Bus(E),
+    /// The operation needs outputs, which the `PCA9702` does not have.
+    UnsupportedOperation,
+}
+
+pub struct Driver<SPI> {
+    spi: SPI,
+    input_state: crate::InputState,
+    claims: crate::PinClaims,
+}
+
+impl<SPI> Driver<SPI> {
+    pub fn new(spi: SPI) -> Self {
+        Self {
+            spi,
+            input_state: crate::InputState::default(),
+            claims: crate::PinClaims::default(),
+        }
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriver for Driver<SPI> {
+    type Error = Error<SPI::BusError>;
+
+    fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        if mask_high | mask_low != 0 {
+            return Err(Error::UnsupportedOperation);
+        }
+        Ok(())
+    }
+
+    fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        if mask_high | mask_low != 0 {
+            return Err(Error::UnsupportedOperation);
+        }
+        Ok(0)
+    }
+
+    fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let in_ = self.input_state.read(mask_high | mask_low, |_| {
+            let mut buf = [0x00];
+            self.spi.read(&mut buf).map_err(|e| Error::Bus(e.into()))?;
+            Ok::<_, Self::Error>(buf[0] as u32)
+        })?;
+        Ok((in_ & mask_high) | (!in_ & mask_low))
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriverSnapshot for Driver<SPI> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        Ok(crate::DriverState {
+            pins: 0xff,
+            outputs: None,
+            output_pins: Some(0x00),
+            pull_ups: None,
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, 0xff, 0)?,
+        })
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriverInputState for Driver<SPI> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriverClaims for Driver<SPI> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriverCost for Driver<SPI> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Get { mask } => self.input_state.read_mask(mask).is_some() as u32,
+            crate::Operation::Set { .. } | crate::Operation::SetDirections { .. } => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::spi as mock_spi;
+
+    #[test]
+    fn pca9702() {
+        let expectations = [
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::read(0b10000001),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::read(0b10000001),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::read(0b10000001),
+            mock_spi::Transaction::transaction_end(),
+        ];
+        let mut bus = mock_spi::Mock::new(&expectations);
+
+        let mut pca = super::Pca9702::new(bus.clone());
+        let pca_pins = pca.split();
+
+        assert!(pca_pins.in0.is_high().unwrap());
+        assert!(pca_pins.in1.is_low().unwrap());
+        assert!(pca_pins.in7.is_high().unwrap());
+
+        bus.done();
+    }
+
+    #[test]
+    fn pca9702_unsupported_operation() {
+        use crate::PortDriver;
+
+        let mut bus = mock_spi::Mock::new(&[]);
+
+        let mut drv = super::Driver::new(bus.clone());
+        assert_eq!(drv.set(0x01, 0x00), Err(super::Error::UnsupportedOperation));
+        assert_eq!(
+            drv.is_set(0x00, 0x80),
+            Err(super::Error::UnsupportedOperation)
+        );
+
+        bus.done();
+    }
+}
@@ -1,27 +1,81 @@
 //! Support for the `PCAL6408A` "8-bit I2C-bus and SMBus I/O port with interrupt"
-use crate::I2cExt;
 
 /// `PCAL6408A` "8-bit I2C-bus and SMBus I/O port with interrupt"
 pub struct Pcal6408a<M>(M);
 
 impl<I2C> Pcal6408a<core::cell::RefCell<Driver<I2C>>>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
 {
     pub fn new(i2c: I2C, addr: bool) -> Self {
         Self::with_mutex(i2c, addr)
     }
 }
 
+#[cfg(feature = "critical-section")]
+impl<I2C> Pcal6408a<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pcal6408a::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, addr: bool) -> Self {
+        Self::with_mutex(i2c, addr)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pcal6408a<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pcal6408a::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, addr: bool) -> Self {
+        Self::with_mutex(i2c, addr)
+    }
+}
+
 impl<I2C, M> Pcal6408a<M>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
     M: crate::PortMutex<Port = Driver<I2C>>,
 {
     pub fn with_mutex(i2c: I2C, addr: bool) -> Self {
         Self(crate::PortMutex::create(Driver::new(i2c, addr)))
     }
 
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x21`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
     pub fn split(&mut self) -> Parts<'_, I2C, M> {
         Parts {
             io0: crate::Pin::new(0, &self.0),
@@ -34,11 +88,48 @@ where
             io7: crate::Pin::new(7, &self.0),
         }
     }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 8,
+        error: I2C::BusError,
+    }
+
+    /// Switch all outputs between push-pull and open-drain.
+    ///
+    /// The chip starts up with push-pull outputs.  In [`OutputMode::OpenDrain`](crate::OutputMode),
+    /// a HIGH output is released instead of driven, so several chips can share a line like an
+    /// interrupt with an external pull-up.
+    pub fn set_output_mode(&self, mode: crate::OutputMode) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverOutputMode::set_output_mode(drv, mode)
+        })
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH and input polarity
+    /// inversion, the input latch and the pull resistors are disabled.  All interrupts are masked.
+    /// Consuming the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
 }
 
 pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
     M: crate::PortMutex<Port = Driver<I2C>>,
 {
     pub io0: crate::Pin<'a, crate::mode::Input, M>,
@@ -74,24 +165,48 @@ impl From<Regs> for u8 {
     }
 }
 
+crate::address::addr_type! {
+    /// I2C address of the `PCAL6408A`.
+    pub struct Addr: 0x20, [addr = 0], 0x20..=0x21;
+}
+
 pub struct Driver<I2C> {
     i2c: I2C,
     out: Option<u8>,
+    dirty: bool,
     addr: u8,
+    address_check: crate::AddressCheck,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
 }
 
 impl<I2C> Driver<I2C> {
     pub fn new(i2c: I2C, addr: bool) -> Self {
-        let addr = 0x20 | (addr as u8);
+        let addr = Addr::from_pins(addr).get();
+        Self::with_address(i2c, addr, crate::AddressCheck::Checked)
+    }
+
+    pub(crate) fn with_address(i2c: I2C, addr: u8, address_check: crate::AddressCheck) -> Self {
         Self {
             i2c,
             out: None,
+            dirty: false,
             addr,
+            address_check,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
         }
     }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
 }
 
-impl<I2C: crate::I2cBus> Driver<I2C> {
+impl<I2C: crate::RegisterBus> Driver<I2C> {
     fn get_out(&mut self) -> Result<u8, I2C::BusError> {
         // Make sure the state of the OutputPort register is actually known instead of assumed to avoid glitches on reboot.
         // This is necessary because the OutputPort register is written instead of updated.
@@ -106,67 +221,169 @@ impl<I2C: crate::I2cBus> Driver<I2C> {
     }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriver for Driver<I2C> {
+impl<I2C: crate::RegisterBus> crate::PortDriver for Driver<I2C> {
     type Error = I2C::BusError;
 
     fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         let mut out = self.get_out()?;
         out |= mask_high as u8;
         out &= !mask_low as u8;
         self.out = Some(out);
-        if (mask_high | mask_low) & 0x00FF != 0 {
+        if (mask_high | mask_low) & 0x00FF != 0 || self.dirty {
+            self.dirty = true;
             self.i2c.write_reg(self.addr, Regs::OutputPort, out)?;
+            self.dirty = false;
         }
         Ok(())
     }
 
     fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         let out = self.get_out()?;
         Ok(((out as u32) & mask_high) | (!(out as u32) & mask_low))
     }
 
     fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
-        let io0 = if (mask_high | mask_low) & 0x00FF != 0 {
-            self.i2c.read_reg(self.addr, Regs::InputPort)?
-        } else {
-            0
-        };
-        let in_ = io0 as u32;
+        let in_ = self.input_state.read(mask_high | mask_low, |mask| {
+            let io0 = if mask & 0x00FF != 0 {
+                self.i2c.read_reg(self.addr, Regs::InputPort)?
+            } else {
+                0
+            };
+            Ok::<_, Self::Error>(io0 as u32)
+        })?;
         Ok((in_ & mask_high) | (!in_ & mask_low))
     }
+
+    fn get_polarity(&mut self, mask: u32) -> Result<u32, Self::Error> {
+        Ok(self.i2c.read_reg(self.addr, Regs::PolarityInversion)? as u32 & mask)
+    }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriverTotemPole for Driver<I2C> {
-    fn set_direction(
-        &mut self,
-        mask: u32,
-        dir: crate::Direction,
-        state: bool,
-    ) -> Result<(), Self::Error> {
-        if mask & 0xFF == 0 {
-            return Ok(());
+impl<I2C: crate::RegisterBus> crate::PortDriverInputState for Driver<I2C> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverOutputInversion for Driver<I2C> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverClaims for Driver<I2C> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverCost for Driver<I2C> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set {
+                mask_high,
+                mask_low,
+            } => self.out.is_none() as u32 + ((mask_high | mask_low) & 0xff != 0) as u32,
+            crate::Operation::Get { mask } => self
+                .input_state
+                .read_mask(mask)
+                .map_or(0, |mask| (mask & 0xff != 0) as u32),
+            crate::Operation::SetDirections { .. } => 2,
         }
-        // set state before switching direction to prevent glitch
-        if dir == crate::Direction::Output {
-            use crate::PortDriver;
-            if state {
-                self.set(mask, 0)?;
-            } else {
-                self.set(0, mask)?;
-            }
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverResync for Driver<I2C> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        self.out = Some(self.i2c.read_reg(self.addr, Regs::OutputPort)?);
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverSnapshot for Driver<I2C> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        let config = self.i2c.read_reg(self.addr, Regs::Configuration)?;
+        let pull_enable = self.i2c.read_reg(self.addr, Regs::PullEnable)?;
+        let pull_selection = self.i2c.read_reg(self.addr, Regs::PullSelection)?;
+        Ok(crate::DriverState {
+            pins: 0xff,
+            outputs: self.out.map(u32::from),
+            output_pins: Some(!config as u32 & 0xff),
+            pull_ups: Some((pull_enable & pull_selection) as u32),
+            pull_downs: Some((pull_enable & !pull_selection) as u32),
+            inputs: crate::PortDriver::get(self, 0xff, 0)?,
+        })
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRestoreDefaults for Driver<I2C> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        // inputs first, so the outputs stop driving before anything else changes
+        self.i2c.write_reg(self.addr, Regs::Configuration, 0xff)?;
+        self.i2c.write_reg(self.addr, Regs::OutputPort, 0xff)?;
+        self.i2c
+            .write_reg(self.addr, Regs::PolarityInversion, 0x00)?;
+        self.i2c.write_reg(self.addr, Regs::InputLatch, 0x00)?;
+        self.i2c.write_reg(self.addr, Regs::PullEnable, 0x00)?;
+        self.i2c.write_reg(self.addr, Regs::PullSelection, 0xff)?;
+        self.i2c.write_reg(self.addr, Regs::InterruptMask, 0xff)?;
+        self.out = Some(0xff);
+        Ok(())
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRefresh for Driver<I2C> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        if mask & 0xFF != 0 {
+            let out = self.get_out()?;
+            self.i2c.write_reg(self.addr, Regs::OutputPort, out)?;
         }
+        Ok(())
+    }
+}
 
-        let (mask_set, mask_clear) = match dir {
-            crate::Direction::Input => (mask as u8, 0),
-            crate::Direction::Output => (0, mask as u8),
+impl<I2C: crate::RegisterBus> crate::PortDriverStatus for Driver<I2C> {
+    fn status(&mut self) -> Result<crate::Status, Self::Error> {
+        let interrupt_source = self.i2c.read_reg(self.addr, Regs::InterruptStatus)?;
+        Ok(crate::Status {
+            reset: None,
+            interrupt_source: Some(interrupt_source as u32),
+        })
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverOutputMode for Driver<I2C> {
+    fn set_output_mode(&mut self, mode: crate::OutputMode) -> Result<(), Self::Error> {
+        let oden = match mode {
+            crate::OutputMode::PushPull => 0x00,
+            crate::OutputMode::OpenDrain => 0x01,
         };
         self.i2c
-            .update_reg(self.addr, Regs::Configuration, mask_set, mask_clear)?;
-        Ok(())
+            .write_reg(self.addr, Regs::OutputPortConfiguration, oden)
     }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriverPolarity for Driver<I2C> {
+impl<I2C: crate::RegisterBus> crate::PortDriverTotemPole for Driver<I2C> {
+    fn set_directions(&mut self, mask_input: u32, mask_output: u32) -> Result<(), Self::Error> {
+        if (mask_input | mask_output) & 0xFF == 0 {
+            return Ok(());
+        }
+        self.i2c.update_reg(
+            self.addr,
+            Regs::Configuration,
+            mask_input as u8,
+            mask_output as u8,
+        )
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverPolarity for Driver<I2C> {
     fn set_polarity(&mut self, mask: u32, inverted: bool) -> Result<(), Self::Error> {
         if mask & 0xFF == 0 {
             return Ok(());
@@ -234,4 +451,33 @@ mod tests {
 
         bus.done();
     }
+
+    #[test]
+    fn pcal6408a_output_mode() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0x47, 0x01]),
+            mock_i2c::Transaction::write(0x20, vec![0x47, 0x00]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let pcal = super::Pcal6408a::new(bus.clone(), false);
+        pcal.set_output_mode(crate::OutputMode::OpenDrain).unwrap();
+        pcal.set_output_mode(crate::OutputMode::PushPull).unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn pcal6408a_empty_directions() {
+        use crate::PortDriverTotemPole;
+
+        let mut bus = mock_i2c::Mock::new(&[]);
+
+        // pins outside of the chip don't cause a bus access
+        let mut drv = super::Driver::new(bus.clone(), false);
+        drv.set_directions(0, 0).unwrap();
+        drv.set_directions(0x100, 0).unwrap();
+
+        bus.done();
+    }
 }
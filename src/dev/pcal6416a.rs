@@ -1,27 +1,81 @@
 //! Support for the `PCAL6416A` "16-bit I2C-bus and SMBus I/O port with interrupt"
-use crate::I2cExt;
 
 /// `PCAL6416A` "16-bit I2C-bus and SMBus I/O port with interrupt"
 pub struct Pcal6416a<M>(M);
 
 impl<I2C> Pcal6416a<core::cell::RefCell<Driver<I2C>>>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
 {
     pub fn new(i2c: I2C, addr: bool) -> Self {
         Self::with_mutex(i2c, addr)
     }
 }
 
+#[cfg(feature = "critical-section")]
+impl<I2C> Pcal6416a<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pcal6416a::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, addr: bool) -> Self {
+        Self::with_mutex(i2c, addr)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pcal6416a<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pcal6416a::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, addr: bool) -> Self {
+        Self::with_mutex(i2c, addr)
+    }
+}
+
 impl<I2C, M> Pcal6416a<M>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
     M: crate::PortMutex<Port = Driver<I2C>>,
 {
     pub fn with_mutex(i2c: I2C, addr: bool) -> Self {
         Self(crate::PortMutex::create(Driver::new(i2c, addr)))
     }
 
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x21`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
     pub fn split(&mut self) -> Parts<'_, I2C, M> {
         Parts {
             io0_0: crate::Pin::new(0, &self.0),
@@ -42,11 +96,49 @@ where
             io1_7: crate::Pin::new(15, &self.0),
         }
     }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 16,
+        error: I2C::BusError,
+    }
+
+    /// Switch all outputs between push-pull and open-drain.
+    ///
+    /// The chip starts up with push-pull outputs.  In [`OutputMode::OpenDrain`](crate::OutputMode),
+    /// a HIGH output is released instead of driven, so several chips can share a line like an
+    /// interrupt with an external pull-up.
+    pub fn set_output_mode(&self, mode: crate::OutputMode) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverOutputMode::set_output_mode(drv, mode)
+        })
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xffff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH and input polarity
+    /// inversion, the input latch and the pull resistors are disabled.  All interrupts are masked
+    /// and the outputs are push-pull again.  Consuming the device requires that all of its pins
+    /// were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
 }
 
 pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
     M: crate::PortMutex<Port = Driver<I2C>>,
 {
     pub io0_0: crate::Pin<'a, crate::mode::Input, M>,
@@ -92,7 +184,7 @@ enum Regs {
     InterruptMask1 = 0x4B,
     InterruptStatus0 = 0x4C,
     InterruptStatus1 = 0x4D,
-    OutputPortConfiguration = 0x4F, // Bit 0: Push-Pull (0) or Open-Drain (1) for all Outputs
+    OutputPortConfiguration = 0x4F, // Bit n: Push-Pull (0) or Open-Drain (1) for Port n
 }
 
 impl From<Regs> for u8 {
@@ -101,145 +193,64 @@ impl From<Regs> for u8 {
     }
 }
 
-pub struct Driver<I2C> {
-    i2c: I2C,
-    out: Option<u16>,
-    addr: u8,
+crate::address::addr_type! {
+    /// I2C address of the `PCAL6416A`.
+    pub struct Addr: 0x20, [addr = 0], 0x20..=0x21;
 }
 
-impl<I2C> Driver<I2C> {
-    pub fn new(i2c: I2C, addr: bool) -> Self {
-        let addr = 0x20 | (addr as u8);
-        Self {
-            i2c,
-            out: None,
-            addr,
-        }
-    }
-}
+/// Register map of the `PCAL6416A`.
+pub struct Registers;
 
-impl<I2C: crate::I2cBus> Driver<I2C> {
-    fn get_out(&mut self) -> Result<u16, I2C::BusError> {
-        // Make sure the state of the OutputPort register is actually known instead of assumed to avoid glitches on reboot.
-        // This is necessary because the OutputPort register is written instead of updated.
-        match self.out {
-            Some(out) => Ok(out),
-            None => {
-                let out_low = self.i2c.read_reg(self.addr, Regs::OutputPort0)? as u16;
-                let out_high = self.i2c.read_reg(self.addr, Regs::OutputPort1)? as u16;
-                let out = out_low | (out_high << 8);
-                self.out = Some(out);
-                Ok(out)
-            }
-        }
-    }
+impl crate::driver::Regs16 for Registers {
+    const INPUT: [u8; 2] = [Regs::InputPort0 as u8, Regs::InputPort1 as u8];
+    const OUTPUT: [u8; 2] = [Regs::OutputPort0 as u8, Regs::OutputPort1 as u8];
+    const POLARITY_INVERSION: [u8; 2] = [
+        Regs::PolarityInversion0 as u8,
+        Regs::PolarityInversion1 as u8,
+    ];
+    const CONFIGURATION: [u8; 2] = [Regs::Configuration0 as u8, Regs::Configuration1 as u8];
+    // Make sure the state of the OutputPort register is actually known instead of assumed to
+    // avoid glitches on reboot.  This is necessary because the OutputPort register is written
+    // instead of updated.
+    const OUTPUT_RESET: Option<u16> = None;
+    const PCAL: Option<crate::driver::PcalRegisters16> = Some(crate::driver::PcalRegisters16 {
+        input_latch: [Regs::InputLatch0 as u8, Regs::InputLatch1 as u8],
+        pull_enable: [Regs::PullEnable0 as u8, Regs::PullEnable1 as u8],
+        pull_selection: [Regs::PullSelection0 as u8, Regs::PullSelection1 as u8],
+        interrupt_mask: [Regs::InterruptMask0 as u8, Regs::InterruptMask1 as u8],
+        output_port_configuration: Regs::OutputPortConfiguration as u8,
+    });
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriver for Driver<I2C> {
-    type Error = I2C::BusError;
-
-    fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
-        let mut out = self.get_out()?;
-        out |= mask_high as u16;
-        out &= !mask_low as u16;
-        self.out = Some(out);
-        if (mask_high | mask_low) & 0x00FF != 0 {
-            self.i2c
-                .write_reg(self.addr, Regs::OutputPort0, (out & 0xFF) as u8)?;
-        }
-        if (mask_high | mask_low) & 0xFF00 != 0 {
-            self.i2c
-                .write_reg(self.addr, Regs::OutputPort1, (out >> 8) as u8)?;
-        }
-        Ok(())
-    }
-
-    fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
-        let out = self.get_out()?;
-        Ok(((out as u32) & mask_high) | (!(out as u32) & mask_low))
-    }
+pub type Driver<I2C> = crate::driver::Driver16<I2C, Registers>;
 
-    fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
-        let io0 = if (mask_high | mask_low) & 0x00FF != 0 {
-            self.i2c.read_reg(self.addr, Regs::InputPort0)?
-        } else {
-            0
-        };
-        let io1 = if (mask_high | mask_low) & 0xFF00 != 0 {
-            self.i2c.read_reg(self.addr, Regs::InputPort1)?
-        } else {
-            0
-        };
-        let in_ = ((io1 as u32) << 8) | io0 as u32;
-        Ok((in_ & mask_high) | (!in_ & mask_low))
+impl<I2C> Driver<I2C> {
+    pub fn new(i2c: I2C, addr: bool) -> Self {
+        let addr = Addr::from_pins(addr).get();
+        Self::with_address(i2c, addr, crate::AddressCheck::Checked)
     }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriverTotemPole for Driver<I2C> {
-    fn set_direction(
-        &mut self,
-        mask: u32,
-        dir: crate::Direction,
-        state: bool,
-    ) -> Result<(), Self::Error> {
-        // set state before switching direction to prevent glitch
-        if dir == crate::Direction::Output {
-            use crate::PortDriver;
-            if state {
-                self.set(mask, 0)?;
-            } else {
-                self.set(0, mask)?;
-            }
-        }
-
-        let (mask_set, mask_clear) = match dir {
-            crate::Direction::Input => (mask as u16, 0),
-            crate::Direction::Output => (0, mask as u16),
-        };
-        if mask & 0x00FF != 0 {
-            self.i2c.update_reg(
-                self.addr,
-                Regs::Configuration0,
-                (mask_set & 0xFF) as u8,
-                (mask_clear & 0xFF) as u8,
-            )?;
-        }
-        if mask & 0xFF00 != 0 {
-            self.i2c.update_reg(
-                self.addr,
-                Regs::Configuration1,
-                (mask_set >> 8) as u8,
-                (mask_clear >> 8) as u8,
-            )?;
-        }
-        Ok(())
+impl<I2C: crate::RegisterBus> crate::PortDriverStatus for Driver<I2C> {
+    fn status(&mut self) -> Result<crate::Status, Self::Error> {
+        let status0 = self.i2c.read_reg(self.addr, Regs::InterruptStatus0)?;
+        let status1 = self.i2c.read_reg(self.addr, Regs::InterruptStatus1)?;
+        Ok(crate::Status {
+            reset: None,
+            interrupt_source: Some(((status1 as u32) << 8) | status0 as u32),
+        })
     }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriverPolarity for Driver<I2C> {
-    fn set_polarity(&mut self, mask: u32, inverted: bool) -> Result<(), Self::Error> {
-        let (mask_set, mask_clear) = match inverted {
-            false => (0, mask as u16),
-            true => (mask as u16, 0),
+impl<I2C: crate::RegisterBus> crate::PortDriverOutputMode for Driver<I2C> {
+    fn set_output_mode(&mut self, mode: crate::OutputMode) -> Result<(), Self::Error> {
+        // one bit per port
+        let oden = match mode {
+            crate::OutputMode::PushPull => 0x00,
+            crate::OutputMode::OpenDrain => 0x03,
         };
-
-        if mask & 0x00FF != 0 {
-            self.i2c.update_reg(
-                self.addr,
-                Regs::PolarityInversion0,
-                (mask_set & 0xFF) as u8,
-                (mask_clear & 0xFF) as u8,
-            )?;
-        }
-        if mask & 0xFF00 != 0 {
-            self.i2c.update_reg(
-                self.addr,
-                Regs::PolarityInversion1,
-                (mask_set >> 8) as u8,
-                (mask_clear >> 8) as u8,
-            )?;
-        }
-        Ok(())
+        self.i2c
+            .write_reg(self.addr, Regs::OutputPortConfiguration, oden)
     }
 }
 
@@ -324,4 +335,48 @@ mod tests {
 
         bus.done();
     }
+
+    #[test]
+    fn pcal6416a_output_mode() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0x4f, 0x03]),
+            mock_i2c::Transaction::write(0x20, vec![0x4f, 0x00]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let pcal = super::Pcal6416a::new(bus.clone(), false);
+        pcal.set_output_mode(crate::OutputMode::OpenDrain).unwrap();
+        pcal.set_output_mode(crate::OutputMode::PushPull).unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn pcal6416a_restore_defaults() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x07, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x04, 0x00]),
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x05, 0x00]),
+            // input latch, pull enable, pull selection and interrupt mask of both ports
+            mock_i2c::Transaction::write(0x20, vec![0x44, 0x00]),
+            mock_i2c::Transaction::write(0x20, vec![0x46, 0x00]),
+            mock_i2c::Transaction::write(0x20, vec![0x48, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x4a, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x45, 0x00]),
+            mock_i2c::Transaction::write(0x20, vec![0x47, 0x00]),
+            mock_i2c::Transaction::write(0x20, vec![0x49, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x4b, 0xff]),
+            // push-pull outputs
+            mock_i2c::Transaction::write(0x20, vec![0x4f, 0x00]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let pcal = super::Pcal6416a::new(bus.clone(), false);
+        pcal.restore_defaults().unwrap();
+
+        bus.done();
+    }
 }
@@ -1,4 +1,11 @@
 //! Support for the `PCF8574` & `PCF8574A` "Remote 8-bit I/O expander for I2C-bus with interrupt"
+//!
+//! The pins are quasi-bidirectional, the chip has no direction register.  So generic code can set
+//! up pins like on other port-expanders, [`into_input()`](crate::Pin::into_input) writes the pin
+//! HIGH, releasing it to the weak internal pull-up, and [`into_output()`](crate::Pin::into_output)
+//! and [`into_output_high()`](crate::Pin::into_output_high) only write the requested level.  On a
+//! LOW to HIGH transition, the chip briefly enables a strong pull-up, so a line which is held LOW
+//! externally sees a short current pulse whenever a pin becomes an input or is set HIGH.
 
 /// `PCF8574` "Remote 8-bit I/O expander for I2C-bus with interrupt"
 pub struct Pcf8574<M>(M);
@@ -12,6 +19,45 @@ where
     pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
         Self::with_mutex(i2c, a0, a1, a2)
     }
+
+    /// Create a new instance and immediately write `initial` to the port.
+    ///
+    /// The chip powers up with all pins HIGH.  Writing the initial state during construction keeps
+    /// the window short in which pins which should be LOW are still HIGH.  Bit `n` of `initial` is
+    /// the `n`-th pin of [`pins()`](Pcf8574::pins).
+    pub fn new_with_state(
+        i2c: I2C,
+        a0: bool,
+        a1: bool,
+        a2: bool,
+        initial: u8,
+    ) -> Result<Self, I2C::BusError> {
+        Self::with_mutex_state(i2c, a0, a1, a2, initial)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Pcf8574<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new()`](Pcf8574::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pcf8574<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new()`](Pcf8574::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
 }
 
 impl<I2C> Pcf8574a<core::cell::RefCell<Driver<I2C>>>
@@ -21,6 +67,45 @@ where
     pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
         Self::with_mutex(i2c, a0, a1, a2)
     }
+
+    /// Create a new instance and immediately write `initial` to the port.
+    ///
+    /// The chip powers up with all pins HIGH.  Writing the initial state during construction keeps
+    /// the window short in which pins which should be LOW are still HIGH.  Bit `n` of `initial` is
+    /// the `n`-th pin of [`pins()`](Pcf8574a::pins).
+    pub fn new_with_state(
+        i2c: I2C,
+        a0: bool,
+        a1: bool,
+        a2: bool,
+        initial: u8,
+    ) -> Result<Self, I2C::BusError> {
+        Self::with_mutex_state(i2c, a0, a1, a2, initial)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Pcf8574a<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new()`](Pcf8574a::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pcf8574a<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new()`](Pcf8574a::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
 }
 
 impl<I2C, M> Pcf8574<M>
@@ -34,6 +119,50 @@ where
         )))
     }
 
+    /// Like [`new_with_state()`](Pcf8574::new_with_state), with a custom mutex.
+    pub fn with_mutex_state(
+        i2c: I2C,
+        a0: bool,
+        a1: bool,
+        a2: bool,
+        initial: u8,
+    ) -> Result<Self, I2C::BusError> {
+        let mut driver = Driver::new(i2c, false, a0, a1, a2);
+        crate::PortDriver::set(&mut driver, initial as u32, !(initial as u32))?;
+        Ok(Self(crate::PortMutex::create(driver)))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x27`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
     pub fn split(&mut self) -> Parts<'_, I2C, M> {
         Parts {
             p0: crate::Pin::new(0, &self.0),
@@ -46,6 +175,23 @@ where
             p7: crate::Pin::new(7, &self.0),
         }
     }
+
+    pin_methods! {
+        mode: crate::mode::QuasiBidirectional,
+        pins: 8,
+        error: I2C::BusError,
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// The chip has no direction register: writing HIGH to all pins turns off the output drivers,
+    /// leaving every pin as an input with the weak pull-up, just like after power-on.  Consuming
+    /// the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
 }
 
 impl<I2C, M> Pcf8574a<M>
@@ -57,6 +203,50 @@ where
         Self(crate::PortMutex::create(Driver::new(i2c, true, a0, a1, a2)))
     }
 
+    /// Like [`new_with_state()`](Pcf8574a::new_with_state), with a custom mutex.
+    pub fn with_mutex_state(
+        i2c: I2C,
+        a0: bool,
+        a1: bool,
+        a2: bool,
+        initial: u8,
+    ) -> Result<Self, I2C::BusError> {
+        let mut driver = Driver::new(i2c, true, a0, a1, a2);
+        crate::PortDriver::set(&mut driver, initial as u32, !(initial as u32))?;
+        Ok(Self(crate::PortMutex::create(driver)))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: AddrA) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x38` to `0x3f`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, AddrA::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
     pub fn split(&mut self) -> Parts<'_, I2C, M> {
         Parts {
             p0: crate::Pin::new(0, &self.0),
@@ -69,6 +259,23 @@ where
             p7: crate::Pin::new(7, &self.0),
         }
     }
+
+    pin_methods! {
+        mode: crate::mode::QuasiBidirectional,
+        pins: 8,
+        error: I2C::BusError,
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// The chip has no direction register: writing HIGH to all pins turns off the output drivers,
+    /// leaving every pin as an input with the weak pull-up, just like after power-on.  Consuming
+    /// the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
 }
 
 pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
@@ -86,49 +293,207 @@ where
     pub p7: crate::Pin<'a, crate::mode::QuasiBidirectional, M>,
 }
 
+crate::address::addr_type! {
+    /// I2C address of the `PCF8574`.
+    pub struct Addr: 0x20, [a0 = 0, a1 = 1, a2 = 2], 0x20..=0x27;
+}
+
+crate::address::addr_type! {
+    /// I2C address of the `PCF8574A`.
+    pub struct AddrA: 0x38, [a0 = 0, a1 = 1, a2 = 2], 0x38..=0x3f;
+}
+
 pub struct Driver<I2C> {
     i2c: I2C,
     out: u8,
+    dirty: bool,
     addr: u8,
+    address_check: crate::AddressCheck,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
+    last_input: u8,
 }
 
 impl<I2C> Driver<I2C> {
     pub fn new(i2c: I2C, is_a_variant: bool, a0: bool, a1: bool, a2: bool) -> Self {
         let addr = if is_a_variant {
-            0x38 | ((a2 as u8) << 2) | ((a1 as u8) << 1) | (a0 as u8)
+            AddrA::from_pins(a0, a1, a2).get()
         } else {
-            0x20 | ((a2 as u8) << 2) | ((a1 as u8) << 1) | (a0 as u8)
+            Addr::from_pins(a0, a1, a2).get()
         };
+        Self::with_address(i2c, addr, crate::AddressCheck::Checked)
+    }
+
+    pub(crate) fn with_address(i2c: I2C, addr: u8, address_check: crate::AddressCheck) -> Self {
         Self {
             i2c,
             out: 0xff,
+            dirty: false,
             addr,
+            address_check,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
+            last_input: 0xff,
         }
     }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
+}
+
+impl<I2C: crate::I2cBus> Driver<I2C> {
+    /// Read all pins and return `(changed_mask, value)`.
+    ///
+    /// `changed_mask` contains all pins whose state differs from the previous call of this
+    /// method.  Before the first call, all pins are assumed HIGH, which is their power-on state.
+    /// This is meant to be called when the chip asserts its `INT` line, to find out which inputs
+    /// changed with a single bus transaction.
+    pub fn read_changes(&mut self) -> Result<(u32, u32), I2C::BusError> {
+        let mut buf = [0x00];
+        self.i2c.read(self.addr, &mut buf)?;
+        let value = buf[0];
+        let changed = value ^ self.last_input;
+        self.last_input = value;
+        Ok((changed as u32, value as u32))
+    }
+
+    /// Write `states` to the port one after another in a single I2C transfer.
+    ///
+    /// Each byte is a complete state of all pins, the pins change with every byte.  This is meant
+    /// for protocols which are bit-banged over the port, like the HD44780 bus of LCD backpacks.
+    /// Output inversion is not applied.
+    #[cfg_attr(
+        feature = "hd44780",
+        doc = "",
+        doc = "See [`Hd44780`](crate::hd44780::Hd44780) for such a protocol."
+    )]
+    pub fn write_sequence(&mut self, states: &[u8]) -> Result<(), I2C::BusError> {
+        if let Some(last) = states.last() {
+            self.i2c.write(self.addr, states)?;
+            self.out = *last;
+        }
+        Ok(())
+    }
 }
 
 impl<I2C: crate::I2cBus> crate::PortDriver for Driver<I2C> {
     type Error = I2C::BusError;
 
     fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         self.out |= mask_high as u8;
         self.out &= !mask_low as u8;
+        // stays set if the write fails
+        self.dirty = true;
         self.i2c.write(self.addr, &[self.out])?;
+        self.dirty = false;
         Ok(())
     }
 
     fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         Ok(((self.out as u32) & mask_high) | (!(self.out as u32) & mask_low))
     }
 
     fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
-        let mut buf = [0x00];
-        self.i2c.read(self.addr, &mut buf)?;
-        let in_ = buf[0] as u32;
+        let in_ = self.input_state.read(mask_high | mask_low, |_| {
+            let mut buf = [0x00];
+            self.i2c.read(self.addr, &mut buf)?;
+            Ok::<_, Self::Error>(buf[0] as u32)
+        })?;
         Ok((in_ & mask_high) | (!in_ & mask_low))
     }
 }
 
+impl<I2C: crate::I2cBus> crate::PortDriverTotemPole for Driver<I2C> {
+    fn set_directions(&mut self, mask_input: u32, _mask_output: u32) -> Result<(), Self::Error> {
+        // quasi-bidirectional pins have no direction: an input is a pin which is released HIGH,
+        // outputs keep their current state
+        if mask_input == 0 {
+            return Ok(());
+        }
+        self.out |= mask_input as u8;
+        self.i2c.write(self.addr, &[self.out])?;
+        Ok(())
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverInputState for Driver<I2C> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverOutputInversion for Driver<I2C> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverClaims for Driver<I2C> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverCost for Driver<I2C> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set { .. } => 1,
+            crate::Operation::Get { mask } => self.input_state.read_mask(mask).is_some() as u32,
+            // only inputs are written, see `set_directions()`
+            crate::Operation::SetDirections { mask_input, .. } => (mask_input != 0) as u32,
+        }
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverResync for Driver<I2C> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        // the output latch cannot be read back, restore it from the cache instead
+        self.i2c.write(self.addr, &[self.out])?;
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverSnapshot for Driver<I2C> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        Ok(crate::DriverState {
+            pins: 0xff,
+            outputs: Some(self.out as u32),
+            output_pins: None,
+            pull_ups: None,
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, 0xff, 0)?,
+        })
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverRestoreDefaults for Driver<I2C> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        // all pins HIGH is the power-on state, which makes them inputs
+        self.out = 0xff;
+        self.i2c.write(self.addr, &[self.out])?;
+        Ok(())
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverRefresh for Driver<I2C> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        if mask & 0xFF != 0 {
+            self.i2c.write(self.addr, &[self.out])?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use embedded_hal_mock::eh1::i2c as mock_i2c;
@@ -155,6 +520,30 @@ mod tests {
         bus.done();
     }
 
+    #[test]
+    fn pcf8574_directions() {
+        let expectations = [
+            // into_output_with(Low) only writes the level
+            mock_i2c::Transaction::write(0x21, vec![0b11111110]),
+            // into_input() releases the pin HIGH
+            mock_i2c::Transaction::write(0x21, vec![0b11111111]),
+            mock_i2c::Transaction::read(0x21, vec![0b11111110]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = super::Pcf8574::new(bus.clone(), true, false, false);
+        let pcf_pins = pcf.split();
+
+        let p0 = pcf_pins
+            .p0
+            .into_output_with(embedded_hal::digital::PinState::Low)
+            .unwrap();
+        let p0 = p0.into_input().unwrap();
+        assert!(p0.is_low().unwrap());
+
+        bus.done();
+    }
+
     #[test]
     fn pcf8574a() {
         let expectations = [
@@ -176,4 +565,102 @@ mod tests {
 
         bus.done();
     }
+
+    #[test]
+    fn pcf8574_addr() {
+        assert_eq!(super::Addr::from_pins(true, false, false).get(), 0x21);
+        assert_eq!(super::AddrA::from_pins(true, false, false).get(), 0x39);
+        assert!(super::Addr::custom(0x39).is_err());
+        assert!(super::AddrA::custom(0x27).is_err());
+    }
+
+    #[test]
+    fn pcf8574_new_with_state() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0b11110000]),
+            mock_i2c::Transaction::write(0x20, vec![0b11110001]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf =
+            super::Pcf8574::new_with_state(bus.clone(), false, false, false, 0xf0).unwrap();
+        let mut pcf_pins = pcf.split();
+
+        assert!(pcf_pins.p0.is_set_low().unwrap());
+        assert!(pcf_pins.p4.is_set_high().unwrap());
+        pcf_pins.p0.toggle().unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn pcf8574_read_changes() {
+        let expectations = [
+            mock_i2c::Transaction::read(0x20, vec![0b11111110]),
+            mock_i2c::Transaction::read(0x20, vec![0b11111110]),
+            mock_i2c::Transaction::read(0x20, vec![0b01111111]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = super::Pcf8574::new(bus.clone(), false, false, false);
+        let pcf_pins = pcf.split();
+
+        let read_changes = || pcf_pins.p0.access_port_driver(|drv| drv.read_changes());
+        assert_eq!(read_changes().unwrap(), (0b00000001, 0b11111110));
+        assert_eq!(read_changes().unwrap(), (0b00000000, 0b11111110));
+        assert_eq!(read_changes().unwrap(), (0b10000001, 0b01111111));
+
+        bus.done();
+    }
+
+    #[test]
+    fn pcf8574_output_inverted() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0b11110111]),
+            mock_i2c::Transaction::write(0x20, vec![0b11111111]),
+            mock_i2c::Transaction::write(0x20, vec![0b11111101]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = super::Pcf8574::new(bus.clone(), false, false, false);
+        let pcf_pins = pcf.split();
+
+        let mut p3 = pcf_pins.p3.into_output_inverted();
+        p3.set_high().unwrap();
+        assert!(p3.is_set_high().unwrap());
+        p3.toggle().unwrap();
+        assert!(p3.is_set_low().unwrap());
+
+        // other pins are not affected
+        let mut p1 = pcf_pins.p1;
+        p1.set_low().unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn pcf8574_set_verified() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0b11111111]),
+            mock_i2c::Transaction::read(0x20, vec![0b00001000]),
+            mock_i2c::Transaction::write(0x20, vec![0b11110111]),
+            mock_i2c::Transaction::read(0x20, vec![0b00000000]),
+            // p3 is held LOW externally
+            mock_i2c::Transaction::write(0x20, vec![0b11111111]),
+            mock_i2c::Transaction::read(0x20, vec![0b00000000]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = super::Pcf8574::new(bus.clone(), false, false, false);
+        let mut p3 = pcf.split().p3;
+
+        p3.set_high_verified().unwrap();
+        p3.set_low_verified().unwrap();
+        assert!(matches!(
+            p3.set_high_verified(),
+            Err(crate::VerifiedSetError::Mismatch)
+        ));
+
+        bus.done();
+    }
 }
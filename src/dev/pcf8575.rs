@@ -1,4 +1,11 @@
 //! Support for the `PCF8575` "Remote 16-bit I/O expander for I2C-bus with interrupt"
+//!
+//! The pins are quasi-bidirectional, the chip has no direction register.  So generic code can set
+//! up pins like on other port-expanders, [`into_input()`](crate::Pin::into_input) writes the pin
+//! HIGH, releasing it to the weak internal pull-up, and [`into_output()`](crate::Pin::into_output)
+//! and [`into_output_high()`](crate::Pin::into_output_high) only write the requested level.  On a
+//! LOW to HIGH transition, the chip briefly enables a strong pull-up, so a line which is held LOW
+//! externally sees a short current pulse whenever a pin becomes an input or is set HIGH.
 
 /// `PCF8575` "Remote 16-bit I/O expander for I2C-bus with interrupt"
 pub struct Pcf8575<M>(M);
@@ -10,6 +17,45 @@ where
     pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
         Self::with_mutex(i2c, a0, a1, a2)
     }
+
+    /// Create a new instance and immediately write `initial` to the port.
+    ///
+    /// The chip powers up with all pins HIGH.  Writing the initial state during construction keeps
+    /// the window short in which pins which should be LOW are still HIGH.  Bit `n` of `initial` is
+    /// the `n`-th pin of [`pins()`](Pcf8575::pins).
+    pub fn new_with_state(
+        i2c: I2C,
+        a0: bool,
+        a1: bool,
+        a2: bool,
+        initial: u16,
+    ) -> Result<Self, I2C::BusError> {
+        Self::with_mutex_state(i2c, a0, a1, a2, initial)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Pcf8575<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new()`](Pcf8575::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pcf8575<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new()`](Pcf8575::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
 }
 
 impl<I2C, M> Pcf8575<M>
@@ -21,6 +67,50 @@ where
         Self(crate::PortMutex::create(Driver::new(i2c, a0, a1, a2)))
     }
 
+    /// Like [`new_with_state()`](Pcf8575::new_with_state), with a custom mutex.
+    pub fn with_mutex_state(
+        i2c: I2C,
+        a0: bool,
+        a1: bool,
+        a2: bool,
+        initial: u16,
+    ) -> Result<Self, I2C::BusError> {
+        let mut driver = Driver::new(i2c, a0, a1, a2);
+        crate::PortDriver::set(&mut driver, initial as u32, !(initial as u32))?;
+        Ok(Self(crate::PortMutex::create(driver)))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x27`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
     pub fn split(&mut self) -> Parts<'_, I2C, M> {
         Parts {
             p00: crate::Pin::new(0, &self.0),
@@ -41,6 +131,23 @@ where
             p17: crate::Pin::new(15, &self.0),
         }
     }
+
+    pin_methods! {
+        mode: crate::mode::QuasiBidirectional,
+        pins: 16,
+        error: I2C::BusError,
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// The chip has no direction register: writing HIGH to all pins turns off the output drivers,
+    /// leaving every pin as an input with the weak pull-up, just like after power-on.  Consuming
+    /// the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
 }
 
 pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
@@ -66,55 +173,261 @@ where
     pub p17: crate::Pin<'a, crate::mode::QuasiBidirectional, M>,
 }
 
+crate::address::addr_type! {
+    /// I2C address of the `PCF8575`.
+    pub struct Addr: 0x20, [a0 = 0, a1 = 1, a2 = 2], 0x20..=0x27;
+}
+
 pub struct Driver<I2C> {
     i2c: I2C,
     out: [u8; 2],
+    dirty: bool,
     addr: u8,
+    address_check: crate::AddressCheck,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
+    last_input: u16,
 }
 
 impl<I2C> Driver<I2C> {
     pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        let addr = Addr::from_pins(a0, a1, a2).get();
+        Self::with_address(i2c, addr, crate::AddressCheck::Checked)
+    }
+
+    pub(crate) fn with_address(i2c: I2C, addr: u8, address_check: crate::AddressCheck) -> Self {
         Self {
             i2c,
             out: [0xff; 2],
-            addr: 0x20 | ((a2 as u8) << 2) | ((a1 as u8) << 1) | (a0 as u8),
+            dirty: false,
+            addr,
+            address_check,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
+            last_input: 0xffff,
         }
     }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
+}
+
+impl<I2C: crate::I2cBus> Driver<I2C> {
+    /// Read all pins and return `(changed_mask, value)`, like `pcf8574::Driver::read_changes()`.
+    pub fn read_changes(&mut self) -> Result<(u32, u32), I2C::BusError> {
+        let mut buf = [0x00; 2];
+        self.i2c.read(self.addr, &mut buf)?;
+        let value = u16::from_le_bytes(buf);
+        let changed = value ^ self.last_input;
+        self.last_input = value;
+        Ok((changed as u32, value as u32))
+    }
 }
 
 impl<I2C: crate::I2cBus> crate::PortDriver for Driver<I2C> {
     type Error = I2C::BusError;
 
     fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         let mut out = u16::from_le_bytes(self.out);
         out |= mask_high as u16;
         out &= !mask_low as u16;
 
         self.out = out.to_le_bytes();
 
+        // stays set if the write fails
+        self.dirty = true;
         self.i2c.write(self.addr, &self.out)?;
+        self.dirty = false;
         Ok(())
     }
 
     fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         let out = u16::from_le_bytes(self.out);
 
         Ok(((out as u32) & mask_high) | (!(out as u32) & mask_low))
     }
 
     fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
-        let mut buf = [0x00; 2];
-        self.i2c.read(self.addr, &mut buf)?;
-        let in_ = u16::from_le_bytes(buf) as u32;
+        let in_ = self.input_state.read(mask_high | mask_low, |_| {
+            let mut buf = [0x00; 2];
+            self.i2c.read(self.addr, &mut buf)?;
+            Ok::<_, Self::Error>(u16::from_le_bytes(buf) as u32)
+        })?;
 
         Ok((in_ & mask_high) | (!in_ & mask_low))
     }
 }
 
+impl<I2C: crate::I2cBus> crate::PortDriverTotemPole for Driver<I2C> {
+    fn set_directions(&mut self, mask_input: u32, _mask_output: u32) -> Result<(), Self::Error> {
+        // quasi-bidirectional pins have no direction: an input is a pin which is released HIGH,
+        // outputs keep their current state
+        if mask_input == 0 {
+            return Ok(());
+        }
+        let out = u16::from_le_bytes(self.out) | mask_input as u16;
+        self.out = out.to_le_bytes();
+        self.i2c.write(self.addr, &self.out)?;
+        Ok(())
+    }
+}
+
+/// The `PCF8575` on an async I2C bus, see [`AsyncPort`](crate::AsyncPort).
+#[cfg(feature = "async")]
+impl<I2C: embedded_hal_async::i2c::I2c> crate::AsyncPortDriver for Driver<I2C> {
+    type Error = I2C::Error;
+
+    const PINS: u32 = 0xffff;
+
+    async fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let mut out = u16::from_le_bytes(self.out);
+        out |= mask_high as u16;
+        out &= !mask_low as u16;
+        self.out = out.to_le_bytes();
+
+        self.dirty = true;
+        self.i2c.write(self.addr, &self.out).await?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    async fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let out = u16::from_le_bytes(self.out);
+        Ok(((out as u32) & mask_high) | (!(out as u32) & mask_low))
+    }
+
+    async fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        // the input state can't await the bus, so read what it needs up front
+        let mut buf = [0x00; 2];
+        if self.input_state.read_mask(mask_high | mask_low).is_some() {
+            self.i2c.read(self.addr, &mut buf).await?;
+        }
+        let in_ = self.input_state.read(mask_high | mask_low, |_| {
+            Ok::<_, Self::Error>(u16::from_le_bytes(buf) as u32)
+        })?;
+        Ok((in_ & mask_high) | (!in_ & mask_low))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<I2C: embedded_hal_async::i2c::I2c> crate::AsyncPortDriverTotemPole for Driver<I2C> {
+    async fn set_directions(
+        &mut self,
+        mask_input: u32,
+        _mask_output: u32,
+    ) -> Result<(), Self::Error> {
+        // see the blocking `set_directions()`
+        if mask_input == 0 {
+            return Ok(());
+        }
+        let out = u16::from_le_bytes(self.out) | mask_input as u16;
+        self.out = out.to_le_bytes();
+        self.i2c.write(self.addr, &self.out).await
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverInputState for Driver<I2C> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverOutputInversion for Driver<I2C> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverClaims for Driver<I2C> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverCost for Driver<I2C> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set { .. } => 1,
+            crate::Operation::Get { mask } => self.input_state.read_mask(mask).is_some() as u32,
+            // only inputs are written, see `set_directions()`
+            crate::Operation::SetDirections { mask_input, .. } => (mask_input != 0) as u32,
+        }
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverResync for Driver<I2C> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        // the output latch cannot be read back, restore it from the cache instead
+        self.i2c.write(self.addr, &self.out)?;
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverSnapshot for Driver<I2C> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        Ok(crate::DriverState {
+            pins: 0xffff,
+            outputs: Some(u16::from_le_bytes(self.out) as u32),
+            output_pins: None,
+            pull_ups: None,
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, 0xffff, 0)?,
+        })
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverRestoreDefaults for Driver<I2C> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        // all pins HIGH is the power-on state, which makes them inputs
+        self.out = [0xff, 0xff];
+        self.i2c.write(self.addr, &self.out)?;
+        Ok(())
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverRefresh for Driver<I2C> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        if mask & 0xFFFF != 0 {
+            self.i2c.write(self.addr, &self.out)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use embedded_hal_mock::eh1::i2c as mock_i2c;
 
+    #[test]
+    fn pcf8575_new_with_state() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0b11111110, 0b01111111]),
+            mock_i2c::Transaction::write(0x20, vec![0b11111111, 0b01111111]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf =
+            super::Pcf8575::new_with_state(bus.clone(), false, false, false, 0x7ffe).unwrap();
+        let mut pcf_pins = pcf.split();
+
+        assert!(pcf_pins.p17.is_set_low().unwrap());
+        pcf_pins.p00.set_high().unwrap();
+
+        bus.done();
+    }
+
     #[test]
     fn pcf8575() {
         let expectations = [
@@ -1,12 +1,11 @@
 //! Support for the `PI4IOE5V6408` "Low-voltage Translating 8-bit I2C-bus I/O Expander"
-use crate::I2cExt;
 
 /// `PI4IOE5V6408` "Low-voltage Translating 8-bit I2C-bus I/O Expander"
 pub struct Pi4ioe5v6408<M>(M);
 
 impl<I2C> Pi4ioe5v6408<core::cell::RefCell<Driver<I2C>>>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
 {
     /// Create a new driver for the `PI4IOE5V6408` "Low-voltage Translating 8-bit I2C-bus I/O Expander"
     /// All pins will be configured as floating inputs
@@ -19,9 +18,33 @@ where
     }
 }
 
+#[cfg(feature = "critical-section")]
+impl<I2C> Pi4ioe5v6408<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pi4ioe5v6408::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, addr: bool) -> Result<Self, I2C::BusError> {
+        Self::with_mutex(i2c, addr)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Pi4ioe5v6408<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Pi4ioe5v6408::new), but the device is wrapped in a `std::sync::Mutex`
+    /// so it can be shared between threads.
+    pub fn new_std(i2c: I2C, addr: bool) -> Result<Self, I2C::BusError> {
+        Self::with_mutex(i2c, addr)
+    }
+}
+
 impl<I2C, M> Pi4ioe5v6408<M>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
     M: crate::PortMutex<Port = Driver<I2C>>,
 {
     /// Create a new driver for the `PI4IOE5V6408` "Low-voltage Translating 8-bit I2C-bus I/O Expander"
@@ -51,6 +74,36 @@ where
         )?)))
     }
 
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Result<Self, I2C::BusError> {
+        let driver = Driver::with_address(i2c, addr.get(), crate::AddressCheck::Checked, false)?;
+        Ok(Self(crate::PortMutex::create(driver)))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x43` or `0x44`)
+    /// or if setting up the chip fails.
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::AddressError<I2C::BusError>> {
+        let addr = Addr::custom(addr)?;
+        Self::from_addr(i2c, addr).map_err(crate::AddressError::Bus)
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Result<Self, I2C::BusError> {
+        Ok(Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+            false,
+        )?)))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
     pub fn split<'a>(&'a mut self) -> Parts<'a, I2C, M> {
         Parts {
             io0: crate::Pin::new(0, &self.0),
@@ -63,11 +116,37 @@ where
             io7: crate::Pin::new(7, &self.0),
         }
     }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 8,
+        error: I2C::BusError,
+    }
+
+    /// Write the output register on every output update, even if it does not change.
+    ///
+    /// By default, the bus transfer is skipped when e.g. `set_high()` is called on a pin which is
+    /// already HIGH.  With `always`, every update is written, for external watchdogs or
+    /// multi-master setups which rely on the write.  See also [`refresh()`](crate::refresh).
+    pub fn set_always_write(&self, always: bool) {
+        crate::PortMutex::lock(&self.0, |drv| drv.set_always_write(always))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs with the outputs switched to high-impedance and the pull-downs
+    /// enabled, and the output, default state and interrupt mask registers are cleared.  Consuming
+    /// the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
 }
 
 pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
     M: crate::PortMutex<Port = Driver<I2C>>,
 {
     pub io0: crate::Pin<'a, crate::mode::Input, M>,
@@ -95,22 +174,45 @@ enum Regs {
     InterruptStatusRegister = 0x13,
 }
 
+/// "(Power on) Reset Interrupt" bit of the DeviceIdControl register, cleared when read
+const RESET_INTERRUPT: u8 = 0x02;
+
 impl From<Regs> for u8 {
     fn from(r: Regs) -> u8 {
         r as u8
     }
 }
 
+crate::address::addr_type! {
+    /// I2C address of the `PI4IOE5V6408`.
+    pub struct Addr: 0x43, [addr = 0], 0x43..=0x44;
+}
+
 pub struct Driver<I2C> {
     i2c: I2C,
     addr: u8,
+    address_check: crate::AddressCheck,
     out: u8,
+    dirty: bool,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
+    reset_pending: bool,
+    always_write: bool,
 }
 
-impl<I2C: crate::I2cBus> Driver<I2C> {
-    pub fn new(mut i2c: I2C, addr: bool, retain_config: bool) -> Result<Self, I2C::BusError> {
-        let addr = if addr { 0x44 } else { 0x43 };
+impl<I2C: crate::RegisterBus> Driver<I2C> {
+    pub fn new(i2c: I2C, addr: bool, retain_config: bool) -> Result<Self, I2C::BusError> {
+        let addr = Addr::from_pins(addr).get();
+        Self::with_address(i2c, addr, crate::AddressCheck::Checked, retain_config)
+    }
 
+    pub(crate) fn with_address(
+        mut i2c: I2C,
+        addr: u8,
+        address_check: crate::AddressCheck,
+        retain_config: bool,
+    ) -> Result<Self, I2C::BusError> {
         let device_id = i2c.read_reg(addr, Regs::DeviceIdControl)?; // Reset the "(Power on) Reset Interrupt" bit (and validate the device ID)
         assert_eq!(
             device_id & 0xFC, // Only check Manufacturer ID (0b101) and Firmware Revision (0b000)
@@ -140,19 +242,44 @@ impl<I2C: crate::I2cBus> Driver<I2C> {
             i2c.write_reg(addr, Regs::PullUpPullDownEnable, 0)?; // Disable pull-up/pull-down on all inputs
         }
 
-        Ok(Self { i2c, addr, out })
+        Ok(Self {
+            i2c,
+            addr,
+            address_check,
+            out,
+            dirty: false,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
+            reset_pending: device_id & RESET_INTERRUPT != 0,
+            always_write: false,
+        })
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
+
+    pub(crate) fn set_always_write(&mut self, always: bool) {
+        self.always_write = always;
     }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriver for Driver<I2C> {
+impl<I2C: crate::RegisterBus> crate::PortDriver for Driver<I2C> {
     type Error = I2C::BusError;
 
     fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         let previous = self.out;
         self.out |= mask_high as u8;
         self.out &= !mask_low as u8;
-        if self.out != previous {
-            self.i2c.write_reg(self.addr, Regs::OutputPort, self.out)
+        // after a failed write, the device may not match the cache even if nothing changed
+        if self.out != previous || self.always_write || self.dirty {
+            self.dirty = true;
+            self.i2c.write_reg(self.addr, Regs::OutputPort, self.out)?;
+            self.dirty = false;
+            Ok(())
         } else {
             // don't do the transfer when nothing changed
             Ok(())
@@ -160,42 +287,150 @@ impl<I2C: crate::I2cBus> crate::PortDriver for Driver<I2C> {
     }
 
     fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         Ok(((self.out as u32) & mask_high) | (!(self.out as u32) & mask_low))
     }
 
     fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
-        let in_ = self.i2c.read_reg(self.addr, Regs::InputStatusRegister)? as u32;
+        let in_ = self.input_state.read(mask_high | mask_low, |_| {
+            self.i2c
+                .read_reg(self.addr, Regs::InputStatusRegister)
+                .map(|in_| in_ as u32)
+        })?;
         Ok((in_ & mask_high) | (!in_ & mask_low))
     }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriverTotemPole for Driver<I2C> {
-    fn set_direction(
-        &mut self,
-        mask: u32,
-        dir: crate::Direction,
-        state: bool,
-    ) -> Result<(), Self::Error> {
-        // set state before switching direction to prevent glitch
-        if dir == crate::Direction::Output {
-            use crate::PortDriver;
-            if state {
-                self.set(mask, 0)?;
-            } else {
-                self.set(0, mask)?;
+impl<I2C: crate::RegisterBus> crate::PortDriverInputState for Driver<I2C> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverOutputInversion for Driver<I2C> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverClaims for Driver<I2C> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverCost for Driver<I2C> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set {
+                mask_high,
+                mask_low,
+            } => {
+                let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+                let out = (self.out | mask_high as u8) & !mask_low as u8;
+                (out != self.out || self.always_write) as u32
             }
+            crate::Operation::Get { mask } => self.input_state.read_mask(mask).is_some() as u32,
+            crate::Operation::SetDirections { .. } => 2,
+        }
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverResync for Driver<I2C> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        self.out = self.i2c.read_reg(self.addr, Regs::OutputPort)?;
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverSnapshot for Driver<I2C> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        let direction = self.i2c.read_reg(self.addr, Regs::IODirection)?;
+        let pull_enable = self.i2c.read_reg(self.addr, Regs::PullUpPullDownEnable)?;
+        let pull_selection = self
+            .i2c
+            .read_reg(self.addr, Regs::PullUpPullDownSelection)?;
+        Ok(crate::DriverState {
+            pins: 0xff,
+            outputs: Some(self.out as u32),
+            output_pins: Some(direction as u32),
+            pull_ups: Some((pull_enable & pull_selection) as u32),
+            pull_downs: Some((pull_enable & !pull_selection) as u32),
+            inputs: crate::PortDriver::get(self, 0xff, 0)?,
+        })
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRestoreDefaults for Driver<I2C> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        self.i2c.write_reg(self.addr, Regs::IODirection, 0x00)?;
+        self.i2c.write_reg(self.addr, Regs::OutputPort, 0x00)?;
+        self.i2c
+            .write_reg(self.addr, Regs::OutputHighImpedance, 0xff)?;
+        self.i2c
+            .write_reg(self.addr, Regs::InputDefaultState, 0x00)?;
+        self.i2c
+            .write_reg(self.addr, Regs::PullUpPullDownEnable, 0xff)?;
+        self.i2c
+            .write_reg(self.addr, Regs::PullUpPullDownSelection, 0x00)?;
+        self.i2c
+            .write_reg(self.addr, Regs::InterruptMaskRegister, 0x00)?;
+        self.out = 0x00;
+        Ok(())
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRefresh for Driver<I2C> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        if mask & 0xFF != 0 {
+            self.i2c.write_reg(self.addr, Regs::OutputPort, self.out)?;
         }
+        Ok(())
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverStatus for Driver<I2C> {
+    fn status(&mut self) -> Result<crate::Status, Self::Error> {
+        let device_id = self.i2c.read_reg(self.addr, Regs::DeviceIdControl)?;
+        let reset = core::mem::take(&mut self.reset_pending) || device_id & RESET_INTERRUPT != 0;
+        let interrupt_source = self
+            .i2c
+            .read_reg(self.addr, Regs::InterruptStatusRegister)?;
+        Ok(crate::Status {
+            reset: Some(reset),
+            interrupt_source: Some(interrupt_source as u32),
+        })
+    }
+}
 
-        let (mask_set, mask_clear) = match dir {
-            crate::Direction::Output => (mask as u8, 0), // Outputs are set to 1
-            crate::Direction::Input => (0, mask as u8),  // Inputs are set to 0
+impl<I2C: crate::RegisterBus> crate::PortDriverTotemPole for Driver<I2C> {
+    fn set_directions(&mut self, mask_input: u32, mask_output: u32) -> Result<(), Self::Error> {
+        // Outputs are set to 1, inputs are set to 0
+        self.i2c.update_reg(
+            self.addr,
+            Regs::IODirection,
+            mask_output as u8,
+            mask_input as u8,
+        )
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverHighImpedance for Driver<I2C> {
+    fn set_high_impedance(&mut self, mask: u32, enable: bool) -> Result<(), Self::Error> {
+        let (mask_set, mask_clear) = match enable {
+            false => (0, mask as u8),
+            true => (mask as u8, 0),
         };
         self.i2c
-            .update_reg(self.addr, Regs::IODirection, mask_set, mask_clear)
+            .update_reg(self.addr, Regs::OutputHighImpedance, mask_set, mask_clear)
     }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriverPullDown for Driver<I2C> {
+impl<I2C: crate::RegisterBus> crate::PortDriverPullDown for Driver<I2C> {
     fn set_pull_down(&mut self, mask: u32, enable: bool) -> Result<(), Self::Error> {
         if enable {
             self.i2c
@@ -210,7 +445,7 @@ impl<I2C: crate::I2cBus> crate::PortDriverPullDown for Driver<I2C> {
     }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriverPullUp for Driver<I2C> {
+impl<I2C: crate::RegisterBus> crate::PortDriverPullUp for Driver<I2C> {
     fn set_pull_up(&mut self, mask: u32, enable: bool) -> Result<(), Self::Error> {
         if enable {
             self.i2c
@@ -223,11 +458,20 @@ impl<I2C: crate::I2cBus> crate::PortDriverPullUp for Driver<I2C> {
         }
         Ok(())
     }
+
+    fn set_input_pull(
+        &mut self,
+        mask: u32,
+        pull: crate::Pull,
+    ) -> Result<(), crate::PullError<Self::Error>> {
+        crate::common::set_input_pull_up_down(self, mask, pull)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use core::cell::RefCell;
+    use embedded_hal::i2c::ErrorKind;
     use embedded_hal_mock::eh1::i2c as mock_i2c;
 
     #[test]
@@ -297,6 +541,35 @@ mod tests {
         bus.done();
     }
 
+    #[test]
+    fn pi4ioe5v6408_input_with_pull() {
+        let expectations = [
+            // driver setup
+            mock_i2c::Transaction::write_read(0x43, vec![0x01], vec![0xa2]),
+            mock_i2c::Transaction::write(0x43, vec![0x07, 0b00000000]),
+            mock_i2c::Transaction::write(0x43, vec![0x11, 0b11111111]),
+            mock_i2c::Transaction::write(0x43, vec![0x0b, 0b00000000]),
+            // io2 pull-down, then input
+            mock_i2c::Transaction::write_read(0x43, vec![0x0d], vec![0b11111111]),
+            mock_i2c::Transaction::write(0x43, vec![0x0d, 0b11111011]),
+            mock_i2c::Transaction::write_read(0x43, vec![0x0b], vec![0b00000000]),
+            mock_i2c::Transaction::write(0x43, vec![0x0b, 0b00000100]),
+            mock_i2c::Transaction::write_read(0x43, vec![0x03], vec![0b00000100]),
+            mock_i2c::Transaction::write(0x43, vec![0x03, 0b00000000]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = super::Pi4ioe5v6408::new(bus.clone(), false).unwrap();
+        let pca_pins = pca.split();
+
+        let _ = pca_pins
+            .io2
+            .into_input_with_pull(crate::Pull::Down)
+            .unwrap();
+
+        bus.done();
+    }
+
     #[test]
     fn pi4ioe5v6408_retained() {
         let expectations = [
@@ -330,4 +603,99 @@ mod tests {
 
         bus.done();
     }
+
+    #[test]
+    fn pi4ioe5v6408_status() {
+        let expectations = [
+            // driver setup, with the reset flag set
+            mock_i2c::Transaction::write_read(0x44, vec![0x01], vec![0xa2]),
+            mock_i2c::Transaction::write_read(0x44, vec![0x05], vec![0b00000000]),
+            // status: reset from before the initialization
+            mock_i2c::Transaction::write_read(0x44, vec![0x01], vec![0xa0]),
+            mock_i2c::Transaction::write_read(0x44, vec![0x13], vec![0b00000100]),
+            // status: no reset since
+            mock_i2c::Transaction::write_read(0x44, vec![0x01], vec![0xa0]),
+            mock_i2c::Transaction::write_read(0x44, vec![0x13], vec![0b00000000]),
+            // status: unexpected reset
+            mock_i2c::Transaction::write_read(0x44, vec![0x01], vec![0xa2]),
+            mock_i2c::Transaction::write_read(0x44, vec![0x13], vec![0b00000000]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca: super::Pi4ioe5v6408<RefCell<_>> =
+            super::Pi4ioe5v6408::with_retained_pin_config(bus.clone(), true).unwrap();
+        let pca_pins = pca.split();
+
+        let status = crate::status(&pca_pins.io0).unwrap();
+        assert_eq!(status.reset, Some(true));
+        assert_eq!(status.interrupt_source, Some(0b00000100));
+
+        let status = crate::status(&pca_pins.io0).unwrap();
+        assert_eq!(status.reset, Some(false));
+        assert_eq!(status.interrupt_source, Some(0b00000000));
+
+        let status = crate::status(&pca_pins.io0).unwrap();
+        assert_eq!(status.reset, Some(true));
+
+        bus.done();
+    }
+
+    #[test]
+    fn pi4ioe5v6408_high_impedance() {
+        let expectations = [
+            // driver setup
+            mock_i2c::Transaction::write_read(0x44, vec![0x01], vec![0xa0]),
+            mock_i2c::Transaction::write_read(0x44, vec![0x05], vec![0b00000000]),
+            // pin setup io3
+            mock_i2c::Transaction::write_read(0x44, vec![0x03], vec![0b00000000]),
+            mock_i2c::Transaction::write(0x44, vec![0x03, 0b00001000]),
+            // tri-state io3 and release it again
+            mock_i2c::Transaction::write_read(0x44, vec![0x07], vec![0b00000000]),
+            mock_i2c::Transaction::write(0x44, vec![0x07, 0b00001000]),
+            mock_i2c::Transaction::write_read(0x44, vec![0x07], vec![0b00001000]),
+            mock_i2c::Transaction::write(0x44, vec![0x07, 0b00000000]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca: super::Pi4ioe5v6408<RefCell<_>> =
+            super::Pi4ioe5v6408::with_retained_pin_config(bus.clone(), true).unwrap();
+        let pca_pins = pca.split();
+
+        let io3 = pca_pins.io3.into_output().unwrap();
+        let mut io3 = io3.into_high_impedance().unwrap();
+        io3.set_high_impedance(false).unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn pi4ioe5v6408_with_address() {
+        let expectations = [
+            // driver setup at 0x44
+            mock_i2c::Transaction::write_read(0x44, vec![0x01], vec![0xa2]),
+            mock_i2c::Transaction::write(0x44, vec![0x07, 0b00000000]),
+            mock_i2c::Transaction::write(0x44, vec![0x11, 0b11111111]),
+            mock_i2c::Transaction::write(0x44, vec![0x0b, 0b00000000]),
+            // the bus fails during the setup at 0x43
+            mock_i2c::Transaction::write_read(0x43, vec![0x01], vec![0xa2])
+                .with_error(ErrorKind::Other),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let err = super::Pi4ioe5v6408::<RefCell<_>>::with_address(bus.clone(), 0x45)
+            .err()
+            .unwrap();
+        assert!(matches!(err, crate::AddressError::InvalidAddress(e) if e.address() == 0x45));
+
+        let pi: super::Pi4ioe5v6408<RefCell<_>> =
+            super::Pi4ioe5v6408::with_address(bus.clone(), 0x44).unwrap();
+        assert_eq!(pi.address_check(), crate::AddressCheck::Checked);
+
+        let err = super::Pi4ioe5v6408::<RefCell<_>>::with_address(bus.clone(), 0x43)
+            .err()
+            .unwrap();
+        assert_eq!(err, crate::AddressError::Bus(ErrorKind::Other));
+
+        bus.done();
+    }
 }
@@ -0,0 +1,226 @@
+//! Support for the `74HC165` "8-bit parallel-load shift register"
+//!
+//! The `74HC165` is not a port-expander, but a plain shift register which is read over SPI.  It
+//! only has inputs, so its pins are in the [`InputOnly`](crate::mode::InputOnly) mode which
+//! cannot be converted into any other mode.  Each read clocks out the state of all eight inputs
+//! in a single SPI transaction, `H` first, so it needs SPI mode 0 with the most significant bit
+//! first.
+//!
+//! The chip-select of the [`SpiDevice`](embedded_hal::spi::SpiDevice) has to be connected to
+//! `SH/LD` through an inverter: while the chip is not selected, `SH/LD` is LOW and the register
+//! follows the inputs, during the transaction it is HIGH and the latched states are shifted out.
+//! `CLK INH` is tied LOW.  Chaining multiple registers is not supported, each one needs its own
+//! chip-select.
+//!
+//! Should the driver still be asked to drive or query outputs, it fails with
+//! [`Error::UnsupportedOperation`] instead of panicking.
+
+/// `74HC165` "8-bit parallel-load shift register"
+pub struct Sn74hc165<M>(M);
+
+impl<SPI> Sn74hc165<core::cell::RefCell<Driver<SPI>>>
+where
+    SPI: crate::SpiBus,
+{
+    pub fn new(spi: SPI) -> Self {
+        Self::with_mutex(spi)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<SPI> Sn74hc165<critical_section::Mutex<core::cell::RefCell<Driver<SPI>>>>
+where
+    SPI: crate::SpiBus,
+{
+    /// Same as [`new()`](Sn74hc165::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(spi: SPI) -> Self {
+        Self::with_mutex(spi)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<SPI> Sn74hc165<std::sync::Mutex<Driver<SPI>>>
+where
+    SPI: crate::SpiBus,
+{
+    /// Same as [`new()`](Sn74hc165::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(spi: SPI) -> Self {
+        Self::with_mutex(spi)
+    }
+}
+
+impl<SPI, M> Sn74hc165<M>
+where
+    SPI: crate::SpiBus,
+    M: crate::PortMutex<Port = Driver<SPI>>,
+{
+    pub fn with_mutex(spi: SPI) -> Self {
+        Self(crate::PortMutex::create(Driver::new(spi)))
+    }
+
+    pub fn split(&mut self) -> Parts<'_, SPI, M> {
+        Parts {
+            a: crate::Pin::new(0, &self.0),
+            b: crate::Pin::new(1, &self.0),
+            c: crate::Pin::new(2, &self.0),
+            d: crate::Pin::new(3, &self.0),
+            e: crate::Pin::new(4, &self.0),
+            f: crate::Pin::new(5, &self.0),
+            g: crate::Pin::new(6, &self.0),
+            h: crate::Pin::new(7, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::InputOnly,
+        pins: 8,
+        error: Error<SPI::BusError>,
+        methods: [pins, pin, take_pin, split_ref, into_arc_pins, write_mask, read_mask],
+    }
+}
+
+pub struct Parts<'a, SPI, M = core::cell::RefCell<Driver<SPI>>>
+where
+    SPI: crate::SpiBus,
+    M: crate::PortMutex<Port = Driver<SPI>>,
+{
+    pub a: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub b: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub c: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub d: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub e: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub f: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub g: crate::Pin<'a, crate::mode::InputOnly, M>,
+    pub h: crate::Pin<'a, crate::mode::InputOnly, M>,
+}
+
+/// Errors of the `74HC165` driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// The SPI bus reported an error.
+    Bus(E),
+    /// The operation needs outputs, which the `74HC165` does not have.
+    UnsupportedOperation,
+}
+
+pub struct Driver<SPI> {
+    spi: SPI,
+    input_state: crate::InputState,
+    claims: crate::PinClaims,
+}
+
+impl<SPI> Driver<SPI> {
+    pub fn new(spi: SPI) -> Self {
+        Self {
+            spi,
+            input_state: crate::InputState::default(),
+            claims: crate::PinClaims::default(),
+        }
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriver for Driver<SPI> {
+    type Error = Error<SPI::BusError>;
+
+    fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        if mask_high | mask_low != 0 {
+            return Err(Error::UnsupportedOperation);
+        }
+        Ok(())
+    }
+
+    fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        if mask_high | mask_low != 0 {
+            return Err(Error::UnsupportedOperation);
+        }
+        Ok(0)
+    }
+
+    fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let in_ = self.input_state.read(mask_high | mask_low, |_| {
+            let mut buf = [0x00];
+            self.spi.read(&mut buf).map_err(|e| Error::Bus(e.into()))?;
+            Ok::<_, Self::Error>(buf[0] as u32)
+        })?;
+        Ok((in_ & mask_high) | (!in_ & mask_low))
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriverSnapshot for Driver<SPI> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        Ok(crate::DriverState {
+            pins: 0xff,
+            outputs: None,
+            output_pins: Some(0x00),
+            pull_ups: None,
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, 0xff, 0)?,
+        })
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriverInputState for Driver<SPI> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriverClaims for Driver<SPI> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriverCost for Driver<SPI> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Get { mask } => self.input_state.read_mask(mask).is_some() as u32,
+            crate::Operation::Set { .. } | crate::Operation::SetDirections { .. } => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::spi as mock_spi;
+
+    #[test]
+    fn sn74hc165() {
+        let expectations = [
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::read(0b10000001),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::read(0b10000001),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::read(0b00000100),
+            mock_spi::Transaction::transaction_end(),
+        ];
+        let mut bus = mock_spi::Mock::new(&expectations);
+
+        let mut sr = super::Sn74hc165::new(bus.clone());
+        let sr_pins = sr.split();
+
+        assert!(sr_pins.a.is_high().unwrap());
+        assert!(sr_pins.h.is_high().unwrap());
+        assert_eq!(sr.read_mask(0xff).unwrap(), 0b00000100);
+
+        bus.done();
+    }
+
+    #[test]
+    fn sn74hc165_unsupported_operation() {
+        use crate::PortDriver;
+
+        let mut bus = mock_spi::Mock::new(&[]);
+
+        let mut drv = super::Driver::new(bus.clone());
+        assert_eq!(drv.set(0x01, 0x00), Err(super::Error::UnsupportedOperation));
+
+        bus.done();
+    }
+}
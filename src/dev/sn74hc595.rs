@@ -0,0 +1,251 @@
+//! Support for the `74HC595` "8-bit shift register with output latches"
+//!
+//! The `74HC595` is not a port-expander, but a plain shift register which is written over SPI.
+//! It only has outputs, so its pins are in the [`OutputOnly`](crate::mode::OutputOnly) mode
+//! which cannot be converted into any other mode.  Each write shifts out the state of all eight
+//! outputs in a single SPI transaction, `QH` first, so it needs SPI mode 0 with the most
+//! significant bit first.
+//!
+//! The chip-select of the [`SpiDevice`](embedded_hal::spi::SpiDevice) has to be connected to
+//! `RCLK`: the outputs change together when the chip-select is released at the end of the
+//! transaction.  Chaining multiple registers is not supported, each one needs its own
+//! chip-select.
+//!
+//! The outputs cannot be read back, the driver keeps them in a cache instead.  The state of the
+//! register after power-on is undefined, so the driver assumes all outputs LOW until the first
+//! write.  Asking the driver to read inputs fails with [`Error::UnsupportedOperation`] instead
+//! of panicking.
+
+/// `74HC595` "8-bit shift register with output latches"
+pub struct Sn74hc595<M>(M);
+
+impl<SPI> Sn74hc595<core::cell::RefCell<Driver<SPI>>>
+where
+    SPI: crate::SpiBus,
+{
+    pub fn new(spi: SPI) -> Self {
+        Self::with_mutex(spi)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<SPI> Sn74hc595<critical_section::Mutex<core::cell::RefCell<Driver<SPI>>>>
+where
+    SPI: crate::SpiBus,
+{
+    /// Same as [`new()`](Sn74hc595::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(spi: SPI) -> Self {
+        Self::with_mutex(spi)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<SPI> Sn74hc595<std::sync::Mutex<Driver<SPI>>>
+where
+    SPI: crate::SpiBus,
+{
+    /// Same as [`new()`](Sn74hc595::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(spi: SPI) -> Self {
+        Self::with_mutex(spi)
+    }
+}
+
+impl<SPI, M> Sn74hc595<M>
+where
+    SPI: crate::SpiBus,
+    M: crate::PortMutex<Port = Driver<SPI>>,
+{
+    pub fn with_mutex(spi: SPI) -> Self {
+        Self(crate::PortMutex::create(Driver::new(spi)))
+    }
+
+    pub fn split(&mut self) -> Parts<'_, SPI, M> {
+        Parts {
+            qa: crate::Pin::new(0, &self.0),
+            qb: crate::Pin::new(1, &self.0),
+            qc: crate::Pin::new(2, &self.0),
+            qd: crate::Pin::new(3, &self.0),
+            qe: crate::Pin::new(4, &self.0),
+            qf: crate::Pin::new(5, &self.0),
+            qg: crate::Pin::new(6, &self.0),
+            qh: crate::Pin::new(7, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::OutputOnly,
+        pins: 8,
+        error: Error<SPI::BusError>,
+        methods: [pins, pin, take_pin, into_arc_pins, write_mask],
+    }
+}
+
+pub struct Parts<'a, SPI, M = core::cell::RefCell<Driver<SPI>>>
+where
+    SPI: crate::SpiBus,
+    M: crate::PortMutex<Port = Driver<SPI>>,
+{
+    pub qa: crate::Pin<'a, crate::mode::OutputOnly, M>,
+    pub qb: crate::Pin<'a, crate::mode::OutputOnly, M>,
+    pub qc: crate::Pin<'a, crate::mode::OutputOnly, M>,
+    pub qd: crate::Pin<'a, crate::mode::OutputOnly, M>,
+    pub qe: crate::Pin<'a, crate::mode::OutputOnly, M>,
+    pub qf: crate::Pin<'a, crate::mode::OutputOnly, M>,
+    pub qg: crate::Pin<'a, crate::mode::OutputOnly, M>,
+    pub qh: crate::Pin<'a, crate::mode::OutputOnly, M>,
+}
+
+/// Errors of the `74HC595` driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// The SPI bus reported an error.
+    Bus(E),
+    /// The operation needs inputs, which the `74HC595` does not have.
+    UnsupportedOperation,
+}
+
+pub struct Driver<SPI> {
+    spi: SPI,
+    out: u8,
+    dirty: bool,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
+}
+
+impl<SPI> Driver<SPI> {
+    pub fn new(spi: SPI) -> Self {
+        Self {
+            spi,
+            out: 0x00,
+            dirty: false,
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
+        }
+    }
+}
+
+impl<SPI: crate::SpiBus> Driver<SPI> {
+    fn write_out(&mut self) -> Result<(), Error<SPI::BusError>> {
+        self.spi
+            .write(&[self.out])
+            .map_err(|e| Error::Bus(e.into()))
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriver for Driver<SPI> {
+    type Error = Error<SPI::BusError>;
+
+    fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        self.out |= mask_high as u8;
+        self.out &= !mask_low as u8;
+        // stays set if the write fails
+        self.dirty = true;
+        self.write_out()?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        Ok(((self.out as u32) & mask_high) | (!(self.out as u32) & mask_low))
+    }
+
+    fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        if mask_high | mask_low != 0 {
+            return Err(Error::UnsupportedOperation);
+        }
+        Ok(0)
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriverOutputInversion for Driver<SPI> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriverClaims for Driver<SPI> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriverCost for Driver<SPI> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set { .. } => 1,
+            crate::Operation::Get { .. } | crate::Operation::SetDirections { .. } => 0,
+        }
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriverResync for Driver<SPI> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        // the outputs cannot be read back, restore them from the cache instead
+        self.write_out()
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<SPI: crate::SpiBus> crate::PortDriverRefresh for Driver<SPI> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        if mask & 0xFF != 0 {
+            self.write_out()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::spi as mock_spi;
+
+    #[test]
+    fn sn74hc595() {
+        let expectations = [
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0b00000001]),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0b10000001]),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0b10000000]),
+            mock_spi::Transaction::transaction_end(),
+            mock_spi::Transaction::transaction_start(),
+            mock_spi::Transaction::write_vec(vec![0b01010000]),
+            mock_spi::Transaction::transaction_end(),
+        ];
+        let mut bus = mock_spi::Mock::new(&expectations);
+
+        let mut sr = super::Sn74hc595::new(bus.clone());
+        let mut sr_pins = sr.split();
+
+        sr_pins.qa.set_high().unwrap();
+        sr_pins.qh.set_high().unwrap();
+        assert!(sr_pins.qh.is_set_high().unwrap());
+        sr_pins.qa.toggle().unwrap();
+        sr.write_mask(0b01010000, 0b10000000).unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn sn74hc595_unsupported_operation() {
+        use crate::PortDriver;
+
+        let mut bus = mock_spi::Mock::new(&[]);
+
+        let mut drv = super::Driver::new(bus.clone());
+        assert_eq!(drv.get(0x01, 0x00), Err(super::Error::UnsupportedOperation));
+
+        bus.done();
+    }
+}
@@ -1,27 +1,81 @@
 //! Support for the `TCA6408A` "Remote 8-Bit I2C AND SMBus Low-power I/O Expander  With Interrupt Output, Reset, and Configuration Registers"
-use crate::I2cExt;
 
 /// `TCA6408A` "Remote 8-Bit I2C AND SMBus Low-power I/O Expander"
 pub struct Tca6408a<M>(M);
 
 impl<I2C> Tca6408a<core::cell::RefCell<Driver<I2C>>>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
 {
     pub fn new(i2c: I2C, a0: bool) -> Self {
         Self::with_mutex(i2c, a0)
     }
 }
 
+#[cfg(feature = "critical-section")]
+impl<I2C> Tca6408a<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Tca6408a::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool) -> Self {
+        Self::with_mutex(i2c, a0)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Tca6408a<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Tca6408a::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool) -> Self {
+        Self::with_mutex(i2c, a0)
+    }
+}
+
 impl<I2C, M> Tca6408a<M>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
     M: crate::PortMutex<Port = Driver<I2C>>,
 {
     pub fn with_mutex(i2c: I2C, a0: bool) -> Self {
         Self(crate::PortMutex::create(Driver::new(i2c, a0)))
     }
 
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x21`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
     pub fn split(&mut self) -> Parts<'_, I2C, M> {
         Parts {
             io0: crate::Pin::new(0, &self.0),
@@ -34,11 +88,36 @@ where
             io7: crate::Pin::new(7, &self.0),
         }
     }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 8,
+        error: I2C::BusError,
+    }
+
+    /// Write the output register on every output update, even if it does not change.
+    ///
+    /// By default, the bus transfer is skipped when e.g. `set_high()` is called on a pin which is
+    /// already HIGH.  With `always`, every update is written, for external watchdogs or
+    /// multi-master setups which rely on the write.  See also [`refresh()`](crate::refresh).
+    pub fn set_always_write(&self, always: bool) {
+        crate::PortMutex::lock(&self.0, |drv| drv.set_always_write(always))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH and input polarity
+    /// inversion is cleared.  Consuming the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
 }
 
 pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
 where
-    I2C: crate::I2cBus,
+    I2C: crate::RegisterBus,
     M: crate::PortMutex<Port = Driver<I2C>>,
 {
     pub io0: crate::Pin<'a, crate::mode::Input, M>,
@@ -66,32 +145,67 @@ impl From<Regs> for u8 {
     }
 }
 
+crate::address::addr_type! {
+    /// I2C address of the `TCA6408A`.
+    pub struct Addr: 0x20, [a0 = 0], 0x20..=0x21;
+}
+
 pub struct Driver<I2C> {
     i2c: I2C,
     addr: u8,
     out: u8,
+    dirty: bool,
+    address_check: crate::AddressCheck,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
+    always_write: bool,
 }
 
 impl<I2C> Driver<I2C> {
     pub fn new(i2c: I2C, a0: bool) -> Self {
-        let addr = 0x20 | (a0 as u8);
+        let addr = Addr::from_pins(a0).get();
+        Self::with_address(i2c, addr, crate::AddressCheck::Checked)
+    }
+
+    pub(crate) fn with_address(i2c: I2C, addr: u8, address_check: crate::AddressCheck) -> Self {
         Self {
             i2c,
             addr,
             out: 0xff,
+            dirty: false,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
+            address_check,
+            always_write: false,
         }
     }
+
+    pub(crate) fn set_always_write(&mut self, always: bool) {
+        self.always_write = always;
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriver for Driver<I2C> {
+impl<I2C: crate::RegisterBus> crate::PortDriver for Driver<I2C> {
     type Error = I2C::BusError;
 
     fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         let previous = self.out;
         self.out |= mask_high as u8;
         self.out &= !mask_low as u8;
-        if self.out != previous {
-            self.i2c.write_reg(self.addr, Regs::OutputPort, self.out)
+        // after a failed write, the device may not match the cache even if nothing changed
+        if self.out != previous || self.always_write || self.dirty {
+            self.dirty = true;
+            self.i2c.write_reg(self.addr, Regs::OutputPort, self.out)?;
+            self.dirty = false;
+            Ok(())
         } else {
             // don't do the transfer when nothing changed
             Ok(())
@@ -99,42 +213,117 @@ impl<I2C: crate::I2cBus> crate::PortDriver for Driver<I2C> {
     }
 
     fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
         Ok(((self.out as u32) & mask_high) | (!(self.out as u32) & mask_low))
     }
 
     fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
-        let in_ = self.i2c.read_reg(self.addr, Regs::InputPort)? as u32;
+        let in_ = self.input_state.read(mask_high | mask_low, |_| {
+            self.i2c
+                .read_reg(self.addr, Regs::InputPort)
+                .map(|in_| in_ as u32)
+        })?;
         Ok((in_ & mask_high) | (!in_ & mask_low))
     }
+
+    fn get_polarity(&mut self, mask: u32) -> Result<u32, Self::Error> {
+        Ok(self.i2c.read_reg(self.addr, Regs::PolarityInversion)? as u32 & mask)
+    }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriverTotemPole for Driver<I2C> {
-    fn set_direction(
-        &mut self,
-        mask: u32,
-        dir: crate::Direction,
-        state: bool,
-    ) -> Result<(), Self::Error> {
-        // set state before switching direction to prevent glitch
-        if dir == crate::Direction::Output {
-            use crate::PortDriver;
-            if state {
-                self.set(mask, 0)?;
-            } else {
-                self.set(0, mask)?;
+impl<I2C: crate::RegisterBus> crate::PortDriverInputState for Driver<I2C> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverOutputInversion for Driver<I2C> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverClaims for Driver<I2C> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverCost for Driver<I2C> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set {
+                mask_high,
+                mask_low,
+            } => {
+                let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+                let out = (self.out | mask_high as u8) & !mask_low as u8;
+                (out != self.out || self.always_write) as u32
             }
+            crate::Operation::Get { mask } => self.input_state.read_mask(mask).is_some() as u32,
+            crate::Operation::SetDirections { .. } => 2,
         }
+    }
+}
 
-        let (mask_set, mask_clear) = match dir {
-            crate::Direction::Input => (mask as u8, 0),
-            crate::Direction::Output => (0, mask as u8),
-        };
+impl<I2C: crate::RegisterBus> crate::PortDriverResync for Driver<I2C> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        self.out = self.i2c.read_reg(self.addr, Regs::OutputPort)?;
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverSnapshot for Driver<I2C> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        let config = self.i2c.read_reg(self.addr, Regs::Configuration)?;
+        Ok(crate::DriverState {
+            pins: 0xff,
+            outputs: Some(self.out as u32),
+            output_pins: Some(!config as u32 & 0xff),
+            pull_ups: None,
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, 0xff, 0)?,
+        })
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRestoreDefaults for Driver<I2C> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        // inputs first, so the outputs stop driving before anything else changes
+        self.i2c.write_reg(self.addr, Regs::Configuration, 0xff)?;
+        self.i2c.write_reg(self.addr, Regs::OutputPort, 0xff)?;
         self.i2c
-            .update_reg(self.addr, Regs::Configuration, mask_set, mask_clear)
+            .write_reg(self.addr, Regs::PolarityInversion, 0x00)?;
+        self.out = 0xff;
+        Ok(())
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRefresh for Driver<I2C> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        if mask & 0xFF != 0 {
+            self.i2c.write_reg(self.addr, Regs::OutputPort, self.out)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverTotemPole for Driver<I2C> {
+    fn set_directions(&mut self, mask_input: u32, mask_output: u32) -> Result<(), Self::Error> {
+        self.i2c.update_reg(
+            self.addr,
+            Regs::Configuration,
+            mask_input as u8,
+            mask_output as u8,
+        )
     }
 }
 
-impl<I2C: crate::I2cBus> crate::PortDriverPolarity for Driver<I2C> {
+impl<I2C: crate::RegisterBus> crate::PortDriverPolarity for Driver<I2C> {
     fn set_polarity(&mut self, mask: u32, inverted: bool) -> Result<(), Self::Error> {
         let (mask_set, mask_clear) = match inverted {
             false => (0, mask as u8),
@@ -196,7 +385,7 @@ mod tests {
         assert!(io0.is_high().unwrap());
         assert!(io0.is_low().unwrap());
 
-        pca_pins.io4.into_inverted().unwrap();
+        let _ = pca_pins.io4.into_inverted().unwrap();
         let mut io5 = pca_pins.io5;
         io5.set_inverted(true).unwrap();
         io5.set_inverted(false).unwrap();
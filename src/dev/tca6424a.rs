@@ -0,0 +1,464 @@
+//! Support for the `TCA6424A` "Low-Voltage 24-Bit I2C and SMBus I/O Expander with Interrupt Output,
+//! Reset, and Configuration Registers"
+//!
+//! The `TCA6424A` by Texas Instruments has three 8-bit ports with the usual input, output, polarity
+//! inversion and configuration registers.  With the auto-increment bit set in the command byte, the
+//! chip steps through the three ports of a register, so the driver reads and writes all 24 pins of
+//! a register in a single transfer.  The chip has no internal pull-up resistors, open inputs will
+//! float.
+/// `PCA9538` "Remote 8-Bit I2C AND SMBus Low-power I/O Expander"
+pub struct Tca6424a<M>(M);
+
+impl<I2C> Tca6424a<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::I2cBus,
+{
+    pub fn new(i2c: I2C, addr: bool) -> Self {
+        Self::with_mutex(i2c, addr)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Tca6424a<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new()`](Tca6424a::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, addr: bool) -> Self {
+        Self::with_mutex(i2c, addr)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Tca6424a<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::I2cBus,
+{
+    /// Same as [`new()`](Tca6424a::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, addr: bool) -> Self {
+        Self::with_mutex(i2c, addr)
+    }
+}
+
+impl<I2C, M> Tca6424a<M>
+where
+    I2C: crate::I2cBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub fn with_mutex(i2c: I2C, addr: bool) -> Self {
+        Self(crate::PortMutex::create(Driver::new(i2c, addr)))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x22` to `0x23`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
+    pub fn split(&mut self) -> Parts<'_, I2C, M> {
+        Parts {
+            io0_0: crate::Pin::new(0, &self.0),
+            io0_1: crate::Pin::new(1, &self.0),
+            io0_2: crate::Pin::new(2, &self.0),
+            io0_3: crate::Pin::new(3, &self.0),
+            io0_4: crate::Pin::new(4, &self.0),
+            io0_5: crate::Pin::new(5, &self.0),
+            io0_6: crate::Pin::new(6, &self.0),
+            io0_7: crate::Pin::new(7, &self.0),
+            io1_0: crate::Pin::new(8, &self.0),
+            io1_1: crate::Pin::new(9, &self.0),
+            io1_2: crate::Pin::new(10, &self.0),
+            io1_3: crate::Pin::new(11, &self.0),
+            io1_4: crate::Pin::new(12, &self.0),
+            io1_5: crate::Pin::new(13, &self.0),
+            io1_6: crate::Pin::new(14, &self.0),
+            io1_7: crate::Pin::new(15, &self.0),
+            io2_0: crate::Pin::new(16, &self.0),
+            io2_1: crate::Pin::new(17, &self.0),
+            io2_2: crate::Pin::new(18, &self.0),
+            io2_3: crate::Pin::new(19, &self.0),
+            io2_4: crate::Pin::new(20, &self.0),
+            io2_5: crate::Pin::new(21, &self.0),
+            io2_6: crate::Pin::new(22, &self.0),
+            io2_7: crate::Pin::new(23, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 24,
+        error: I2C::BusError,
+    }
+
+    /// Write the output register on every output update, even if it does not change.
+    ///
+    /// By default, the bus transfer is skipped when e.g. `set_high()` is called on a pin which is
+    /// already HIGH.  With `always`, every update is written, for external watchdogs or
+    /// multi-master setups which rely on the write.  See also [`refresh()`](crate::refresh).
+    pub fn set_always_write(&self, always: bool) {
+        crate::PortMutex::lock(&self.0, |drv| drv.set_always_write(always))
+    }
+
+    /// Check that all pins follow their outputs, e.g. for production tests.
+    ///
+    /// Each pin is driven HIGH and LOW as an output and read back through the input register,
+    /// see [`SelfTestReport`](crate::SelfTestReport) for the faults which are detected.  Only run
+    /// this on hardware where all pins may be driven, afterwards they are inputs again.
+    pub fn self_test(&mut self) -> Result<crate::SelfTestReport, I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| crate::selftest::self_test(drv, 0xffffff))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH and input polarity
+    /// inversion is cleared.  Consuming the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::I2cBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub io0_0: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_1: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_2: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_3: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_4: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_5: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_6: crate::Pin<'a, crate::mode::Input, M>,
+    pub io0_7: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_0: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_1: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_2: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_3: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_4: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_5: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_6: crate::Pin<'a, crate::mode::Input, M>,
+    pub io1_7: crate::Pin<'a, crate::mode::Input, M>,
+    pub io2_0: crate::Pin<'a, crate::mode::Input, M>,
+    pub io2_1: crate::Pin<'a, crate::mode::Input, M>,
+    pub io2_2: crate::Pin<'a, crate::mode::Input, M>,
+    pub io2_3: crate::Pin<'a, crate::mode::Input, M>,
+    pub io2_4: crate::Pin<'a, crate::mode::Input, M>,
+    pub io2_5: crate::Pin<'a, crate::mode::Input, M>,
+    pub io2_6: crate::Pin<'a, crate::mode::Input, M>,
+    pub io2_7: crate::Pin<'a, crate::mode::Input, M>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Regs {
+    InputPort0 = 0x00,
+    OutputPort0 = 0x04,
+    PolarityInversion0 = 0x08,
+    Configuration0 = 0x0c,
+}
+
+impl From<Regs> for u8 {
+    fn from(r: Regs) -> u8 {
+        r as u8
+    }
+}
+
+/// Command bit which makes the chip step to the next port after each byte.
+const AUTO_INCREMENT: u8 = 0x80;
+
+crate::address::addr_type! {
+    /// I2C address of the `TCA6424A`.
+    pub struct Addr: 0x22, [addr = 0], 0x22..=0x23;
+}
+
+pub struct Driver<I2C> {
+    i2c: I2C,
+    addr: u8,
+    out: u32,
+    dirty: bool,
+    address_check: crate::AddressCheck,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
+    always_write: bool,
+}
+
+impl<I2C> Driver<I2C> {
+    pub fn new(i2c: I2C, addr: bool) -> Self {
+        let addr = Addr::from_pins(addr).get();
+        Self::with_address(i2c, addr, crate::AddressCheck::Checked)
+    }
+
+    pub(crate) fn with_address(i2c: I2C, addr: u8, address_check: crate::AddressCheck) -> Self {
+        Self {
+            i2c,
+            addr,
+            out: 0xffffff,
+            dirty: false,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
+            address_check,
+            always_write: false,
+        }
+    }
+
+    pub(crate) fn set_always_write(&mut self, always: bool) {
+        self.always_write = always;
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
+}
+
+/// Read all three ports of `reg` in a single transfer.
+fn read_reg24<I2C: crate::I2cBus>(
+    i2c: &mut I2C,
+    addr: u8,
+    reg: Regs,
+) -> Result<u32, I2C::BusError> {
+    let mut buf = [0x00; 3];
+    i2c.write_read(addr, &[u8::from(reg) | AUTO_INCREMENT], &mut buf)?;
+    for (port, value) in buf.iter().enumerate() {
+        crate::bus::trace_read(addr, u8::from(reg) + port as u8, *value);
+    }
+    Ok(u32::from_le_bytes([buf[0], buf[1], buf[2], 0x00]))
+}
+
+/// Write all three ports of `reg` in a single transfer.
+fn write_reg24<I2C: crate::I2cBus>(
+    i2c: &mut I2C,
+    addr: u8,
+    reg: Regs,
+    value: u32,
+) -> Result<(), I2C::BusError> {
+    let [port0, port1, port2, _] = value.to_le_bytes();
+    i2c.write(addr, &[u8::from(reg) | AUTO_INCREMENT, port0, port1, port2])?;
+    for (port, value) in [port0, port1, port2].into_iter().enumerate() {
+        crate::bus::trace_write(addr, u8::from(reg) + port as u8, value);
+    }
+    Ok(())
+}
+
+impl<I2C: crate::I2cBus> Driver<I2C> {
+    fn read_reg24(&mut self, reg: Regs) -> Result<u32, I2C::BusError> {
+        read_reg24(&mut self.i2c, self.addr, reg)
+    }
+
+    fn write_reg24(&mut self, reg: Regs, value: u32) -> Result<(), I2C::BusError> {
+        write_reg24(&mut self.i2c, self.addr, reg, value)
+    }
+
+    fn update_reg24(
+        &mut self,
+        reg: Regs,
+        mask_set: u32,
+        mask_clear: u32,
+    ) -> Result<(), I2C::BusError> {
+        let value = self.read_reg24(reg)?;
+        self.write_reg24(reg, (value | mask_set) & !mask_clear)
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriver for Driver<I2C> {
+    type Error = I2C::BusError;
+
+    fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let previous = self.out;
+        self.out |= mask_high & 0xffffff;
+        self.out &= !mask_low;
+        // after a failed write, the device may not match the cache even if nothing changed
+        if self.out != previous || self.always_write || self.dirty {
+            self.dirty = true;
+            self.write_reg24(Regs::OutputPort0, self.out)?;
+            self.dirty = false;
+            Ok(())
+        } else {
+            // don't do the transfer when nothing changed
+            Ok(())
+        }
+    }
+
+    fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        Ok((self.out & mask_high) | (!self.out & mask_low))
+    }
+
+    fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let in_ = self.input_state.read(mask_high | mask_low, |_| {
+            read_reg24(&mut self.i2c, self.addr, Regs::InputPort0)
+        })?;
+        Ok((in_ & mask_high) | (!in_ & mask_low))
+    }
+
+    fn get_polarity(&mut self, mask: u32) -> Result<u32, Self::Error> {
+        Ok(self.read_reg24(Regs::PolarityInversion0)? & mask)
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverInputState for Driver<I2C> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverOutputInversion for Driver<I2C> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverClaims for Driver<I2C> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverCost for Driver<I2C> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set {
+                mask_high,
+                mask_low,
+            } => {
+                let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+                let out = (self.out | (mask_high & 0xffffff)) & !mask_low;
+                (out != self.out || self.always_write) as u32
+            }
+            crate::Operation::Get { mask } => self.input_state.read_mask(mask).is_some() as u32,
+            crate::Operation::SetDirections { .. } => 2,
+        }
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverResync for Driver<I2C> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        self.out = self.read_reg24(Regs::OutputPort0)?;
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverSnapshot for Driver<I2C> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        let config = self.read_reg24(Regs::Configuration0)?;
+        Ok(crate::DriverState {
+            pins: 0xffffff,
+            outputs: Some(self.out),
+            output_pins: Some(!config & 0xffffff),
+            pull_ups: None,
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, 0xffffff, 0)?,
+        })
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverRestoreDefaults for Driver<I2C> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        // inputs first, so the outputs stop driving before anything else changes
+        self.write_reg24(Regs::Configuration0, 0xffffff)?;
+        self.write_reg24(Regs::OutputPort0, 0xffffff)?;
+        self.write_reg24(Regs::PolarityInversion0, 0x000000)?;
+        self.out = 0xffffff;
+        Ok(())
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverRefresh for Driver<I2C> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        if mask & 0xffffff != 0 {
+            self.write_reg24(Regs::OutputPort0, self.out)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverTotemPole for Driver<I2C> {
+    fn set_directions(&mut self, mask_input: u32, mask_output: u32) -> Result<(), Self::Error> {
+        self.update_reg24(Regs::Configuration0, mask_input, mask_output)
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::PortDriverPolarity for Driver<I2C> {
+    fn set_polarity(&mut self, mask: u32, inverted: bool) -> Result<(), Self::Error> {
+        let (mask_set, mask_clear) = match inverted {
+            false => (0, mask),
+            true => (mask, 0),
+        };
+
+        self.update_reg24(Regs::PolarityInversion0, mask_set, mask_clear)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn tca6424a() {
+        let expectations = [
+            // pin setup io2_0
+            mock_i2c::Transaction::write(0x23, vec![0x84, 0xff, 0xff, 0xfe]),
+            mock_i2c::Transaction::write_read(0x23, vec![0x8c], vec![0xff, 0xff, 0xff]),
+            mock_i2c::Transaction::write(0x23, vec![0x8c, 0xff, 0xff, 0xfe]),
+            // io2_0 writes
+            mock_i2c::Transaction::write(0x23, vec![0x84, 0xff, 0xff, 0xff]),
+            mock_i2c::Transaction::write(0x23, vec![0x84, 0xff, 0xff, 0xfe]),
+            // io1_3 and io0_1 reads
+            mock_i2c::Transaction::write_read(0x23, vec![0x80], vec![0x02, 0x08, 0x00]),
+            mock_i2c::Transaction::write_read(0x23, vec![0x80], vec![0x00, 0x08, 0x00]),
+            // io1_7 polarity
+            mock_i2c::Transaction::write_read(0x23, vec![0x88], vec![0x00, 0x00, 0x00]),
+            mock_i2c::Transaction::write(0x23, vec![0x88, 0x00, 0x80, 0x00]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut tca = super::Tca6424a::new(bus.clone(), true);
+        let tca_pins = tca.split();
+
+        let mut io2_0 = tca_pins.io2_0.into_output().unwrap();
+        io2_0.set_high().unwrap();
+        io2_0.toggle().unwrap();
+
+        assert!(tca_pins.io1_3.is_high().unwrap());
+        assert!(tca_pins.io0_1.is_low().unwrap());
+
+        let _ = tca_pins.io1_7.into_inverted().unwrap();
+
+        bus.done();
+    }
+}
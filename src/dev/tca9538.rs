@@ -0,0 +1,150 @@
+//! Support for the `TCA9538` "Low Voltage 8-Bit I2C and SMBus Low-Power I/O Expander With
+//! Interrupt Output, Reset, and Configuration Registers"
+//!
+//! The `TCA9538` by Texas Instruments is register-compatible with the [`PCA9538`](super::pca9538)
+//! and uses the same I2C address range (`0x70` to `0x73`).  A LOW pulse on its `RESET` pin puts
+//! the registers back into their power-up state, after which the port driver's view of the output
+//! register no longer matches the chip.  Create a new instance after resetting the chip.  The
+//! driver and the pin layout are shared with the `PCA9538`.
+pub use super::pca9538::{Addr, Driver, Parts};
+
+/// `TCA9538` "Low Voltage 8-Bit I2C and SMBus Low-Power I/O Expander With Reset"
+pub struct Tca9538<M>(M);
+
+impl<I2C> Tca9538<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    pub fn new(i2c: I2C, a0: bool, a1: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Tca9538<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Tca9538::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Tca9538<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Tca9538::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1)
+    }
+}
+
+impl<I2C, M> Tca9538<M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub fn with_mutex(i2c: I2C, a0: bool, a1: bool) -> Self {
+        Self(crate::PortMutex::create(Driver::new(i2c, a0, a1)))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x70` to `0x73`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
+    pub fn split(&mut self) -> Parts<'_, I2C, M> {
+        Parts {
+            io0: crate::Pin::new(0, &self.0),
+            io1: crate::Pin::new(1, &self.0),
+            io2: crate::Pin::new(2, &self.0),
+            io3: crate::Pin::new(3, &self.0),
+            io4: crate::Pin::new(4, &self.0),
+            io5: crate::Pin::new(5, &self.0),
+            io6: crate::Pin::new(6, &self.0),
+            io7: crate::Pin::new(7, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 8,
+        error: I2C::BusError,
+    }
+
+    /// Write the output register on every output update, even if it does not change.
+    ///
+    /// By default, the bus transfer is skipped when e.g. `set_high()` is called on a pin which is
+    /// already HIGH.  With `always`, every update is written, for external watchdogs or
+    /// multi-master setups which rely on the write.  See also [`refresh()`](crate::refresh).
+    pub fn set_always_write(&self, always: bool) {
+        crate::PortMutex::lock(&self.0, |drv| drv.set_always_write(always))
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH and input polarity
+    /// inversion is cleared.  Consuming the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn tca9538() {
+        let expectations = [
+            // pin setup io5
+            mock_i2c::Transaction::write(0x73, vec![0x01, 0xdf]),
+            mock_i2c::Transaction::write_read(0x73, vec![0x03], vec![0xff]),
+            mock_i2c::Transaction::write(0x73, vec![0x03, 0xdf]),
+            // output io5
+            mock_i2c::Transaction::write(0x73, vec![0x01, 0xff]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut tca = super::Tca9538::new(bus.clone(), true, true);
+        let tca_pins = tca.split();
+
+        let mut io5 = tca_pins.io5.into_output().unwrap();
+        io5.set_high().unwrap();
+
+        bus.done();
+    }
+}
@@ -0,0 +1,170 @@
+//! Support for the Exar/MaxLinear `XRA1200` "8-bit I2C/SMBus GPIO Expander with Integrated Level
+//! Shifters"
+//!
+//! The `XRA1200` is the 8-bit version of the [`XRA1201`](super::xra1201) and shares its driver,
+//! including the per-pin interrupt edge selection and input filters.
+pub use super::xra1201::{Addr, Driver, Trigger};
+
+/// `XRA1200` "8-bit I2C/SMBus GPIO Expander with Integrated Level Shifters"
+pub struct Xra1200<M>(M);
+
+impl<I2C> Xra1200<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Xra1200<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Xra1200::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Xra1200<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Xra1200::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+impl<I2C, M> Xra1200<M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub fn with_mutex(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self(crate::PortMutex::create(Driver::new_xra1200(
+            i2c, a0, a1, a2,
+        )))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            1,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x27`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            1,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
+    pub fn split(&mut self) -> Parts<'_, I2C, M> {
+        Parts {
+            p0: crate::Pin::new(0, &self.0),
+            p1: crate::Pin::new(1, &self.0),
+            p2: crate::Pin::new(2, &self.0),
+            p3: crate::Pin::new(3, &self.0),
+            p4: crate::Pin::new(4, &self.0),
+            p5: crate::Pin::new(5, &self.0),
+            p6: crate::Pin::new(6, &self.0),
+            p7: crate::Pin::new(7, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 8,
+        error: I2C::BusError,
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH, input inversion, the
+    /// pull-ups and the interrupts are cleared and the input filter is enabled again.  Consuming
+    /// the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub p0: crate::Pin<'a, crate::mode::Input, M>,
+    pub p1: crate::Pin<'a, crate::mode::Input, M>,
+    pub p2: crate::Pin<'a, crate::mode::Input, M>,
+    pub p3: crate::Pin<'a, crate::mode::Input, M>,
+    pub p4: crate::Pin<'a, crate::mode::Input, M>,
+    pub p5: crate::Pin<'a, crate::mode::Input, M>,
+    pub p6: crate::Pin<'a, crate::mode::Input, M>,
+    pub p7: crate::Pin<'a, crate::mode::Input, M>,
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn xra1200() {
+        let expectations = [
+            // p3 output HIGH
+            mock_i2c::Transaction::write_read(0x24, vec![0x01], vec![0x00]),
+            mock_i2c::Transaction::write(0x24, vec![0x01, 0x08]),
+            mock_i2c::Transaction::write_read(0x24, vec![0x03], vec![0xff]),
+            mock_i2c::Transaction::write(0x24, vec![0x03, 0xf7]),
+            // p5 interrupt on both edges
+            mock_i2c::Transaction::write_read(0x24, vec![0x08], vec![0x00]),
+            mock_i2c::Transaction::write(0x24, vec![0x08, 0x20]),
+            mock_i2c::Transaction::write_read(0x24, vec![0x09], vec![0x00]),
+            mock_i2c::Transaction::write(0x24, vec![0x09, 0x20]),
+            mock_i2c::Transaction::write_read(0x24, vec![0x05], vec![0x00]),
+            mock_i2c::Transaction::write(0x24, vec![0x05, 0x20]),
+            // p5 input
+            mock_i2c::Transaction::write_read(0x24, vec![0x00], vec![0x20]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut xra = super::Xra1200::new(bus.clone(), false, false, true);
+        let mut xra_pins = xra.split();
+
+        let _ = xra_pins.p3.into_output_high().unwrap();
+        xra_pins
+            .p5
+            .set_interrupt_trigger(super::Trigger::BothEdges)
+            .unwrap();
+        assert!(xra_pins.p5.is_high().unwrap());
+
+        bus.done();
+    }
+}
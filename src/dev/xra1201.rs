@@ -0,0 +1,603 @@
+//! Support for the Exar/MaxLinear `XRA1201` "16-bit I2C/SMBus GPIO Expander with Integrated Level
+//! Shifters"
+//!
+//! Besides the usual GPIO registers, each input has its own interrupt enable, rising and falling
+//! edge selection, and glitch filter.  They are configured per pin with
+//! [`Pin::set_interrupt_trigger()`](crate::Pin::set_interrupt_trigger) and
+//! [`Pin::set_input_filter()`](crate::Pin::set_input_filter).  The pins which caused an interrupt
+//! are reported by [`status()`](crate::status), which clears them on the chip.
+//!
+//! The 8-bit [`XRA1200`](super::xra1200) shares this driver.
+
+/// `XRA1201` "16-bit I2C/SMBus GPIO Expander with Integrated Level Shifters"
+pub struct Xra1201<M>(M);
+
+impl<I2C> Xra1201<core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<I2C> Xra1201<critical_section::Mutex<core::cell::RefCell<Driver<I2C>>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Xra1201::new), but the device is wrapped in a
+    /// `critical_section::Mutex` so it can be shared with interrupt handlers.
+    pub fn new_critical_section(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl<I2C> Xra1201<std::sync::Mutex<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+{
+    /// Same as [`new()`](Xra1201::new), but the device is wrapped in a `std::sync::Mutex` so
+    /// it can be shared between threads.
+    pub fn new_std(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+impl<I2C, M> Xra1201<M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub fn with_mutex(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self(crate::PortMutex::create(Driver::new(i2c, a0, a1, a2)))
+    }
+
+    /// Create a new instance with the I2C address `addr`.
+    pub fn from_addr(i2c: I2C, addr: Addr) -> Self {
+        let addr = addr.get();
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            2,
+            crate::AddressCheck::Checked,
+        )))
+    }
+
+    /// Create a new instance with an explicit I2C address.
+    ///
+    /// Returns an error if `addr` is not an address documented in the datasheet (`0x20` to `0x27`).
+    pub fn with_address(i2c: I2C, addr: u8) -> Result<Self, crate::InvalidAddress> {
+        Ok(Self::from_addr(i2c, Addr::custom(addr)?))
+    }
+
+    /// Like `with_address()`, but `addr` is not validated, see [`crate::AddressCheck`].
+    pub fn with_address_unchecked(i2c: I2C, addr: u8) -> Self {
+        Self(crate::PortMutex::create(Driver::with_address(
+            i2c,
+            addr,
+            2,
+            crate::AddressCheck::Unchecked,
+        )))
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        crate::PortMutex::lock(&self.0, |drv| drv.address_check())
+    }
+
+    pub fn split(&mut self) -> Parts<'_, I2C, M> {
+        Parts {
+            p0: crate::Pin::new(0, &self.0),
+            p1: crate::Pin::new(1, &self.0),
+            p2: crate::Pin::new(2, &self.0),
+            p3: crate::Pin::new(3, &self.0),
+            p4: crate::Pin::new(4, &self.0),
+            p5: crate::Pin::new(5, &self.0),
+            p6: crate::Pin::new(6, &self.0),
+            p7: crate::Pin::new(7, &self.0),
+            p8: crate::Pin::new(8, &self.0),
+            p9: crate::Pin::new(9, &self.0),
+            p10: crate::Pin::new(10, &self.0),
+            p11: crate::Pin::new(11, &self.0),
+            p12: crate::Pin::new(12, &self.0),
+            p13: crate::Pin::new(13, &self.0),
+            p14: crate::Pin::new(14, &self.0),
+            p15: crate::Pin::new(15, &self.0),
+        }
+    }
+
+    pin_methods! {
+        mode: crate::mode::Input,
+        pins: 16,
+        error: I2C::BusError,
+    }
+
+    /// Put the chip back into its power-on configuration and release it.
+    ///
+    /// All pins become inputs, the output register goes back to all HIGH, input inversion, the
+    /// pull-ups, the interrupts and the output three-state control are cleared and the input
+    /// filters are enabled again.  Consuming the device requires that all of its pins were dropped.
+    pub fn restore_defaults(self) -> Result<(), I2C::BusError> {
+        crate::PortMutex::lock(&self.0, |drv| {
+            crate::PortDriverRestoreDefaults::restore_defaults(drv)
+        })
+    }
+}
+
+pub struct Parts<'a, I2C, M = core::cell::RefCell<Driver<I2C>>>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    pub p0: crate::Pin<'a, crate::mode::Input, M>,
+    pub p1: crate::Pin<'a, crate::mode::Input, M>,
+    pub p2: crate::Pin<'a, crate::mode::Input, M>,
+    pub p3: crate::Pin<'a, crate::mode::Input, M>,
+    pub p4: crate::Pin<'a, crate::mode::Input, M>,
+    pub p5: crate::Pin<'a, crate::mode::Input, M>,
+    pub p6: crate::Pin<'a, crate::mode::Input, M>,
+    pub p7: crate::Pin<'a, crate::mode::Input, M>,
+    pub p8: crate::Pin<'a, crate::mode::Input, M>,
+    pub p9: crate::Pin<'a, crate::mode::Input, M>,
+    pub p10: crate::Pin<'a, crate::mode::Input, M>,
+    pub p11: crate::Pin<'a, crate::mode::Input, M>,
+    pub p12: crate::Pin<'a, crate::mode::Input, M>,
+    pub p13: crate::Pin<'a, crate::mode::Input, M>,
+    pub p14: crate::Pin<'a, crate::mode::Input, M>,
+    pub p15: crate::Pin<'a, crate::mode::Input, M>,
+}
+
+/// Edges of an input which raise an interrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Trigger {
+    Disabled,
+    RisingEdge,
+    FallingEdge,
+    BothEdges,
+}
+
+/// Registers of the chip.
+///
+/// The `XRA1201` has one register per port for each of them, at `2 * reg + port`.  The `XRA1200`
+/// only has port 0, so its registers are at `reg`.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Regs {
+    GpioState = 0x00,
+    OutputControl = 0x01,
+    InputPolarityInversion = 0x02,
+    /// 0: output, 1: input
+    GpioConfiguration = 0x03,
+    InputPullUpEnable = 0x04,
+    InputInterruptEnable = 0x05,
+    OutputThreeStateControl = 0x06,
+    InputInterruptStatus = 0x07,
+    InputRisingEdgeInterruptEnable = 0x08,
+    InputFallingEdgeInterruptEnable = 0x09,
+    InputFilterEnable = 0x0a,
+}
+
+crate::address::addr_type! {
+    /// I2C address of the `XRA1201`.
+    pub struct Addr: 0x20, [a0 = 0, a1 = 1, a2 = 2], 0x20..=0x27;
+}
+
+pub struct Driver<I2C> {
+    i2c: I2C,
+    addr: u8,
+    ports: u8,
+    out: Option<u16>,
+    dirty: bool,
+    address_check: crate::AddressCheck,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    claims: crate::PinClaims,
+}
+
+impl<I2C> Driver<I2C> {
+    /// Create a driver for the 16-bit `XRA1201`.
+    pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        let addr = Addr::from_pins(a0, a1, a2).get();
+        Self::with_address(i2c, addr, 2, crate::AddressCheck::Checked)
+    }
+
+    /// Create a driver for the 8-bit `XRA1200`.
+    pub fn new_xra1200(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        let addr = Addr::from_pins(a0, a1, a2).get();
+        Self::with_address(i2c, addr, 1, crate::AddressCheck::Checked)
+    }
+
+    pub(crate) fn with_address(
+        i2c: I2C,
+        addr: u8,
+        ports: u8,
+        address_check: crate::AddressCheck,
+    ) -> Self {
+        Self {
+            i2c,
+            addr,
+            ports,
+            // The output register is read from the chip before it is first written, so a driver
+            // restarted without the chip does not glitch its outputs.
+            out: None,
+            dirty: false,
+            address_check,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            claims: crate::PinClaims::default(),
+        }
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
+
+    fn reg(&self, reg: Regs, port: u8) -> u8 {
+        reg as u8 * self.ports + port
+    }
+
+    /// Number of ports touched by `mask`.
+    fn banks(&self, mask: u32) -> u32 {
+        (0..self.ports)
+            .filter(|port| mask & (0xff << (8 * port)) != 0)
+            .count() as u32
+    }
+}
+
+impl<I2C: crate::RegisterBus> Driver<I2C> {
+    fn get_out(&mut self) -> Result<u16, I2C::BusError> {
+        match self.out {
+            Some(out) => Ok(out),
+            None => {
+                let mut out = 0x0000;
+                for port in 0..self.ports {
+                    let reg = self.reg(Regs::OutputControl, port);
+                    out |= (self.i2c.read_reg(self.addr, reg)? as u16) << (8 * port);
+                }
+                self.out = Some(out);
+                Ok(out)
+            }
+        }
+    }
+
+    fn write_out(&mut self, mask: u32, out: u16) -> Result<(), I2C::BusError> {
+        for port in 0..self.ports {
+            if mask & (0xff << (8 * port)) != 0 {
+                let reg = self.reg(Regs::OutputControl, port);
+                self.i2c
+                    .write_reg(self.addr, reg, (out >> (8 * port)) as u8)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the bits in `mask_set` and clear the bits in `mask_clear` of the register `reg`.
+    ///
+    /// Ports which are not touched by either mask are not accessed.
+    fn update_regs(
+        &mut self,
+        reg: Regs,
+        mask_set: u32,
+        mask_clear: u32,
+    ) -> Result<(), I2C::BusError> {
+        for port in 0..self.ports {
+            let shift = 8 * port;
+            if (mask_set | mask_clear) & (0xff << shift) != 0 {
+                let reg = self.reg(reg, port);
+                self.i2c.update_reg(
+                    self.addr,
+                    reg,
+                    (mask_set >> shift) as u8,
+                    (mask_clear >> shift) as u8,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Configure which edges of the inputs in `mask` raise an interrupt.
+    pub fn set_interrupt(&mut self, mask: u32, trigger: Trigger) -> Result<(), I2C::BusError> {
+        let (rising, falling) = match trigger {
+            Trigger::Disabled => return self.update_regs(Regs::InputInterruptEnable, 0, mask),
+            Trigger::RisingEdge => (mask, 0),
+            Trigger::FallingEdge => (0, mask),
+            Trigger::BothEdges => (mask, mask),
+        };
+        self.update_regs(Regs::InputRisingEdgeInterruptEnable, rising, mask & !rising)?;
+        self.update_regs(
+            Regs::InputFallingEdgeInterruptEnable,
+            falling,
+            mask & !falling,
+        )?;
+        self.update_regs(Regs::InputInterruptEnable, mask, 0)
+    }
+
+    /// Enable or disable the glitch filter of the inputs in `mask`.
+    ///
+    /// The filters are enabled after reset.
+    pub fn set_input_filter(&mut self, mask: u32, enable: bool) -> Result<(), I2C::BusError> {
+        match enable {
+            true => self.update_regs(Regs::InputFilterEnable, mask, 0),
+            false => self.update_regs(Regs::InputFilterEnable, 0, mask),
+        }
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriver for Driver<I2C> {
+    type Error = I2C::BusError;
+
+    fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let mut out = self.get_out()?;
+        out |= mask_high as u16;
+        out &= !mask_low as u16;
+        self.out = Some(out);
+        // a failed write leaves the device in an unknown state, so all ports are written until a
+        // write succeeds again
+        let mask = match self.dirty {
+            true => 0xFFFF,
+            false => mask_high | mask_low,
+        };
+        self.dirty = true;
+        self.write_out(mask, out)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let out = self.get_out()?;
+        Ok(((out as u32) & mask_high) | (!(out as u32) & mask_low))
+    }
+
+    fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let ports = self.ports;
+        let in_ = self.input_state.read(mask_high | mask_low, |mask| {
+            let mut in_ = 0x00000000;
+            for port in 0..ports {
+                if mask & (0xff << (8 * port)) != 0 {
+                    let reg = Regs::GpioState as u8 * ports + port;
+                    in_ |= (self.i2c.read_reg(self.addr, reg)? as u32) << (8 * port);
+                }
+            }
+            Ok::<_, Self::Error>(in_)
+        })?;
+        Ok((in_ & mask_high) | (!in_ & mask_low))
+    }
+
+    fn get_polarity(&mut self, mask: u32) -> Result<u32, Self::Error> {
+        let mut inverted = 0;
+        for port in 0..self.ports {
+            if mask & (0xff << (8 * port)) != 0 {
+                let reg = self.reg(Regs::InputPolarityInversion, port);
+                inverted |= (self.i2c.read_reg(self.addr, reg)? as u32) << (8 * port);
+            }
+        }
+        Ok(inverted & mask)
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverInputState for Driver<I2C> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverOutputInversion for Driver<I2C> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverClaims for Driver<I2C> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverCost for Driver<I2C> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set {
+                mask_high,
+                mask_low,
+            } => {
+                let read_out = if self.out.is_none() {
+                    self.ports as u32
+                } else {
+                    0
+                };
+                read_out + self.banks(mask_high | mask_low)
+            }
+            crate::Operation::Get { mask } => self
+                .input_state
+                .read_mask(mask)
+                .map_or(0, |mask| self.banks(mask)),
+            crate::Operation::SetDirections {
+                mask_input,
+                mask_output,
+            } => 2 * self.banks(mask_input | mask_output),
+        }
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverResync for Driver<I2C> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        self.out = None;
+        self.get_out()?;
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverSnapshot for Driver<I2C> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        let pins = (1u32 << (8 * self.ports)) - 1;
+        let mut config = 0x00000000;
+        let mut pull_ups = 0x00000000;
+        for port in 0..self.ports {
+            let shift = 8 * port;
+            let reg = self.reg(Regs::GpioConfiguration, port);
+            config |= (self.i2c.read_reg(self.addr, reg)? as u32) << shift;
+            let reg = self.reg(Regs::InputPullUpEnable, port);
+            pull_ups |= (self.i2c.read_reg(self.addr, reg)? as u32) << shift;
+        }
+        Ok(crate::DriverState {
+            pins,
+            outputs: self.out.map(u32::from),
+            output_pins: Some(!config & pins),
+            pull_ups: Some(pull_ups),
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, pins, 0)?,
+        })
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRestoreDefaults for Driver<I2C> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        // inputs first, so the outputs stop driving before anything else changes
+        for (reg, value) in [
+            (Regs::GpioConfiguration, 0xff),
+            (Regs::OutputControl, 0xff),
+            (Regs::InputPolarityInversion, 0x00),
+            (Regs::InputPullUpEnable, 0x00),
+            (Regs::InputInterruptEnable, 0x00),
+            (Regs::OutputThreeStateControl, 0x00),
+            (Regs::InputRisingEdgeInterruptEnable, 0x00),
+            (Regs::InputFallingEdgeInterruptEnable, 0x00),
+            (Regs::InputFilterEnable, 0xff),
+        ] {
+            for port in 0..self.ports {
+                let reg = self.reg(reg, port);
+                self.i2c.write_reg(self.addr, reg, value)?;
+            }
+        }
+        self.out = Some(0xffff);
+        Ok(())
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverRefresh for Driver<I2C> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        let out = self.get_out()?;
+        self.write_out(mask, out)
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverStatus for Driver<I2C> {
+    fn status(&mut self) -> Result<crate::Status, Self::Error> {
+        let mut interrupt_source = 0x00000000;
+        for port in 0..self.ports {
+            let reg = self.reg(Regs::InputInterruptStatus, port);
+            interrupt_source |= (self.i2c.read_reg(self.addr, reg)? as u32) << (8 * port);
+        }
+        Ok(crate::Status {
+            reset: None,
+            interrupt_source: Some(interrupt_source),
+        })
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverTotemPole for Driver<I2C> {
+    fn set_directions(&mut self, mask_input: u32, mask_output: u32) -> Result<(), Self::Error> {
+        self.update_regs(Regs::GpioConfiguration, mask_input, mask_output)
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverPolarity for Driver<I2C> {
+    fn set_polarity(&mut self, mask: u32, inverted: bool) -> Result<(), Self::Error> {
+        match inverted {
+            true => self.update_regs(Regs::InputPolarityInversion, mask, 0),
+            false => self.update_regs(Regs::InputPolarityInversion, 0, mask),
+        }
+    }
+}
+
+impl<I2C: crate::RegisterBus> crate::PortDriverPullUp for Driver<I2C> {
+    fn set_pull_up(&mut self, mask: u32, enable: bool) -> Result<(), Self::Error> {
+        match enable {
+            true => self.update_regs(Regs::InputPullUpEnable, mask, 0),
+            false => self.update_regs(Regs::InputPullUpEnable, 0, mask),
+        }
+    }
+}
+
+impl<'a, MODE: crate::mode::HasInput + crate::mode::Configurable, I2C, M> crate::Pin<'a, MODE, M>
+where
+    I2C: crate::RegisterBus,
+    M: crate::PortMutex<Port = Driver<I2C>>,
+{
+    /// Configure which edges of this input raise an interrupt.
+    pub fn set_interrupt_trigger(
+        &mut self,
+        trigger: Trigger,
+    ) -> Result<(), crate::pin::PinError<I2C::BusError>> {
+        self.access_port_driver(|drv| drv.set_interrupt(self.pin_mask(), trigger))?;
+        Ok(())
+    }
+
+    /// Enable or disable the glitch filter of this input.
+    pub fn set_input_filter(
+        &mut self,
+        enable: bool,
+    ) -> Result<(), crate::pin::PinError<I2C::BusError>> {
+        self.access_port_driver(|drv| drv.set_input_filter(self.pin_mask(), enable))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn xra1201() {
+        let expectations = [
+            // p9 output LOW
+            mock_i2c::Transaction::write_read(0x21, vec![0x02], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x03], vec![0xff]),
+            mock_i2c::Transaction::write(0x21, vec![0x03, 0xfd]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x07], vec![0xff]),
+            mock_i2c::Transaction::write(0x21, vec![0x07, 0xfd]),
+            // p0 pull-up, interrupt on falling edges, no filter
+            mock_i2c::Transaction::write_read(0x21, vec![0x08], vec![0x00]),
+            mock_i2c::Transaction::write(0x21, vec![0x08, 0x01]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x10], vec![0x00]),
+            mock_i2c::Transaction::write(0x21, vec![0x10, 0x00]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x12], vec![0x00]),
+            mock_i2c::Transaction::write(0x21, vec![0x12, 0x01]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x0a], vec![0x00]),
+            mock_i2c::Transaction::write(0x21, vec![0x0a, 0x01]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x14], vec![0xff]),
+            mock_i2c::Transaction::write(0x21, vec![0x14, 0xfe]),
+            // p0 input
+            mock_i2c::Transaction::write_read(0x21, vec![0x00], vec![0xfe]),
+            // interrupt status
+            mock_i2c::Transaction::write_read(0x21, vec![0x0e], vec![0x01]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x0f], vec![0x00]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut xra = super::Xra1201::new(bus.clone(), true, false, false);
+        let xra_pins = xra.split();
+
+        let _ = xra_pins.p9.into_output().unwrap();
+        let mut p0 = xra_pins.p0.into_pull_up().unwrap();
+        p0.set_interrupt_trigger(super::Trigger::FallingEdge)
+            .unwrap();
+        p0.set_input_filter(false).unwrap();
+        assert!(p0.is_low().unwrap());
+
+        let status = crate::status(&p0).unwrap();
+        assert_eq!(status.interrupt_source, Some(0x0001));
+
+        bus.done();
+    }
+}
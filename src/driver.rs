@@ -0,0 +1,528 @@
+//! Generic driver for 16-bit port-expanders with a `PCA9555`-style register map.
+//!
+//! Many 16-bit port-expanders have the same basic registers: An input, an output, a polarity
+//! inversion, and a configuration register, each split into two 8-bit halves for the two ports.
+//! Only the register addresses differ.  [`Driver16`] implements all of the common functionality
+//! for such chips, a device module only needs to describe its registers by implementing
+//! [`Regs16`] and to add constructors and device-specific features on top.
+//!
+//! The register map is kept in the driver as a [`RegisterMap16`] table.  `Regs16` provides the
+//! default table of a device, silicon revisions with a different register map can select their
+//! own table at construction or later with [`Driver16::set_register_map()`], e.g. after reading
+//! an ID register.
+
+/// Register map of a 16-bit port-expander driven by [`Driver16`].
+///
+/// Each register is given as the addresses of its port 0 and port 1 halves.  In the configuration
+/// register, a 1 bit makes a pin an input.
+pub trait Regs16 {
+    const INPUT: [u8; 2];
+    const OUTPUT: [u8; 2];
+    const POLARITY_INVERSION: [u8; 2];
+    const CONFIGURATION: [u8; 2];
+
+    /// Output register contents after power-up.
+    ///
+    /// If this is `None`, the output register is read from the chip before it is first written.
+    /// This avoids glitches when the chip was not reset together with the microcontroller.
+    const OUTPUT_RESET: Option<u16>;
+
+    /// Additional registers of the `PCAL` variants, `None` for chips without them.
+    const PCAL: Option<PcalRegisters16> = None;
+
+    /// Default register map of the device, built from the constants above.
+    const MAP: RegisterMap16 = RegisterMap16 {
+        input: Self::INPUT,
+        output: Self::OUTPUT,
+        polarity_inversion: Self::POLARITY_INVERSION,
+        configuration: Self::CONFIGURATION,
+        output_reset: Self::OUTPUT_RESET,
+        pcal: Self::PCAL,
+    };
+}
+
+/// Register map of one silicon revision of a 16-bit port-expander.
+///
+/// Each register is given as the addresses of its port 0 and port 1 halves.  In the configuration
+/// register, a 1 bit makes a pin an input.  With an `output_reset` of `None`, the output register
+/// is read from the chip before it is first written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RegisterMap16 {
+    pub input: [u8; 2],
+    pub output: [u8; 2],
+    pub polarity_inversion: [u8; 2],
+    pub configuration: [u8; 2],
+    pub output_reset: Option<u16>,
+    pub pcal: Option<PcalRegisters16>,
+}
+
+/// Additional registers of the `PCAL` variants like the `PCAL6416A`.
+///
+/// These are only used by `restore_defaults()` to put the chip back into its power-on
+/// configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PcalRegisters16 {
+    pub input_latch: [u8; 2],
+    pub pull_enable: [u8; 2],
+    pub pull_selection: [u8; 2],
+    pub interrupt_mask: [u8; 2],
+    /// Push-pull or open-drain selection, one bit per port.
+    pub output_port_configuration: u8,
+}
+
+/// Generic driver for 16-bit port-expanders with the register map `R`.
+pub struct Driver16<I2C, R> {
+    pub(crate) i2c: I2C,
+    pub(crate) out: Option<u16>,
+    dirty: bool,
+    pub(crate) addr: u8,
+    regs: RegisterMap16,
+    address_check: crate::AddressCheck,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+    #[cfg(any(feature = "mcp23016", feature = "pca9555", feature = "pcal6416a"))]
+    claims: crate::PinClaims,
+    _regs: core::marker::PhantomData<R>,
+}
+
+impl<I2C, R: Regs16> Driver16<I2C, R> {
+    pub(crate) fn with_address(i2c: I2C, addr: u8, address_check: crate::AddressCheck) -> Self {
+        Self::with_register_map(i2c, addr, address_check, R::MAP)
+    }
+
+    /// Create a driver which uses the register map `regs` instead of the default one of `R`.
+    pub(crate) fn with_register_map(
+        i2c: I2C,
+        addr: u8,
+        address_check: crate::AddressCheck,
+        regs: RegisterMap16,
+    ) -> Self {
+        Self {
+            i2c,
+            out: regs.output_reset,
+            dirty: false,
+            addr,
+            regs,
+            address_check,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+            #[cfg(any(feature = "mcp23016", feature = "pca9555", feature = "pcal6416a"))]
+            claims: crate::PinClaims::default(),
+            _regs: core::marker::PhantomData,
+        }
+    }
+
+    /// Whether the I2C address of this device was validated against the datasheet.
+    pub fn address_check(&self) -> crate::AddressCheck {
+        self.address_check
+    }
+
+    /// The register map which is currently in use.
+    pub fn register_map(&self) -> RegisterMap16 {
+        self.regs
+    }
+
+    /// Switch to the register map `regs`, e.g. for a silicon revision detected at runtime.
+    ///
+    /// The cached output state is reset to `regs.output_reset`, so with `None` the output
+    /// register is read again from its new address before the next write.
+    pub fn set_register_map(&mut self, regs: RegisterMap16) {
+        self.regs = regs;
+        self.out = regs.output_reset;
+    }
+
+    /// Forget the cached chip state after the chip was reset, e.g. through its RESET pin.
+    #[cfg(feature = "pca9539")]
+    pub(crate) fn invalidate(&mut self) {
+        self.out = self.regs.output_reset;
+        self.input_state.invalidate();
+    }
+}
+
+impl<I2C: crate::RegisterBus, R: Regs16> Driver16<I2C, R> {
+    fn get_out(&mut self) -> Result<u16, I2C::BusError> {
+        match self.out {
+            Some(out) => Ok(out),
+            None => {
+                let out_low = self.i2c.read_reg(self.addr, self.regs.output[0])? as u16;
+                let out_high = self.i2c.read_reg(self.addr, self.regs.output[1])? as u16;
+                let out = out_low | (out_high << 8);
+                self.out = Some(out);
+                Ok(out)
+            }
+        }
+    }
+
+    /// Write the output, polarity inversion and configuration registers of both ports.
+    ///
+    /// The outputs are written first, so pins which become outputs immediately drive their state.
+    #[cfg(feature = "pca9555")]
+    pub(crate) fn write_config(
+        &mut self,
+        output: u16,
+        polarity_inversion: u16,
+        configuration: u16,
+    ) -> Result<(), I2C::BusError> {
+        for (regs, value) in [
+            (self.regs.output, output),
+            (self.regs.polarity_inversion, polarity_inversion),
+            (self.regs.configuration, configuration),
+        ] {
+            self.i2c.write_reg(self.addr, regs[0], value as u8)?;
+            self.i2c.write_reg(self.addr, regs[1], (value >> 8) as u8)?;
+        }
+        self.out = Some(output);
+        Ok(())
+    }
+
+    /// Set the bits in `mask_set` and clear the bits in `mask_clear` of both halves of `regs`.
+    ///
+    /// Halves which are not touched by either mask are not accessed.
+    fn update_regs16(
+        &mut self,
+        regs: [u8; 2],
+        mask_set: u32,
+        mask_clear: u32,
+    ) -> Result<(), I2C::BusError> {
+        let mask = mask_set | mask_clear;
+        if mask & 0x00FF != 0 {
+            self.i2c.update_reg(
+                self.addr,
+                regs[0],
+                (mask_set & 0xFF) as u8,
+                (mask_clear & 0xFF) as u8,
+            )?;
+        }
+        if mask & 0xFF00 != 0 {
+            self.i2c.update_reg(
+                self.addr,
+                regs[1],
+                ((mask_set >> 8) & 0xFF) as u8,
+                ((mask_clear >> 8) & 0xFF) as u8,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<I2C: crate::RegisterBus, R: Regs16> crate::PortDriver for Driver16<I2C, R> {
+    type Error = I2C::BusError;
+
+    fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let mut out = self.get_out()?;
+        out |= mask_high as u16;
+        out &= !mask_low as u16;
+        self.out = Some(out);
+        // a failed write leaves the device in an unknown state, so all banks are written until a
+        // write succeeds again
+        let mask = match self.dirty {
+            true => 0xFFFF,
+            false => mask_high | mask_low,
+        };
+        self.dirty = true;
+        if mask & 0x00FF != 0 {
+            self.i2c
+                .write_reg(self.addr, self.regs.output[0], (out & 0xFF) as u8)?;
+        }
+        if mask & 0xFF00 != 0 {
+            self.i2c
+                .write_reg(self.addr, self.regs.output[1], (out >> 8) as u8)?;
+        }
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let out = self.get_out()?;
+        Ok(((out as u32) & mask_high) | (!(out as u32) & mask_low))
+    }
+
+    fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let in_ = self.input_state.read(mask_high | mask_low, |mask| {
+            let io0 = if mask & 0x00FF != 0 {
+                self.i2c.read_reg(self.addr, self.regs.input[0])?
+            } else {
+                0
+            };
+            let io1 = if mask & 0xFF00 != 0 {
+                self.i2c.read_reg(self.addr, self.regs.input[1])?
+            } else {
+                0
+            };
+            Ok::<_, Self::Error>(((io1 as u32) << 8) | io0 as u32)
+        })?;
+        Ok((in_ & mask_high) | (!in_ & mask_low))
+    }
+
+    fn get_polarity(&mut self, mask: u32) -> Result<u32, Self::Error> {
+        let mut inverted = 0;
+        for (port, reg) in self.regs.polarity_inversion.into_iter().enumerate() {
+            if mask & (0xFF << (8 * port)) != 0 {
+                inverted |= (self.i2c.read_reg(self.addr, reg)? as u32) << (8 * port);
+            }
+        }
+        Ok(inverted & mask)
+    }
+}
+
+impl<I2C: crate::RegisterBus, R: Regs16> crate::PortDriverInputState for Driver16<I2C, R> {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl<I2C: crate::RegisterBus, R: Regs16> crate::PortDriverOutputInversion for Driver16<I2C, R> {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+#[cfg(any(feature = "mcp23016", feature = "pca9555", feature = "pcal6416a"))]
+impl<I2C: crate::RegisterBus, R: Regs16> crate::PortDriverClaims for Driver16<I2C, R> {
+    fn claims(&mut self) -> &mut crate::PinClaims {
+        &mut self.claims
+    }
+}
+
+impl<I2C: crate::RegisterBus, R: Regs16> crate::PortDriverCost for Driver16<I2C, R> {
+    fn cost(&self, op: crate::Operation) -> u32 {
+        match op {
+            crate::Operation::Set {
+                mask_high,
+                mask_low,
+            } => {
+                let read_out = if self.out.is_none() { 2 } else { 0 };
+                read_out + crate::cost::banks16(mask_high | mask_low)
+            }
+            crate::Operation::Get { mask } => self
+                .input_state
+                .read_mask(mask)
+                .map_or(0, crate::cost::banks16),
+            crate::Operation::SetDirections {
+                mask_input,
+                mask_output,
+            } => 2 * crate::cost::banks16(mask_input | mask_output),
+        }
+    }
+}
+
+impl<I2C: crate::RegisterBus, R: Regs16> crate::PortDriverResync for Driver16<I2C, R> {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        self.out = None;
+        self.get_out()?;
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl<I2C: crate::RegisterBus, R: Regs16> crate::PortDriverSnapshot for Driver16<I2C, R> {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        let config0 = self.i2c.read_reg(self.addr, self.regs.configuration[0])?;
+        let config1 = self.i2c.read_reg(self.addr, self.regs.configuration[1])?;
+        let config = ((config1 as u32) << 8) | config0 as u32;
+        Ok(crate::DriverState {
+            pins: 0xffff,
+            outputs: self.out.map(u32::from),
+            output_pins: Some(!config & 0xffff),
+            pull_ups: None,
+            pull_downs: None,
+            inputs: crate::PortDriver::get(self, 0xffff, 0)?,
+        })
+    }
+}
+
+#[cfg(any(feature = "mcp23016", feature = "pca9555", feature = "pcal6416a"))]
+impl<I2C: crate::RegisterBus, R: Regs16> crate::PortDriverRestoreDefaults for Driver16<I2C, R> {
+    fn restore_defaults(&mut self) -> Result<(), Self::Error> {
+        // inputs first, so the outputs stop driving before anything else changes
+        for port in 0..2 {
+            self.i2c
+                .write_reg(self.addr, self.regs.configuration[port], 0xff)?;
+        }
+        let output = self.regs.output_reset.unwrap_or(0xffff);
+        for port in 0..2 {
+            self.i2c.write_reg(
+                self.addr,
+                self.regs.output[port],
+                (output >> (8 * port)) as u8,
+            )?;
+            self.i2c
+                .write_reg(self.addr, self.regs.polarity_inversion[port], 0x00)?;
+        }
+        if let Some(pcal) = self.regs.pcal {
+            for port in 0..2 {
+                self.i2c
+                    .write_reg(self.addr, pcal.input_latch[port], 0x00)?;
+                self.i2c
+                    .write_reg(self.addr, pcal.pull_enable[port], 0x00)?;
+                self.i2c
+                    .write_reg(self.addr, pcal.pull_selection[port], 0xff)?;
+                self.i2c
+                    .write_reg(self.addr, pcal.interrupt_mask[port], 0xff)?;
+            }
+            self.i2c
+                .write_reg(self.addr, pcal.output_port_configuration, 0x00)?;
+        }
+        self.out = Some(output);
+        Ok(())
+    }
+}
+
+impl<I2C: crate::RegisterBus, R: Regs16> crate::PortDriverRefresh for Driver16<I2C, R> {
+    fn refresh(&mut self, mask: u32) -> Result<(), Self::Error> {
+        let out = self.get_out()?;
+        if mask & 0x00FF != 0 {
+            self.i2c
+                .write_reg(self.addr, self.regs.output[0], (out & 0xFF) as u8)?;
+        }
+        if mask & 0xFF00 != 0 {
+            self.i2c
+                .write_reg(self.addr, self.regs.output[1], (out >> 8) as u8)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I2C: crate::RegisterBus, R: Regs16> crate::PortDriverTotemPole for Driver16<I2C, R> {
+    fn set_directions(&mut self, mask_input: u32, mask_output: u32) -> Result<(), Self::Error> {
+        self.update_regs16(self.regs.configuration, mask_input, mask_output)
+    }
+}
+
+impl<I2C: crate::RegisterBus, R: Regs16> crate::PortDriverPolarity for Driver16<I2C, R> {
+    fn set_polarity(&mut self, mask: u32, inverted: bool) -> Result<(), Self::Error> {
+        let (mask_set, mask_clear) = match inverted {
+            false => (0, mask),
+            true => (mask, 0),
+        };
+        self.update_regs16(self.regs.polarity_inversion, mask_set, mask_clear)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<I2C: embedded_hal_async::i2c::I2c, R: Regs16> Driver16<I2C, R> {
+    async fn get_out_async(&mut self) -> Result<u16, I2C::Error> {
+        match self.out {
+            Some(out) => Ok(out),
+            None => {
+                let out_low =
+                    crate::bus::read_reg_async(&mut self.i2c, self.addr, self.regs.output[0])
+                        .await? as u16;
+                let out_high =
+                    crate::bus::read_reg_async(&mut self.i2c, self.addr, self.regs.output[1])
+                        .await? as u16;
+                let out = out_low | (out_high << 8);
+                self.out = Some(out);
+                Ok(out)
+            }
+        }
+    }
+
+    /// Async counterpart of `update_regs16()`.
+    async fn update_regs16_async(
+        &mut self,
+        regs: [u8; 2],
+        mask_set: u32,
+        mask_clear: u32,
+    ) -> Result<(), I2C::Error> {
+        let mask = mask_set | mask_clear;
+        for (port, reg) in regs.into_iter().enumerate() {
+            let shift = 8 * port;
+            if (mask >> shift) & 0xFF != 0 {
+                crate::bus::update_reg_async(
+                    &mut self.i2c,
+                    self.addr,
+                    reg,
+                    ((mask_set >> shift) & 0xFF) as u8,
+                    ((mask_clear >> shift) & 0xFF) as u8,
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<I2C: embedded_hal_async::i2c::I2c, R: Regs16> crate::AsyncPortDriver for Driver16<I2C, R> {
+    type Error = I2C::Error;
+
+    const PINS: u32 = 0xffff;
+
+    async fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let mut out = self.get_out_async().await?;
+        out |= mask_high as u16;
+        out &= !mask_low as u16;
+        self.out = Some(out);
+        // see the blocking `set()`
+        let mask = match self.dirty {
+            true => 0xFFFF,
+            false => mask_high | mask_low,
+        };
+        self.dirty = true;
+        if mask & 0x00FF != 0 {
+            crate::bus::write_reg_async(&mut self.i2c, self.addr, self.regs.output[0], out as u8)
+                .await?;
+        }
+        if mask & 0xFF00 != 0 {
+            crate::bus::write_reg_async(
+                &mut self.i2c,
+                self.addr,
+                self.regs.output[1],
+                (out >> 8) as u8,
+            )
+            .await?;
+        }
+        self.dirty = false;
+        Ok(())
+    }
+
+    async fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        let out = self.get_out_async().await?;
+        Ok(((out as u32) & mask_high) | (!(out as u32) & mask_low))
+    }
+
+    async fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        // the input state can't await the bus, so read what it needs up front
+        let raw = match self.input_state.read_mask(mask_high | mask_low) {
+            Some(mask) => {
+                let mut raw = 0;
+                for (port, reg) in self.regs.input.into_iter().enumerate() {
+                    if (mask >> (8 * port)) & 0xFF != 0 {
+                        let in_ = crate::bus::read_reg_async(&mut self.i2c, self.addr, reg).await?;
+                        raw |= (in_ as u32) << (8 * port);
+                    }
+                }
+                raw
+            }
+            None => 0,
+        };
+        let in_ = self
+            .input_state
+            .read(mask_high | mask_low, |_| Ok::<_, Self::Error>(raw))?;
+        Ok((in_ & mask_high) | (!in_ & mask_low))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<I2C: embedded_hal_async::i2c::I2c, R: Regs16> crate::AsyncPortDriverTotemPole
+    for Driver16<I2C, R>
+{
+    async fn set_directions(
+        &mut self,
+        mask_input: u32,
+        mask_output: u32,
+    ) -> Result<(), Self::Error> {
+        self.update_regs16_async(self.regs.configuration, mask_input, mask_output)
+            .await
+    }
+}
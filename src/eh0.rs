@@ -0,0 +1,92 @@
+//! Implementations of the `embedded-hal` 0.2 digital traits for [`Pin`], for drivers which were
+//! not ported to `embedded-hal` 1.0 yet.
+use crate::Pin;
+use embedded_hal_02::digital::v2 as hal_digital;
+
+impl<'a, MODE: crate::mode::HasInput, MUTEX, PD> hal_digital::InputPin for Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    type Error = crate::pin::PinError<PD::Error>;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Pin::is_high(self)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Pin::is_low(self)
+    }
+}
+
+impl<'a, MODE: crate::mode::HasOutput, MUTEX, PD> hal_digital::OutputPin for Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    type Error = crate::pin::PinError<PD::Error>;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Pin::set_low(self)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Pin::set_high(self)
+    }
+}
+
+impl<'a, MODE: crate::mode::HasOutput, MUTEX, PD> hal_digital::StatefulOutputPin
+    for Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        Pin::is_set_high(self)
+    }
+
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        Pin::is_set_low(self)
+    }
+}
+
+impl<'a, MODE: crate::mode::HasOutput, MUTEX, PD> hal_digital::ToggleableOutputPin
+    for Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    type Error = crate::pin::PinError<PD::Error>;
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        Pin::toggle(self)
+    }
+}
+
+#[cfg(all(test, feature = "pcf8574"))]
+mod tests {
+    use embedded_hal_02::digital::v2::{InputPin, OutputPin, ToggleableOutputPin};
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    fn blink<P: OutputPin + ToggleableOutputPin>(pin: &mut P) {
+        pin.set_high().ok();
+        pin.toggle().ok();
+    }
+
+    #[test]
+    fn pcf8574_eh0() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x21, vec![0b1111_1111]),
+            mock_i2c::Transaction::write(0x21, vec![0b1111_1110]),
+            mock_i2c::Transaction::read(0x21, vec![0b0000_0010]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), true, false, false);
+        let mut p = pcf.split();
+        blink(&mut p.p0);
+        assert!(InputPin::is_high(&p.p1).unwrap());
+
+        bus.done();
+    }
+}
@@ -0,0 +1,190 @@
+/// Change of the position for each transition from the old state (high two bits) to the new state
+/// (low two bits), with state `A << 1 | B`.  Transitions which skip a state are ignored.
+const TRANSITIONS: [i8; 16] = [0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1, -1, 0];
+
+/// Quadrature rotary encoder connected to two inputs of a port-expander.
+///
+/// Front panels often connect a rotary encoder, and maybe its push button, to a port-expander.
+/// [`poll()`](RotaryEncoder::poll) reads both channels, and the button, with a single call to
+/// [`read_multiple()`](crate::read_multiple) and counts the quadrature transitions since the
+/// previous poll.  The position is kept across polls.  Poll at least once per transition, e.g.
+/// whenever the `INT` line of the chip asserts, otherwise steps are missed.  A poll within the
+/// scope of [`cached_inputs_for()`](crate::cached_inputs_for) uses the snapshot of the inputs
+/// like any other read.
+///
+/// Channel `A` leading `B` counts up.  Most encoders go through four transitions from one detent
+/// to the next, `steps_per_detent` divides them so that the position counts detents instead.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pcf = port_expander::Pcf8574::new(i2c, false, false, false);
+/// # let p = pcf.split();
+/// use port_expander::RotaryEncoder;
+///
+/// // A and B on p0 and p1, the button connects p2 to GND
+/// let mut encoder = RotaryEncoder::new(p.p0, p.p1, 4).with_button(p.p2, true);
+/// loop {
+///     let delta = encoder.poll().unwrap();
+///     if delta != 0 {
+///         println!("position {}", encoder.position());
+///     }
+///     if encoder.is_pressed() {
+///         // ...
+///     }
+/// }
+/// ```
+pub struct RotaryEncoder<'a, MODE, MUTEX> {
+    a: crate::Pin<'a, MODE, MUTEX>,
+    b: crate::Pin<'a, MODE, MUTEX>,
+    button: Option<(crate::Pin<'a, MODE, MUTEX>, bool)>,
+    steps_per_detent: u8,
+    /// Last state of the channels, `None` before the first poll.
+    state: Option<u8>,
+    /// Transitions counted so far.
+    steps: i32,
+    pressed: bool,
+}
+
+impl<'a, MODE: crate::mode::HasInput, MUTEX, PD> RotaryEncoder<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Decode the encoder with channel `A` on pin `a` and channel `B` on pin `b`.
+    ///
+    /// # Panics
+    /// Both pins must belong to the same port-expander and `steps_per_detent` must not be 0.
+    pub fn new(
+        a: crate::Pin<'a, MODE, MUTEX>,
+        b: crate::Pin<'a, MODE, MUTEX>,
+        steps_per_detent: u8,
+    ) -> Self {
+        assert!(core::ptr::eq(a.port_driver(), b.port_driver()));
+        assert!(steps_per_detent != 0);
+        Self {
+            a,
+            b,
+            button: None,
+            steps_per_detent,
+            state: None,
+            steps: 0,
+            pressed: false,
+        }
+    }
+
+    /// Also read the push button of the encoder on `pin`, which is pressed when LOW if
+    /// `active_low` is set.
+    ///
+    /// # Panics
+    /// The button must belong to the same port-expander as the channels.
+    pub fn with_button(mut self, pin: crate::Pin<'a, MODE, MUTEX>, active_low: bool) -> Self {
+        assert!(core::ptr::eq(pin.port_driver(), self.a.port_driver()));
+        self.button = Some((pin, active_low));
+        self
+    }
+
+    /// Read the inputs and return by how many detents the position changed since the last poll.
+    ///
+    /// The first poll only records the initial state of the channels and returns 0.
+    pub fn poll(&mut self) -> Result<i32, PD::Error> {
+        let (a, b) = match &self.button {
+            Some((button, active_low)) => {
+                let [a, b, button] = crate::read_multiple([&self.a, &self.b, button])?;
+                self.pressed = button != *active_low;
+                (a, b)
+            }
+            None => {
+                let [a, b] = crate::read_multiple([&self.a, &self.b])?;
+                (a, b)
+            }
+        };
+
+        let state = (a as u8) << 1 | b as u8;
+        let position = self.position();
+        if let Some(old) = self.state {
+            self.steps += TRANSITIONS[(old << 2 | state) as usize] as i32;
+        }
+        self.state = Some(state);
+        Ok(self.position() - position)
+    }
+
+    /// Position in detents, as of the last poll.
+    pub fn position(&self) -> i32 {
+        self.steps.div_euclid(self.steps_per_detent as i32)
+    }
+
+    /// Set the position, e.g. to start over at 0.
+    pub fn set_position(&mut self, position: i32) {
+        self.steps = position * self.steps_per_detent as i32;
+    }
+
+    /// Whether the button was pressed at the last poll.
+    ///
+    /// Always `false` without a button, see [`with_button()`](Self::with_button).
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Release the pins of the channels and, if any, of the button.
+    #[allow(clippy::type_complexity)]
+    pub fn release(
+        self,
+    ) -> (
+        crate::Pin<'a, MODE, MUTEX>,
+        crate::Pin<'a, MODE, MUTEX>,
+        Option<crate::Pin<'a, MODE, MUTEX>>,
+    ) {
+        (self.a, self.b, self.button.map(|(pin, _)| pin))
+    }
+}
+
+#[cfg(all(test, feature = "pcf8574"))]
+mod tests {
+    use super::RotaryEncoder;
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pcf8574_encoder() {
+        // A on p0, B on p1, button on p2 (active low)
+        let states = [
+            0b0000_0100u8,
+            // one detent forward, A leading
+            0b0000_0101,
+            0b0000_0111,
+            0b0000_0110,
+            0b0000_0100,
+            // half a detent back, button pressed
+            0b0000_0010,
+            0b0000_0011,
+            // skipped state, ignored
+            0b0000_0000,
+        ];
+        let expectations = states.map(|s| mock_i2c::Transaction::read(0x20, vec![s]));
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), false, false, false);
+        let p = pcf.split();
+        let mut encoder = RotaryEncoder::new(p.p0, p.p1, 4).with_button(p.p2, true);
+
+        assert_eq!(encoder.poll().unwrap(), 0);
+        assert!(!encoder.is_pressed());
+        assert_eq!(encoder.poll().unwrap(), 0);
+        assert_eq!(encoder.poll().unwrap(), 0);
+        assert_eq!(encoder.poll().unwrap(), 0);
+        assert_eq!(encoder.poll().unwrap(), 1);
+        assert_eq!(encoder.position(), 1);
+
+        assert_eq!(encoder.poll().unwrap(), -1);
+        assert!(encoder.is_pressed());
+        assert_eq!(encoder.poll().unwrap(), 0);
+        assert_eq!(encoder.position(), 0);
+        assert_eq!(encoder.poll().unwrap(), 0);
+        assert_eq!(encoder.position(), 0);
+
+        encoder.set_position(10);
+        assert_eq!(encoder.position(), 10);
+
+        bus.done();
+    }
+}
@@ -0,0 +1,539 @@
+use embedded_hal::digital as hal_digital;
+
+/// Direction of an input change reported to a [`PinEvents`] callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Edge {
+    /// The input changed from LOW to HIGH.
+    Rising,
+    /// The input changed from HIGH to LOW.
+    Falling,
+}
+
+/// Error returned by [`PinEvents::on_change()`] when all `N` slots are in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EventsFull;
+
+/// Pin mask and the function to call when the pin changes, if any.
+type Callback = (u32, Option<fn(Edge)>);
+
+/// Input change of one pin, collected in an [`EventQueue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PinEvent {
+    /// Number of the pin, see [`Pin::pin_index()`](crate::Pin::pin_index).
+    pub pin: u8,
+    pub edge: Edge,
+}
+
+/// Fixed-capacity queue of up to `N` [`PinEvent`]s, see [`PinEvents::poll_into()`].
+///
+/// When the queue is full, further events are dropped and counted in
+/// [`dropped()`](EventQueue::dropped).
+#[derive(Debug)]
+pub struct EventQueue<const N: usize> {
+    events: [Option<PinEvent>; N],
+    head: usize,
+    len: usize,
+    dropped: u32,
+}
+
+impl<const N: usize> EventQueue<N> {
+    pub const fn new() -> Self {
+        Self {
+            events: [None; N],
+            head: 0,
+            len: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Remove the oldest event from the queue.
+    pub fn pop(&mut self) -> Option<PinEvent> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.events[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        event
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of events which were dropped because the queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    fn push(&mut self, event: PinEvent) {
+        if self.len == N {
+            self.dropped = self.dropped.saturating_add(1);
+            return;
+        }
+        self.events[(self.head + self.len) % N] = Some(event);
+        self.len += 1;
+    }
+}
+
+impl<const N: usize> Default for EventQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Callbacks for input changes of a port-expander, for firmware without an async executor.
+///
+/// Up to `N` callbacks are registered with [`on_change()`](PinEvents::on_change), each for one
+/// input pin of the same port-expander.  [`poll()`](PinEvents::poll) reads all of these pins in a
+/// single lock and calls the callbacks of the pins which changed since the last call.  Call it
+/// from the superloop, or call [`handle_interrupt()`](PinEvents::handle_interrupt) when the
+/// interrupt line of the chip is asserted.
+///
+/// Instead of callbacks, which can't capture any state, the changes can also be collected in an
+/// [`EventQueue`]: Register the pins with [`watch()`](PinEvents::watch) and read them with
+/// [`poll_into()`](PinEvents::poll_into), e.g. in an interrupt handler, and process the queue
+/// later in the superloop.
+///
+/// Changes are detected relative to the first read: the first call only records the input state
+/// and does not invoke any callback.  A pin which changes twice between two calls is not reported.
+/// The callbacks run after the port-expander was unlocked, so they can access its pins.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pcf = port_expander::Pcf8574::new(i2c, false, false, false);
+/// # let p = pcf.split();
+/// use port_expander::{Edge, PinEvents};
+///
+/// fn button(edge: Edge) {
+///     if edge == Edge::Falling {
+///         // button was pressed
+///     }
+/// }
+///
+/// let mut events: PinEvents<_, 4> = PinEvents::new(&p.p0);
+/// events.on_change(&p.p0, button).unwrap();
+/// loop {
+///     events.poll().unwrap();
+///     // ...
+/// }
+/// ```
+pub struct PinEvents<'a, MUTEX, const N: usize> {
+    port_driver: &'a MUTEX,
+    callbacks: [Option<Callback>; N],
+    last: Option<u32>,
+}
+
+impl<'a, MUTEX, PD, const N: usize> PinEvents<'a, MUTEX, N>
+where
+    PD: crate::PortDriver + crate::PortDriverInputState,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Create an empty callback registry for the port-expander of `pin`.
+    ///
+    /// `pin` can be any pin of the port-expander, it is only used to find the chip.
+    pub fn new<MODE>(pin: &crate::Pin<'a, MODE, MUTEX>) -> Self {
+        Self {
+            port_driver: pin.port_driver(),
+            callbacks: [None; N],
+            last: None,
+        }
+    }
+
+    /// Call `callback` whenever the input level of `pin` changes.
+    ///
+    /// Registering a second callback for the same pin replaces the first one.
+    ///
+    /// # Panics
+    /// Panics if `pin` belongs to a different port-expander than this registry.
+    pub fn on_change<MODE: crate::mode::HasInput>(
+        &mut self,
+        pin: &crate::Pin<'a, MODE, MUTEX>,
+        callback: fn(Edge),
+    ) -> Result<(), EventsFull> {
+        self.register(pin, Some(callback))
+    }
+
+    /// Report changes of `pin` to the [`EventQueue`] of [`poll_into()`](PinEvents::poll_into),
+    /// without a callback.
+    ///
+    /// This replaces a callback registered for the same pin.
+    ///
+    /// # Panics
+    /// Panics if `pin` belongs to a different port-expander than this registry.
+    pub fn watch<MODE: crate::mode::HasInput>(
+        &mut self,
+        pin: &crate::Pin<'a, MODE, MUTEX>,
+    ) -> Result<(), EventsFull> {
+        self.register(pin, None)
+    }
+
+    fn register<MODE>(
+        &mut self,
+        pin: &crate::Pin<'a, MODE, MUTEX>,
+        callback: Option<fn(Edge)>,
+    ) -> Result<(), EventsFull> {
+        assert!(
+            core::ptr::eq(pin.port_driver(), self.port_driver),
+            "pin belongs to a different port-expander"
+        );
+        let mask = pin.pin_mask();
+        let slot = match self
+            .callbacks
+            .iter()
+            .position(|c| matches!(c, Some((m, _)) if *m == mask))
+        {
+            Some(i) => i,
+            None => self
+                .callbacks
+                .iter()
+                .position(Option::is_none)
+                .ok_or(EventsFull)?,
+        };
+        self.callbacks[slot] = Some((mask, callback));
+        // the new pin has no previous state yet
+        self.last = None;
+        Ok(())
+    }
+
+    /// Remove the callback of `pin`, if any.
+    pub fn remove<MODE>(&mut self, pin: &crate::Pin<'a, MODE, MUTEX>) {
+        let mask = pin.pin_mask();
+        for c in self.callbacks.iter_mut() {
+            if matches!(c, Some((m, _)) if *m == mask) {
+                *c = None;
+            }
+        }
+    }
+
+    /// Read the registered pins and call the callbacks of those which changed.
+    ///
+    /// Returns the mask of the pins which changed.  Reads are answered from a snapshot while a
+    /// guard of [`cached_inputs_for()`](crate::cached_inputs_for) is alive.
+    pub fn poll(&mut self) -> Result<u32, PD::Error> {
+        let mask = self.mask();
+        let state = self.port_driver.lock(|drv| drv.get(mask, 0))?;
+        Ok(self.dispatch(mask, state, None::<&mut EventQueue<0>>))
+    }
+
+    /// Like [`poll()`](PinEvents::poll), but additionally adds an event for each changed pin to
+    /// `queue`.
+    pub fn poll_into<const M: usize>(
+        &mut self,
+        queue: &mut EventQueue<M>,
+    ) -> Result<u32, PD::Error> {
+        let mask = self.mask();
+        let state = self.port_driver.lock(|drv| drv.get(mask, 0))?;
+        Ok(self.dispatch(mask, state, Some(queue)))
+    }
+
+    /// Like [`poll()`](PinEvents::poll), but always reads the chip.
+    ///
+    /// Call this when the interrupt line of the port-expander is asserted.  A snapshot of
+    /// [`cached_inputs_for()`](crate::cached_inputs_for) is dropped first.  On most chips, reading
+    /// the inputs also clears the interrupt.
+    pub fn handle_interrupt(&mut self) -> Result<u32, PD::Error> {
+        let state = self.read_interrupt()?;
+        Ok(self.dispatch(self.mask(), state, None::<&mut EventQueue<0>>))
+    }
+
+    /// Like [`handle_interrupt()`](PinEvents::handle_interrupt), but additionally adds an event
+    /// for each changed pin to `queue`.
+    pub fn handle_interrupt_into<const M: usize>(
+        &mut self,
+        queue: &mut EventQueue<M>,
+    ) -> Result<u32, PD::Error> {
+        let state = self.read_interrupt()?;
+        Ok(self.dispatch(self.mask(), state, Some(queue)))
+    }
+
+    fn read_interrupt(&mut self) -> Result<u32, PD::Error> {
+        let mask = self.mask();
+        self.port_driver.lock(|drv| {
+            drv.input_state().invalidate();
+            drv.get(mask, 0)
+        })
+    }
+
+    fn mask(&self) -> u32 {
+        self.callbacks
+            .iter()
+            .flatten()
+            .fold(0, |mask, (m, _)| mask | m)
+    }
+
+    fn dispatch<const M: usize>(
+        &mut self,
+        mask: u32,
+        state: u32,
+        mut queue: Option<&mut EventQueue<M>>,
+    ) -> u32 {
+        let changed = match self.last.replace(state) {
+            Some(last) => (last ^ state) & mask,
+            None => 0,
+        };
+        for (m, callback) in self.callbacks.iter().flatten() {
+            if changed & m == 0 {
+                continue;
+            }
+            let edge = if state & m != 0 {
+                Edge::Rising
+            } else {
+                Edge::Falling
+            };
+            if let Some(queue) = queue.as_deref_mut() {
+                queue.push(PinEvent {
+                    pin: m.trailing_zeros() as u8,
+                    edge,
+                });
+            }
+            if let Some(callback) = callback {
+                callback(edge);
+            }
+        }
+        changed
+    }
+}
+
+impl<'a, MUTEX, PD, const N: usize> InterruptHandler for PinEvents<'a, MUTEX, N>
+where
+    PD: crate::PortDriver + crate::PortDriverInputState,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    type Error = PD::Error;
+
+    fn handle_interrupt(&mut self) -> Result<u32, Self::Error> {
+        PinEvents::handle_interrupt(self)
+    }
+}
+
+/// Something which services the interrupt of one port-expander, see [`MultiInterruptHandler`].
+pub trait InterruptHandler {
+    type Error;
+
+    /// Read the chip, which also clears its interrupt, and return the mask of pins which changed.
+    fn handle_interrupt(&mut self) -> Result<u32, Self::Error>;
+}
+
+/// Error type of [`MultiInterruptHandler::handle()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MultiInterruptError<E, P> {
+    /// A port driver reported an error.
+    Driver(E),
+    /// Reading the interrupt line failed.
+    Line(P),
+}
+
+/// Services `N` port-expanders whose interrupt outputs are wired together on one line.
+///
+/// The interrupt outputs of most port-expanders are open-drain and LOW while an interrupt is
+/// pending, so several chips can share one input of the microcontroller.  When the line is
+/// asserted, [`handle()`](MultiInterruptHandler::handle) services the chips in order and checks
+/// the line after each one.  Once it is released, the remaining chips had nothing pending and are
+/// not read at all.  Put the chips whose inputs change most often first.
+///
+/// The chips may use different drivers, but they must share one bus error type.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut int_pin = embedded_hal_mock::eh1::digital::Mock::new(&[]);
+/// # fn button(_: port_expander::Edge) {}
+/// use port_expander::{MultiInterruptHandler, PinEvents};
+///
+/// let mut pca0 = port_expander::Pca9555::new(i2c.clone(), false, false, false);
+/// let mut pca1 = port_expander::Pca9555::new(i2c.clone(), true, false, false);
+/// let (p0, p1) = (pca0.split(), pca1.split());
+///
+/// let mut events0: PinEvents<_, 4> = PinEvents::new(&p0.io0_0);
+/// events0.on_change(&p0.io0_0, button).unwrap();
+/// let mut events1: PinEvents<_, 4> = PinEvents::new(&p1.io0_0);
+/// events1.on_change(&p1.io1_3, button).unwrap();
+///
+/// let mut irq = MultiInterruptHandler::new([&mut events0, &mut events1]);
+/// // in the handler of the shared INT line:
+/// let changed = irq.handle(&mut int_pin).unwrap();
+/// ```
+pub struct MultiInterruptHandler<'a, E, const N: usize> {
+    handlers: [&'a mut dyn InterruptHandler<Error = E>; N],
+}
+
+impl<'a, E, const N: usize> MultiInterruptHandler<'a, E, N> {
+    pub fn new(handlers: [&'a mut dyn InterruptHandler<Error = E>; N]) -> Self {
+        Self { handlers }
+    }
+
+    /// Service the chips until the active-low interrupt line `int` is released.
+    ///
+    /// Returns the masks of the pins which changed, one for each chip in the order they were
+    /// passed to [`new()`](MultiInterruptHandler::new).  Chips which were not read report 0.
+    pub fn handle<INT: hal_digital::InputPin>(
+        &mut self,
+        int: &mut INT,
+    ) -> Result<[u32; N], MultiInterruptError<E, INT::Error>> {
+        let mut changed = [0; N];
+        for (handler, changed) in self.handlers.iter_mut().zip(changed.iter_mut()) {
+            if int.is_high().map_err(MultiInterruptError::Line)? {
+                break;
+            }
+            *changed = handler
+                .handle_interrupt()
+                .map_err(MultiInterruptError::Driver)?;
+        }
+        Ok(changed)
+    }
+
+    /// Service all chips, e.g. when the interrupt line can't be read back.
+    pub fn handle_all(&mut self) -> Result<[u32; N], E> {
+        let mut changed = [0; N];
+        for (handler, changed) in self.handlers.iter_mut().zip(changed.iter_mut()) {
+            *changed = handler.handle_interrupt()?;
+        }
+        Ok(changed)
+    }
+}
+
+#[cfg(all(test, any(feature = "pca9555", feature = "pcf8574")))]
+mod tests {
+    #[cfg(feature = "pca9555")]
+    use super::MultiInterruptHandler;
+    use super::{Edge, PinEvents};
+    #[cfg(feature = "pcf8574")]
+    use super::{EventQueue, PinEvent};
+    #[cfg(feature = "pca9555")]
+    use embedded_hal_mock::eh1::digital as mock_digital;
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+    #[cfg(feature = "pca9555")]
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[cfg(feature = "pca9555")]
+    static RISING: AtomicU32 = AtomicU32::new(0);
+    #[cfg(feature = "pca9555")]
+    static FALLING: AtomicU32 = AtomicU32::new(0);
+
+    #[cfg(feature = "pca9555")]
+    fn count(edge: Edge) {
+        match edge {
+            Edge::Rising => RISING.fetch_add(1, Ordering::Relaxed),
+            Edge::Falling => FALLING.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn pca9555_events() {
+        let expectations = [
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0b00000001]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0b10000000]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0b00000000]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0b10000000]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0b00000010]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0b00000000]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = crate::Pca9555::new(bus.clone(), false, false, false);
+        let p = pca.split();
+
+        let mut events: PinEvents<_, 2> = PinEvents::new(&p.io0_0);
+        events.on_change(&p.io0_0, count).unwrap();
+        events.on_change(&p.io1_7, count).unwrap();
+        assert_eq!(events.on_change(&p.io0_1, count), Err(super::EventsFull));
+
+        assert_eq!(events.poll().unwrap(), 0);
+        assert_eq!(events.poll().unwrap(), 0x0001);
+        assert_eq!(FALLING.load(Ordering::Relaxed), 1);
+        assert_eq!(events.handle_interrupt().unwrap(), 0x8000);
+        assert_eq!(FALLING.load(Ordering::Relaxed), 2);
+        assert_eq!(RISING.load(Ordering::Relaxed), 0);
+
+        bus.done();
+    }
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn pca9555_multi_interrupt() {
+        let expectations = [
+            // first interrupt, only the first chip had something pending
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0b00000001]),
+            // second interrupt, pending on both chips
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0b00000000]),
+            mock_i2c::Transaction::write_read(0x21, vec![0x01], vec![0b00001000]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+        let int_expectations = [
+            mock_digital::Transaction::get(mock_digital::State::Low),
+            mock_digital::Transaction::get(mock_digital::State::High),
+            mock_digital::Transaction::get(mock_digital::State::Low),
+            mock_digital::Transaction::get(mock_digital::State::Low),
+        ];
+        let mut int = mock_digital::Mock::new(&int_expectations);
+
+        let mut pca0 = crate::Pca9555::new(bus.clone(), false, false, false);
+        let mut pca1 = crate::Pca9555::new(bus.clone(), true, false, false);
+        let (p0, p1) = (pca0.split(), pca1.split());
+
+        fn ignore(_: Edge) {}
+        let mut events0: PinEvents<_, 1> = PinEvents::new(&p0.io0_0);
+        events0.on_change(&p0.io0_0, ignore).unwrap();
+        let mut events1: PinEvents<_, 1> = PinEvents::new(&p1.io0_0);
+        events1.on_change(&p1.io1_3, ignore).unwrap();
+
+        let mut irq = MultiInterruptHandler::new([&mut events0, &mut events1]);
+        assert_eq!(irq.handle(&mut int).unwrap(), [0, 0]);
+        assert_eq!(irq.handle(&mut int).unwrap(), [0x0001, 0]);
+
+        bus.done();
+        int.done();
+    }
+
+    #[cfg(feature = "pcf8574")]
+    #[test]
+    fn pcf8574_event_queue() {
+        let expectations = [
+            mock_i2c::Transaction::read(0x21, vec![0b0000_0011]),
+            mock_i2c::Transaction::read(0x21, vec![0b0000_0000]),
+            mock_i2c::Transaction::read(0x21, vec![0b0000_0010]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), true, false, false);
+        let p = pcf.split();
+
+        let mut events: PinEvents<_, 2> = PinEvents::new(&p.p0);
+        events.watch(&p.p0).unwrap();
+        events.watch(&p.p1).unwrap();
+        let mut queue: EventQueue<2> = EventQueue::new();
+
+        assert_eq!(events.poll_into(&mut queue).unwrap(), 0);
+        assert_eq!(events.poll_into(&mut queue).unwrap(), 0b11);
+        assert_eq!(events.handle_interrupt_into(&mut queue).unwrap(), 0b10);
+        assert_eq!(queue.dropped(), 1);
+        assert_eq!(
+            queue.pop(),
+            Some(PinEvent {
+                pin: 0,
+                edge: Edge::Falling
+            })
+        );
+        assert_eq!(
+            queue.pop(),
+            Some(PinEvent {
+                pin: 1,
+                edge: Edge::Falling
+            })
+        );
+        assert_eq!(queue.pop(), None);
+
+        bus.done();
+    }
+}
@@ -0,0 +1,156 @@
+/// Types which describe the pins of a port-expander as a set of flags.
+///
+/// Implementing this trait for a `bitflags!` struct (or any other bitfield type) describing the
+/// port layout of a board allows reading and writing the pins with
+/// [`write_flags()`] and [`read_flags()`] without juggling raw masks.  Bit `n` corresponds to the
+/// `n`-th pin of the port-expander, in the same order as the fields of its `Parts`.
+///
+/// ## Example
+/// ```
+/// bitflags::bitflags! {
+///     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///     struct Board: u16 {
+///         const RELAY1 = 1 << 0;
+///         const RELAY2 = 1 << 1;
+///         const LED1 = 1 << 8;
+///         const LED2 = 1 << 9;
+///     }
+/// }
+///
+/// impl port_expander::PortFlags for Board {
+///     fn port_mask() -> u32 {
+///         Self::all().bits() as u32
+///     }
+///
+///     fn to_port_bits(self) -> u32 {
+///         self.bits() as u32
+///     }
+///
+///     fn from_port_bits(bits: u32) -> Self {
+///         Self::from_bits_truncate(bits as u16)
+///     }
+/// }
+/// ```
+pub trait PortFlags: Copy {
+    /// Mask of all pins which are described by this type.
+    ///
+    /// Pins outside of this mask are never touched by [`write_flags()`].
+    fn port_mask() -> u32;
+
+    /// Pins which are set in `self`.
+    fn to_port_bits(self) -> u32;
+
+    /// Convert the pin states in `bits` into flags.  Pins outside of
+    /// [`port_mask()`](PortFlags::port_mask) should be ignored.
+    fn from_port_bits(bits: u32) -> Self;
+}
+
+/// Write all pins described by `F` together.
+///
+/// Pins contained in `flags` are set HIGH, all other pins in [`PortFlags::port_mask()`] are set
+/// LOW.  `pin` can be any pin of the port-expander, it is only used to find the chip.  Like
+/// [`write_multiple()`](crate::write_multiple), all pins are written under a single lock with one
+/// bus transaction per port register, so on 16-bit chips pins of both ports take two.
+///
+/// Note that this also writes the output state of pins which are currently inputs or which are
+/// owned by other code.  `F` should therefore only describe pins which are used as outputs.
+///
+/// ## Example
+/// ```no_run
+/// # bitflags::bitflags! {
+/// #     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// #     struct Board: u16 {
+/// #         const RELAY1 = 1 << 0;
+/// #         const LED2 = 1 << 9;
+/// #     }
+/// # }
+/// # impl port_expander::PortFlags for Board {
+/// #     fn port_mask() -> u32 { Self::all().bits() as u32 }
+/// #     fn to_port_bits(self) -> u32 { self.bits() as u32 }
+/// #     fn from_port_bits(bits: u32) -> Self { Self::from_bits_truncate(bits as u16) }
+/// # }
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pca = port_expander::Pca9555::new(i2c, false, false, false);
+/// # let p = pca.split();
+/// port_expander::write_flags(&p.io0_0, Board::RELAY1 | Board::LED2).unwrap();
+/// ```
+pub fn write_flags<F, PD, MUTEX, MODE>(
+    pin: &crate::Pin<'_, MODE, MUTEX>,
+    flags: F,
+) -> Result<(), PD::Error>
+where
+    F: PortFlags,
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    let mask = F::port_mask();
+    let mask_high = flags.to_port_bits() & mask;
+    let mask_low = !mask_high & mask;
+    pin.port_driver().lock(|drv| drv.set(mask_high, mask_low))
+}
+
+/// Read all pins described by `F` together.
+///
+/// The returned flags contain all pins in [`PortFlags::port_mask()`] which are HIGH.  `pin` can be
+/// any pin of the port-expander, it is only used to find the chip.  Like
+/// [`read_multiple()`](crate::read_multiple), all pins are read under a single lock with one bus
+/// transaction per port register, so on 16-bit chips pins of both ports take two.
+pub fn read_flags<F, PD, MUTEX, MODE>(pin: &crate::Pin<'_, MODE, MUTEX>) -> Result<F, PD::Error>
+where
+    F: PortFlags,
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    let bits = pin.port_driver().lock(|drv| drv.get(F::port_mask(), 0))?;
+    Ok(F::from_port_bits(bits))
+}
+
+#[cfg(all(test, feature = "pca9555"))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Board(u16);
+
+    impl Board {
+        const RELAY1: Self = Self(1 << 0);
+        const LED2: Self = Self(1 << 9);
+    }
+
+    impl super::PortFlags for Board {
+        fn port_mask() -> u32 {
+            0b0000_0011_0000_0011
+        }
+
+        fn to_port_bits(self) -> u32 {
+            self.0 as u32
+        }
+
+        fn from_port_bits(bits: u32) -> Self {
+            Self(bits as u16 & 0b0000_0011_0000_0011)
+        }
+    }
+
+    #[test]
+    fn pca9555_flags() {
+        let expectations = [
+            // write_flags
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfd]),
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0xfe]),
+            // read_flags
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xf1]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0x02]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = crate::Pca9555::new(bus.clone(), false, false, false);
+        let pca_pins = pca.split();
+
+        super::write_flags(&pca_pins.io0_0, Board(Board::RELAY1.0 | Board::LED2.0)).unwrap();
+
+        let flags: Board = super::read_flags(&pca_pins.io0_0).unwrap();
+        assert_eq!(flags, Board(Board::RELAY1.0 | Board::LED2.0));
+
+        bus.done();
+    }
+}
@@ -0,0 +1,249 @@
+//! HD44780 character LCDs on a `PCF8574` I2C backpack.
+//!
+//! The common I2C backpacks for HD44780 LCDs drive the display in its 4-bit mode from a
+//! [`PCF8574`](crate::Pcf8574), with this pinout:
+//!
+//! | Pin | `P0` | `P1` | `P2` | `P3`      | `P4` | `P5` | `P6` | `P7` |
+//! |-----|------|------|------|-----------|------|------|------|------|
+//! | LCD | RS   | RW   | E    | backlight | D4   | D5   | D6   | D7   |
+//!
+//! [`Hd44780`] takes the whole [`pcf8574::Driver`](crate::dev::pcf8574::Driver) of the backpack
+//! and sends each byte as two nibbles with their enable pulses in a single I2C transfer of four
+//! port states.  The display is only written, RW is always LOW.
+//!
+//! Only available with the `hd44780` feature.
+//!
+//! ## Example
+//! ```no_run
+//! # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+//! # let mut delay = embedded_hal_mock::eh1::delay::NoopDelay::new();
+//! use port_expander::dev::pcf8574;
+//! use port_expander::hd44780::Hd44780;
+//!
+//! let mut lcd = Hd44780::new(pcf8574::Driver::new(i2c, false, true, true, true));
+//! lcd.init(&mut delay).unwrap();
+//! lcd.write_str("Hello").unwrap();
+//! lcd.set_cursor(0, 1).unwrap();
+//! lcd.write_str("World").unwrap();
+//! ```
+use crate::dev::pcf8574::Driver;
+use embedded_hal::delay::DelayNs;
+
+const RS: u8 = 0x01;
+const E: u8 = 0x04;
+const BACKLIGHT: u8 = 0x08;
+
+/// DDRAM addresses of the first column of each row.
+const ROW_OFFSETS: [u8; 4] = [0x00, 0x40, 0x14, 0x54];
+/// Number of DDRAM addresses of each line in the two-line mode.
+const ROW_LENGTH: u8 = 40;
+
+/// Error of [`Hd44780::set_cursor()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CursorError<E> {
+    /// The I2C bus reported an error.
+    Bus(E),
+    /// The position is outside of the display memory.  Nothing was written.
+    OutOfRange { col: u8, row: u8 },
+}
+
+impl<E> From<E> for CursorError<E> {
+    fn from(value: E) -> Self {
+        Self::Bus(value)
+    }
+}
+
+/// An HD44780 character LCD on a `PCF8574` backpack.
+///
+/// See the [module documentation](self) for details.
+pub struct Hd44780<I2C> {
+    driver: Driver<I2C>,
+    backlight: u8,
+}
+
+impl<I2C: crate::I2cBus> Hd44780<I2C> {
+    /// Take the driver of the backpack's port-expander.
+    ///
+    /// This does not touch the hardware, call [`init()`](Hd44780::init) before using the
+    /// display.  The backlight is switched on with the first write.
+    pub fn new(driver: Driver<I2C>) -> Self {
+        Self {
+            driver,
+            backlight: BACKLIGHT,
+        }
+    }
+
+    /// Release the driver of the port-expander again.
+    pub fn release(self) -> Driver<I2C> {
+        self.driver
+    }
+
+    /// Switch the display into the 4-bit mode and set it up for two lines with 5x8 dots.
+    ///
+    /// Afterwards, the display is on and cleared, without a cursor.  This works no matter which
+    /// mode the display was in before, so it can be called again to recover from glitches.
+    pub fn init<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), I2C::BusError> {
+        // power-on time of the display
+        delay.delay_ms(50);
+        // three times 8-bit mode, then 4-bit mode, as in figure 24 of the datasheet
+        self.write_nibble(0x3, 0)?;
+        delay.delay_us(4500);
+        self.write_nibble(0x3, 0)?;
+        delay.delay_us(150);
+        self.write_nibble(0x3, 0)?;
+        self.write_nibble(0x2, 0)?;
+
+        // function set: 4-bit, two lines, 5x8 dots
+        self.command(0x28)?;
+        // display on, no cursor, no blinking
+        self.command(0x0c)?;
+        self.clear(delay)?;
+        // entry mode: move the cursor to the right
+        self.command(0x06)
+    }
+
+    /// Send the instruction `cmd` to the display.
+    ///
+    /// Most instructions finish within the duration of the next transfer.  Clearing the display
+    /// and returning home take longer, use [`clear()`](Hd44780::clear) and
+    /// [`home()`](Hd44780::home) for those.
+    pub fn command(&mut self, cmd: u8) -> Result<(), I2C::BusError> {
+        self.write_byte(cmd, 0)
+    }
+
+    /// Write the byte `data` at the cursor position.
+    pub fn write_data(&mut self, data: u8) -> Result<(), I2C::BusError> {
+        self.write_byte(data, RS)
+    }
+
+    /// Write the bytes of `s` at the cursor position.
+    ///
+    /// The display has its own character set, which matches ASCII for most printable characters.
+    pub fn write_str(&mut self, s: &str) -> Result<(), I2C::BusError> {
+        s.bytes().try_for_each(|b| self.write_data(b))
+    }
+
+    /// Clear the display and move the cursor to the first column of the first row.
+    pub fn clear<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), I2C::BusError> {
+        self.command(0x01)?;
+        delay.delay_us(2000);
+        Ok(())
+    }
+
+    /// Move the cursor to the first column of the first row and undo any display shift.
+    pub fn home<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), I2C::BusError> {
+        self.command(0x02)?;
+        delay.delay_us(2000);
+        Ok(())
+    }
+
+    /// Move the cursor to column `col` of row `row`, counting from 0.
+    ///
+    /// `row` must be in the range `0..=3`.  The cursor has to stay within the display memory of
+    /// the row, so `col` must be below 40 in rows 0 and 1 and below 20 in rows 2 and 3.  Otherwise
+    /// [`CursorError::OutOfRange`] is returned and nothing is written.
+    pub fn set_cursor(&mut self, col: u8, row: u8) -> Result<(), CursorError<I2C::BusError>> {
+        let offset = *ROW_OFFSETS
+            .get(usize::from(row))
+            .ok_or(CursorError::OutOfRange { col, row })?;
+        // rows 2 and 3 continue rows 0 and 1 of a 40 character line
+        if col >= ROW_LENGTH - (offset & 0x3f) {
+            return Err(CursorError::OutOfRange { col, row });
+        }
+        self.command(0x80 | (offset + col))?;
+        Ok(())
+    }
+
+    /// Switch the backlight on or off.
+    pub fn set_backlight(&mut self, on: bool) -> Result<(), I2C::BusError> {
+        self.backlight = if on { BACKLIGHT } else { 0x00 };
+        self.driver.write_sequence(&[self.backlight])
+    }
+
+    fn write_nibble(&mut self, nibble: u8, rs: u8) -> Result<(), I2C::BusError> {
+        let state = (nibble << 4) | self.backlight | rs;
+        self.driver.write_sequence(&[state | E, state])
+    }
+
+    fn write_byte(&mut self, byte: u8, rs: u8) -> Result<(), I2C::BusError> {
+        let high = (byte & 0xf0) | self.backlight | rs;
+        let low = (byte << 4) | self.backlight | rs;
+        self.driver.write_sequence(&[high | E, high, low | E, low])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn hd44780_pcf8574() {
+        let expectations = [
+            // init
+            mock_i2c::Transaction::write(0x27, vec![0x3c, 0x38]),
+            mock_i2c::Transaction::write(0x27, vec![0x3c, 0x38]),
+            mock_i2c::Transaction::write(0x27, vec![0x3c, 0x38]),
+            mock_i2c::Transaction::write(0x27, vec![0x2c, 0x28]),
+            mock_i2c::Transaction::write(0x27, vec![0x2c, 0x28, 0x8c, 0x88]),
+            mock_i2c::Transaction::write(0x27, vec![0x0c, 0x08, 0xcc, 0xc8]),
+            mock_i2c::Transaction::write(0x27, vec![0x0c, 0x08, 0x1c, 0x18]),
+            mock_i2c::Transaction::write(0x27, vec![0x0c, 0x08, 0x6c, 0x68]),
+            // 'A' in the second row
+            mock_i2c::Transaction::write(0x27, vec![0xcc, 0xc8, 0x0c, 0x08]),
+            mock_i2c::Transaction::write(0x27, vec![0x4d, 0x49, 0x1d, 0x19]),
+            // backlight off
+            mock_i2c::Transaction::write(0x27, vec![0x00]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let driver = crate::dev::pcf8574::Driver::new(bus.clone(), false, true, true, true);
+        let mut lcd = super::Hd44780::new(driver);
+        lcd.init(&mut NoopDelay::new()).unwrap();
+        lcd.set_cursor(0, 1).unwrap();
+        lcd.write_str("A").unwrap();
+        lcd.set_backlight(false).unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    fn hd44780_set_cursor_out_of_range() {
+        use super::CursorError;
+
+        let expectations = [
+            // last columns of the rows
+            mock_i2c::Transaction::write(0x27, vec![0xac, 0xa8, 0x7c, 0x78]),
+            mock_i2c::Transaction::write(0x27, vec![0xec, 0xe8, 0x7c, 0x78]),
+            mock_i2c::Transaction::write(0x27, vec![0xac, 0xa8, 0x7c, 0x78]),
+            mock_i2c::Transaction::write(0x27, vec![0xec, 0xe8, 0x7c, 0x78]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let driver = crate::dev::pcf8574::Driver::new(bus.clone(), false, true, true, true);
+        let mut lcd = super::Hd44780::new(driver);
+        lcd.set_cursor(39, 0).unwrap();
+        lcd.set_cursor(39, 1).unwrap();
+        lcd.set_cursor(19, 2).unwrap();
+        lcd.set_cursor(19, 3).unwrap();
+        assert_eq!(
+            lcd.set_cursor(40, 0),
+            Err(CursorError::OutOfRange { col: 40, row: 0 })
+        );
+        assert_eq!(
+            lcd.set_cursor(20, 3),
+            Err(CursorError::OutOfRange { col: 20, row: 3 })
+        );
+        assert_eq!(
+            lcd.set_cursor(255, 1),
+            Err(CursorError::OutOfRange { col: 255, row: 1 })
+        );
+        assert_eq!(
+            lcd.set_cursor(0, 4),
+            Err(CursorError::OutOfRange { col: 0, row: 4 })
+        );
+
+        bus.done();
+    }
+}
@@ -0,0 +1,168 @@
+use embedded_hal::delay::DelayNs;
+
+/// Steps of the blink pattern of [`identify()`]: output state and duration in milliseconds.
+///
+/// Two short flashes per second stand out from the steady or slowly blinking status LEDs found
+/// on most units.
+const PATTERN: [(bool, u32); 4] = [(true, 100), (false, 100), (true, 100), (false, 700)];
+
+/// Blink outputs in a distinctive pattern to physically locate a unit, e.g. in a rack.
+///
+/// All `pins` flash twice per second for `duration_ms` milliseconds (rounded up to whole
+/// seconds), in unison.  Pins may belong to different port-expanders, all pins of the same chip
+/// are switched in a single bus transaction.  Pins which are currently inputs are made outputs
+/// for the duration of the pattern.
+///
+/// Afterwards, the exact previous state is restored: Former inputs become inputs again before
+/// the output states of all pins are written back, so they never drive their old latched state.
+/// The output states are taken from the drivers' caches, which usually does not need any bus
+/// transaction.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut delay = embedded_hal_mock::eh1::delay::NoopDelay::new();
+/// # let mut pcf = port_expander::Pcf8574::new(i2c.clone(), false, false, false);
+/// # let mut pca = port_expander::Pca9555::new(i2c, false, false, false);
+/// # let pcf_pins = pcf.split();
+/// # let pca_pins = pca.split();
+/// let mut leds = [
+///     pcf_pins.p0.into_any(),
+///     pca_pins.io0_0.into_output().unwrap().into_any(),
+/// ];
+/// port_expander::identify(&mut leds, 10_000, &mut delay).unwrap();
+/// ```
+pub fn identify<D: DelayNs, const N: usize>(
+    pins: &mut [crate::AnyPin<'_>; N],
+    duration_ms: u32,
+    delay: &mut D,
+) -> Result<(), crate::AnyPinError> {
+    let was_input: fn(&crate::AnyPin<'_>) -> bool =
+        |p| p.direction() == Some(crate::Direction::Input);
+
+    let mut states = [false; N];
+    for (i, mask) in chips(pins, |_| true) {
+        let high = pins[i].is_set_port(mask, 0)?;
+        for (pin, state) in pins.iter().zip(states.iter_mut()) {
+            if pin.same_chip(&pins[i]) {
+                *state = high & pin.pin_mask() != 0;
+            }
+        }
+    }
+
+    let cycles = duration_ms.div_ceil(1000).max(1);
+    for cycle in 0..cycles {
+        for (step, (on, duration_ms)) in PATTERN.iter().enumerate() {
+            for (i, mask) in chips(pins, |_| true) {
+                match on {
+                    true => pins[i].set_port(mask, 0)?,
+                    false => pins[i].set_port(0, mask)?,
+                }
+            }
+            if cycle == 0 && step == 0 {
+                for (i, mask) in chips(pins, was_input) {
+                    pins[i].set_port_direction(mask, crate::Direction::Output, true)?;
+                }
+            }
+            delay.delay_ms(*duration_ms);
+        }
+    }
+
+    for (i, mask) in chips(pins, was_input) {
+        pins[i].set_port_direction(mask, crate::Direction::Input, false)?;
+    }
+    for (i, _) in chips(pins, |_| true) {
+        let mut mask_high = 0x00;
+        let mut mask_low = 0x00;
+        for (pin, state) in pins.iter().zip(states.iter()) {
+            if !pin.same_chip(&pins[i]) {
+                continue;
+            }
+            if *state {
+                mask_high |= pin.pin_mask();
+            } else {
+                mask_low |= pin.pin_mask();
+            }
+        }
+        pins[i].set_port(mask_high, mask_low)?;
+    }
+
+    Ok(())
+}
+
+/// Group the pins selected by `select` by their port-expander.
+///
+/// Yields the index of the first selected pin of each chip and the mask of all selected pins of
+/// this chip.
+fn chips<'p>(
+    pins: &'p [crate::AnyPin<'p>],
+    select: fn(&crate::AnyPin<'_>) -> bool,
+) -> impl Iterator<Item = (usize, u32)> + 'p {
+    (0..pins.len())
+        .filter(move |&i| select(&pins[i]))
+        .filter(move |&i| !pins[..i].iter().any(|p| select(p) && p.same_chip(&pins[i])))
+        .map(move |i| {
+            let mask = pins[i..]
+                .iter()
+                .filter(|p| select(p) && p.same_chip(&pins[i]))
+                .fold(0x00, |mask, p| mask | p.pin_mask());
+            (i, mask)
+        })
+}
+
+#[cfg(all(test, feature = "pca9536", feature = "pcf8574"))]
+mod tests {
+    use embedded_hal_mock::eh1::delay as mock_delay;
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn identify_two_chips() {
+        let expectations = [
+            // pca9536 io0: output LOW
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xfe]),
+            mock_i2c::Transaction::write_read(0x41, vec![0x03], vec![0xff]),
+            mock_i2c::Transaction::write(0x41, vec![0x03, 0xfe]),
+            // on, pca9536 io1 becomes an output
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xff]),
+            mock_i2c::Transaction::write(0x21, vec![0xff]),
+            mock_i2c::Transaction::write_read(0x41, vec![0x03], vec![0xfe]),
+            mock_i2c::Transaction::write(0x41, vec![0x03, 0xfc]),
+            // off, on, off
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xfc]),
+            mock_i2c::Transaction::write(0x21, vec![0xfb]),
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xff]),
+            mock_i2c::Transaction::write(0x21, vec![0xff]),
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xfc]),
+            mock_i2c::Transaction::write(0x21, vec![0xfb]),
+            // restore, io1 becomes an input before its latch is written
+            mock_i2c::Transaction::write_read(0x41, vec![0x03], vec![0xfc]),
+            mock_i2c::Transaction::write(0x41, vec![0x03, 0xfe]),
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xfe]),
+            mock_i2c::Transaction::write(0x21, vec![0xff]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+        let delay_expectations = [
+            mock_delay::Transaction::blocking_delay_ms(100),
+            mock_delay::Transaction::blocking_delay_ms(100),
+            mock_delay::Transaction::blocking_delay_ms(100),
+            mock_delay::Transaction::blocking_delay_ms(700),
+        ];
+        let mut delay = mock_delay::CheckedDelay::new(&delay_expectations);
+
+        let mut pca = crate::Pca9536::new(bus.clone());
+        let pca_pins = pca.split();
+        let mut pcf = crate::Pcf8574::new(bus.clone(), true, false, false);
+        let pcf_pins = pcf.split();
+
+        let mut pins = [
+            pca_pins.io0.into_output().unwrap().into_any(),
+            pcf_pins.p2.into_any(),
+            pca_pins.io1.into_any(),
+        ];
+        crate::identify(&mut pins, 800, &mut delay).unwrap();
+        assert_eq!(pins[2].direction(), Some(crate::Direction::Input));
+
+        bus.done();
+        delay.done();
+    }
+}
@@ -0,0 +1,53 @@
+/// Error type of the index-based device methods like `write_pins()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PinIndexError<E> {
+    /// The port driver reported an error.
+    Driver(E),
+    /// The index is not a pin of the device.  Nothing was written.
+    OutOfRange(u8),
+}
+
+impl<E> From<E> for PinIndexError<E> {
+    fn from(value: E) -> Self {
+        Self::Driver(value)
+    }
+}
+
+cfg_expanders! {
+    /// Port mask of the pin with index `index`, where the first of `count` pins is bit `offset`.
+    fn pin_mask<E>(index: u8, count: u8, offset: u8) -> Result<u32, PinIndexError<E>> {
+        if index >= count {
+            return Err(PinIndexError::OutOfRange(index));
+        }
+        Ok(1 << (index + offset))
+    }
+
+    /// Masks of the pins which should be set HIGH and LOW.
+    pub(crate) fn write_masks<E>(
+        pins: &[(u8, bool)],
+        count: u8,
+        offset: u8,
+    ) -> Result<(u32, u32), PinIndexError<E>> {
+        let mut mask_high = 0x00000000;
+        let mut mask_low = 0x00000000;
+        for &(index, state) in pins {
+            let mask = pin_mask(index, count, offset)?;
+            if state {
+                mask_high |= mask;
+                mask_low &= !mask;
+            } else {
+                mask_low |= mask;
+                mask_high &= !mask;
+            }
+        }
+        Ok((mask_high, mask_low))
+    }
+
+    /// Mask of all pins in `pins`.
+    pub(crate) fn mask<E>(pins: &[u8], count: u8, offset: u8) -> Result<u32, PinIndexError<E>> {
+        pins.iter().try_fold(0x00000000, |mask, &index| {
+            Ok(mask | pin_mask(index, count, offset)?)
+        })
+    }
+}
@@ -0,0 +1,49 @@
+/// Condition under which an input raises an interrupt, see
+/// [`Pin::set_interrupt()`](crate::Pin::set_interrupt).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InterruptMode {
+    /// The input does not raise interrupts.  This is the reset state of most chips.
+    Disabled,
+    /// Whenever the input changes, on both edges.
+    OnChange,
+    /// As long as the input is HIGH.
+    WhileHigh,
+    /// As long as the input is LOW.
+    WhileLow,
+}
+
+/// Cause of a pending interrupt of a port-expander, see [`irq_state()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct IrqState {
+    /// Pins which caused the interrupt.
+    pub source: u32,
+    /// Input state of all pins at the moment the interrupt occurred.
+    pub captured: u32,
+}
+
+/// Read which pins caused the pending interrupt and the input state captured with it.
+///
+/// `pin` can be any pin of the port-expander, it is only used to find the chip.  Call this when
+/// the interrupt line of the chip is asserted.  On most chips, reading the captured inputs also
+/// clears the interrupt.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut mcp = port_expander::Mcp23x17::new_mcp23017(i2c, false, false, false);
+/// # let p = mcp.split();
+/// let irq = port_expander::irq_state(&p.gpa0).unwrap();
+/// if irq.source & p.gpa0.pin_mask() != 0 {
+///     let level = irq.captured & p.gpa0.pin_mask() != 0;
+///     // ...
+/// }
+/// ```
+pub fn irq_state<PD, MUTEX, MODE>(pin: &crate::Pin<'_, MODE, MUTEX>) -> Result<IrqState, PD::Error>
+where
+    PD: crate::PortDriver + crate::PortDriverIrqState,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    pin.port_driver().lock(|drv| drv.irq_state())
+}
@@ -23,22 +23,72 @@
 //! ## Accessing multiple pins at the same time
 //! Sometimes timing constraints mandate that multiple pin accesses (reading or writing) happen at
 //! the same time.  The [`write_multiple()`] and [`read_multiple()`] methods are designed for doing
-//! this.
+//! this.  If the direction of pins needs to change as well, a [`Transaction`] can be used to
+//! combine all changes into as few bus transactions as possible.  For loops which read many
+//! pins of the same chip one after the other, [`cached_inputs_for()`] answers all reads within a
+//! scope from a single snapshot.
+//!
+//! If the pins of a board are described by a `bitflags!` struct (or any other bitfield type)
+//! implementing [`PortFlags`], [`write_flags()`] and [`read_flags()`] access all of them at once.
+//!
+//! ## Bounded latency
+//! Each access to a pin needs one or a few bus transactions, depending on the device and on what
+//! the driver has cached.  A plain pin access never waits, and it only retries failed
+//! transactions if the bus is wrapped in a [`RetryBus`], so its latency is bounded by the time of
+//! these transactions and of the configured retries.  Waiting is left to the explicit
+//! `wait_for_*()` methods and to the few operations which document a delay, like a timed RESET
+//! pulse.  Real-time code can query the number of transactions before an access with
+//! [`cost_of()`] and budget bus time accordingly.
 //!
 //! ## Supported Devices
 //! The following list is what `port-expander` currently supports.  If you needs support for an
 //! additional device, it should be easy to add.  It's best to take a similar existing
 //! implementation as inspiration.  Contributions welcome!
 //!
+//! - [`AW9523B`](Aw9523b)
+//! - [`CH422`](Ch422)
+//! - [`CH423`](Ch423)
+//! - [`MAX7300`/`MAX7301`](Max7300)
 //! - [`MAX7321`](Max7321)
 //! - [`PCA9536`](Pca9536)
 //! - [`PCA9538`](Pca9538)
+//! - [`PCA9554`/`PCA9554A`](dev::pca9554)
+//! - [`PCA9539`](Pca9539)
+//! - [`PCA9539A`](Pca9539A)
+//! - [`PCA9535`](Pca9535)
+//! - [`PCA9535A`](Pca9535A)
 //! - [`PCA9555`](Pca9555)
+//! - [`PCA9574`](Pca9574)
+//! - [`PCA9575`](Pca9575)
+//! - [`PCA9702`](Pca9702)
+//! - [`PCAL6408A`](Pcal6408a)
+//! - [`PCAL6416A`](Pcal6416a)
 //! - [`PCF8574A`](Pcf8574a)
 //! - [`PCF8574`](Pcf8574)
 //! - [`PCF8575`](Pcf8575)
+//! - [`74HC165`](Sn74hc165) shift register
+//! - [`74HC595`](Sn74hc595) shift register
 //! - [`TCA6408A`](Tca6408a)
+//! - [`TCA6424A`](Tca6424a)
+//! - [`TCA9534`](Tca9534)
+//! - [`TCA9534A`](Tca9534A)
+//! - [`TCA9535`](Tca9535)
+//! - [`TCA9538`](Tca9538)
+//! - [`MCP23016`](Mcp23016)
 //! - [`MCP23x17`](Mcp23x17)
+//! - [`PI4IOE5V6408`](dev::pi4ioe5v6408)
+//! - [`PI4IOE5V6416`](Pi4ioe5v6416)
+//! - [`PI4IOE5V9535`](Pi4ioe5v9535)
+//! - [`XRA1200`](Xra1200)
+//! - [`XRA1201`](Xra1201)
+//!
+//! Each driver is behind a cargo feature named after its module, e.g. `pcf8574`.  All of them are
+//! enabled by the default `full` feature.  To only compile the drivers you need, disable the
+//! default features:
+//!
+//! ```toml
+//! port-expander = { version = "0.6", default-features = false, features = ["pcf8574"] }
+//! ```
 //!
 //! ## Non-local sharing
 //! `port-expander` uses a custom trait for abstracting different kinds of mutexes:
@@ -55,40 +105,538 @@
 //!     port_expander::Pca9555::with_mutex(i2c, true, false, false);
 //! let pca_pins = pca9555.split();
 //! ```
+//!
+//! For the two most common mutexes, each device also has convenience constructors which spare you
+//! the type annotation: `new_std()` (with the `std` feature) uses a `std::sync::Mutex` and
+//! `new_critical_section()` (with the `critical-section` feature) uses a
+//! `critical_section::Mutex<RefCell<_>>`.
 
 #![cfg_attr(not(any(test, feature = "std")), no_std)]
 
+/// Only compile the items if at least one device driver which hands out pins at runtime, with
+/// `take_pin()` or `into_arc_pins()`, is enabled.
+///
+/// Some of the shared helpers are only used by these drivers, they would be dead code otherwise.
+macro_rules! cfg_drivers {
+    ($($item:item)*) => {
+        $(
+            #[cfg(any(
+                feature = "aw9523b",
+                feature = "max7300",
+                feature = "max7321",
+                feature = "mcp23016",
+                feature = "mcp23x17",
+                feature = "pca9536",
+                feature = "pca9538",
+                feature = "pca9554",
+                feature = "pca9555",
+                feature = "pca9575",
+                feature = "pca9702",
+                feature = "pcal6408a",
+                feature = "pcal6416a",
+                feature = "pcf8574",
+                feature = "pcf8575",
+                feature = "pi4ioe5v6408",
+                feature = "sn74hc165",
+                feature = "sn74hc595",
+                feature = "tca6408a",
+                feature = "tca6424a",
+                feature = "xra1201",
+            ))]
+            $item
+        )*
+    };
+}
+
+/// Only compile the items if at least one driver for a port-expander with configurable pins is
+/// enabled, i.e. any driver except the plain shift registers.
+macro_rules! cfg_expanders {
+    ($($item:item)*) => {
+        $(
+            #[cfg(any(
+                feature = "aw9523b",
+                feature = "ch422",
+                feature = "ch423",
+                feature = "max7300",
+                feature = "max7321",
+                feature = "mcp23016",
+                feature = "mcp23x17",
+                feature = "pca9536",
+                feature = "pca9538",
+                feature = "pca9554",
+                feature = "pca9555",
+                feature = "pca9575",
+                feature = "pcal6408a",
+                feature = "pcal6416a",
+                feature = "pcf8574",
+                feature = "pcf8575",
+                feature = "pi4ioe5v6408",
+                feature = "tca6408a",
+                feature = "tca6424a",
+                feature = "xra1201",
+            ))]
+            $item
+        )*
+    };
+}
+
+/// Generate the methods which access the pins of a device by number or by mask.
+///
+/// This is expanded inside the `impl` block of a device, which must be a newtype around its
+/// `PortMutex` `M`.  `mode` is the mode of the pins in `Parts`, `pins` is their number, plus the
+/// port bit of the first pin if that isn't bit 0, and `error` is the error type of the driver.
+/// By default, all of `pins()`, `pin()`, `take_pin()`, `split_ref()`, `into_arc_pins()`,
+/// `write_mask()`, `read_mask()`, `write_pins()` and `toggle_pins()` are generated, `methods`
+/// selects only some of them.  `bits` replaces the description of the mask bits in the docs of
+/// `write_mask()`, which is needed for devices without `mode`.
+#[allow(unused_macros)] // without any device features
+macro_rules! pin_methods {
+    (
+        $(mode: $mode:ty,)?
+        pins: $count:literal $(+ $offset:literal)?,
+        error: $error:ty,
+        $(bits: $bits:literal,)?
+    ) => {
+        pin_methods! {
+            $(mode: $mode,)?
+            pins: $count $(+ $offset)?,
+            error: $error,
+            $(bits: $bits,)?
+            methods: [
+                pins, pin, take_pin, split_ref, into_arc_pins,
+                write_mask, read_mask, write_pins, toggle_pins,
+            ],
+        }
+    };
+    (
+        $(mode: $mode:ty,)?
+        pins: $count:literal $(+ $offset:literal)?,
+        error: $error:ty,
+        $(bits: $bits:literal,)?
+        methods: [$($method:ident),+ $(,)?],
+    ) => {
+        pin_methods!(@each [$($mode)?] [$count] [$($offset)?] [$error] [$($bits)?] $($method)+);
+    };
+    (@each $mode:tt $count:tt $offset:tt $error:tt $bits:tt $($method:ident)+) => {
+        $(pin_methods!(@$method $mode $count $offset $error $bits);)+
+    };
+
+    (@pins [$mode:ty] [$count:literal] [$($offset:literal)?] $error:tt $bits:tt) => {
+        /// Get access to all pins as an array instead of named fields.
+        ///
+        /// Element `n` of the array is the same pin as the `n`-th field of [`Parts`].
+        pub fn pins(&mut self) -> [$crate::Pin<'_, $mode, M>; $count] {
+            core::array::from_fn(|i| $crate::Pin::new(i as u8 $(+ $offset)?, &self.0))
+        }
+    };
+    (@pin [$mode:ty] [$count:literal] [$($offset:literal)?] $error:tt $bits:tt) => {
+        /// Get the `n`-th pin of [`pins()`](Self::pins), or `None` if `n` is out of range.
+        ///
+        /// Like [`split()`](Self::split), this borrows the device mutably, so the pin cannot exist
+        /// twice.  This is meant for table-driven configuration which refers to pins by number.
+        pub fn pin(&mut self, n: u8) -> Option<$crate::Pin<'_, $mode, M>> {
+            (n < $count).then(|| $crate::Pin::new(n $(+ $offset)?, &self.0))
+        }
+    };
+    (@take_pin [$mode:ty] [$count:literal] [$($offset:literal)?] $error:tt $bits:tt) => {
+        /// Claim the `n`-th pin of [`pins()`](Self::pins) at runtime.
+        ///
+        /// Unlike [`split()`](Self::split), this only needs a shared reference, e.g. for pin
+        /// assignments loaded from a configuration at boot.  Each pin can only be taken once:
+        /// `None` is returned if `n` is out of range or the pin was taken before, even if that pin
+        /// was dropped in the meantime.
+        pub fn take_pin(&self, n: u8) -> Option<$crate::Pin<'_, $mode, M>> {
+            if n >= $count {
+                return None;
+            }
+            let claimed = $crate::PortMutex::lock(&self.0, |drv| {
+                $crate::PortDriverClaims::claims(drv).claim(1 << (n $(+ $offset)?))
+            });
+            claimed.then(|| $crate::Pin::new(n $(+ $offset)?, &self.0))
+        }
+    };
+    (@split_ref [$mode:ty] [$count:literal] [$($offset:literal)?] $error:tt $bits:tt) => {
+        /// Get read-only views of all pins from a shared reference.
+        ///
+        /// The pins are in the [`ReadOnly`]($crate::mode::ReadOnly) mode, so they can only read
+        /// their inputs.  Element `n` is the same pin as the `n`-th pin of [`pins()`](Self::pins).
+        pub fn split_ref(&self) -> [$crate::Pin<'_, $crate::mode::ReadOnly, M>; $count] {
+            core::array::from_fn(|i| $crate::Pin::new(i as u8 $(+ $offset)?, &self.0))
+        }
+    };
+    (@into_arc_pins [$mode:ty] [$count:literal] [$($offset:literal)?] $error:tt $bits:tt) => {
+        /// Turn the device into [`ArcPin`]($crate::ArcPin)s which own a reference count of it.
+        ///
+        /// Element `n` is the same pin as the `n`-th pin of [`pins()`](Self::pins).  Only
+        /// available with the `std` feature.
+        #[cfg(any(test, feature = "std"))]
+        pub fn into_arc_pins(self) -> [$crate::ArcPin<$mode, M>; $count] {
+            let port_driver = std::sync::Arc::new(self.0);
+            core::array::from_fn(|i| {
+                $crate::ArcPin::new(i as u8 $(+ $offset)?, port_driver.clone())
+            })
+        }
+    };
+    (@write_mask $mode:tt $count:tt $offset:tt [$error:ty] $bits:tt) => {
+        /// Set the pins in `mask_high` HIGH and the pins in `mask_low` LOW in a single operation.
+        ///
+        #[doc = pin_methods!(@bits $mode $bits)]
+        pub fn write_mask(&self, mask_high: u32, mask_low: u32) -> Result<(), $error> {
+            $crate::PortMutex::lock(&self.0, |drv| {
+                $crate::PortDriver::set(drv, mask_high, mask_low)
+            })
+        }
+    };
+    (@read_mask $mode:tt $count:tt $offset:tt [$error:ty] $bits:tt) => {
+        /// Read the pins in `mask` and return the mask of those which are HIGH.
+        ///
+        /// The bits are the same as for [`write_mask()`](Self::write_mask).
+        pub fn read_mask(&self, mask: u32) -> Result<u32, $error> {
+            $crate::PortMutex::lock(&self.0, |drv| $crate::PortDriver::get(drv, mask, 0))
+        }
+    };
+    (@write_pins $mode:tt [$count:literal] [$($offset:literal)?] [$error:ty] $bits:tt) => {
+        /// Set the output states of the pins with the given indices in a single operation.
+        ///
+        #[doc = pin_methods!(@indices $mode)]
+        /// Like [`write_mask()`](Self::write_mask), this bypasses the pin types.  If any index is
+        /// out of range, nothing is written.
+        pub fn write_pins(
+            &self,
+            pins: &[(u8, bool)],
+        ) -> Result<(), $crate::PinIndexError<$error>> {
+            let (mask_high, mask_low) = $crate::index::write_masks(pins, $count, 0 $(+ $offset)?)?;
+            self.write_mask(mask_high, mask_low)?;
+            Ok(())
+        }
+    };
+    (@toggle_pins $mode:tt [$count:literal] [$($offset:literal)?] [$error:ty] $bits:tt) => {
+        /// Toggle the output states of the pins with the given indices in a single operation.
+        ///
+        /// See [`write_pins()`](Self::write_pins).
+        pub fn toggle_pins(&self, pins: &[u8]) -> Result<(), $crate::PinIndexError<$error>> {
+            let mask = $crate::index::mask(pins, $count, 0 $(+ $offset)?)?;
+            $crate::PortMutex::lock(&self.0, |drv| $crate::PortDriver::toggle(drv, mask))?;
+            Ok(())
+        }
+    };
+
+    (@bits $mode:tt [$bits:literal]) => {
+        $bits
+    };
+    (@bits [$mode:ty] []) => {
+        "Bit `n` of the masks is the `n`-th pin of [`pins()`](Self::pins).  This bypasses the pin \
+        types, so the pins are not checked to be outputs."
+    };
+    (@indices []) => {
+        "Indices are the bits of [`write_mask()`](Self::write_mask)."
+    };
+    (@indices [$mode:ty]) => {
+        "Index `n` is the `n`-th pin of [`pins()`](Self::pins)."
+    };
+}
+
+mod address;
+mod any;
+#[cfg(any(test, feature = "std"))]
+mod arc;
+#[cfg(feature = "async")]
+mod asynch;
+mod bank;
+mod board;
 mod bus;
+#[cfg(feature = "async-button")]
+mod button;
+mod cache;
 mod common;
+mod cost;
+mod define;
 pub mod dev;
+mod driver;
+#[cfg(feature = "eh0")]
+mod eh0;
+mod encoder;
+mod events;
+mod flags;
+#[cfg(feature = "hd44780")]
+pub mod hd44780;
+mod identify;
+mod index;
+mod irq;
 mod multi;
 mod mutex;
+mod mux;
 mod pin;
+mod powerdown;
+mod refresh;
+mod resync;
+mod retry;
+mod selftest;
+#[cfg(feature = "sevenseg")]
+pub mod sevenseg;
+mod shared;
+mod snapshot;
+#[cfg(feature = "stats")]
+mod stats;
+mod status;
+mod transaction;
+mod typed;
+mod value;
+mod verify;
+#[cfg(feature = "test-util")]
+pub mod virt;
+mod wait;
 
+pub use address::AddressCheck;
+pub use address::AddressError;
+pub use address::InvalidAddress;
+pub use any::AnyPin;
+pub use any::AnyPinError;
+#[cfg(any(test, feature = "std"))]
+pub use arc::ArcPin;
+#[cfg(feature = "async")]
+pub use asynch::AsyncPin;
+#[cfg(feature = "async")]
+pub use asynch::AsyncPort;
+#[cfg(all(feature = "async", feature = "critical-section"))]
+pub use asynch::InterruptSignal;
+pub use bank::GpioBank;
 pub use bus::I2cBus;
+pub use bus::InvalidTenBitBase;
+pub use bus::TenBitBase;
+pub use bus::TenBitI2c;
+#[cfg(feature = "async-button")]
+pub use button::AsyncButton;
+#[cfg(feature = "async-button")]
+pub use button::ButtonEvent;
+#[cfg(feature = "async-button")]
+pub use button::ButtonTiming;
+pub use cache::cached_inputs_for;
+pub use cache::CachedInputs;
 pub use common::mode;
+pub use common::Direction;
+pub use common::OutputMode;
+pub use common::Pull;
+pub use common::PullError;
+pub use cost::cost_of;
+pub use cost::Operation;
+pub use encoder::RotaryEncoder;
+pub use events::Edge;
+pub use events::EventQueue;
+pub use events::EventsFull;
+pub use events::InterruptHandler;
+pub use events::MultiInterruptError;
+pub use events::MultiInterruptHandler;
+pub use events::PinEvent;
+pub use events::PinEvents;
+pub use flags::read_flags;
+pub use flags::write_flags;
+pub use flags::PortFlags;
+pub use identify::identify;
+pub use index::PinIndexError;
+pub use irq::irq_state;
+pub use irq::InterruptMode;
+pub use irq::IrqState;
+pub use multi::read_filtered;
 pub use multi::read_multiple;
+pub use multi::staggered_write;
+pub use multi::toggle_multiple;
 pub use multi::write_multiple;
+pub use multi::OutputPins;
+#[cfg(feature = "file-lock")]
+pub use mutex::FileLockMutex;
+#[cfg(feature = "file-lock")]
+pub use mutex::LockKey;
+pub use mutex::NullMutex;
 pub use mutex::PortMutex;
+pub use mutex::SingleContext;
+pub use mux::Pca9548;
+pub use mux::Pca9548Bus;
+pub use mux::Pca9548Channel;
 pub use pin::Pin;
+pub use pin::VerifiedSetError;
+pub use powerdown::PowerDownSequence;
+pub use refresh::refresh;
+pub use refresh::RefreshSchedule;
+pub use resync::flush;
+pub use resync::is_dirty;
+pub use resync::resync;
+pub use retry::NoDelay;
+pub use retry::RetryBus;
+pub use retry::RetryPolicy;
+pub use selftest::SelfTestReport;
+pub use shared::SharedOutputPin;
+pub use snapshot::snapshot;
+pub use snapshot::DriverState;
+#[cfg(feature = "stats")]
+pub use stats::stats;
+#[cfg(feature = "stats")]
+pub use stats::LockStats;
+#[cfg(feature = "stats")]
+pub use stats::StatsMutex;
+pub use status::status;
+pub use status::Status;
+pub use transaction::Transaction;
+pub use typed::TypedPin;
+pub use value::read_value;
+pub use value::write_value;
+pub use value::BitOrder;
+pub use value::Encoding;
+pub use value::ValueError;
+pub use verify::VerifyError;
+pub use verify::VerifyingI2c;
+#[cfg(feature = "test-util")]
+pub use virt::VirtualExpander;
+
+/// Items used by the expansion of `board_pins!()` and `define_expander!()`, not part of the
+/// public API.
+#[doc(hidden)]
+pub mod __macro {
+    pub use crate::bus::RegisterBus;
+    pub use crate::common::PortDriver;
+    pub use crate::driver::{Driver16, Regs16};
+    pub use crate::pin::PinError;
+
+    pub fn driver16<I2C, R: Regs16>(
+        i2c: I2C,
+        addr: u8,
+        address_check: crate::AddressCheck,
+    ) -> Driver16<I2C, R> {
+        Driver16::with_address(i2c, addr, address_check)
+    }
+
+    pub fn validate_address(
+        addr: u8,
+        valid: core::ops::RangeInclusive<u8>,
+    ) -> Result<u8, crate::InvalidAddress> {
+        crate::address::validate(addr, valid)
+    }
+
+    pub fn pin<MODE, MUTEX, PD>(pin_number: u8, port_driver: &MUTEX) -> crate::Pin<'_, MODE, MUTEX>
+    where
+        PD: PortDriver,
+        MUTEX: crate::PortMutex<Port = PD>,
+    {
+        crate::Pin::new(pin_number, port_driver)
+    }
+}
 
-pub(crate) use bus::I2cExt;
+#[cfg(feature = "async")]
+pub(crate) use asynch::AsyncPortDriver;
+#[cfg(feature = "async")]
+pub(crate) use asynch::AsyncPortDriverTotemPole;
+pub(crate) use bus::RegisterBus;
+#[cfg(any(
+    feature = "max7300",
+    feature = "mcp23x17",
+    feature = "pca9702",
+    feature = "sn74hc165",
+    feature = "sn74hc595",
+))]
 pub(crate) use bus::SpiBus;
-pub(crate) use common::Direction;
+#[cfg(feature = "mcp23x17")]
+pub(crate) use bus::SpiFraming;
+#[cfg(feature = "mcp23x17")]
+pub(crate) use bus::SpiRegisters;
+pub(crate) use common::InputState;
+pub(crate) use common::OutputInversion;
+cfg_drivers! {
+    pub(crate) use common::PinClaims;
+    pub(crate) use common::PortDriverClaims;
+}
 pub(crate) use common::PortDriver;
+pub(crate) use common::PortDriverCost;
+pub(crate) use common::PortDriverHighImpedance;
+pub(crate) use common::PortDriverInputState;
+pub(crate) use common::PortDriverInterrupt;
+pub(crate) use common::PortDriverIrqState;
+pub(crate) use common::PortDriverOutputInversion;
+#[cfg(any(
+    feature = "ch422",
+    feature = "ch423",
+    feature = "pcal6408a",
+    feature = "pcal6416a"
+))]
+pub(crate) use common::PortDriverOutputMode;
 pub(crate) use common::PortDriverPolarity;
+#[cfg(any(feature = "ch422", feature = "ch423", feature = "max7300"))]
+pub(crate) use common::PortDriverPower;
 pub(crate) use common::PortDriverPullDown;
 pub(crate) use common::PortDriverPullUp;
+pub(crate) use common::PortDriverRefresh;
+cfg_expanders! {
+    pub(crate) use common::PortDriverRestoreDefaults;
+}
+pub(crate) use common::PortDriverResync;
+pub(crate) use common::PortDriverSnapshot;
+pub(crate) use common::PortDriverStatus;
 pub(crate) use common::PortDriverTotemPole;
 
+#[cfg(feature = "aw9523b")]
+pub use dev::aw9523b::Aw9523b;
+#[cfg(feature = "ch422")]
+pub use dev::ch422::Ch422;
+#[cfg(feature = "ch423")]
+pub use dev::ch423::Ch423;
+#[cfg(feature = "max7300")]
+pub use dev::max7300::Max7300;
+#[cfg(feature = "max7321")]
 pub use dev::max7321::Max7321;
+#[cfg(feature = "mcp23016")]
+pub use dev::mcp23016::Mcp23016;
+#[cfg(feature = "mcp23x17")]
 pub use dev::mcp23x17::Mcp23x17;
+#[cfg(feature = "pca9535")]
+pub use dev::pca9535::Pca9535;
+#[cfg(feature = "pca9535")]
+pub use dev::pca9535::Pca9535A;
+#[cfg(feature = "pca9536")]
 pub use dev::pca9536::Pca9536;
+#[cfg(feature = "pca9538")]
 pub use dev::pca9538::Pca9538;
+#[cfg(feature = "pca9539")]
+pub use dev::pca9539::Pca9539;
+#[cfg(feature = "pca9539")]
+pub use dev::pca9539::Pca9539A;
+#[cfg(feature = "pca9555")]
 pub use dev::pca9555::Pca9555;
+#[cfg(feature = "pca9574")]
+pub use dev::pca9574::Pca9574;
+#[cfg(feature = "pca9575")]
+pub use dev::pca9575::Pca9575;
+#[cfg(feature = "pca9702")]
+pub use dev::pca9702::Pca9702;
+#[cfg(feature = "pcal6408a")]
 pub use dev::pcal6408a::Pcal6408a;
+#[cfg(feature = "pcal6416a")]
 pub use dev::pcal6416a::Pcal6416a;
+#[cfg(feature = "pcf8574")]
 pub use dev::pcf8574::Pcf8574;
+#[cfg(feature = "pcf8574")]
 pub use dev::pcf8574::Pcf8574a;
+#[cfg(feature = "pcf8575")]
 pub use dev::pcf8575::Pcf8575;
+#[cfg(feature = "pi4ioe5v6416")]
+pub use dev::pi4ioe5v6416::Pi4ioe5v6416;
+#[cfg(feature = "pi4ioe5v9535")]
+pub use dev::pi4ioe5v9535::Pi4ioe5v9535;
+#[cfg(feature = "sn74hc165")]
+pub use dev::sn74hc165::Sn74hc165;
+#[cfg(feature = "sn74hc595")]
+pub use dev::sn74hc595::Sn74hc595;
+#[cfg(feature = "tca6408a")]
 pub use dev::tca6408a::Tca6408a;
+#[cfg(feature = "tca6424a")]
+pub use dev::tca6424a::Tca6424a;
+#[cfg(feature = "tca9534")]
+pub use dev::tca9534::Tca9534;
+#[cfg(feature = "tca9534")]
+pub use dev::tca9534::Tca9534A;
+#[cfg(feature = "tca9535")]
+pub use dev::tca9535::Tca9535;
+#[cfg(feature = "tca9538")]
+pub use dev::tca9538::Tca9538;
+#[cfg(feature = "xra1200")]
+pub use dev::xra1200::Xra1200;
+#[cfg(feature = "xra1201")]
+pub use dev::xra1201::Xra1201;
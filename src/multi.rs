@@ -28,8 +28,20 @@
 ///     [true, false],
 /// ).unwrap();
 /// ```
-pub fn write_multiple<PD, MUTEX, MODE: crate::mode::HasOutput, const N: usize>(
-    pins: [&mut crate::Pin<'_, MODE, MUTEX>; N],
+///
+/// Pins in different output modes, e.g. an `Output` and a `QuasiBidirectional` pin, can be passed
+/// as a tuple instead of an array, see [`OutputPins`]:
+///
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pcf = port_expander::Pcf8574::new(i2c, false, false, false);
+/// # let p = pcf.split();
+/// let mut led = p.p0.into_output().unwrap();
+/// let mut bus_line = p.p1;
+/// port_expander::write_multiple((&mut led, &mut bus_line), [true, false]).unwrap();
+/// ```
+pub fn write_multiple<PD, MUTEX, MODE, const N: usize>(
+    pins: impl OutputPins<N, Mutex = MUTEX, Mode = MODE>,
     states: [bool; N],
 ) -> Result<(), PD::Error>
 where
@@ -39,22 +51,213 @@ where
     let mut mask_set_high = 0x00;
     let mut mask_set_low = 0x00;
 
-    let port_driver = pins[0].port_driver();
-    for (pin, state) in pins.iter().zip(states.iter()) {
-        assert!(core::ptr::eq(pin.port_driver(), port_driver));
+    for (pin_mask, state) in pins.pin_masks().iter().zip(states.iter()) {
         if *state {
-            mask_set_high |= pin.pin_mask();
+            mask_set_high |= pin_mask;
         } else {
-            mask_set_low |= pin.pin_mask();
+            mask_set_low |= pin_mask;
         }
     }
 
-    pins[0].port_driver().lock(|drv| {
+    pins.port_driver().lock(|drv| {
         drv.set(mask_set_high, mask_set_low)?;
         Ok(())
     })
 }
 
+/// Toggle multiple pins in a single bus transaction.
+///
+/// Like [`write_multiple()`], but each pin is switched to the opposite of its current output
+/// state.  The current states are taken from the driver's cached output register where possible,
+/// so this usually costs a single write.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pcf = port_expander::Pcf8574::new(i2c, false, false, false);
+/// # let p = pcf.split();
+/// # let mut io0 = p.p0;
+/// # let mut io1 = p.p1;
+/// // alternating blinker
+/// port_expander::toggle_multiple([&mut io0, &mut io1]).unwrap();
+/// ```
+pub fn toggle_multiple<P, PD, MUTEX, const N: usize>(pins: P) -> Result<(), PD::Error>
+where
+    P: OutputPins<N, Mutex = MUTEX>,
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    let mask = pins.pin_masks().iter().fold(0x00, |mask, m| mask | m);
+    pins.port_driver().lock(|drv| drv.toggle(mask))
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A group of `N` output pins of the same port-expander, see [`write_multiple()`].
+///
+/// Implemented for arrays of pins in the same mode and for tuples of up to 8 pins in different
+/// modes, as long as all of the modes implement [`HasOutput`](crate::mode::HasOutput).
+pub trait OutputPins<const N: usize>: sealed::Sealed {
+    /// Mutex of the port-expander the pins belong to.
+    type Mutex;
+
+    /// Mode of the pins, or a tuple of the modes for a tuple of pins.
+    type Mode;
+
+    #[doc(hidden)]
+    fn pin_masks(&self) -> [u32; N];
+
+    /// # Panics
+    /// All pins must belong to the same port-expander.
+    #[doc(hidden)]
+    fn port_driver(&self) -> &Self::Mutex;
+}
+
+impl<MODE: crate::mode::HasOutput, MUTEX, const N: usize> sealed::Sealed
+    for [&mut crate::Pin<'_, MODE, MUTEX>; N]
+{
+}
+
+impl<MODE, MUTEX, PD, const N: usize> OutputPins<N> for [&mut crate::Pin<'_, MODE, MUTEX>; N]
+where
+    MODE: crate::mode::HasOutput,
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    type Mutex = MUTEX;
+    type Mode = MODE;
+
+    fn pin_masks(&self) -> [u32; N] {
+        core::array::from_fn(|i| self[i].pin_mask())
+    }
+
+    fn port_driver(&self) -> &MUTEX {
+        let port_driver = self[0].port_driver();
+        for pin in self.iter() {
+            assert!(core::ptr::eq(pin.port_driver(), port_driver));
+        }
+        port_driver
+    }
+}
+
+macro_rules! impl_output_pins_tuple {
+    ($n:literal: $($idx:tt $mode:ident),+) => {
+        impl<'a, MUTEX, $($mode: crate::mode::HasOutput),+> sealed::Sealed
+            for ($(&mut crate::Pin<'a, $mode, MUTEX>,)+)
+        {
+        }
+
+        impl<'a, MUTEX, PD, $($mode),+> OutputPins<$n> for ($(&mut crate::Pin<'a, $mode, MUTEX>,)+)
+        where
+            $($mode: crate::mode::HasOutput,)+
+            PD: crate::PortDriver,
+            MUTEX: crate::PortMutex<Port = PD>,
+        {
+            type Mutex = MUTEX;
+            type Mode = ($($mode,)+);
+
+            fn pin_masks(&self) -> [u32; $n] {
+                [$(self.$idx.pin_mask()),+]
+            }
+
+            fn port_driver(&self) -> &MUTEX {
+                let port_driver = self.0.port_driver();
+                $(assert!(core::ptr::eq(self.$idx.port_driver(), port_driver));)+
+                port_driver
+            }
+        }
+    };
+}
+
+impl_output_pins_tuple!(2: 0 M0, 1 M1);
+impl_output_pins_tuple!(3: 0 M0, 1 M1, 2 M2);
+impl_output_pins_tuple!(4: 0 M0, 1 M1, 2 M2, 3 M3);
+impl_output_pins_tuple!(5: 0 M0, 1 M1, 2 M2, 3 M3, 4 M4);
+impl_output_pins_tuple!(6: 0 M0, 1 M1, 2 M2, 3 M3, 4 M4, 5 M5);
+impl_output_pins_tuple!(7: 0 M0, 1 M1, 2 M2, 3 M3, 4 M4, 5 M5, 6 M6);
+impl_output_pins_tuple!(8: 0 M0, 1 M1, 2 M2, 3 M3, 4 M4, 5 M5, 6 M6, 7 M7);
+
+/// Set multiple pins in small groups with a delay in between.
+///
+/// Switching many loads (relays, LED strings, ...) at the same instant can cause a large inrush
+/// current.  `staggered_write()` only touches the pins whose output state actually changes and
+/// switches them in groups of at most `group_size` pins, in the order in which they are passed.
+/// Each group is written in a single bus transaction, like with [`write_multiple()`], and `delay`
+/// waits for `delay_us` microseconds between two groups.  The port-expander is not locked during
+/// the delays.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut delay = embedded_hal_mock::eh1::delay::NoopDelay::new();
+/// # let mut pcf = port_expander::Pcf8574::new(i2c, false, false, false);
+/// # let p = pcf.split();
+/// # let mut relay0 = p.p0;
+/// # let mut relay1 = p.p1;
+/// # let mut relay2 = p.p2;
+/// // Turn on two relays at a time, 20ms apart
+/// port_expander::staggered_write(
+///     [&mut relay0, &mut relay1, &mut relay2],
+///     [true, true, true],
+///     2,
+///     &mut delay,
+///     20_000,
+/// ).unwrap();
+/// ```
+pub fn staggered_write<PD, MUTEX, MODE: crate::mode::HasOutput, D, const N: usize>(
+    pins: [&mut crate::Pin<'_, MODE, MUTEX>; N],
+    states: [bool; N],
+    group_size: usize,
+    delay: &mut D,
+    delay_us: u32,
+) -> Result<(), PD::Error>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+    D: embedded_hal::delay::DelayNs,
+{
+    let mut mask_high = 0x00;
+    let mut mask_low = 0x00;
+
+    let port_driver = pins[0].port_driver();
+    for (pin, state) in pins.iter().zip(states.iter()) {
+        assert!(core::ptr::eq(pin.port_driver(), port_driver));
+        if *state {
+            mask_high |= pin.pin_mask();
+        } else {
+            mask_low |= pin.pin_mask();
+        }
+    }
+    let unchanged = port_driver.lock(|drv| drv.is_set(mask_high, mask_low))?;
+
+    let group_size = group_size.max(1);
+    let mut changes = pins
+        .iter()
+        .zip(states.iter())
+        .filter(|(pin, _)| unchanged & pin.pin_mask() == 0)
+        .peekable();
+    while changes.peek().is_some() {
+        let mut group_high = 0x00;
+        let mut group_low = 0x00;
+        for (pin, state) in changes.by_ref().take(group_size) {
+            if *state {
+                group_high |= pin.pin_mask();
+            } else {
+                group_low |= pin.pin_mask();
+            }
+        }
+        port_driver.lock(|drv| drv.set(group_high, group_low))?;
+
+        if changes.peek().is_some() {
+            delay.delay_us(delay_us);
+        }
+    }
+
+    Ok(())
+}
+
 /// Read multiple pins at the same time.
 ///
 /// When a port-expander sends an interrupt that one of its inputs changed state, it might be
@@ -113,10 +316,81 @@ where
     Ok(ret)
 }
 
-#[cfg(test)]
+/// Read multiple pins several times and decide each pin's state by majority vote.
+///
+/// Noisy inputs (long cables, relay contacts, ...) without an external RC filter can read wrong
+/// once in a while.  `read_filtered()` reads all `pins` `samples` times, waiting `delay_us`
+/// microseconds in between, and returns for each pin whether it was HIGH in more than half of the
+/// reads.  Use an odd number of samples to avoid ties, which count as LOW.
+///
+/// Like with [`read_multiple()`], all pins are read in a single bus transaction per sample.  The
+/// port-expander stays locked for all samples, including the delays, and a snapshot of
+/// [`cached_inputs_for()`](crate::cached_inputs_for) is dropped before each sample.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut delay = embedded_hal_mock::eh1::delay::NoopDelay::new();
+/// # let mut pcf = port_expander::Pcf8574::new(i2c, false, false, false);
+/// # let p = pcf.split();
+/// # let io0 = p.p0;
+/// # let io1 = p.p1;
+/// // 5 samples, 1ms apart
+/// let values = port_expander::read_filtered([&io0, &io1], 5, &mut delay, 1_000).unwrap();
+/// ```
+pub fn read_filtered<PD, MUTEX, MODE: crate::mode::HasInput, D, const N: usize>(
+    pins: [&crate::Pin<'_, MODE, MUTEX>; N],
+    samples: u32,
+    delay: &mut D,
+    delay_us: u32,
+) -> Result<[bool; N], PD::Error>
+where
+    PD: crate::PortDriver + crate::PortDriverInputState,
+    MUTEX: crate::PortMutex<Port = PD>,
+    D: embedded_hal::delay::DelayNs,
+{
+    let mask = pins.iter().map(|p| p.pin_mask()).fold(0, |m, p| m | p);
+    let port_driver = pins[0].port_driver();
+    for pin in pins.iter() {
+        assert!(core::ptr::eq(pin.port_driver(), port_driver));
+    }
+
+    let samples = samples.max(1);
+    let mut high = [0u32; N];
+    port_driver.lock(|drv| {
+        for i in 0..samples {
+            if i != 0 {
+                delay.delay_us(delay_us);
+            }
+            drv.input_state().invalidate();
+            let mask_in = drv.get(mask, 0)?;
+            for (pin, count) in pins.iter().zip(high.iter_mut()) {
+                if mask_in & pin.pin_mask() != 0 {
+                    *count += 1;
+                }
+            }
+        }
+        Ok::<_, PD::Error>(())
+    })?;
+
+    Ok(high.map(|count| count * 2 > samples))
+}
+
+#[cfg(all(
+    test,
+    any(
+        feature = "pca9536",
+        feature = "pca9538",
+        feature = "pcf8574",
+        feature = "pcf8575"
+    )
+))]
 mod tests {
+    #[cfg(any(feature = "pca9536", feature = "pcf8574"))]
+    use embedded_hal_mock::eh1::delay as mock_delay;
     use embedded_hal_mock::eh1::i2c as mock_i2c;
 
+    #[cfg(feature = "pcf8574")]
     #[test]
     fn pcf8574_write_multiple() {
         let expectations = [
@@ -135,11 +409,59 @@ mod tests {
         )
         .unwrap();
 
-        super::write_multiple([&mut pcf_pins.p2, &mut pcf_pins.p4], [true, false]).unwrap();
+        super::write_multiple::<_, _, crate::mode::QuasiBidirectional, 2>(
+            [&mut pcf_pins.p2, &mut pcf_pins.p4],
+            [true, false],
+        )
+        .unwrap();
+
+        bus.done();
+    }
+
+    #[cfg(feature = "pcf8574")]
+    #[test]
+    fn pcf8574_write_multiple_mixed_modes() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x21, vec![0b11111110]),
+            mock_i2c::Transaction::write(0x21, vec![0b11011011]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), true, false, false);
+        let pcf_pins = pcf.split();
+
+        let mut p0 = pcf_pins.p0.into_output().unwrap();
+        let mut p2 = pcf_pins.p2;
+        let mut p5 = pcf_pins.p5;
+        super::write_multiple((&mut p0, &mut p2, &mut p5), [true, false, false]).unwrap();
 
         bus.done();
     }
 
+    #[cfg(feature = "pcf8574")]
+    #[test]
+    fn pcf8574_toggle_multiple() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x21, vec![0b11111110]),
+            mock_i2c::Transaction::write(0x21, vec![0b11111110]),
+            // both pins toggle in a single write, without reading the port
+            mock_i2c::Transaction::write(0x21, vec![0b11111101]),
+            mock_i2c::Transaction::write(0x21, vec![0b11111110]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), true, false, false);
+        let pcf_pins = pcf.split();
+
+        let mut p0 = pcf_pins.p0.into_output().unwrap();
+        let mut p1 = pcf_pins.p1.into_output_high().unwrap();
+        super::toggle_multiple([&mut p0, &mut p1]).unwrap();
+        super::toggle_multiple([&mut p0, &mut p1]).unwrap();
+
+        bus.done();
+    }
+
+    #[cfg(feature = "pcf8575")]
     #[test]
     fn pcf8575_write_multiple() {
         let expectations = [
@@ -179,6 +501,41 @@ mod tests {
         bus.done();
     }
 
+    #[cfg(feature = "pcf8574")]
+    #[test]
+    fn pcf8574_staggered_write() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0b11111100]),
+            mock_i2c::Transaction::write(0x20, vec![0b11110000]),
+            mock_i2c::Transaction::write(0x20, vec![0b10010000]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+        let delay_expectations = [
+            mock_delay::Transaction::blocking_delay_us(500),
+            mock_delay::Transaction::blocking_delay_us(500),
+        ];
+        let mut delay = mock_delay::CheckedDelay::new(&delay_expectations);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), false, false, false);
+        let mut p = pcf.split();
+
+        // p7 is already HIGH and is skipped
+        super::staggered_write(
+            [
+                &mut p.p0, &mut p.p7, &mut p.p1, &mut p.p2, &mut p.p3, &mut p.p5, &mut p.p6,
+            ],
+            [false, true, false, false, false, false, false],
+            2,
+            &mut delay,
+            500,
+        )
+        .unwrap();
+
+        bus.done();
+        delay.done();
+    }
+
+    #[cfg(feature = "pca9536")]
     #[test]
     fn pca9536_read_multiple() {
         let expectations = [
@@ -200,6 +557,37 @@ mod tests {
         bus.done();
     }
 
+    #[cfg(feature = "pca9536")]
+    #[test]
+    fn pca9536_read_filtered() {
+        let expectations = [
+            mock_i2c::Transaction::write_read(0x41, vec![0x00], vec![0b00000101]),
+            mock_i2c::Transaction::write_read(0x41, vec![0x00], vec![0b00000100]),
+            mock_i2c::Transaction::write_read(0x41, vec![0x00], vec![0b00000011]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+        let mut delay = mock_delay::CheckedDelay::new(&[
+            mock_delay::Transaction::delay_us(500),
+            mock_delay::Transaction::delay_us(500),
+        ]);
+
+        let mut pca = crate::Pca9536::new(bus.clone());
+        let pca_pins = pca.split();
+
+        let res = super::read_filtered(
+            [&pca_pins.io0, &pca_pins.io1, &pca_pins.io2],
+            3,
+            &mut delay,
+            500,
+        )
+        .unwrap();
+        assert_eq!(res, [true, false, true]);
+
+        bus.done();
+        delay.done();
+    }
+
+    #[cfg(feature = "pca9538")]
     #[test]
     #[should_panic]
     fn pca9538_multiple_assert_same_chip() {
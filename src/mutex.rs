@@ -10,11 +10,21 @@
 /// | [`core::cell::RefCell`] | _always available_ | For sharing within a single execution context. |
 /// | [`std::sync::Mutex`][mutex-std] | `std` | For platforms where `std` is available. |
 /// | [`critical_section::Mutex`][mutex-cs] | `critical-section` | Use critical sections to ensure synchronized access, via the [`critical-section`][crate-critical-section] crate. |
+/// | [`NullMutex`] | _always available_ | No locking at all, requires an `unsafe` promise of exclusive access. |
+/// | `FileLockMutex` | `file-lock` | Additionally locks a file, for sharing between processes. |
 ///
 /// [mutex-std]: https://doc.rust-lang.org/std/sync/struct.Mutex.html
 /// [mutex-cs]: https://docs.rs/critical-section/latest/critical_section/struct.Mutex.html
 /// [crate-critical-section]: https://crates.io/crates/critical-section
 ///
+/// ## RTIC
+/// RTIC resources are locked by the framework, not by the resource itself, and a locked resource
+/// is only accessible within the closure passed to `lock()`.  As the pins borrow the
+/// port-expander, the port-expander and its pins can't be split across separate resources.
+/// Instead, keep the port-expander in a `#[local]` resource of the one task which uses it
+/// (`RefCell` is sufficient then).  If multiple tasks of different priorities need the pins, use
+/// `critical_section::Mutex` and store the port-expander in a `static`.
+///
 /// For other mutex types, a custom implementation is needed.  Due to the orphan rule, it might be
 /// necessary to wrap it in a newtype.  As an example, this is what such a custom implementation
 /// might look like:
@@ -88,3 +98,319 @@ impl<T> PortMutex for critical_section::Mutex<core::cell::RefCell<T>> {
         })
     }
 }
+
+/// Marker for [`NullMutex`] users which guarantee exclusive access to their port-expanders.
+///
+/// # Safety
+/// By implementing this trait, you promise that every port-expander using a
+/// `NullMutex<_, Self>` and all of its pins are only ever accessed from a single execution
+/// context, i.e. never from an interrupt handler or another thread while in use elsewhere.
+/// Additionally, no pin of a port-expander may be accessed from within
+/// [`Pin::access_port_driver()`](crate::Pin::access_port_driver) of the same port-expander.
+pub unsafe trait SingleContext {}
+
+/// Mutex which does no locking at all.
+///
+/// Unlike [`RefCell`](core::cell::RefCell), this has no overhead, not even a borrow flag.  It can
+/// only be used with a marker type `C` implementing the unsafe [`SingleContext`] trait, which is
+/// the explicit promise that the port-expander is never accessed concurrently or re-entrantly.
+///
+/// ## Example
+/// ```no_run
+/// struct MainLoop;
+/// // SAFETY: The port-expander is only used from the main loop.
+/// unsafe impl port_expander::SingleContext for MainLoop {}
+///
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// let mut pca9555: port_expander::Pca9555<port_expander::NullMutex<_, MainLoop>> =
+///     port_expander::Pca9555::with_mutex(i2c, false, false, false);
+/// let pca_pins = pca9555.split();
+/// ```
+pub struct NullMutex<T, C: SingleContext> {
+    port: core::cell::UnsafeCell<T>,
+    _context: core::marker::PhantomData<C>,
+}
+
+impl<T, C: SingleContext> PortMutex for NullMutex<T, C> {
+    type Port = T;
+
+    fn create(v: Self::Port) -> Self {
+        Self {
+            port: core::cell::UnsafeCell::new(v),
+            _context: core::marker::PhantomData,
+        }
+    }
+
+    fn lock<R, F: FnOnce(&mut Self::Port) -> R>(&self, f: F) -> R {
+        // SAFETY: `UnsafeCell` makes this type `!Sync` and the `SingleContext` implementor
+        // promised that no other access to the port can be active at the same time.
+        f(unsafe { &mut *self.port.get() })
+    }
+}
+
+/// Identifies the port-expander guarded by a [`FileLockMutex`].
+///
+/// All processes which access the same chip must use the same key, i.e. the same bus number and
+/// address.  The lock file is `DIR/port-expander-i2c-BUS-ADDRESS.lock`.
+#[cfg(feature = "file-lock")]
+pub trait LockKey {
+    /// Number of the I2C bus, `N` in `/dev/i2c-N`.
+    const BUS: u8;
+    /// 7-bit I2C address of the port-expander.
+    const ADDRESS: u8;
+    /// Directory where the lock file is created.
+    const DIR: &'static str = "/run/lock";
+
+    /// Open the lock file, creating it if it doesn't exist yet.
+    ///
+    /// A [`FileLockMutex`] opens the file on its first access and panics if that fails.  Call this
+    /// before constructing the device to handle a missing directory or permission as an error.
+    fn open_lock_file() -> std::io::Result<std::fs::File> {
+        let path = format!(
+            "{}/port-expander-i2c-{}-{:02x}.lock",
+            Self::DIR,
+            Self::BUS,
+            Self::ADDRESS
+        );
+        std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+    }
+}
+
+/// Mutex which also excludes other processes using the same port-expander.
+///
+/// In addition to an in-process [`std::sync::Mutex`], each access takes an exclusive `flock()`
+/// on a lock file named after the bus and address from `K`.  This allows multiple processes (e.g.
+/// on embedded Linux) to share one port-expander.
+///
+/// When another process (or another `FileLockMutex` for the same chip) accessed the chip since
+/// the last access through this mutex, the driver is resynced first, like with
+/// [`resync()`](crate::resync).  Outputs set by the other process are therefore kept.  This
+/// relies on a readable output register: quasi-bidirectional chips (`PCF8574`, `PCF8575`,
+/// `MAX7321`) write their cached state on resync and will overwrite the outputs of the other
+/// process.  Only the accesses through pins are guarded, not the initialization done by the
+/// device constructors.
+///
+/// Available with the `file-lock` feature.
+///
+/// # Panics
+/// The lock file is opened on the first access.  As [`PortMutex::lock()`] can't return an error,
+/// an access panics if the lock file can't be opened or locked.  Check beforehand with
+/// [`LockKey::open_lock_file()`] to get an `io::Error` for this instead.
+///
+/// ## Example
+/// ```no_run
+/// use port_expander::LockKey;
+///
+/// struct Expander;
+/// impl LockKey for Expander {
+///     const BUS: u8 = 1;
+///     const ADDRESS: u8 = 0x20;
+/// }
+///
+/// # fn main() -> std::io::Result<()> {
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// Expander::open_lock_file()?;
+/// let mut pca9555: port_expander::Pca9555<port_expander::FileLockMutex<_, Expander>> =
+///     port_expander::Pca9555::with_mutex(i2c, false, false, false);
+/// let pca_pins = pca9555.split();
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "file-lock")]
+pub struct FileLockMutex<T, K: LockKey> {
+    port: std::sync::Mutex<T>,
+    file: std::sync::OnceLock<std::fs::File>,
+    token: u64,
+    _key: core::marker::PhantomData<K>,
+}
+
+#[cfg(feature = "file-lock")]
+impl<T, K: LockKey> FileLockMutex<T, K> {
+    /// The lock file, which is opened on first use.
+    fn file(&self) -> std::io::Result<&std::fs::File> {
+        if let Some(file) = self.file.get() {
+            return Ok(file);
+        }
+        let file = K::open_lock_file()?;
+        // the in-process mutex is held, so no other thread can have set it in the meantime
+        Ok(self.file.get_or_init(|| file))
+    }
+
+    /// Read the token of the last holder from the lock file.
+    fn last_token(file: &std::fs::File) -> u64 {
+        use std::io::{Read, Seek};
+
+        let mut buf = [0u8; 8];
+        let mut file = file;
+        match file.rewind().and_then(|_| file.read_exact(&mut buf)) {
+            Ok(()) => u64::from_le_bytes(buf),
+            Err(_) => 0,
+        }
+    }
+
+    fn store_token(&self, file: &std::fs::File) -> std::io::Result<()> {
+        use std::io::{Seek, Write};
+
+        let mut file = file;
+        file.rewind()?;
+        file.write_all(&self.token.to_le_bytes())
+    }
+}
+
+#[cfg(feature = "file-lock")]
+mod flock {
+    use std::os::fd::AsRawFd;
+
+    // from <sys/file.h>, the same on Linux and the BSDs
+    const LOCK_EX: core::ffi::c_int = 2;
+    const LOCK_UN: core::ffi::c_int = 8;
+
+    extern "C" {
+        fn flock(fd: core::ffi::c_int, operation: core::ffi::c_int) -> core::ffi::c_int;
+    }
+
+    fn call(file: &std::fs::File, operation: core::ffi::c_int) -> std::io::Result<()> {
+        // SAFETY: `flock()` only operates on the file descriptor, which stays open as long as
+        // `file` is borrowed.
+        match unsafe { flock(file.as_raw_fd(), operation) } {
+            0 => Ok(()),
+            _ => Err(std::io::Error::last_os_error()),
+        }
+    }
+
+    /// Take an exclusive lock on `file`, blocking until it is available.
+    pub(super) fn lock(file: &std::fs::File) -> std::io::Result<()> {
+        call(file, LOCK_EX)
+    }
+
+    /// Release the lock taken by [`lock()`].
+    pub(super) fn unlock(file: &std::fs::File) -> std::io::Result<()> {
+        call(file, LOCK_UN)
+    }
+}
+
+#[cfg(feature = "file-lock")]
+impl<T, K> PortMutex for FileLockMutex<T, K>
+where
+    T: crate::PortDriverResync + crate::PortDriverInputState,
+    K: LockKey,
+{
+    type Port = T;
+
+    fn create(v: Self::Port) -> Self {
+        static INSTANCES: core::sync::atomic::AtomicU32 = core::sync::atomic::AtomicU32::new(1);
+
+        let instance = INSTANCES.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+
+        Self {
+            port: std::sync::Mutex::new(v),
+            file: std::sync::OnceLock::new(),
+            token: ((std::process::id() as u64) << 32) | instance as u64,
+            _key: core::marker::PhantomData,
+        }
+    }
+
+    fn lock<R, F: FnOnce(&mut Self::Port) -> R>(&self, f: F) -> R {
+        struct Unlock<'a>(&'a std::fs::File);
+
+        impl Drop for Unlock<'_> {
+            fn drop(&mut self) {
+                let _ = flock::unlock(self.0);
+            }
+        }
+
+        let mut v = self.port.lock().unwrap();
+        let file = match self
+            .file()
+            .and_then(|file| flock::lock(file).map(|()| file))
+        {
+            Ok(file) => file,
+            Err(e) => panic!("failed locking port-expander lock file: {e}"),
+        };
+        let _unlock = Unlock(file);
+
+        if Self::last_token(file) != self.token {
+            v.input_state().invalidate();
+            // On failure, the token is not stored so the next access tries again.  The same
+            // happens if storing the token fails, which only costs another resync.
+            if v.resync().is_ok() {
+                let _ = self.store_token(file);
+            }
+        }
+        f(&mut v)
+    }
+}
+
+#[cfg(all(test, feature = "file-lock", feature = "pca9555"))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    struct Key;
+    impl super::LockKey for Key {
+        const BUS: u8 = 250;
+        const ADDRESS: u8 = 0x20;
+        const DIR: &'static str = "/tmp";
+    }
+
+    type Pca9555<I2C> = crate::Pca9555<super::FileLockMutex<crate::dev::pca9555::Driver<I2C>, Key>>;
+
+    #[test]
+    fn file_lock_resync() {
+        let _ = std::fs::remove_file("/tmp/port-expander-i2c-250-20.lock");
+
+        let expectations_a = [
+            // first access syncs the cache
+            mock_i2c::Transaction::write_read(0x20, vec![0x02], vec![0x00]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x03], vec![0x00]),
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0x01]),
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0x03]),
+            // b wrote in between
+            mock_i2c::Transaction::write_read(0x20, vec![0x02], vec![0x03]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x03], vec![0x80]),
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0x07]),
+        ];
+        let expectations_b = [
+            mock_i2c::Transaction::write_read(0x20, vec![0x02], vec![0x03]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x03], vec![0x00]),
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0x80]),
+        ];
+        let mut bus_a = mock_i2c::Mock::new(&expectations_a);
+        let mut bus_b = mock_i2c::Mock::new(&expectations_b);
+
+        let a: Pca9555<_> = crate::Pca9555::with_mutex(bus_a.clone(), false, false, false);
+        let b: Pca9555<_> = crate::Pca9555::with_mutex(bus_b.clone(), false, false, false);
+
+        a.write_mask(0x0001, 0).unwrap();
+        a.write_mask(0x0002, 0).unwrap();
+        b.write_mask(0x8000, 0).unwrap();
+        a.write_mask(0x0004, 0).unwrap();
+
+        bus_a.done();
+        bus_b.done();
+    }
+
+    #[test]
+    fn file_lock_missing_dir() {
+        use super::LockKey;
+
+        struct Missing;
+        impl super::LockKey for Missing {
+            const BUS: u8 = 250;
+            const ADDRESS: u8 = 0x21;
+            const DIR: &'static str = "/nonexistent/port-expander";
+        }
+
+        assert!(Missing::open_lock_file().is_err());
+
+        // constructing the device doesn't touch the lock file yet
+        let mut bus = mock_i2c::Mock::new(&[]);
+        let _: crate::Pca9555<super::FileLockMutex<_, Missing>> =
+            crate::Pca9555::with_mutex(bus.clone(), false, true, false);
+        bus.done();
+    }
+}
@@ -0,0 +1,178 @@
+use embedded_hal::i2c as hal_i2c;
+
+/// Minimal driver for the `PCA9548A` "Low voltage 8-channel I2C switch with reset".
+///
+/// The `PCA9548A` connects its upstream bus to one of eight downstream channels.  This allows
+/// using multiple port-expanders with the same I2C address, e.g. four `PCF8575` which can only be
+/// strapped to a few addresses.  [`channel()`](Pca9548::channel) hands out a
+/// [`Pca9548Channel`] which implements [`embedded_hal::i2c::I2c`] and can be passed to the
+/// constructor of any port-expander.  Before each transfer, the channel selects itself in the
+/// switch if a different channel was selected last.
+///
+/// The switch and its bus are protected by a [`PortMutex`](crate::PortMutex), just like a
+/// port-expander.  Use [`with_mutex()`](Pca9548::with_mutex) to share the channels across
+/// task/thread boundaries.
+///
+/// Only a single channel is ever enabled at a time.  Port-expanders behind different channels
+/// can thus use the same address.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// // A0: LOW, A1: LOW, A2: LOW
+/// let mux = port_expander::Pca9548::new(i2c, false, false, false);
+///
+/// let mut pcf0 = port_expander::Pcf8575::new(mux.channel(0), false, false, false);
+/// let mut pcf1 = port_expander::Pcf8575::new(mux.channel(1), false, false, false);
+/// let pcf0_pins = pcf0.split();
+/// let pcf1_pins = pcf1.split();
+/// ```
+pub struct Pca9548<M>(M);
+
+/// Bus and selection state of a [`Pca9548`].
+pub struct Pca9548Bus<I2C> {
+    i2c: I2C,
+    addr: u8,
+    selected: Option<u8>,
+}
+
+impl<I2C: hal_i2c::I2c> Pca9548Bus<I2C> {
+    fn select(&mut self, channel: u8) -> Result<(), I2C::Error> {
+        if self.selected != Some(channel) {
+            // the selection is unknown if the write fails
+            self.selected = None;
+            self.i2c.write(self.addr, &[1 << channel])?;
+            self.selected = Some(channel);
+        }
+        Ok(())
+    }
+}
+
+impl<I2C> Pca9548<core::cell::RefCell<Pca9548Bus<I2C>>>
+where
+    I2C: hal_i2c::I2c,
+{
+    pub fn new(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        Self::with_mutex(i2c, a0, a1, a2)
+    }
+}
+
+impl<I2C, M> Pca9548<M>
+where
+    I2C: hal_i2c::I2c,
+    M: crate::PortMutex<Port = Pca9548Bus<I2C>>,
+{
+    pub fn with_mutex(i2c: I2C, a0: bool, a1: bool, a2: bool) -> Self {
+        let addr = 0x70 | ((a2 as u8) << 2) | ((a1 as u8) << 1) | (a0 as u8);
+        Self(crate::PortMutex::create(Pca9548Bus {
+            i2c,
+            addr,
+            selected: None,
+        }))
+    }
+
+    /// Get a bus for the downstream channel `channel`.
+    ///
+    /// # Panics
+    /// `channel` must be in the range `0..=7`.
+    pub fn channel(&self, channel: u8) -> Pca9548Channel<'_, M> {
+        assert!(channel < 8);
+        Pca9548Channel {
+            mux: &self.0,
+            channel,
+        }
+    }
+}
+
+/// One downstream channel of a [`Pca9548`].
+pub struct Pca9548Channel<'a, M> {
+    mux: &'a M,
+    channel: u8,
+}
+
+impl<I2C, M> hal_i2c::ErrorType for Pca9548Channel<'_, M>
+where
+    I2C: hal_i2c::I2c,
+    M: crate::PortMutex<Port = Pca9548Bus<I2C>>,
+{
+    type Error = I2C::Error;
+}
+
+impl<I2C, M> hal_i2c::I2c for Pca9548Channel<'_, M>
+where
+    I2C: hal_i2c::I2c,
+    M: crate::PortMutex<Port = Pca9548Bus<I2C>>,
+{
+    fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        self.mux.lock(|mux| {
+            mux.select(self.channel)?;
+            mux.i2c.read(address, read)
+        })
+    }
+
+    fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        self.mux.lock(|mux| {
+            mux.select(self.channel)?;
+            mux.i2c.write(address, write)
+        })
+    }
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.mux.lock(|mux| {
+            mux.select(self.channel)?;
+            mux.i2c.write_read(address, write, read)
+        })
+    }
+
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [hal_i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.mux.lock(|mux| {
+            mux.select(self.channel)?;
+            mux.i2c.transaction(address, operations)
+        })
+    }
+}
+
+#[cfg(all(test, feature = "pcf8575"))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pcf8575_behind_mux() {
+        let expectations = [
+            // channel 0: p00 low
+            mock_i2c::Transaction::write(0x71, vec![0x01]),
+            mock_i2c::Transaction::write(0x20, vec![0b11111110, 0b11111111]),
+            // channel 3: p10 low
+            mock_i2c::Transaction::write(0x71, vec![0x08]),
+            mock_i2c::Transaction::write(0x20, vec![0b11111111, 0b11111110]),
+            // channel 3: read p17, already selected
+            mock_i2c::Transaction::read(0x20, vec![0b00000000, 0b10000000]),
+            // channel 0: read p07
+            mock_i2c::Transaction::write(0x71, vec![0x01]),
+            mock_i2c::Transaction::read(0x20, vec![0b00000000, 0b00000000]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mux = super::Pca9548::new(bus.clone(), true, false, false);
+        let mut pcf0 = crate::Pcf8575::new(mux.channel(0), false, false, false);
+        let mut pcf3 = crate::Pcf8575::new(mux.channel(3), false, false, false);
+        let mut pcf0_pins = pcf0.split();
+        let mut pcf3_pins = pcf3.split();
+
+        pcf0_pins.p00.set_low().unwrap();
+        pcf3_pins.p10.set_low().unwrap();
+        assert!(pcf3_pins.p17.is_high().unwrap());
+        assert!(pcf0_pins.p07.is_low().unwrap());
+
+        bus.done();
+    }
+}
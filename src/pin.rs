@@ -5,6 +5,11 @@ use embedded_hal::digital::{self as hal_digital};
 ///
 /// `Pin` is not constructed directly, this type is created by instanciating a port-expander and
 /// then getting access to all its pins using the `.split()` method.
+///
+/// Mode changes like [`into_output()`](Pin::into_output) consume the pin and return it in its new
+/// mode.  Dropping the returned pin makes it inaccessible for the rest of the program, so it is
+/// `#[must_use]`.
+#[must_use = "a dropped pin cannot be accessed again"]
 pub struct Pin<'a, MODE, MUTEX> {
     pin_mask: u32,
     port_driver: &'a MUTEX,
@@ -29,10 +34,42 @@ where
         self.pin_mask
     }
 
-    pub(crate) fn port_driver(&self) -> &MUTEX {
+    /// Number of this pin on its port-expander, i.e. the bit which is set in
+    /// [`pin_mask()`](Pin::pin_mask).
+    ///
+    /// This is the index of the pin in the device's `pins()`, except for the `MAX7300`, whose
+    /// ports are numbered from 4 like in the datasheet.
+    pub fn pin_index(&self) -> u8 {
+        self.pin_mask.trailing_zeros() as u8
+    }
+
+    pub(crate) fn port_driver(&self) -> &'a MUTEX {
         self.port_driver
     }
 
+    /// Get a read-only view of this pin.
+    ///
+    /// The view can be handed to diagnostic or monitoring code which should observe the input
+    /// while this pin stays with its owner.  It only borrows the port-expander, not this pin.
+    pub fn read_only(&self) -> Pin<'a, crate::mode::ReadOnly, MUTEX> {
+        Pin {
+            pin_mask: self.pin_mask,
+            port_driver: self.port_driver,
+            _m: PhantomData,
+        }
+    }
+
+    /// Change the type-level mode of this pin without touching the hardware.
+    ///
+    /// Drivers with device-specific pin modes use this after reconfiguring the pin themselves.
+    pub(crate) fn into_mode<NEWMODE>(self) -> Pin<'a, NEWMODE, MUTEX> {
+        Pin {
+            pin_mask: self.pin_mask,
+            port_driver: self.port_driver,
+            _m: PhantomData,
+        }
+    }
+
     pub fn access_port_driver<F, R>(&self, f: F) -> R
     where
         F: FnOnce(&mut PD) -> R,
@@ -43,6 +80,7 @@ where
 
 /// Error type for [`Pin`] which implements [`embedded_hal::digital::Error`].
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct PinError<PDE> {
     driver_error: PDE,
 }
@@ -82,13 +120,17 @@ where
 
 impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
 where
-    PD: crate::PortDriver + crate::PortDriverTotemPole,
+    PD: crate::PortDriver,
     MUTEX: crate::PortMutex<Port = PD>,
 {
     /// Configure this pin as an input.
     ///
     /// The exact electrical details depend on the port-expander device which is used.
-    pub fn into_input(self) -> Result<Pin<'a, crate::mode::Input, MUTEX>, PinError<PD::Error>> {
+    pub fn into_input(self) -> Result<Pin<'a, crate::mode::Input, MUTEX>, PinError<PD::Error>>
+    where
+        MODE: crate::mode::Configurable,
+        PD: crate::PortDriverTotemPole,
+    {
         self.port_driver
             .lock(|drv| drv.set_direction(self.pin_mask, crate::Direction::Input, false))?;
         Ok(Pin {
@@ -102,7 +144,11 @@ where
     ///
     /// The LOW state is, as long as he port-expander chip allows this, entered without any
     /// electrical glitch.
-    pub fn into_output(self) -> Result<Pin<'a, crate::mode::Output, MUTEX>, PinError<PD::Error>> {
+    pub fn into_output(self) -> Result<Pin<'a, crate::mode::Output, MUTEX>, PinError<PD::Error>>
+    where
+        MODE: crate::mode::Configurable,
+        PD: crate::PortDriverTotemPole,
+    {
         self.port_driver
             .lock(|drv| drv.set_direction(self.pin_mask, crate::Direction::Output, false))?;
         Ok(Pin {
@@ -118,7 +164,11 @@ where
     /// electrical glitch.
     pub fn into_output_high(
         self,
-    ) -> Result<Pin<'a, crate::mode::Output, MUTEX>, PinError<PD::Error>> {
+    ) -> Result<Pin<'a, crate::mode::Output, MUTEX>, PinError<PD::Error>>
+    where
+        MODE: crate::mode::Configurable,
+        PD: crate::PortDriverTotemPole,
+    {
         self.port_driver
             .lock(|drv| drv.set_direction(self.pin_mask, crate::Direction::Output, true))?;
         Ok(Pin {
@@ -127,76 +177,319 @@ where
             _m: PhantomData,
         })
     }
+
+    /// Configure this pin as an output with the initial state `state`.
+    ///
+    /// See [`into_output()`](Pin::into_output) and [`into_output_high()`](Pin::into_output_high).
+    pub fn into_output_with(
+        self,
+        state: hal_digital::PinState,
+    ) -> Result<Pin<'a, crate::mode::Output, MUTEX>, PinError<PD::Error>>
+    where
+        MODE: crate::mode::Configurable,
+        PD: crate::PortDriverTotemPole,
+    {
+        match state {
+            hal_digital::PinState::Low => self.into_output(),
+            hal_digital::PinState::High => self.into_output_high(),
+        }
+    }
 }
 
 impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
 where
-    PD: crate::PortDriver + crate::PortDriverPolarity,
+    PD: crate::PortDriver,
     MUTEX: crate::PortMutex<Port = PD>,
 {
     /// Turn on hardware polarity inversion for this pin.
-    pub fn into_inverted(self) -> Result<Self, PinError<PD::Error>> {
+    pub fn into_inverted(self) -> Result<Self, PinError<PD::Error>>
+    where
+        MODE: crate::mode::Configurable,
+        PD: crate::PortDriverPolarity,
+    {
         self.port_driver
             .lock(|drv| drv.set_polarity(self.pin_mask, true))?;
         Ok(self)
     }
 
     /// Set hardware polarity inversion for this pin.
-    pub fn set_inverted(&mut self, inverted: bool) -> Result<(), PinError<PD::Error>> {
+    pub fn set_inverted(&mut self, inverted: bool) -> Result<(), PinError<PD::Error>>
+    where
+        MODE: crate::mode::Configurable,
+        PD: crate::PortDriverPolarity,
+    {
         self.port_driver
             .lock(|drv| drv.set_polarity(self.pin_mask, inverted))?;
         Ok(())
     }
 }
 
-impl<'a, MODE: crate::mode::HasInput, MUTEX, PD> Pin<'a, MODE, MUTEX>
+impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Turn on software output inversion for this pin.
+    ///
+    /// See [`set_output_inverted()`](Pin::set_output_inverted).
+    pub fn into_output_inverted(mut self) -> Self
+    where
+        MODE: crate::mode::Configurable,
+        PD: crate::PortDriverOutputInversion,
+    {
+        self.set_output_inverted(true);
+        self
+    }
+
+    /// Set software output inversion for this pin.
+    ///
+    /// The hardware polarity inversion of most port-expanders only affects inputs.  With output
+    /// inversion, the driver swaps HIGH and LOW for all writes to this pin instead, so e.g.
+    /// [`set_high()`](Pin::set_high) drives it LOW.  This is useful for active-low wiring.
+    ///
+    /// No bus transaction is performed, the current electrical state of the pin stays the same.
+    /// Configure the inversion before making the pin an output, so it enters the intended state
+    /// right away.
+    pub fn set_output_inverted(&mut self, inverted: bool)
+    where
+        MODE: crate::mode::Configurable,
+        PD: crate::PortDriverOutputInversion,
+    {
+        self.port_driver
+            .lock(|drv| drv.output_inversion().set(self.pin_mask, inverted));
+    }
+}
+
+impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
 where
     PD: crate::PortDriver,
     MUTEX: crate::PortMutex<Port = PD>,
 {
     /// Read the pin's input state and return `true` if it is HIGH.
-    pub fn is_high(&self) -> Result<bool, PinError<PD::Error>> {
+    pub fn is_high(&self) -> Result<bool, PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasInput,
+    {
         self.port_driver
             .lock(|drv| Ok(drv.get(self.pin_mask, 0)? == self.pin_mask))
     }
 
     /// Read the pin's input state and return `true` if it is LOW.
-    pub fn is_low(&self) -> Result<bool, PinError<PD::Error>> {
+    pub fn is_low(&self) -> Result<bool, PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasInput,
+    {
         self.port_driver
             .lock(|drv| Ok(drv.get(0, self.pin_mask)? == self.pin_mask))
     }
 }
 
-impl<'a, MODE: crate::mode::HasInput, MUTEX, PD> Pin<'a, MODE, MUTEX>
+impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
 where
-    PD: crate::PortDriver + crate::PortDriverPullUp,
+    PD: crate::PortDriver,
     MUTEX: crate::PortMutex<Port = PD>,
 {
+    /// Enable the pull-up resistor for this pin.
+    ///
+    /// See [`enable_pull_up()`](Pin::enable_pull_up).
+    pub fn into_pull_up(mut self) -> Result<Self, PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasInput + crate::mode::Configurable,
+        PD: crate::PortDriverPullUp,
+    {
+        self.enable_pull_up(true)?;
+        Ok(self)
+    }
+
     /// Enable/Disable pull-up resistors for this pin.
     ///
     /// If `enable` is `true`, the pull-up resistor is enabled, otherwise the pin is configured as floating input.
-    pub fn enable_pull_up(&mut self, enable: bool) -> Result<(), PinError<PD::Error>> {
+    pub fn enable_pull_up(&mut self, enable: bool) -> Result<(), PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasInput + crate::mode::Configurable,
+        PD: crate::PortDriverPullUp,
+    {
         self.port_driver
             .lock(|drv| drv.set_pull_up(self.pin_mask, enable))?;
         Ok(())
     }
 }
 
-impl<'a, MODE: crate::mode::HasInput, MUTEX, PD> Pin<'a, MODE, MUTEX>
+impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
 where
-    PD: crate::PortDriver + crate::PortDriverPullDown,
+    PD: crate::PortDriver,
     MUTEX: crate::PortMutex<Port = PD>,
 {
+    /// Enable the pull-down resistor for this pin.
+    ///
+    /// See [`enable_pull_down()`](Pin::enable_pull_down).
+    pub fn into_pull_down(mut self) -> Result<Self, PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasInput + crate::mode::Configurable,
+        PD: crate::PortDriverPullDown,
+    {
+        self.enable_pull_down(true)?;
+        Ok(self)
+    }
+
     /// Enable/Disable pull-down resistors for this pin.
     ///
     /// If `enable` is `true`, the pull-down resistor is enabled, otherwise the pin is configured as floating input.
-    pub fn enable_pull_down(&mut self, enable: bool) -> Result<(), PinError<PD::Error>> {
+    pub fn enable_pull_down(&mut self, enable: bool) -> Result<(), PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasInput + crate::mode::Configurable,
+        PD: crate::PortDriverPullDown,
+    {
         self.port_driver
             .lock(|drv| drv.set_pull_down(self.pin_mask, enable))?;
         Ok(())
     }
 }
 
+impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Configure this pin as an input with its pull-up resistor enabled.
+    ///
+    /// Unlike [`into_input()`](Pin::into_input) followed by
+    /// [`enable_pull_up()`](Pin::enable_pull_up), the pull-up is enabled before the pin becomes an
+    /// input, so it never floats.  Both happen while the port-expander is locked once.
+    pub fn into_pull_up_input(
+        self,
+    ) -> Result<Pin<'a, crate::mode::Input, MUTEX>, PinError<PD::Error>>
+    where
+        MODE: crate::mode::Configurable,
+        PD: crate::PortDriverTotemPole + crate::PortDriverPullUp,
+    {
+        self.port_driver
+            .lock(|drv| drv.set_input_pull_up(self.pin_mask))?;
+        Ok(self.into_mode())
+    }
+}
+
+impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Configure this pin as an input with its pull-down resistor enabled.
+    ///
+    /// See [`into_pull_up_input()`](Pin::into_pull_up_input).
+    pub fn into_pull_down_input(
+        self,
+    ) -> Result<Pin<'a, crate::mode::Input, MUTEX>, PinError<PD::Error>>
+    where
+        MODE: crate::mode::Configurable,
+        PD: crate::PortDriverTotemPole + crate::PortDriverPullDown,
+    {
+        self.port_driver
+            .lock(|drv| drv.set_input_pull_down(self.pin_mask))?;
+        Ok(self.into_mode())
+    }
+}
+
+impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Configure this pin as an input with the given pull resistor.
+    ///
+    /// The pull resistors are configured before the pin becomes an input, so it never briefly
+    /// floats or pulls against the requested [`Pull`](crate::Pull).  Both happen while the
+    /// port-expander is locked once.  On chips without pull-down resistors, [`Pull::Down`](crate::Pull::Down) returns
+    /// [`PullError::Unsupported`](crate::PullError::Unsupported).
+    pub fn into_input_with_pull(
+        self,
+        pull: crate::Pull,
+    ) -> Result<Pin<'a, crate::mode::Input, MUTEX>, crate::PullError<PD::Error>>
+    where
+        MODE: crate::mode::Configurable,
+        PD: crate::PortDriverTotemPole + crate::PortDriverPullUp,
+    {
+        self.port_driver
+            .lock(|drv| drv.set_input_pull(self.pin_mask, pull))?;
+        Ok(self.into_mode())
+    }
+}
+
+impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Configure when this input raises an interrupt.
+    ///
+    /// Use [`irq_state()`](crate::irq_state) to find out which pin caused an interrupt.
+    pub fn set_interrupt(&mut self, mode: crate::InterruptMode) -> Result<(), PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasInput + crate::mode::Configurable,
+        PD: crate::PortDriverInterrupt,
+    {
+        self.port_driver
+            .lock(|drv| drv.set_interrupt(self.pin_mask, mode))?;
+        Ok(())
+    }
+}
+
+impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Put this output into high-impedance state.
+    ///
+    /// See [`set_high_impedance()`](Pin::set_high_impedance).
+    pub fn into_high_impedance(mut self) -> Result<Self, PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasOutput,
+        PD: crate::PortDriverHighImpedance,
+    {
+        self.set_high_impedance(true)?;
+        Ok(self)
+    }
+
+    /// Tri-state this output or let it drive its output state again.
+    ///
+    /// While `enable` is `true`, the pin neither drives HIGH nor LOW, e.g. to release a line which
+    /// is shared with other drivers.  The output state can still be changed in the meantime and
+    /// is driven again after calling this with `false`.
+    pub fn set_high_impedance(&mut self, enable: bool) -> Result<(), PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasOutput,
+        PD: crate::PortDriverHighImpedance,
+    {
+        self.port_driver
+            .lock(|drv| drv.set_high_impedance(self.pin_mask, enable))?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "force-input")]
+impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Force the input state reported for this pin, overriding the physical input.
+    ///
+    /// With `Some(state)`, all reads of this pin report `state`, no matter which level is actually
+    /// present on the pin.  `None` returns to reading the physical input.  This is meant for
+    /// hardware-in-the-loop tests which need to simulate inputs without changing application code.
+    ///
+    /// Only available with the `force-input` feature.
+    pub fn force_input(&mut self, state: Option<bool>)
+    where
+        MODE: crate::mode::HasInput + crate::mode::Configurable,
+        PD: crate::PortDriverInputState,
+    {
+        self.port_driver
+            .lock(|drv| drv.input_state().force(self.pin_mask, state));
+    }
+}
+
 impl<'a, MODE: crate::mode::HasInput, MUTEX, PD> hal_digital::InputPin for Pin<'a, MODE, MUTEX>
 where
     PD: crate::PortDriver,
@@ -212,7 +505,7 @@ where
     }
 }
 
-impl<'a, MODE: crate::mode::HasOutput, MUTEX, PD> Pin<'a, MODE, MUTEX>
+impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
 where
     PD: crate::PortDriver,
     MUTEX: crate::PortMutex<Port = PD>,
@@ -220,7 +513,10 @@ where
     /// Set the pin's output state to HIGH.
     ///
     /// Note that this can have different electrical meanings depending on the port-expander chip.
-    pub fn set_high(&mut self) -> Result<(), PinError<PD::Error>> {
+    pub fn set_high(&mut self) -> Result<(), PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasOutput,
+    {
         self.port_driver.lock(|drv| drv.set(self.pin_mask, 0))?;
         Ok(())
     }
@@ -228,7 +524,10 @@ where
     /// Set the pin's output state to LOW.
     ///
     /// Note that this can have different electrical meanings depending on the port-expander chip.
-    pub fn set_low(&mut self) -> Result<(), PinError<PD::Error>> {
+    pub fn set_low(&mut self) -> Result<(), PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasOutput,
+    {
         self.port_driver.lock(|drv| drv.set(0, self.pin_mask))?;
         Ok(())
     }
@@ -236,7 +535,10 @@ where
     /// Return `true` if the pin's output state is HIGH.
     ///
     /// This method does **not** read the pin's electrical state.
-    pub fn is_set_high(&self) -> Result<bool, PinError<PD::Error>> {
+    pub fn is_set_high(&self) -> Result<bool, PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasOutput,
+    {
         self.port_driver
             .lock(|drv| Ok(drv.is_set(self.pin_mask, 0)? == self.pin_mask))
     }
@@ -244,18 +546,109 @@ where
     /// Return `true` if the pin's output state is LOW.
     ///
     /// This method does **not** read the pin's electrical state.
-    pub fn is_set_low(&self) -> Result<bool, PinError<PD::Error>> {
+    pub fn is_set_low(&self) -> Result<bool, PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasOutput,
+    {
         self.port_driver
             .lock(|drv| Ok(drv.is_set(0, self.pin_mask)? == self.pin_mask))
     }
 
     /// Toggle the pin's output state.
-    pub fn toggle(&mut self) -> Result<(), PinError<PD::Error>> {
+    pub fn toggle(&mut self) -> Result<(), PinError<PD::Error>>
+    where
+        MODE: crate::mode::HasOutput,
+    {
         self.port_driver.lock(|drv| drv.toggle(self.pin_mask))?;
         Ok(())
     }
 }
 
+/// Error returned by [`Pin::set_high_verified()`] and [`Pin::set_low_verified()`].
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VerifiedSetError<PDE> {
+    /// The port driver reported an error.
+    Driver(PDE),
+    /// The pin was set, but reading it back showed a different level.
+    Mismatch,
+}
+
+impl<PDE> hal_digital::Error for VerifiedSetError<PDE>
+where
+    PDE: core::fmt::Debug,
+{
+    fn kind(&self) -> hal_digital::ErrorKind {
+        hal_digital::ErrorKind::Other
+    }
+}
+
+impl<PDE> From<PDE> for VerifiedSetError<PDE> {
+    fn from(value: PDE) -> Self {
+        Self::Driver(value)
+    }
+}
+
+impl<'a, MODE, MUTEX, PD> Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Set the pin's output state to HIGH and check that the pin actually reached this level.
+    ///
+    /// After setting the output, the input register is read back while still holding the lock.
+    /// If the electrical level of the pin does not match, [`VerifiedSetError::Mismatch`] is
+    /// returned.  This catches e.g. shorted outputs or a quasi-bidirectional pin which is held LOW
+    /// externally.  Both software output inversion and hardware polarity inversion of the input
+    /// (see [`set_inverted()`](Pin::set_inverted)) are taken into account, the latter costs
+    /// another register read on chips which support it.
+    pub fn set_high_verified(&mut self) -> Result<(), VerifiedSetError<PD::Error>>
+    where
+        MODE: crate::mode::HasOutput,
+        PD: crate::PortDriverInputState + crate::PortDriverOutputInversion,
+    {
+        self.set_verified(true)
+    }
+
+    /// Set the pin's output state to LOW and check that the pin actually reached this level.
+    ///
+    /// See [`set_high_verified()`](Pin::set_high_verified).
+    pub fn set_low_verified(&mut self) -> Result<(), VerifiedSetError<PD::Error>>
+    where
+        MODE: crate::mode::HasOutput,
+        PD: crate::PortDriverInputState + crate::PortDriverOutputInversion,
+    {
+        self.set_verified(false)
+    }
+
+    fn set_verified(&mut self, state: bool) -> Result<(), VerifiedSetError<PD::Error>>
+    where
+        MODE: crate::mode::HasOutput,
+        PD: crate::PortDriverInputState + crate::PortDriverOutputInversion,
+    {
+        let mask = self.pin_mask;
+        self.port_driver.lock(|drv| {
+            let (mask_high, mask_low) = if state { (mask, 0) } else { (0, mask) };
+            drv.set(mask_high, mask_low)?;
+
+            // the level must be read from the pin, not from an older snapshot
+            drv.input_state().invalidate();
+            let (mask_high, mask_low) = drv.output_inversion().apply(mask_high, mask_low);
+            // an inverted input reads back the opposite level
+            let inverted = drv.get_polarity(mask)?;
+            let (mask_high, mask_low) = (
+                (mask_high & !inverted) | (mask_low & inverted),
+                (mask_low & !inverted) | (mask_high & inverted),
+            );
+            if drv.get(mask_high, mask_low)? == mask {
+                Ok(())
+            } else {
+                Err(VerifiedSetError::Mismatch)
+            }
+        })
+    }
+}
+
 impl<'a, MODE: crate::mode::HasOutput, MUTEX, PD> hal_digital::OutputPin for Pin<'a, MODE, MUTEX>
 where
     PD: crate::PortDriver,
@@ -290,3 +683,50 @@ where
         Pin::toggle(self)
     }
 }
+
+/// Name the full type of a port-expander pin.
+///
+/// Pin types are long to spell out, e.g. when pins are stored in a struct of RTIC resources.
+/// `pin_type!()` expands to the full [`Pin`] type from the name of the device module, the pin
+/// mode, the bus type and optionally the mutex type, using `_` in place of the driver:
+///
+/// ```
+/// # type I2c = embedded_hal_mock::eh1::i2c::Mock;
+/// struct MainLoop;
+/// unsafe impl port_expander::SingleContext for MainLoop {}
+///
+/// struct Resources {
+///     // Pin<'static, mode::Output, RefCell<pca9555::Driver<I2c>>>
+///     led: port_expander::pin_type!('static, pca9555, Output, I2c),
+///     // Pin<'static, mode::QuasiBidirectional, std::sync::Mutex<pcf8574::Driver<I2c>>>
+///     button: port_expander::pin_type!('static, pcf8574, QuasiBidirectional, I2c, std::sync::Mutex<_>),
+///     // Pin<'static, mode::Input, NullMutex<tca6408a::Driver<I2c>, MainLoop>>
+///     sensor: port_expander::pin_type!('static, tca6408a, Input, I2c, port_expander::NullMutex<_, MainLoop>),
+/// }
+/// ```
+///
+/// Nested mutex types like `critical_section::Mutex<core::cell::RefCell<_>>` are supported as
+/// well.
+#[macro_export]
+macro_rules! pin_type {
+    ($lt:lifetime, $dev:ident, $mode:ident, $bus:ty) => {
+        $crate::pin_type!($lt, $dev, $mode, $bus, core::cell::RefCell<_>)
+    };
+    ($lt:lifetime, $dev:ident, $mode:ident, $bus:ty, $($mutex:ident)::+ < $($inner:ident)::+ < _ > >) => {
+        $crate::pin_type!($lt, $dev, $mode, $bus, $($mutex)::+<$($inner)::+<_>>)
+    };
+    ($lt:lifetime, $dev:ident, $mode:ident, $bus:ty, $($mutex:ident)::+ < $($inner:ident)::+ < _ >>) => {
+        $crate::Pin<
+            $lt,
+            $crate::mode::$mode,
+            $($mutex)::+<$($inner)::+<$crate::dev::$dev::Driver<$bus>>>,
+        >
+    };
+    ($lt:lifetime, $dev:ident, $mode:ident, $bus:ty, $($mutex:ident)::+ < _ $(, $extra:ty)* >) => {
+        $crate::Pin<
+            $lt,
+            $crate::mode::$mode,
+            $($mutex)::+<$crate::dev::$dev::Driver<$bus> $(, $extra)*>,
+        >
+    };
+}
@@ -0,0 +1,194 @@
+use embedded_hal::delay::DelayNs;
+
+/// Ordered power-down of outputs spread over multiple port-expanders.
+///
+/// Battery-powered devices often need to switch off their rails in a fixed order, with some time
+/// in between for each rail to discharge.  A `PowerDownSequence` collects up to `N` pins, possibly
+/// of different port-expanders, each with an `order` in which it is switched, the state it should
+/// be switched to, and a time to wait after its step.  [`shutdown()`](Self::shutdown) then
+/// executes the sequence:
+///
+/// - Steps are executed in ascending `order`.
+/// - All pins of a step which belong to the same port-expander are switched in a single bus
+///   transaction.
+/// - After each step except the last one, `delay` waits for the longest `delay_us` of the pins in
+///   this step.
+///
+/// Pins are added as [`AnyPin`](crate::AnyPin) so pins of different chips can be mixed.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut delay = embedded_hal_mock::eh1::delay::NoopDelay::new();
+/// # let mut pcf = port_expander::Pcf8574::new(i2c.clone(), false, false, false);
+/// # let mut pca = port_expander::Pca9555::new(i2c, false, false, false);
+/// # let pcf_pins = pcf.split();
+/// # let pca_pins = pca.split();
+/// let mut sequence = port_expander::PowerDownSequence::<3>::new();
+/// // first the 5V rail, then wait 10ms for it to discharge
+/// sequence.add(pcf_pins.p0.into_any(), 0, false, 10_000).ok().unwrap();
+/// // then the 3.3V rail and the backlight at the same time
+/// sequence.add(pca_pins.io0_0.into_any(), 1, false, 0).ok().unwrap();
+/// sequence.add(pcf_pins.p4.into_any(), 1, false, 0).ok().unwrap();
+///
+/// sequence.shutdown(&mut delay).unwrap();
+/// ```
+pub struct PowerDownSequence<'a, const N: usize> {
+    entries: [Option<Entry<'a>>; N],
+}
+
+struct Entry<'a> {
+    pin: crate::AnyPin<'a>,
+    order: u8,
+    state: bool,
+    delay_us: u32,
+}
+
+impl<'a, const N: usize> Default for PowerDownSequence<'a, N> {
+    fn default() -> Self {
+        Self {
+            entries: core::array::from_fn(|_| None),
+        }
+    }
+}
+
+impl<'a, const N: usize> PowerDownSequence<'a, N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `pin` to the step `order`, switching it to `state` and waiting at least `delay_us`
+    /// microseconds after the step.
+    ///
+    /// The pin is not touched until [`shutdown()`](Self::shutdown).  If the sequence is already
+    /// full, the pin is handed back as the error.
+    pub fn add(
+        &mut self,
+        pin: crate::AnyPin<'a>,
+        order: u8,
+        state: bool,
+        delay_us: u32,
+    ) -> Result<(), crate::AnyPin<'a>> {
+        match self.entries.iter_mut().find(|e| e.is_none()) {
+            Some(slot) => {
+                *slot = Some(Entry {
+                    pin,
+                    order,
+                    state,
+                    delay_us,
+                });
+                Ok(())
+            }
+            None => Err(pin),
+        }
+    }
+
+    /// Execute the power-down sequence.
+    pub fn shutdown<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), crate::AnyPinError> {
+        let mut last_order = None;
+        loop {
+            let order = self
+                .entries
+                .iter()
+                .flatten()
+                .map(|e| e.order)
+                .filter(|order| last_order.is_none_or(|last| *order > last))
+                .min();
+            let order = match order {
+                Some(order) => order,
+                None => return Ok(()),
+            };
+
+            if let Some(last) = last_order {
+                let delay_us = self
+                    .entries
+                    .iter()
+                    .flatten()
+                    .filter(|e| e.order == last)
+                    .map(|e| e.delay_us)
+                    .max()
+                    .unwrap_or(0);
+                delay.delay_us(delay_us);
+            }
+
+            let step: &[Option<Entry<'a>>] = &self.entries;
+            let in_step = |e: &&Entry<'_>| e.order == order;
+            for (i, entry) in step.iter().enumerate() {
+                let entry = match entry.as_ref().filter(|e| in_step(e)) {
+                    Some(entry) => entry,
+                    None => continue,
+                };
+                // each chip is handled together with its first pin in this step
+                if step[..i]
+                    .iter()
+                    .flatten()
+                    .filter(in_step)
+                    .any(|e| e.pin.same_chip(&entry.pin))
+                {
+                    continue;
+                }
+
+                let mut mask_high = 0x00;
+                let mut mask_low = 0x00;
+                for e in step[i..]
+                    .iter()
+                    .flatten()
+                    .filter(in_step)
+                    .filter(|e| e.pin.same_chip(&entry.pin))
+                {
+                    if e.state {
+                        mask_high |= e.pin.pin_mask();
+                    } else {
+                        mask_low |= e.pin.pin_mask();
+                    }
+                }
+                entry.pin.set_port(mask_high, mask_low)?;
+            }
+
+            last_order = Some(order);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "pca9536", feature = "pcf8574"))]
+mod tests {
+    use embedded_hal_mock::eh1::delay as mock_delay;
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn power_down_two_chips() {
+        let expectations = [
+            // step 0
+            mock_i2c::Transaction::write(0x20, vec![0b11111100]),
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xfb]),
+            // step 1
+            mock_i2c::Transaction::write(0x20, vec![0b11101100]),
+            // step 5
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xfa]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+        let delay_expectations = [
+            mock_delay::Transaction::blocking_delay_us(2000),
+            mock_delay::Transaction::blocking_delay_us(0),
+        ];
+        let mut delay = mock_delay::CheckedDelay::new(&delay_expectations);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), false, false, false);
+        let pcf_pins = pcf.split();
+        let mut pca = crate::Pca9536::new(bus.clone());
+        let pca_pins = pca.split();
+
+        let mut sequence = super::PowerDownSequence::<5>::new();
+        assert!(sequence.add(pca_pins.io0.into_any(), 5, false, 100).is_ok());
+        assert!(sequence.add(pcf_pins.p0.into_any(), 0, false, 1000).is_ok());
+        assert!(sequence.add(pca_pins.io2.into_any(), 0, false, 0).is_ok());
+        assert!(sequence.add(pcf_pins.p4.into_any(), 1, false, 0).is_ok());
+        assert!(sequence.add(pcf_pins.p1.into_any(), 0, false, 2000).is_ok());
+        assert!(sequence.add(pcf_pins.p2.into_any(), 0, true, 0).is_err());
+
+        sequence.shutdown(&mut delay).unwrap();
+
+        bus.done();
+        delay.done();
+    }
+}
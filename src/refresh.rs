@@ -0,0 +1,140 @@
+/// Write the current output state of the pins in `mask` to the port-expander again.
+///
+/// Drivers normally only write to the chip when an output changes, and some skip the write
+/// entirely when it would not change anything.  `refresh()` bypasses this and rewrites all
+/// output registers containing pins in `mask` from the cached state.  This is useful for
+/// external watchdogs or safety relays which expect to see their line written periodically.
+/// See [`RefreshSchedule`] for doing this every N ticks.
+///
+/// `pin` can be any pin of the port-expander, it is only used to find the chip.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// let mut pca9555 = port_expander::Pca9555::new(i2c, false, false, false);
+/// let p = pca9555.split();
+/// let relay = p.io1_0.into_output_high().unwrap();
+/// port_expander::refresh(&relay, relay.pin_mask()).unwrap();
+/// ```
+pub fn refresh<PD, MUTEX, MODE>(
+    pin: &crate::Pin<'_, MODE, MUTEX>,
+    mask: u32,
+) -> Result<(), PD::Error>
+where
+    PD: crate::PortDriver + crate::PortDriverRefresh,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    pin.port_driver().lock(|drv| drv.refresh(mask))
+}
+
+/// Periodically [`refresh()`] outputs of a port-expander.
+///
+/// Call [`tick()`](RefreshSchedule::tick) from a timer or the main loop.  Every `period`-th tick,
+/// the outputs in `mask` are rewritten.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// let mut pca9555 = port_expander::Pca9555::new(i2c, false, false, false);
+/// let p = pca9555.split();
+/// let relay = p.io1_0.into_output_high().unwrap();
+///
+/// // with a 10 ms tick, the relay line is rewritten every 100 ms
+/// let mut schedule = port_expander::RefreshSchedule::new(relay.pin_mask(), 10);
+/// loop {
+///     // wait for the next tick ...
+///     schedule.tick(&relay).unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RefreshSchedule {
+    mask: u32,
+    period: u32,
+    elapsed: u32,
+}
+
+impl RefreshSchedule {
+    /// Refresh the outputs in `mask` every `period` ticks.
+    ///
+    /// # Panics
+    /// `period` must not be 0.
+    pub const fn new(mask: u32, period: u32) -> Self {
+        assert!(period > 0);
+        Self {
+            mask,
+            period,
+            elapsed: 0,
+        }
+    }
+
+    /// Advance the schedule by one tick and refresh the outputs when their period is over.
+    ///
+    /// Returns `true` if the outputs were refreshed during this tick.  `pin` can be any pin of
+    /// the port-expander.
+    pub fn tick<PD, MUTEX, MODE>(
+        &mut self,
+        pin: &crate::Pin<'_, MODE, MUTEX>,
+    ) -> Result<bool, PD::Error>
+    where
+        PD: crate::PortDriver + crate::PortDriverRefresh,
+        MUTEX: crate::PortMutex<Port = PD>,
+    {
+        self.elapsed += 1;
+        if self.elapsed < self.period {
+            return Ok(false);
+        }
+        self.elapsed = 0;
+        refresh(pin, self.mask)?;
+        Ok(true)
+    }
+}
+
+#[cfg(all(test, any(feature = "pca9538", feature = "pca9555")))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[cfg(feature = "pca9538")]
+    #[test]
+    fn pca9538_refresh() {
+        let expectations = [
+            // io3 output LOW
+            mock_i2c::Transaction::write(0x70, vec![0x01, 0b11110111]),
+            mock_i2c::Transaction::write_read(0x70, vec![0x03], vec![0xff]),
+            mock_i2c::Transaction::write(0x70, vec![0x03, 0b11110111]),
+            // refresh even though nothing changed, every third tick
+            mock_i2c::Transaction::write(0x70, vec![0x01, 0b11110111]),
+            mock_i2c::Transaction::write(0x70, vec![0x01, 0b11110111]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = crate::Pca9538::new(bus.clone(), false, false);
+        let pca_pins = pca.split();
+
+        let mut relay = pca_pins.io3.into_output().unwrap();
+        // skipped, the state did not change
+        relay.set_low().unwrap();
+
+        let mut schedule = crate::RefreshSchedule::new(relay.pin_mask(), 3);
+        let refreshed: Vec<bool> = (0..6).map(|_| schedule.tick(&relay).unwrap()).collect();
+        assert_eq!(refreshed, [false, false, true, false, false, true]);
+
+        bus.done();
+    }
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn pca9555_refresh() {
+        let expectations = [
+            // only the register of port 1 is written
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0xff]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = crate::Pca9555::new(bus.clone(), false, false, false);
+        let pca_pins = pca.split();
+
+        crate::refresh(&pca_pins.io0_0, 0x8000).unwrap();
+
+        bus.done();
+    }
+}
@@ -0,0 +1,213 @@
+/// Bring the cached state of a port-expander back in line with the chip.
+///
+/// Drivers cache the output state of their chip to avoid reading it back before every write.
+/// After the microcontroller was reset on its own (e.g. by a brown-out or a watchdog), the chip
+/// keeps driving its old outputs while a new driver starts from the power-on defaults.  The first
+/// write then glitches all other outputs of the chip back to their defaults.  `resync()` restores
+/// consistency:
+///
+/// - Chips with a readable output register (e.g. `PCA9555`, `MCP23x17`) reload the cache from it,
+///   so the outputs keep their state.
+/// - Quasi-bidirectional chips (`PCF8574`, `PCF8575`, `MAX7321`) have no such register.  Their
+///   cached state is written to the chip instead.
+///
+/// A snapshot of the inputs taken by [`cached_inputs_for()`](crate::cached_inputs_for) is dropped
+/// as well, and the chip is no longer [dirty](is_dirty).  `pin` can be any pin of the
+/// port-expander, it is only used to find the chip.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// let mut pca9555 = port_expander::Pca9555::new(i2c, false, false, false);
+/// let p = pca9555.split();
+/// // we came out of a watchdog reset, keep the outputs as they are
+/// port_expander::resync(&p.io0_0).unwrap();
+/// let led = p.io0_0.into_output_high().unwrap();
+/// ```
+pub fn resync<PD, MUTEX, MODE>(pin: &crate::Pin<'_, MODE, MUTEX>) -> Result<(), PD::Error>
+where
+    PD: crate::PortDriver + crate::PortDriverResync + crate::PortDriverInputState,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    pin.port_driver().lock(|drv| {
+        drv.input_state().invalidate();
+        drv.resync()?;
+        *drv.dirty() = false;
+        Ok(())
+    })
+}
+
+/// Whether a failed write may have left a port-expander with different outputs than cached.
+///
+/// Drivers update their cached output state before writing it to the chip.  When the write fails
+/// midway, e.g. with a NACK, the chip may still hold the old state of some outputs.  The driver
+/// then marks itself dirty until the outputs are in line again, which happens in one of these
+/// ways:
+///
+/// - The next successful write of any output, which rewrites all output registers while dirty.
+/// - [`flush()`], which writes the cached state, i.e. the state the failed write wanted to reach.
+/// - [`resync()`], which reloads the cache from the chip where possible, i.e. keeps the state the
+///   chip actually has.
+///
+/// Directions and other settings are not cached, they are read from the chip before each
+/// change.  `pin` can be any pin of the port-expander, it is only used to find the chip.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// let mut pca9555 = port_expander::Pca9555::new(i2c, false, false, false);
+/// let p = pca9555.split();
+/// let mut led = p.io0_0.into_output().unwrap();
+/// if led.set_high().is_err() && port_expander::is_dirty(&led) {
+///     port_expander::flush(&led).unwrap();
+/// }
+/// ```
+pub fn is_dirty<PD, MUTEX, MODE>(pin: &crate::Pin<'_, MODE, MUTEX>) -> bool
+where
+    PD: crate::PortDriver + crate::PortDriverResync,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    pin.port_driver().lock(|drv| *drv.dirty())
+}
+
+/// Write the cached output state of a port-expander to the chip, see [`is_dirty()`].
+///
+/// All output registers are written, even if the driver is not dirty.  `pin` can be any pin of
+/// the port-expander, it is only used to find the chip.
+pub fn flush<PD, MUTEX, MODE>(pin: &crate::Pin<'_, MODE, MUTEX>) -> Result<(), PD::Error>
+where
+    PD: crate::PortDriver + crate::PortDriverResync + crate::PortDriverRefresh,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    pin.port_driver().lock(|drv| {
+        drv.refresh(0xffffffff)?;
+        *drv.dirty() = false;
+        Ok(())
+    })
+}
+
+#[cfg(all(
+    test,
+    any(feature = "pca9555", feature = "pcf8574", feature = "tca6408a")
+))]
+mod tests {
+    #[cfg(any(feature = "pca9555", feature = "tca6408a"))]
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn pca9555_resync() {
+        let expectations = [
+            // resync
+            mock_i2c::Transaction::write_read(0x20, vec![0x02], vec![0b00001111]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x03], vec![0b10000000]),
+            // io0_0 output LOW, the other outputs keep their state
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0b00001110]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0xfe]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = crate::Pca9555::new(bus.clone(), false, false, false);
+        let pca_pins = pca.split();
+
+        crate::resync(&pca_pins.io1_7).unwrap();
+        let _ = pca_pins.io0_0.into_output().unwrap();
+
+        bus.done();
+    }
+
+    #[cfg(feature = "pcf8574")]
+    #[test]
+    fn pcf8574_resync() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x21, vec![0b11111110]),
+            // resync writes the cached state
+            mock_i2c::Transaction::write(0x21, vec![0b11111110]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), true, false, false);
+        let mut pcf_pins = pcf.split();
+
+        pcf_pins.p0.set_low().unwrap();
+        crate::resync(&pcf_pins.p0).unwrap();
+
+        bus.done();
+    }
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn pca9555_write_error_recovery() {
+        let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data);
+        let expectations = [
+            // io0_0 and io1_0 outputs LOW
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0xfe]),
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x07], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x07, 0xfe]),
+            // io0_0 HIGH fails
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xff]).with_error(nack),
+            // the next write of io1_0 writes both ports
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0xff]),
+            // io0_0 LOW fails, flush() writes the cached state
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfe]).with_error(nack),
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0xff]),
+            // io1_0 LOW fails, resync() reloads the cache from the chip
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0xfe]).with_error(nack),
+            mock_i2c::Transaction::write_read(0x20, vec![0x02], vec![0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x03], vec![0xff]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = crate::Pca9555::new(bus.clone(), false, false, false);
+        let pca_pins = pca.split();
+        let mut io0_0 = pca_pins.io0_0.into_output().unwrap();
+        let mut io1_0 = pca_pins.io1_0.into_output().unwrap();
+        assert!(!crate::is_dirty(&io0_0));
+
+        assert!(io0_0.set_high().is_err());
+        assert!(crate::is_dirty(&io0_0));
+        io1_0.set_high().unwrap();
+        assert!(!crate::is_dirty(&io0_0));
+
+        assert!(io0_0.set_low().is_err());
+        assert!(crate::is_dirty(&io1_0));
+        crate::flush(&io0_0).unwrap();
+        assert!(!crate::is_dirty(&io1_0));
+
+        assert!(io1_0.set_low().is_err());
+        crate::resync(&io1_0).unwrap();
+        assert!(!crate::is_dirty(&io1_0));
+        assert!(io1_0.is_set_high().unwrap());
+
+        bus.done();
+    }
+
+    #[cfg(feature = "tca6408a")]
+    #[test]
+    fn tca6408a_write_error_retry() {
+        let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data);
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0x01, 0xfe]).with_error(nack),
+            // the retry is written although the cached state did not change
+            mock_i2c::Transaction::write(0x20, vec![0x01, 0xfe]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let tca = crate::Tca6408a::new(bus.clone(), false);
+        let pins = tca.split_ref();
+
+        assert!(tca.write_mask(0x00, 0x01).is_err());
+        assert!(crate::is_dirty(&pins[0]));
+        tca.write_mask(0x00, 0x01).unwrap();
+        assert!(!crate::is_dirty(&pins[0]));
+
+        bus.done();
+    }
+}
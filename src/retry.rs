@@ -0,0 +1,222 @@
+use embedded_hal::{delay::DelayNs, i2c as hal_i2c};
+
+/// How often and how fast a [`RetryBus`] retries failed transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RetryPolicy {
+    retries: u8,
+    backoff_us: u32,
+    exponential: bool,
+}
+
+impl RetryPolicy {
+    /// Retry a failed transaction up to `retries` times, without waiting in between.
+    pub const fn new(retries: u8) -> Self {
+        Self {
+            retries,
+            backoff_us: 0,
+            exponential: false,
+        }
+    }
+
+    /// Wait `backoff_us` microseconds before each retry.
+    ///
+    /// The wait only happens if the [`RetryBus`] was created with a delay, see
+    /// [`RetryBus::with_delay()`].
+    pub const fn with_backoff(mut self, backoff_us: u32) -> Self {
+        self.backoff_us = backoff_us;
+        self
+    }
+
+    /// Double the wait before each further retry.
+    pub const fn exponential(mut self) -> Self {
+        self.exponential = true;
+        self
+    }
+
+    /// Wait before retry number `retry`, counting from 0.
+    fn backoff_us(&self, retry: u8) -> u32 {
+        match self.exponential {
+            true => self.backoff_us.saturating_mul(1 << retry.min(31)),
+            false => self.backoff_us,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Three retries without a wait.
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+/// Delay for a [`RetryBus`] which retries immediately.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// I2C bus wrapper which retries transactions that failed due to a busy or noisy bus.
+///
+/// On a shared bus, transactions occasionally fail with a lost arbitration or a NACK from a chip
+/// which is still busy, although the same transaction would succeed a moment later.  A
+/// port-expander constructed on top of a `RetryBus` repeats such transactions according to its
+/// [`RetryPolicy`].  Only when all retries failed, the error of the last attempt is passed on and
+/// reported through [`PinError`](crate::pin::PinError) as usual.
+///
+/// Errors of the kinds `ArbitrationLoss`, `NoAcknowledge` and `Bus` are retried, all other errors
+/// are passed on immediately.  Note that a retried read of a quasi-bidirectional chip or of an
+/// interrupt status register may observe a different state than the failed attempt.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let delay = embedded_hal_mock::eh1::delay::NoopDelay::new();
+/// use port_expander::{RetryBus, RetryPolicy};
+///
+/// // up to 5 retries, waiting 100us, 200us, 400us, ... in between
+/// let policy = RetryPolicy::new(5).with_backoff(100).exponential();
+/// let i2c = RetryBus::with_delay(i2c, policy, delay);
+/// let mut pca9555 = port_expander::Pca9555::new(i2c, false, false, false);
+/// ```
+pub struct RetryBus<I2C, D = NoDelay> {
+    i2c: I2C,
+    policy: RetryPolicy,
+    delay: D,
+}
+
+impl<I2C> RetryBus<I2C> {
+    /// Wrap `i2c` to retry failed transactions without waiting in between.
+    pub fn new(i2c: I2C, policy: RetryPolicy) -> Self {
+        Self::with_delay(i2c, policy, NoDelay)
+    }
+}
+
+impl<I2C, D> RetryBus<I2C, D> {
+    /// Wrap `i2c` to retry failed transactions, using `delay` for the backoff of `policy`.
+    pub fn with_delay(i2c: I2C, policy: RetryPolicy, delay: D) -> Self {
+        Self { i2c, policy, delay }
+    }
+
+    /// Release the wrapped bus and delay.
+    pub fn into_inner(self) -> (I2C, D) {
+        (self.i2c, self.delay)
+    }
+}
+
+impl<I2C: hal_i2c::I2c, D: DelayNs> RetryBus<I2C, D> {
+    fn retry<T>(
+        &mut self,
+        mut f: impl FnMut(&mut I2C) -> Result<T, I2C::Error>,
+    ) -> Result<T, I2C::Error> {
+        let mut retry = 0;
+        loop {
+            match f(&mut self.i2c) {
+                Err(e) if retry < self.policy.retries && is_transient(&e) => {
+                    self.delay.delay_us(self.policy.backoff_us(retry));
+                    retry += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+fn is_transient<E: hal_i2c::Error>(e: &E) -> bool {
+    matches!(
+        e.kind(),
+        hal_i2c::ErrorKind::ArbitrationLoss
+            | hal_i2c::ErrorKind::NoAcknowledge(_)
+            | hal_i2c::ErrorKind::Bus
+    )
+}
+
+impl<I2C: hal_i2c::ErrorType, D> hal_i2c::ErrorType for RetryBus<I2C, D> {
+    type Error = I2C::Error;
+}
+
+impl<I2C: hal_i2c::I2c, D: DelayNs> hal_i2c::I2c for RetryBus<I2C, D> {
+    fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        self.retry(|i2c| i2c.read(address, read))
+    }
+
+    fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        self.retry(|i2c| i2c.write(address, write))
+    }
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.retry(|i2c| i2c.write_read(address, write, read))
+    }
+
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [hal_i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.retry(|i2c| i2c.transaction(address, operations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "pca9555")]
+    use super::RetryBus;
+    use super::RetryPolicy;
+    #[cfg(feature = "pca9555")]
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+    #[cfg(feature = "pca9555")]
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn pca9555_retry() {
+        let nack = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+        let expectations = [
+            // pin setup io0_0
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0xfe]),
+            // set_high() succeeds on the second attempt
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xff]).with_error(nack),
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xff]),
+            // is_high() of io0_1 gives up after 2 retries
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x00])
+                .with_error(ErrorKind::ArbitrationLoss),
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x00]).with_error(nack),
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x00]).with_error(nack),
+            // other errors are not retried
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x00])
+                .with_error(ErrorKind::Overrun),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let i2c = RetryBus::new(bus.clone(), RetryPolicy::new(2).with_backoff(10));
+        let mut pca = crate::Pca9555::new(i2c, false, false, false);
+        let pca_pins = pca.split();
+
+        let mut io0_0 = pca_pins.io0_0.into_output().unwrap();
+        io0_0.set_high().unwrap();
+        assert_eq!(*pca_pins.io0_1.is_high().unwrap_err().driver_error(), nack);
+        assert_eq!(
+            *pca_pins.io0_1.is_high().unwrap_err().driver_error(),
+            ErrorKind::Overrun
+        );
+
+        bus.done();
+    }
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = RetryPolicy::new(3).with_backoff(100).exponential();
+        assert_eq!(policy.backoff_us(0), 100);
+        assert_eq!(policy.backoff_us(2), 400);
+        assert_eq!(RetryPolicy::new(3).with_backoff(100).backoff_us(2), 100);
+    }
+}
@@ -0,0 +1,135 @@
+/// Result of a device self-test, e.g. [`Pca9555::self_test()`](crate::Pca9555::self_test).
+///
+/// Each field is a mask of pins, bit `n` is the `n`-th pin of the device's `pins()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SelfTestReport {
+    /// Pins which read LOW while driven HIGH, e.g. shorted to ground.
+    pub stuck_low: u32,
+    /// Pins which read HIGH while driven LOW, e.g. shorted to the supply.
+    pub stuck_high: u32,
+    /// Pins which did not read back their own level while only one pin was driven HIGH, e.g.
+    /// because they are shorted to each other.
+    pub shorted: u32,
+}
+
+impl SelfTestReport {
+    /// Mask of all pins which failed the test.
+    pub fn failed(&self) -> u32 {
+        self.stuck_low | self.stuck_high | self.shorted
+    }
+
+    /// Whether all pins followed their outputs.
+    pub fn passed(&self) -> bool {
+        self.failed() == 0
+    }
+}
+
+/// Drive the pins in `pins` as outputs and check that their inputs follow.
+///
+/// First, all pins are driven HIGH and then LOW together to find stuck pins.  Then each pin is
+/// driven HIGH on its own while all others are LOW, to find pins which are shorted to each other.
+/// Afterwards, all pins are inputs again.
+#[cfg(any(
+    feature = "mcp23016",
+    feature = "mcp23x17",
+    feature = "pca9536",
+    feature = "pca9538",
+    feature = "pca9554",
+    feature = "pca9555",
+    feature = "pca9575",
+    feature = "pcal6408a",
+    feature = "pcal6416a",
+    feature = "tca6424a",
+))]
+pub(crate) fn self_test<PD>(drv: &mut PD, pins: u32) -> Result<SelfTestReport, PD::Error>
+where
+    PD: crate::PortDriverTotemPole + crate::PortDriverInputState,
+{
+    let mut report = SelfTestReport::default();
+
+    drv.set_direction(pins, crate::Direction::Output, true)?;
+    report.stuck_low = pins & !read(drv, pins)?;
+    drv.set(0, pins)?;
+    report.stuck_high = pins & read(drv, pins)?;
+
+    let stuck = report.stuck_low | report.stuck_high;
+    for n in 0..32 {
+        let mask = 1 << n;
+        if pins & mask == 0 {
+            continue;
+        }
+        drv.set(mask, 0)?;
+        // any pin not reading its own level, except for those already known to be stuck
+        report.shorted |= (read(drv, pins)? ^ mask) & pins & !stuck;
+        drv.set(0, mask)?;
+    }
+
+    drv.set_directions(pins, 0)?;
+    Ok(report)
+}
+
+/// Read the levels of `pins`, never from a cached snapshot.
+#[cfg(any(
+    feature = "mcp23016",
+    feature = "mcp23x17",
+    feature = "pca9536",
+    feature = "pca9538",
+    feature = "pca9554",
+    feature = "pca9555",
+    feature = "pca9575",
+    feature = "pcal6408a",
+    feature = "pcal6416a",
+    feature = "tca6424a",
+))]
+fn read<PD>(drv: &mut PD, pins: u32) -> Result<u32, PD::Error>
+where
+    PD: crate::PortDriverInputState,
+{
+    drv.input_state().invalidate();
+    drv.get(pins, 0)
+}
+
+#[cfg(all(test, feature = "pca9536"))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pca9536_self_test() {
+        let expectations = [
+            // all pins HIGH, io3 is stuck LOW
+            mock_i2c::Transaction::write_read(0x41, vec![0x03], vec![0xff]),
+            mock_i2c::Transaction::write(0x41, vec![0x03, 0xf0]),
+            mock_i2c::Transaction::write_read(0x41, vec![0x00], vec![0xf7]),
+            // all pins LOW
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xf0]),
+            mock_i2c::Transaction::write_read(0x41, vec![0x00], vec![0xf0]),
+            // io0 and io1 are shorted
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xf1]),
+            mock_i2c::Transaction::write_read(0x41, vec![0x00], vec![0xf3]),
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xf0]),
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xf2]),
+            mock_i2c::Transaction::write_read(0x41, vec![0x00], vec![0xf3]),
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xf0]),
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xf4]),
+            mock_i2c::Transaction::write_read(0x41, vec![0x00], vec![0xf4]),
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xf0]),
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xf8]),
+            mock_i2c::Transaction::write_read(0x41, vec![0x00], vec![0xf0]),
+            mock_i2c::Transaction::write(0x41, vec![0x01, 0xf0]),
+            // back to inputs
+            mock_i2c::Transaction::write_read(0x41, vec![0x03], vec![0xf0]),
+            mock_i2c::Transaction::write(0x41, vec![0x03, 0xff]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = crate::Pca9536::new(bus.clone());
+        let report = pca.self_test().unwrap();
+        assert_eq!(report.stuck_low, 0b1000);
+        assert_eq!(report.stuck_high, 0b0000);
+        assert_eq!(report.shorted, 0b0011);
+        assert!(!report.passed());
+
+        bus.done();
+    }
+}
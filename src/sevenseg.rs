@@ -0,0 +1,187 @@
+//! Seven-segment displays connected to a port-expander.
+//!
+//! A [`SevenSegment`] display takes eight pins of the same port-expander, wired to the
+//! segments `a` to `g` and the decimal point `dp`, in this order.  These can be one bank of a
+//! 16-bit port-expander or all pins of an 8-bit one.  Each update of the display changes all
+//! segments at once, in a single bus transaction.
+//!
+//! ## Example
+//! ```
+//! use port_expander::sevenseg::{Polarity, SevenSegment};
+//! # let expectations = [
+//! #     embedded_hal_mock::eh1::i2c::Transaction::write(0x20, vec![0x03, 0xff]),
+//! #     embedded_hal_mock::eh1::i2c::Transaction::write(0x20, vec![0x03, 0x80]),
+//! #     embedded_hal_mock::eh1::i2c::Transaction::write(0x20, vec![0x03, 0x0e]),
+//! # ];
+//! # let mut i2c = embedded_hal_mock::eh1::i2c::Mock::new(&expectations);
+//!
+//! let mut pca9555 = port_expander::Pca9555::new(i2c.clone(), false, false, false);
+//! let pins = pca9555.split();
+//!
+//! // Common anode display on the second bank, segments are lit by pulling them LOW.
+//! let mut display = SevenSegment::new(
+//!     [
+//!         pins.io1_0, pins.io1_1, pins.io1_2, pins.io1_3,
+//!         pins.io1_4, pins.io1_5, pins.io1_6, pins.io1_7,
+//!     ],
+//!     Polarity::ActiveLow,
+//! );
+//! display.clear().unwrap();
+//! display.set_digit(8, false).unwrap();
+//! display.set_digit(0xf, true).unwrap();
+//! # i2c.done();
+//! ```
+//!
+//! The pins do not need to be outputs when creating the display.  On chips with totem-pole
+//! outputs, the segments can thus be cleared before the pins are made outputs, e.g. using a
+//! [`Transaction`](crate::Transaction), so they do not light up randomly.
+
+/// Segments of the hexadecimal digits `0` to `F`.
+///
+/// Bit 0 is segment `a`, bit 6 is segment `g`.  The decimal point (bit 7) is never set.
+pub const DIGITS: [u8; 16] = [
+    0x3f, 0x06, 0x5b, 0x4f, 0x66, 0x6d, 0x7d, 0x07, 0x7f, 0x6f, 0x77, 0x7c, 0x39, 0x5e, 0x79, 0x71,
+];
+
+/// Segment bit of the decimal point.
+pub const DP: u8 = 0x80;
+
+/// Which output level lights a segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Polarity {
+    /// Segments are lit when their pin is HIGH (common cathode displays).
+    ActiveHigh,
+    /// Segments are lit when their pin is LOW (common anode displays).
+    ActiveLow,
+}
+
+/// A seven-segment display connected to eight pins of a port-expander.
+///
+/// See the [module documentation](self) for details.
+pub struct SevenSegment<'a, MODE, MUTEX> {
+    pins: [crate::Pin<'a, MODE, MUTEX>; 8],
+    polarity: Polarity,
+}
+
+impl<'a, MODE: crate::mode::Configurable, MUTEX, PD> SevenSegment<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Create a display from its segment pins `a`, `b`, `c`, `d`, `e`, `f`, `g`, and `dp`.
+    ///
+    /// This does not touch the hardware.
+    ///
+    /// # Panics
+    /// All pins must belong to the same port-expander.
+    pub fn new(pins: [crate::Pin<'a, MODE, MUTEX>; 8], polarity: Polarity) -> Self {
+        let port_driver = pins[0].port_driver();
+        assert!(pins
+            .iter()
+            .all(|pin| core::ptr::eq(pin.port_driver(), port_driver)));
+        Self { pins, polarity }
+    }
+
+    /// Release the segment pins again.
+    pub fn release(self) -> [crate::Pin<'a, MODE, MUTEX>; 8] {
+        self.pins
+    }
+
+    /// Light exactly the segments which are set in `segments`.
+    ///
+    /// Bit 0 is segment `a`, bit 6 is segment `g` and bit 7 is the decimal point.
+    pub fn set_segments(&mut self, segments: u8) -> Result<(), PD::Error> {
+        let mut mask_lit = 0x00;
+        let mut mask_dark = 0x00;
+        for (i, pin) in self.pins.iter().enumerate() {
+            if segments & (1 << i) != 0 {
+                mask_lit |= pin.pin_mask();
+            } else {
+                mask_dark |= pin.pin_mask();
+            }
+        }
+
+        let (mask_high, mask_low) = match self.polarity {
+            Polarity::ActiveHigh => (mask_lit, mask_dark),
+            Polarity::ActiveLow => (mask_dark, mask_lit),
+        };
+        self.pins[0]
+            .port_driver()
+            .lock(|drv| drv.set(mask_high, mask_low))
+    }
+
+    /// Show the hexadecimal digit `digit` and optionally the decimal point.
+    ///
+    /// # Panics
+    /// `digit` must be in the range `0..=15`.
+    pub fn set_digit(&mut self, digit: u8, dp: bool) -> Result<(), PD::Error> {
+        let dp = if dp { DP } else { 0x00 };
+        self.set_segments(DIGITS[usize::from(digit)] | dp)
+    }
+
+    /// Turn off all segments.
+    pub fn clear(&mut self) -> Result<(), PD::Error> {
+        self.set_segments(0x00)
+    }
+}
+
+#[cfg(all(test, any(feature = "pca9555", feature = "pcf8574")))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[cfg(feature = "pcf8574")]
+    #[test]
+    fn sevenseg_pcf8574() {
+        let expectations = [
+            // 1.
+            mock_i2c::Transaction::write(0x21, vec![0b10000110]),
+            // 2
+            mock_i2c::Transaction::write(0x21, vec![0b01011011]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), true, false, false);
+        let p = pcf.split();
+
+        let mut display = super::SevenSegment::new(
+            [p.p0, p.p1, p.p2, p.p3, p.p4, p.p5, p.p6, p.p7],
+            super::Polarity::ActiveHigh,
+        );
+        display.set_digit(1, true).unwrap();
+        display.set_digit(2, false).unwrap();
+
+        bus.done();
+    }
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn sevenseg_active_low_bank() {
+        let expectations = [
+            // make io0_* outputs with all segments dark
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0x00]),
+            // 7
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xf8]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = crate::Pca9555::new(bus.clone(), false, false, false);
+        let mut pins = pca.pins();
+        let mut transaction = crate::Transaction::new();
+        for pin in pins[..8].iter_mut() {
+            transaction = transaction
+                .set(pin, true)
+                .direction(pin, crate::Direction::Output);
+        }
+        transaction.commit().unwrap();
+
+        let [a, b, c, d, e, f, g, dp, ..] = pins;
+        let mut display =
+            super::SevenSegment::new([a, b, c, d, e, f, g, dp], super::Polarity::ActiveLow);
+        display.set_digit(7, false).unwrap();
+
+        bus.done();
+    }
+}
@@ -0,0 +1,218 @@
+use embedded_hal::digital::{self as hal_digital};
+
+/// An output pin which can be shared between multiple consumers.
+///
+/// All accesses to a port-expander go through its [`PortMutex`](crate::PortMutex) anyway, so
+/// there is no need for exclusive access to the output pin itself.  A `SharedOutputPin` is created
+/// from an output pin with [`Pin::into_shared()`](crate::Pin::into_shared) and can be cloned into
+/// any number of handles.  It also implements the `embedded-hal` output traits for
+/// `&SharedOutputPin`, for drivers which should only get a shared reference.
+///
+/// All handles control the same pin: the output state is whatever the handle which wrote last
+/// set it to.  There is no reference counting, so e.g. one consumer setting a shared enable line
+/// LOW disables it for all other consumers as well.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pca = port_expander::Pca9555::new(i2c, false, false, false);
+/// # let p = pca.split();
+/// let enable = p.io0_0.into_output().unwrap().into_shared();
+///
+/// let enable_a = enable.clone();
+/// let enable_b = enable.clone();
+/// enable_a.set_high().unwrap();
+/// enable_b.set_low().unwrap();
+/// // the last write wins, the pin is now LOW for all handles
+/// assert!(enable_a.is_set_low().unwrap());
+/// ```
+#[must_use = "a dropped pin cannot be accessed again"]
+pub struct SharedOutputPin<'a, MUTEX> {
+    pin_mask: u32,
+    port_driver: &'a MUTEX,
+}
+
+impl<MUTEX> Clone for SharedOutputPin<'_, MUTEX> {
+    fn clone(&self) -> Self {
+        Self {
+            pin_mask: self.pin_mask,
+            port_driver: self.port_driver,
+        }
+    }
+}
+
+impl<'a, MODE: crate::mode::HasOutput, MUTEX, PD> crate::Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Turn this pin into a [`SharedOutputPin`] which can be cloned into multiple handles.
+    pub fn into_shared(self) -> SharedOutputPin<'a, MUTEX> {
+        SharedOutputPin {
+            pin_mask: self.pin_mask(),
+            port_driver: self.port_driver(),
+        }
+    }
+}
+
+impl<'a, MUTEX, PD> SharedOutputPin<'a, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    pub fn pin_mask(&self) -> u32 {
+        self.pin_mask
+    }
+
+    /// Number of this pin on its port-expander, see [`Pin::pin_index()`](crate::Pin::pin_index).
+    pub fn pin_index(&self) -> u8 {
+        self.pin_mask.trailing_zeros() as u8
+    }
+
+    /// Set the pin's output state to HIGH.
+    pub fn set_high(&self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        self.port_driver.lock(|drv| drv.set(self.pin_mask, 0))?;
+        Ok(())
+    }
+
+    /// Set the pin's output state to LOW.
+    pub fn set_low(&self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        self.port_driver.lock(|drv| drv.set(0, self.pin_mask))?;
+        Ok(())
+    }
+
+    /// Return `true` if the pin's output state is HIGH.
+    ///
+    /// This method does **not** read the pin's electrical state.
+    pub fn is_set_high(&self) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        self.port_driver
+            .lock(|drv| Ok(drv.is_set(self.pin_mask, 0)? == self.pin_mask))
+    }
+
+    /// Return `true` if the pin's output state is LOW.
+    ///
+    /// This method does **not** read the pin's electrical state.
+    pub fn is_set_low(&self) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        self.port_driver
+            .lock(|drv| Ok(drv.is_set(0, self.pin_mask)? == self.pin_mask))
+    }
+
+    /// Toggle the pin's output state.
+    pub fn toggle(&self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        self.port_driver.lock(|drv| drv.toggle(self.pin_mask))?;
+        Ok(())
+    }
+}
+
+impl<MUTEX, PD> hal_digital::ErrorType for SharedOutputPin<'_, MUTEX>
+where
+    PD: crate::PortDriver,
+    PD::Error: core::fmt::Debug,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    type Error = crate::pin::PinError<PD::Error>;
+}
+
+impl<MUTEX, PD> hal_digital::OutputPin for SharedOutputPin<'_, MUTEX>
+where
+    PD: crate::PortDriver,
+    PD::Error: core::fmt::Debug,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        SharedOutputPin::set_low(self)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        SharedOutputPin::set_high(self)
+    }
+}
+
+impl<MUTEX, PD> hal_digital::OutputPin for &SharedOutputPin<'_, MUTEX>
+where
+    PD: crate::PortDriver,
+    PD::Error: core::fmt::Debug,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        SharedOutputPin::set_low(self)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        SharedOutputPin::set_high(self)
+    }
+}
+
+impl<MUTEX, PD> hal_digital::StatefulOutputPin for SharedOutputPin<'_, MUTEX>
+where
+    PD: crate::PortDriver,
+    PD::Error: core::fmt::Debug,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        SharedOutputPin::is_set_high(self)
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        SharedOutputPin::is_set_low(self)
+    }
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        SharedOutputPin::toggle(self)
+    }
+}
+
+impl<MUTEX, PD> hal_digital::StatefulOutputPin for &SharedOutputPin<'_, MUTEX>
+where
+    PD: crate::PortDriver,
+    PD::Error: core::fmt::Debug,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        SharedOutputPin::is_set_high(self)
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        SharedOutputPin::is_set_low(self)
+    }
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        SharedOutputPin::toggle(self)
+    }
+}
+
+#[cfg(all(test, feature = "pcf8574"))]
+mod tests {
+    use embedded_hal::digital::OutputPin;
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    fn pulse(mut pin: impl OutputPin) {
+        pin.set_high().unwrap();
+        pin.set_low().unwrap();
+    }
+
+    #[test]
+    fn pcf8574_shared_output_pin() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x20, vec![0b11111110]),
+            mock_i2c::Transaction::write(0x20, vec![0b11111111]),
+            mock_i2c::Transaction::write(0x20, vec![0b11111110]),
+            mock_i2c::Transaction::write(0x20, vec![0b11111111]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), false, false, false);
+        let pcf_pins = pcf.split();
+
+        let enable = pcf_pins.p0.into_shared();
+        let other = enable.clone();
+
+        other.set_low().unwrap();
+        pulse(&enable);
+        assert!(enable.is_set_low().unwrap());
+        other.toggle().unwrap();
+        assert!(enable.is_set_high().unwrap());
+
+        bus.done();
+    }
+}
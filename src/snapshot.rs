@@ -0,0 +1,89 @@
+/// Diagnostic view of a port-expander, as returned by [`snapshot()`].
+///
+/// All masks use the pin numbering of the driver, bit `n` is pin `n`.  Fields are `None` if the
+/// port-expander has no such setting or the driver cannot report it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct DriverState {
+    /// Pins of the port-expander.
+    pub pins: u32,
+    /// Output levels as cached by the driver, before output inversion.  `None` if the driver did
+    /// not learn the output state yet.
+    pub outputs: Option<u32>,
+    /// Pins which are configured as outputs.  `None` for quasi-bidirectional port-expanders.
+    pub output_pins: Option<u32>,
+    /// Pins with an enabled pull-up resistor.
+    pub pull_ups: Option<u32>,
+    /// Pins with an enabled pull-down resistor.
+    pub pull_downs: Option<u32>,
+    /// Input levels of all pins.
+    pub inputs: u32,
+}
+
+/// Take a snapshot of the state of a port-expander for diagnostics.
+///
+/// The configuration is read from the chip where it has a register for it and taken from the
+/// driver's cache otherwise.  The inputs are read like with
+/// [`read_multiple()`](crate::read_multiple), so they come from the snapshot of
+/// [`cached_inputs_for()`](crate::cached_inputs_for) while its guard is alive.  `pin` can be any
+/// pin of the port-expander, it is only used to find the chip.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pca9555 = port_expander::Pca9555::new(i2c, false, false, false);
+/// # let p = pca9555.split();
+/// let state = port_expander::snapshot(&p.io0_0).unwrap();
+/// println!("{state:?}");
+/// ```
+pub fn snapshot<PD, MUTEX, MODE>(
+    pin: &crate::Pin<'_, MODE, MUTEX>,
+) -> Result<DriverState, PD::Error>
+where
+    PD: crate::PortDriver + crate::PortDriverSnapshot,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    pin.port_driver().lock(|drv| drv.snapshot())
+}
+
+#[cfg(all(test, feature = "pca9555"))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pca9555_snapshot() {
+        let expectations = [
+            // io0_0 output HIGH
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0xfe]),
+            // snapshot
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x07], vec![0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x01]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x01], vec![0x80]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = crate::Pca9555::new(bus.clone(), false, false, false);
+        let p = pca.split();
+        let _io0_0 = p.io0_0.into_output_high().unwrap();
+
+        let state = crate::snapshot(&p.io1_7).unwrap();
+        assert_eq!(
+            state,
+            super::DriverState {
+                pins: 0xffff,
+                outputs: Some(0xffff),
+                output_pins: Some(0x0001),
+                pull_ups: None,
+                pull_downs: None,
+                inputs: 0x8001,
+            }
+        );
+
+        bus.done();
+    }
+}
@@ -0,0 +1,122 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Lock statistics of a port-expander, see [`stats()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct LockStats {
+    /// Number of times the port-expander was locked.
+    pub acquisitions: u32,
+    /// Total time spent waiting for the lock.
+    ///
+    /// Only available with the `std` feature.
+    #[cfg(any(test, feature = "std"))]
+    pub wait_time: std::time::Duration,
+}
+
+/// Mutex wrapper which records how often and how long its port-expander is locked.
+///
+/// When a port-expander is shared between tasks or threads, contention on its mutex can become a
+/// bottleneck.  Wrapping the mutex type `M` in a `StatsMutex` records the number of lock
+/// acquisitions and, with the `std` feature, the total time spent waiting for the lock.  Read
+/// them with [`stats()`].
+///
+/// The counters are only updated while holding the lock of `M`, so this works on targets without
+/// atomic read-modify-write instructions as well.
+///
+/// Only available with the `stats` feature.
+///
+/// ## Example
+/// ```no_run
+/// use port_expander::StatsMutex;
+///
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// let mut pca9555: port_expander::Pca9555<StatsMutex<std::sync::Mutex<_>>> =
+///     port_expander::Pca9555::with_mutex(i2c, false, false, false);
+/// let pca_pins = pca9555.split();
+///
+/// // ... share the pins between threads ...
+///
+/// let stats = port_expander::stats(&pca_pins.io0_0);
+/// println!("{} locks, {:?} waiting", stats.acquisitions, stats.wait_time);
+/// ```
+pub struct StatsMutex<M> {
+    mutex: M,
+    acquisitions: AtomicU32,
+    #[cfg(any(test, feature = "std"))]
+    wait_ns: core::sync::atomic::AtomicU64,
+}
+
+impl<M: crate::PortMutex> crate::PortMutex for StatsMutex<M> {
+    type Port = M::Port;
+
+    fn create(v: Self::Port) -> Self {
+        Self {
+            mutex: M::create(v),
+            acquisitions: AtomicU32::new(0),
+            #[cfg(any(test, feature = "std"))]
+            wait_ns: core::sync::atomic::AtomicU64::new(0),
+        }
+    }
+
+    fn lock<R, F: FnOnce(&mut Self::Port) -> R>(&self, f: F) -> R {
+        #[cfg(any(test, feature = "std"))]
+        let start = std::time::Instant::now();
+        self.mutex.lock(|port| {
+            // the lock of `M` is held, so nobody else updates the counters in between
+            let acquisitions = self.acquisitions.load(Ordering::Relaxed);
+            self.acquisitions
+                .store(acquisitions.wrapping_add(1), Ordering::Relaxed);
+            #[cfg(any(test, feature = "std"))]
+            {
+                let wait_ns = self.wait_ns.load(Ordering::Relaxed);
+                let waited = start.elapsed().as_nanos() as u64;
+                self.wait_ns
+                    .store(wait_ns.saturating_add(waited), Ordering::Relaxed);
+            }
+            f(port)
+        })
+    }
+}
+
+/// Read the lock statistics of a port-expander using a [`StatsMutex`].
+///
+/// `pin` can be any pin of the port-expander, it is only used to find the chip.  This does not
+/// lock the port-expander.
+pub fn stats<PD, M, MODE>(pin: &crate::Pin<'_, MODE, StatsMutex<M>>) -> LockStats
+where
+    PD: crate::PortDriver,
+    M: crate::PortMutex<Port = PD>,
+{
+    let mutex = pin.port_driver();
+    LockStats {
+        acquisitions: mutex.acquisitions.load(Ordering::Relaxed),
+        #[cfg(any(test, feature = "std"))]
+        wait_time: std::time::Duration::from_nanos(mutex.wait_ns.load(Ordering::Relaxed)),
+    }
+}
+
+#[cfg(all(test, feature = "pca9555"))]
+mod tests {
+    use super::StatsMutex;
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pca9555_stats() {
+        let expectations = [
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x01]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0x01]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca: crate::Pca9555<StatsMutex<std::sync::Mutex<_>>> =
+            crate::Pca9555::with_mutex(bus.clone(), false, false, false);
+        let pca_pins = pca.split();
+
+        assert_eq!(crate::stats(&pca_pins.io0_0).acquisitions, 0);
+        assert!(pca_pins.io0_0.is_high().unwrap());
+        assert!(pca_pins.io0_0.is_high().unwrap());
+        assert_eq!(crate::stats(&pca_pins.io0_0).acquisitions, 2);
+
+        bus.done();
+    }
+}
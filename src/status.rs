@@ -0,0 +1,40 @@
+/// Status flags of a port-expander.
+///
+/// Each flag is `None` if the port-expander cannot report it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct Status {
+    /// Whether the chip was reset (e.g. by a power-on or a brown-out) since the status was last
+    /// read.
+    ///
+    /// A reset loses all configuration of the chip, so the application should reconfigure it when
+    /// this is `Some(true)`.  The reset which preceded the initialization of the driver is
+    /// reported by the first status read.
+    pub reset: Option<bool>,
+    /// Pins which caused the currently pending interrupt.
+    pub interrupt_source: Option<u32>,
+}
+
+/// Read the status flags of a port-expander.
+///
+/// `pin` can be any pin of the port-expander, it is only used to find the chip.  Reading the
+/// status may clear flags on the chip, see the documentation of [`Status`].
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pi4ioe = port_expander::dev::pi4ioe5v6408::Pi4ioe5v6408::new(i2c, false).unwrap();
+/// # let p = pi4ioe.split();
+/// let status = port_expander::status(&p.io0).unwrap();
+/// if status.reset == Some(true) {
+///     // the port-expander lost its configuration, set it up again
+/// }
+/// ```
+pub fn status<PD, MUTEX, MODE>(pin: &crate::Pin<'_, MODE, MUTEX>) -> Result<Status, PD::Error>
+where
+    PD: crate::PortDriver + crate::PortDriverStatus,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    pin.port_driver().lock(|drv| drv.status())
+}
@@ -0,0 +1,208 @@
+/// Change output states and directions of multiple pins at once.
+///
+/// A transaction collects changes for pins of the same port-expander and applies them in a single
+/// mutex lock when [`commit()`](Transaction::commit) is called.  All output changes are written
+/// first, followed by all direction changes, each with the minimum number of register writes the
+/// device needs.  Pins which become outputs thus immediately drive the state requested in the same
+/// transaction.  Pins which become outputs without an explicit state keep their current output
+/// state.
+///
+/// Note that [`direction()`](Transaction::direction) only changes the electrical direction of a
+/// pin, not the mode of the [`Pin`](crate::Pin) type.  It is meant for pins whose direction is
+/// managed at runtime, e.g. the lines of a bidirectional data bus.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pca = port_expander::Pca9555::new(i2c, false, false, false);
+/// # let p = pca.split();
+/// let mut io0_0 = p.io0_0.into_output().unwrap();
+/// let mut io0_1 = p.io0_1.into_output().unwrap();
+/// let mut io1_0 = p.io1_0;
+///
+/// port_expander::Transaction::new()
+///     .set(&mut io0_0, true)
+///     .set(&mut io0_1, false)
+///     .direction(&mut io1_0, port_expander::Direction::Output)
+///     .commit()
+///     .unwrap();
+/// ```
+#[must_use = "a transaction does nothing unless it is committed"]
+pub struct Transaction<'a, MUTEX> {
+    port_driver: Option<&'a MUTEX>,
+    mask_high: u32,
+    mask_low: u32,
+    mask_input: u32,
+    mask_output: u32,
+}
+
+impl<'a, MUTEX> Default for Transaction<'a, MUTEX> {
+    fn default() -> Self {
+        Self {
+            port_driver: None,
+            mask_high: 0x00,
+            mask_low: 0x00,
+            mask_input: 0x00,
+            mask_output: 0x00,
+        }
+    }
+}
+
+impl<'a, MUTEX, PD> Transaction<'a, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Start a new, empty transaction.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add_pin<MODE>(&mut self, pin: &crate::Pin<'a, MODE, MUTEX>) -> u32 {
+        let port_driver = pin.port_driver();
+        match self.port_driver {
+            Some(pd) => assert!(core::ptr::eq(pd, port_driver)),
+            None => self.port_driver = Some(port_driver),
+        }
+        pin.pin_mask()
+    }
+
+    /// Set the output state of `pin` to HIGH (`true`) or LOW (`false`).
+    ///
+    /// This is allowed for pins of any mode so the state of pins which are made outputs in the
+    /// same transaction can be chosen.  For input pins, the state takes effect once the pin
+    /// becomes an output.
+    pub fn set<MODE: crate::mode::Configurable>(
+        mut self,
+        pin: &mut crate::Pin<'a, MODE, MUTEX>,
+        state: bool,
+    ) -> Self {
+        let mask = self.add_pin(pin);
+        if state {
+            self.mask_high |= mask;
+            self.mask_low &= !mask;
+        } else {
+            self.mask_low |= mask;
+            self.mask_high &= !mask;
+        }
+        self
+    }
+}
+
+impl<'a, MUTEX, PD> Transaction<'a, MUTEX>
+where
+    PD: crate::PortDriver + crate::PortDriverTotemPole,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Change the direction of `pin` to `dir`.
+    ///
+    /// The mode of `pin` is not changed, so after the commit its type no longer matches the chip.
+    /// A `Pin<Output>` which became an input still has `set_high()`, which then only changes the
+    /// output register, and a `Pin<Input>` which became an output can't change its state except
+    /// through [`set()`](Transaction::set).  Don't mix this with the typed mode changes like
+    /// [`Pin::into_output()`](crate::Pin::into_output) for the same pin, they would assume a
+    /// direction the chip no longer has.
+    pub fn direction<MODE: crate::mode::Configurable>(
+        mut self,
+        pin: &mut crate::Pin<'a, MODE, MUTEX>,
+        dir: crate::Direction,
+    ) -> Self {
+        let mask = self.add_pin(pin);
+        match dir {
+            crate::Direction::Input => {
+                self.mask_input |= mask;
+                self.mask_output &= !mask;
+            }
+            crate::Direction::Output => {
+                self.mask_output |= mask;
+                self.mask_input &= !mask;
+            }
+        }
+        self
+    }
+
+    /// Apply all changes of this transaction to the port-expander.
+    pub fn commit(self) -> Result<(), PD::Error> {
+        let port_driver = match self.port_driver {
+            Some(pd) => pd,
+            // nothing to do for an empty transaction
+            None => return Ok(()),
+        };
+
+        port_driver.lock(|drv| {
+            if self.mask_high | self.mask_low != 0 {
+                drv.set(self.mask_high, self.mask_low)?;
+            }
+            if self.mask_input | self.mask_output != 0 {
+                drv.set_directions(self.mask_input, self.mask_output)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(all(test, any(feature = "pca9538", feature = "pca9555")))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn pca9555_transaction() {
+        let expectations = [
+            // pin setup io0_0
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0xfe]),
+            // outputs of the transaction
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfc]),
+            mock_i2c::Transaction::write(0x20, vec![0x03, 0xfe]),
+            // directions of the transaction
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xfe]),
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0xfd]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x07], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x07, 0xfe]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca = crate::Pca9555::new(bus.clone(), false, false, false);
+        let mut pca_pins = pca.split();
+
+        let mut io0_0 = pca_pins.io0_0.into_output().unwrap();
+
+        super::Transaction::new()
+            .set(&mut io0_0, false)
+            .set(&mut pca_pins.io0_1, false)
+            .set(&mut pca_pins.io1_0, true)
+            .set(&mut pca_pins.io1_0, false)
+            .direction(&mut io0_0, crate::Direction::Input)
+            .direction(&mut pca_pins.io0_1, crate::Direction::Output)
+            .direction(&mut pca_pins.io1_0, crate::Direction::Output)
+            .commit()
+            .unwrap();
+
+        // an empty transaction does nothing
+        super::Transaction::<core::cell::RefCell<crate::dev::pca9555::Driver<mock_i2c::Mock>>>::new()
+            .commit()
+            .unwrap();
+
+        bus.done();
+    }
+
+    #[cfg(feature = "pca9538")]
+    #[test]
+    #[should_panic]
+    fn transaction_assert_same_chip() {
+        let mut bus = mock_i2c::Mock::new(&[]);
+
+        let mut pca0 = crate::Pca9538::new(bus.clone(), false, false);
+        let mut pca0_pins = pca0.split();
+        let mut pca1 = crate::Pca9538::new(bus.clone(), false, true);
+        let mut pca1_pins = pca1.split();
+
+        let _ = super::Transaction::new()
+            .direction(&mut pca0_pins.io0, crate::Direction::Output)
+            .direction(&mut pca1_pins.io0, crate::Direction::Output);
+
+        bus.done();
+    }
+}
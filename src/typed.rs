@@ -0,0 +1,225 @@
+use core::marker::PhantomData;
+use embedded_hal::digital::{self as hal_digital};
+
+/// A port-expander pin whose pin number `N` is known at compile time.
+///
+/// A [`Pin`](crate::Pin) stores its pin mask and computes the masks of each access at runtime.
+/// `TypedPin` carries the pin number in its type instead, so the mask is a constant the optimizer
+/// can fold into the driver code, and the pin itself is only a reference to the port-expander.
+/// This can save code size and RAM on small targets.
+///
+/// A `TypedPin` is created from a [`Pin`](crate::Pin) with
+/// [`into_typed()`](crate::Pin::into_typed), which checks the pin number once.  It offers the
+/// same input and output methods as `Pin`.  For reconfiguring the pin, convert it back with
+/// [`into_pin()`](TypedPin::into_pin).
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pca9555 = port_expander::Pca9555::new(i2c, false, false, false);
+/// # let p = pca9555.split();
+/// let mut led = p.io0_3.into_output().unwrap().into_typed::<3>();
+/// led.set_high().unwrap();
+/// ```
+#[must_use = "a dropped pin cannot be accessed again"]
+pub struct TypedPin<'a, const N: u8, MODE, MUTEX> {
+    port_driver: &'a MUTEX,
+    _m: PhantomData<MODE>,
+}
+
+impl<'a, MODE, MUTEX, PD> crate::Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Turn this pin into a [`TypedPin`] with the pin number `N`.
+    ///
+    /// # Panics
+    /// `N` must be the pin number of this pin, i.e. its [`pin_mask()`](crate::Pin::pin_mask) must
+    /// be `1 << N`.
+    pub fn into_typed<const N: u8>(self) -> TypedPin<'a, N, MODE, MUTEX> {
+        assert!(N < 32 && self.pin_mask() == 1 << N);
+        TypedPin {
+            port_driver: self.port_driver(),
+            _m: PhantomData,
+        }
+    }
+}
+
+impl<'a, const N: u8, MODE, MUTEX, PD> TypedPin<'a, N, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    const PIN_MASK: u32 = 1 << N;
+
+    pub fn pin_mask(&self) -> u32 {
+        Self::PIN_MASK
+    }
+
+    /// Number of this pin on its port-expander, see [`Pin::pin_index()`](crate::Pin::pin_index).
+    pub fn pin_index(&self) -> u8 {
+        N
+    }
+
+    /// Turn this pin back into a [`Pin`](crate::Pin).
+    pub fn into_pin(self) -> crate::Pin<'a, MODE, MUTEX> {
+        crate::Pin::new(N, self.port_driver)
+    }
+}
+
+impl<'a, const N: u8, MODE: crate::mode::HasInput, MUTEX, PD> TypedPin<'a, N, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Read the pin's input state and return `true` if it is HIGH.
+    pub fn is_high(&self) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        self.port_driver
+            .lock(|drv| Ok(drv.get(Self::PIN_MASK, 0)? == Self::PIN_MASK))
+    }
+
+    /// Read the pin's input state and return `true` if it is LOW.
+    pub fn is_low(&self) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        self.port_driver
+            .lock(|drv| Ok(drv.get(0, Self::PIN_MASK)? == Self::PIN_MASK))
+    }
+}
+
+impl<'a, const N: u8, MODE: crate::mode::HasOutput, MUTEX, PD> TypedPin<'a, N, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    /// Set the pin's output state to HIGH.
+    pub fn set_high(&mut self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        self.port_driver.lock(|drv| drv.set(Self::PIN_MASK, 0))?;
+        Ok(())
+    }
+
+    /// Set the pin's output state to LOW.
+    pub fn set_low(&mut self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        self.port_driver.lock(|drv| drv.set(0, Self::PIN_MASK))?;
+        Ok(())
+    }
+
+    /// Return `true` if the pin's output state is HIGH.
+    ///
+    /// This method does **not** read the pin's electrical state.
+    pub fn is_set_high(&self) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        self.port_driver
+            .lock(|drv| Ok(drv.is_set(Self::PIN_MASK, 0)? == Self::PIN_MASK))
+    }
+
+    /// Return `true` if the pin's output state is LOW.
+    ///
+    /// This method does **not** read the pin's electrical state.
+    pub fn is_set_low(&self) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        self.port_driver
+            .lock(|drv| Ok(drv.is_set(0, Self::PIN_MASK)? == Self::PIN_MASK))
+    }
+
+    /// Toggle the pin's output state.
+    pub fn toggle(&mut self) -> Result<(), crate::pin::PinError<PD::Error>> {
+        self.port_driver.lock(|drv| drv.toggle(Self::PIN_MASK))?;
+        Ok(())
+    }
+}
+
+impl<const N: u8, MODE, MUTEX, PD> hal_digital::ErrorType for TypedPin<'_, N, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    PD::Error: core::fmt::Debug,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    type Error = crate::pin::PinError<PD::Error>;
+}
+
+impl<const N: u8, MODE: crate::mode::HasInput, MUTEX, PD> hal_digital::InputPin
+    for TypedPin<'_, N, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    PD::Error: core::fmt::Debug,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        TypedPin::is_high(self)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        TypedPin::is_low(self)
+    }
+}
+
+impl<const N: u8, MODE: crate::mode::HasOutput, MUTEX, PD> hal_digital::OutputPin
+    for TypedPin<'_, N, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    PD::Error: core::fmt::Debug,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        TypedPin::set_low(self)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        TypedPin::set_high(self)
+    }
+}
+
+impl<const N: u8, MODE: crate::mode::HasOutput, MUTEX, PD> hal_digital::StatefulOutputPin
+    for TypedPin<'_, N, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    PD::Error: core::fmt::Debug,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        TypedPin::is_set_high(self)
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        TypedPin::is_set_low(self)
+    }
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        TypedPin::toggle(self)
+    }
+}
+
+#[cfg(all(test, feature = "pcf8574"))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pcf8574_typed_pin() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x21, vec![0b11111011]),
+            mock_i2c::Transaction::read(0x21, vec![0b11111011]),
+            mock_i2c::Transaction::write(0x21, vec![0b11111111]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), true, false, false);
+        let pcf_pins = pcf.split();
+
+        let mut p2 = pcf_pins.p2.into_typed::<2>();
+        assert_eq!(p2.pin_mask(), 0b00000100);
+        p2.set_low().unwrap();
+        assert!(p2.is_low().unwrap());
+        let mut p2 = p2.into_pin();
+        p2.toggle().unwrap();
+
+        bus.done();
+    }
+
+    #[test]
+    #[should_panic]
+    fn pcf8574_typed_pin_wrong_number() {
+        let bus = mock_i2c::Mock::new(&[]);
+        let mut pcf = crate::Pcf8574::new(bus, true, false, false);
+        let pcf_pins = pcf.split();
+
+        let _ = pcf_pins.p2.into_typed::<3>();
+    }
+}
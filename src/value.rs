@@ -0,0 +1,231 @@
+/// Encoding of a number on a group of pins, see [`read_value()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Encoding {
+    /// Plain binary, e.g. DIP switches.
+    Binary,
+    /// Gray code, e.g. absolute rotary encoders.
+    Gray,
+    /// Binary-coded decimal, four pins per decimal digit, e.g. decimal rotary switches.
+    Bcd,
+}
+
+/// Order in which the bits of a value map to a group of pins, see [`read_value()`] and
+/// [`write_value()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BitOrder {
+    /// The first pin is the least significant bit.
+    LsbFirst,
+    /// The first pin is the most significant bit, e.g. for reversed wiring.
+    MsbFirst,
+}
+
+impl BitOrder {
+    /// Bit of the value belonging to pin `index` of `count` pins.
+    fn bit(self, index: usize, count: usize) -> usize {
+        match self {
+            BitOrder::LsbFirst => index,
+            BitOrder::MsbFirst => count - 1 - index,
+        }
+    }
+}
+
+/// Error type of [`read_value()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ValueError<E> {
+    /// The port driver reported an error.
+    Driver(E),
+    /// A BCD digit was larger than 9.  Contains the raw value read from the pins.
+    InvalidBcd(u32),
+}
+
+impl<E> From<E> for ValueError<E> {
+    fn from(value: E) -> Self {
+        Self::Driver(value)
+    }
+}
+
+/// Read a group of pins as a number.
+///
+/// With [`BitOrder::LsbFirst`], `pins` are ordered from the least to the most significant bit,
+/// with [`BitOrder::MsbFirst`] the other way around.  They are read in a single bus transaction,
+/// like with [`read_multiple()`](crate::read_multiple), and decoded according to `encoding`.
+/// With `inverted`, a LOW pin is a 1 bit, which is the common wiring of switches to ground with
+/// pull-up resistors.
+///
+/// # Panics
+/// All pins must belong to the same port-expander.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pcf = port_expander::Pcf8574::new(i2c, false, false, false);
+/// # let p = pcf.split();
+/// use port_expander::{BitOrder, Encoding};
+///
+/// // hex rotary switch which pulls the pins LOW
+/// let pins = [&p.p0, &p.p1, &p.p2, &p.p3];
+/// let address = port_expander::read_value(pins, Encoding::Binary, BitOrder::LsbFirst, true)
+///     .unwrap();
+/// ```
+pub fn read_value<PD, MUTEX, MODE: crate::mode::HasInput, const N: usize>(
+    pins: [&crate::Pin<'_, MODE, MUTEX>; N],
+    encoding: Encoding,
+    bit_order: BitOrder,
+    inverted: bool,
+) -> Result<u32, ValueError<PD::Error>>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    let states = crate::read_multiple(pins)?;
+    let raw = states.iter().enumerate().fold(0, |raw, (i, state)| {
+        raw | ((*state != inverted) as u32) << bit_order.bit(i, N)
+    });
+
+    match encoding {
+        Encoding::Binary => Ok(raw),
+        Encoding::Gray => {
+            let mut value = raw;
+            let mut shift = raw >> 1;
+            while shift != 0 {
+                value ^= shift;
+                shift >>= 1;
+            }
+            Ok(value)
+        }
+        Encoding::Bcd => {
+            let mut value = 0;
+            let mut factor = 1;
+            let mut digits = raw;
+            while digits != 0 {
+                let digit = digits & 0xf;
+                if digit > 9 {
+                    return Err(ValueError::InvalidBcd(raw));
+                }
+                value += digit * factor;
+                factor *= 10;
+                digits >>= 4;
+            }
+            Ok(value)
+        }
+    }
+}
+
+/// Write a binary value to a group of pins.
+///
+/// Bit `n` of `value` drives the `n`-th pin with [`BitOrder::LsbFirst`], or the `n`-th pin from
+/// the end with [`BitOrder::MsbFirst`].  Bits beyond the number of pins are ignored.  All pins
+/// change state in a single bus transaction, like with [`write_multiple()`](crate::write_multiple).
+///
+/// # Panics
+/// All pins must belong to the same port-expander.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut pcf = port_expander::Pcf8574::new(i2c, false, false, false);
+/// # let p = pcf.split();
+/// # let [mut d0, mut d1, mut d2, mut d3] = [p.p0, p.p1, p.p2, p.p3];
+/// use port_expander::BitOrder;
+///
+/// // 4-bit bus wired with bit 0 on the highest pin
+/// let pins = [&mut d0, &mut d1, &mut d2, &mut d3];
+/// port_expander::write_value(pins, 0b0011, BitOrder::MsbFirst).unwrap();
+/// ```
+pub fn write_value<PD, MUTEX, MODE: crate::mode::HasOutput, const N: usize>(
+    pins: [&mut crate::Pin<'_, MODE, MUTEX>; N],
+    value: u32,
+    bit_order: BitOrder,
+) -> Result<(), PD::Error>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    let mut mask_high = 0x00;
+    let mut mask_low = 0x00;
+
+    let port_driver = pins[0].port_driver();
+    for (i, pin) in pins.iter().enumerate() {
+        assert!(core::ptr::eq(pin.port_driver(), port_driver));
+        if value & (1 << bit_order.bit(i, N)) != 0 {
+            mask_high |= pin.pin_mask();
+        } else {
+            mask_low |= pin.pin_mask();
+        }
+    }
+
+    port_driver.lock(|drv| drv.set(mask_high, mask_low))
+}
+
+#[cfg(all(test, feature = "pcf8574"))]
+mod tests {
+    use super::{BitOrder, Encoding, ValueError};
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pcf8574_read_value() {
+        let expectations = [
+            mock_i2c::Transaction::read(0x21, vec![0b0110_0101]),
+            mock_i2c::Transaction::read(0x21, vec![0b0110_0101]),
+            mock_i2c::Transaction::read(0x21, vec![0b0110_0101]),
+            mock_i2c::Transaction::read(0x21, vec![0b1111_1010]),
+            mock_i2c::Transaction::read(0x21, vec![0b0101_1010]),
+            mock_i2c::Transaction::read(0x21, vec![0b0110_0101]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), true, false, false);
+        let p = pcf.split();
+        let pins = [&p.p0, &p.p1, &p.p2, &p.p3, &p.p4, &p.p5, &p.p6, &p.p7];
+
+        assert_eq!(
+            crate::read_value(pins, Encoding::Binary, BitOrder::LsbFirst, false),
+            Ok(0x65)
+        );
+        // gray 0110_0101 is binary 0100_0110
+        assert_eq!(
+            crate::read_value(pins, Encoding::Gray, BitOrder::LsbFirst, false),
+            Ok(0x46)
+        );
+        assert_eq!(
+            crate::read_value(pins, Encoding::Bcd, BitOrder::LsbFirst, false),
+            Ok(65)
+        );
+        assert_eq!(
+            crate::read_value(pins, Encoding::Bcd, BitOrder::LsbFirst, true),
+            Ok(5)
+        );
+        assert_eq!(
+            crate::read_value(pins, Encoding::Bcd, BitOrder::LsbFirst, true),
+            Err(ValueError::InvalidBcd(0xa5))
+        );
+        assert_eq!(
+            crate::read_value(pins, Encoding::Binary, BitOrder::MsbFirst, false),
+            Ok(0xa6)
+        );
+
+        bus.done();
+    }
+
+    #[test]
+    fn pcf8574_write_value() {
+        let expectations = [
+            mock_i2c::Transaction::write(0x21, vec![0b1111_0011]),
+            mock_i2c::Transaction::write(0x21, vec![0b1111_1100]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), true, false, false);
+        let mut p = pcf.split();
+
+        let pins = [&mut p.p0, &mut p.p1, &mut p.p2, &mut p.p3];
+        crate::write_value(pins, 0x13, BitOrder::LsbFirst).unwrap();
+        let pins = [&mut p.p0, &mut p.p1, &mut p.p2, &mut p.p3];
+        crate::write_value(pins, 0b0011, BitOrder::MsbFirst).unwrap();
+
+        bus.done();
+    }
+}
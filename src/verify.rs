@@ -0,0 +1,234 @@
+use embedded_hal::i2c as hal_i2c;
+
+/// I2C bus wrapper which verifies every register write by reading the register back.
+///
+/// For high-reliability applications, a port-expander can be constructed on top of a
+/// `VerifyingI2c` instead of the raw bus.  Every register write issued by the driver is then
+/// followed by a read of the same register.  If the value read back differs, the operation fails
+/// with [`VerifyError::VerificationFailed`].
+///
+/// The wrapper does not implement the I2C traits itself, it only provides register accesses.  It
+/// can therefore only be used with drivers which access the chip through single registers.
+/// Drivers for register-less chips (`PCF8574`, `PCF8575`, `MAX7321`, `CH422`, `CH423`) or which
+/// use auto-increment transfers (`TCA6424A`) do not accept it.  Also note that write-only or
+/// self-clearing registers, like the software reset of the `AW9523B`, will fail verification.
+///
+/// Some chips read back the levels of the pins instead of the output latch.  The `MCP23x17`
+/// driver writes the `OLAT` registers, which read back as written.  The port registers of the
+/// `MAX7300` have no such counterpart, so only the bits of pins which are outputs are compared
+/// for them.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// let i2c = port_expander::VerifyingI2c::new(i2c);
+/// let mut pca9555 = port_expander::Pca9555::new(i2c, false, false, false);
+/// let pca_pins = pca9555.split();
+///
+/// let mut io0_0 = pca_pins.io0_0.into_output().unwrap();
+/// io0_0.set_high().unwrap();
+/// ```
+///
+/// Register-less drivers don't accept the wrapper:
+/// ```compile_fail
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// let i2c = port_expander::VerifyingI2c::new(i2c);
+/// let mut pcf8574 = port_expander::Pcf8574::new(i2c, false, false, false);
+/// ```
+pub struct VerifyingI2c<I2C> {
+    i2c: I2C,
+}
+
+impl<I2C> VerifyingI2c<I2C> {
+    /// Wrap `i2c` to verify all register writes.
+    pub fn new(i2c: I2C) -> Self {
+        Self { i2c }
+    }
+
+    /// Release the wrapped bus.
+    pub fn into_inner(self) -> I2C {
+        self.i2c
+    }
+}
+
+/// Error type of [`VerifyingI2c`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VerifyError<E> {
+    /// Error of the underlying bus.
+    Bus(E),
+    /// Reading back register `register` returned `actual` instead of the `expected` value.
+    VerificationFailed {
+        register: u8,
+        expected: u8,
+        actual: u8,
+    },
+}
+
+impl<E> From<E> for VerifyError<E> {
+    fn from(value: E) -> Self {
+        Self::Bus(value)
+    }
+}
+
+impl<E: hal_i2c::Error> hal_i2c::Error for VerifyError<E> {
+    fn kind(&self) -> hal_i2c::ErrorKind {
+        match self {
+            VerifyError::Bus(e) => e.kind(),
+            VerifyError::VerificationFailed { .. } => hal_i2c::ErrorKind::Other,
+        }
+    }
+}
+
+impl<I2C: crate::I2cBus> crate::RegisterBus for VerifyingI2c<I2C> {
+    type BusError = VerifyError<I2C::BusError>;
+
+    fn write_reg<R: Into<u8>>(
+        &mut self,
+        addr: u8,
+        reg: R,
+        value: u8,
+    ) -> Result<(), Self::BusError> {
+        self.write_reg_masked(addr, reg, value, 0xff)
+    }
+
+    fn write_reg_masked<R: Into<u8>>(
+        &mut self,
+        addr: u8,
+        reg: R,
+        value: u8,
+        mask: u8,
+    ) -> Result<(), Self::BusError> {
+        let register = reg.into();
+        self.i2c.write_reg(addr, register, value)?;
+        if mask == 0 {
+            // none of the bits reads back what was written
+            return Ok(());
+        }
+        let actual = self.i2c.read_reg(addr, register)?;
+        if (actual ^ value) & mask != 0 {
+            return Err(VerifyError::VerificationFailed {
+                register,
+                expected: value,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    fn read_reg<R: Into<u8>>(&mut self, addr: u8, reg: R) -> Result<u8, Self::BusError> {
+        Ok(self.i2c.read_reg(addr, reg)?)
+    }
+}
+
+#[cfg(all(
+    test,
+    any(feature = "max7300", feature = "mcp23x17", feature = "pca9555")
+))]
+mod tests {
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[cfg(feature = "pca9555")]
+    #[test]
+    fn pca9555_verified_writes() {
+        let expectations = [
+            // pin setup io0_0, every write is read back
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x02], vec![0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x06, 0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x06], vec![0xfe]),
+            // set_high() with a failing read-back
+            mock_i2c::Transaction::write(0x20, vec![0x02, 0xff]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x02], vec![0xfe]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut pca =
+            crate::Pca9555::new(super::VerifyingI2c::new(bus.clone()), false, false, false);
+        let pca_pins = pca.split();
+
+        let mut io0_0 = pca_pins.io0_0.into_output().unwrap();
+        let err = io0_0.set_high().unwrap_err();
+        assert_eq!(
+            *err.driver_error(),
+            super::VerifyError::VerificationFailed {
+                register: 0x02,
+                expected: 0xff,
+                actual: 0xfe,
+            }
+        );
+
+        bus.done();
+    }
+
+    #[cfg(feature = "mcp23x17")]
+    #[test]
+    fn mcp23017_verified_writes_mixed_directions() {
+        let expectations = [
+            // gpa0 as output
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xff]),
+            mock_i2c::Transaction::write(0x20, vec![0x00, 0xfe]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x00], vec![0xfe]),
+            // set_high() writes the output latch, the input gpa1 is HIGH on the port
+            mock_i2c::Transaction::write(0x20, vec![0x14, 0x01]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x14], vec![0x01]),
+            mock_i2c::Transaction::write_read(0x20, vec![0x12], vec![0x03]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut mcp: crate::Mcp23x17<core::cell::RefCell<_>> =
+            crate::Mcp23x17::with_mutex(super::VerifyingI2c::new(bus.clone()), false, false, false);
+        let mcp_pins = mcp.split();
+
+        let mut gpa0 = mcp_pins.gpa0.into_output().unwrap();
+        gpa0.set_high().unwrap();
+        assert!(mcp_pins.gpa1.is_high().unwrap());
+
+        bus.done();
+    }
+
+    #[cfg(feature = "max7300")]
+    #[test]
+    fn max7300_verified_writes_mixed_directions() {
+        use crate::dev::max7300::AddressPin;
+
+        let expectations = [
+            // leave shutdown mode
+            mock_i2c::Transaction::write(0x40, vec![0x04, 0x01]),
+            mock_i2c::Transaction::write_read(0x40, vec![0x04], vec![0x01]),
+            // p4 output LOW, it is not an output yet so the range is not read back
+            mock_i2c::Transaction::write(0x40, vec![0x44, 0x00]),
+            mock_i2c::Transaction::write(0x40, vec![0x09, 0b10101001]),
+            mock_i2c::Transaction::write_read(0x40, vec![0x09], vec![0b10101001]),
+            // set_high() with the input p5 HIGH
+            mock_i2c::Transaction::write(0x40, vec![0x44, 0x01]),
+            mock_i2c::Transaction::write_read(0x40, vec![0x44], vec![0x03]),
+            // set_low() with p4 stuck HIGH
+            mock_i2c::Transaction::write(0x40, vec![0x44, 0x00]),
+            mock_i2c::Transaction::write_read(0x40, vec![0x44], vec![0x03]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+
+        let mut max: crate::Max7300<core::cell::RefCell<_>> = crate::Max7300::with_mutex(
+            super::VerifyingI2c::new(bus.clone()),
+            AddressPin::Gnd,
+            AddressPin::Gnd,
+        );
+        let max_pins = max.split();
+
+        let mut p4 = max_pins.p4.into_output().unwrap();
+        p4.set_high().unwrap();
+        let err = p4.set_low().unwrap_err();
+        assert_eq!(
+            *err.driver_error(),
+            super::VerifyError::VerificationFailed {
+                register: 0x44,
+                expected: 0x00,
+                actual: 0x03,
+            }
+        );
+
+        bus.done();
+    }
+}
@@ -0,0 +1,312 @@
+//! Virtual port-expander for host-side tests of application code.
+
+/// Port-expander without hardware, for unit tests of code using [`Pin`](crate::Pin)s.
+///
+/// The virtual expander behaves like a chip with totem-pole outputs, pull-up and pull-down
+/// resistors and input polarity inversion on up to 32 pins.  Test code plays the outside world:
+/// [`drive()`](VirtualExpander::drive) sets the levels applied to the pins and
+/// [`outputs()`](VirtualExpander::outputs), [`output_pins()`](VirtualExpander::output_pins) and
+/// friends inspect what the application configured.  A pin which is not driven reads the level of
+/// its pull resistor, or LOW when it has none.  Output pins read back their own output state.
+///
+/// Unlike the drivers of real chips, pins are handed out by [`pins()`](VirtualExpander::pins)
+/// from a shared reference, so the test can access the expander while the pins are in use.
+///
+/// Only available with the `test-util` feature.
+///
+/// ## Example
+/// ```
+/// let expander = port_expander::VirtualExpander::new();
+/// let [button, led] = expander.pins();
+/// let mut led = led.into_output().unwrap();
+///
+/// expander.drive(0, button.pin_mask());
+/// if button.is_low().unwrap() {
+///     led.set_high().unwrap();
+/// }
+/// assert_eq!(expander.outputs(), led.pin_mask());
+/// ```
+pub struct VirtualExpander<M>(M);
+
+impl VirtualExpander<core::cell::RefCell<Driver>> {
+    pub fn new() -> Self {
+        Self::with_mutex()
+    }
+}
+
+impl Default for VirtualExpander<core::cell::RefCell<Driver>> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl VirtualExpander<std::sync::Mutex<Driver>> {
+    /// Same as [`new()`](VirtualExpander::new), but the expander is wrapped in a
+    /// `std::sync::Mutex` so it can be shared between threads.
+    pub fn new_std() -> Self {
+        Self::with_mutex()
+    }
+}
+
+impl<M> VirtualExpander<M>
+where
+    M: crate::PortMutex<Port = Driver>,
+{
+    pub fn with_mutex() -> Self {
+        Self(crate::PortMutex::create(Driver::new()))
+    }
+
+    /// Get the first `N` pins, all configured as inputs.
+    ///
+    /// # Panics
+    /// `N` must not be larger than 32.
+    pub fn pins<const N: usize>(&self) -> [crate::Pin<'_, crate::mode::Input, M>; N] {
+        assert!(N <= 32);
+        core::array::from_fn(|i| crate::Pin::new(i as u8, &self.0))
+    }
+
+    /// Drive the pins in `mask_high` HIGH and the pins in `mask_low` LOW from the outside.
+    pub fn drive(&self, mask_high: u32, mask_low: u32) {
+        crate::PortMutex::lock(&self.0, |drv| {
+            drv.driven |= mask_high | mask_low;
+            drv.levels |= mask_high;
+            drv.levels &= !mask_low;
+        })
+    }
+
+    /// Stop driving the pins in `mask` from the outside.
+    pub fn release(&self, mask: u32) {
+        crate::PortMutex::lock(&self.0, |drv| drv.driven &= !mask)
+    }
+
+    /// Output state of all pins, including those which are currently inputs.
+    pub fn outputs(&self) -> u32 {
+        crate::PortMutex::lock(&self.0, |drv| drv.out)
+    }
+
+    /// Mask of the pins which are configured as outputs.
+    pub fn output_pins(&self) -> u32 {
+        crate::PortMutex::lock(&self.0, |drv| drv.output_pins)
+    }
+
+    /// Mask of the pins with an enabled pull-up resistor.
+    pub fn pull_ups(&self) -> u32 {
+        crate::PortMutex::lock(&self.0, |drv| drv.pull_ups)
+    }
+
+    /// Mask of the pins with an enabled pull-down resistor.
+    pub fn pull_downs(&self) -> u32 {
+        crate::PortMutex::lock(&self.0, |drv| drv.pull_downs)
+    }
+
+    /// Mask of the pins with inverted input polarity.
+    pub fn inverted(&self) -> u32 {
+        crate::PortMutex::lock(&self.0, |drv| drv.inverted)
+    }
+}
+
+pub struct Driver {
+    out: u32,
+    dirty: bool,
+    output_pins: u32,
+    pull_ups: u32,
+    pull_downs: u32,
+    inverted: u32,
+    driven: u32,
+    levels: u32,
+    input_state: crate::InputState,
+    output_inversion: crate::OutputInversion,
+}
+
+impl Driver {
+    pub fn new() -> Self {
+        Self {
+            out: 0x00000000,
+            dirty: false,
+            output_pins: 0x00000000,
+            pull_ups: 0x00000000,
+            pull_downs: 0x00000000,
+            inverted: 0x00000000,
+            driven: 0x00000000,
+            levels: 0x00000000,
+            input_state: crate::InputState::default(),
+            output_inversion: crate::OutputInversion::default(),
+        }
+    }
+
+    /// Electrical level of all pins.
+    fn pin_levels(&self) -> u32 {
+        let inputs = (self.levels & self.driven) | (self.pull_ups & !self.driven);
+        (self.out & self.output_pins) | (inputs & !self.output_pins)
+    }
+}
+
+impl Default for Driver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl crate::PortDriver for Driver {
+    type Error = core::convert::Infallible;
+
+    fn set(&mut self, mask_high: u32, mask_low: u32) -> Result<(), Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        self.out |= mask_high;
+        self.out &= !mask_low;
+        Ok(())
+    }
+
+    fn is_set(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let (mask_high, mask_low) = self.output_inversion.apply(mask_high, mask_low);
+        Ok((self.out & mask_high) | (!self.out & mask_low))
+    }
+
+    fn get(&mut self, mask_high: u32, mask_low: u32) -> Result<u32, Self::Error> {
+        let in_ = self.pin_levels() ^ self.inverted;
+        let in_ = self
+            .input_state
+            .read(mask_high | mask_low, |_| Ok::<_, Self::Error>(in_))?;
+        Ok((in_ & mask_high) | (!in_ & mask_low))
+    }
+
+    fn get_polarity(&mut self, mask: u32) -> Result<u32, Self::Error> {
+        Ok(self.inverted & mask)
+    }
+}
+
+impl crate::PortDriverInputState for Driver {
+    fn input_state(&mut self) -> &mut crate::InputState {
+        &mut self.input_state
+    }
+}
+
+impl crate::PortDriverOutputInversion for Driver {
+    fn output_inversion(&mut self) -> &mut crate::OutputInversion {
+        &mut self.output_inversion
+    }
+}
+
+impl crate::PortDriverCost for Driver {
+    fn cost(&self, _op: crate::Operation) -> u32 {
+        0
+    }
+}
+
+impl crate::PortDriverResync for Driver {
+    fn resync(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn dirty(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+impl crate::PortDriverSnapshot for Driver {
+    fn snapshot(&mut self) -> Result<crate::DriverState, Self::Error> {
+        Ok(crate::DriverState {
+            pins: 0xffffffff,
+            outputs: Some(self.out),
+            output_pins: Some(self.output_pins),
+            pull_ups: Some(self.pull_ups),
+            pull_downs: Some(self.pull_downs),
+            inputs: crate::PortDriver::get(self, 0xffffffff, 0)?,
+        })
+    }
+}
+
+impl crate::PortDriverRefresh for Driver {
+    fn refresh(&mut self, _mask: u32) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl crate::PortDriverStatus for Driver {
+    fn status(&mut self) -> Result<crate::Status, Self::Error> {
+        Ok(crate::Status {
+            reset: None,
+            interrupt_source: None,
+        })
+    }
+}
+
+impl crate::PortDriverTotemPole for Driver {
+    fn set_directions(&mut self, mask_input: u32, mask_output: u32) -> Result<(), Self::Error> {
+        self.output_pins |= mask_output;
+        self.output_pins &= !mask_input;
+        Ok(())
+    }
+}
+
+impl crate::PortDriverPolarity for Driver {
+    fn set_polarity(&mut self, mask: u32, inverted: bool) -> Result<(), Self::Error> {
+        match inverted {
+            true => self.inverted |= mask,
+            false => self.inverted &= !mask,
+        }
+        Ok(())
+    }
+}
+
+impl crate::PortDriverPullUp for Driver {
+    fn set_pull_up(&mut self, mask: u32, enable: bool) -> Result<(), Self::Error> {
+        match enable {
+            true => {
+                self.pull_ups |= mask;
+                self.pull_downs &= !mask;
+            }
+            false => self.pull_ups &= !mask,
+        }
+        Ok(())
+    }
+
+    fn set_input_pull(
+        &mut self,
+        mask: u32,
+        pull: crate::Pull,
+    ) -> Result<(), crate::PullError<Self::Error>> {
+        crate::common::set_input_pull_up_down(self, mask, pull)
+    }
+}
+
+impl crate::PortDriverPullDown for Driver {
+    fn set_pull_down(&mut self, mask: u32, enable: bool) -> Result<(), Self::Error> {
+        match enable {
+            true => {
+                self.pull_downs |= mask;
+                self.pull_ups &= !mask;
+            }
+            false => self.pull_downs &= !mask,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn virtual_expander() {
+        let expander = super::VirtualExpander::new();
+        let [p0, p1, p2]: [_; 3] = expander.pins();
+
+        let p0 = p0.into_pull_up().unwrap();
+        assert!(p0.is_high().unwrap());
+        expander.drive(0, 0b001);
+        assert!(p0.is_low().unwrap());
+        expander.release(0b001);
+        assert!(p0.is_high().unwrap());
+
+        let mut p1 = p1.into_output_high().unwrap();
+        assert_eq!(expander.output_pins(), 0b010);
+        assert_eq!(expander.outputs(), 0b010);
+        p1.toggle().unwrap();
+        assert_eq!(expander.outputs(), 0b000);
+
+        let p2 = p2.into_inverted().unwrap();
+        assert!(p2.is_high().unwrap());
+        assert_eq!(expander.inverted(), 0b100);
+        assert_eq!(expander.pull_ups(), 0b001);
+    }
+}
@@ -0,0 +1,161 @@
+use embedded_hal::delay::DelayNs;
+
+/// Blocking waits for input pins.
+///
+/// Without an interrupt line, the only way to wait for a pin is to poll it.  These methods read
+/// the pin every `poll_interval_us` microseconds, sleeping with `delay` in between, until the
+/// condition is met or `timeout_us` microseconds have passed.  They return `Ok(true)` when the
+/// condition was met and `Ok(false)` on timeout.
+///
+/// Edges are detected relative to the first sample: a rising edge is only reported after the pin
+/// was seen LOW and then HIGH.  Pulses which are shorter than the poll interval can be missed.
+///
+/// ## Example
+/// ```no_run
+/// # let i2c = embedded_hal_mock::eh1::i2c::Mock::new(&[]);
+/// # let mut delay = embedded_hal_mock::eh1::delay::NoopDelay::new();
+/// # let mut pcf = port_expander::Pcf8574::new(i2c, false, false, false);
+/// # let p = pcf.split();
+/// let mut button = p.p0;
+/// // Poll every 10ms for at most 5s
+/// if button.wait_for_falling_edge_blocking(&mut delay, 10_000, 5_000_000).unwrap() {
+///     // button was pressed
+/// }
+/// ```
+impl<'a, MODE: crate::mode::HasInput, MUTEX, PD> crate::Pin<'a, MODE, MUTEX>
+where
+    PD: crate::PortDriver,
+    MUTEX: crate::PortMutex<Port = PD>,
+{
+    fn wait_blocking<D: DelayNs, F: FnMut(bool) -> bool>(
+        &mut self,
+        delay: &mut D,
+        poll_interval_us: u32,
+        timeout_us: u32,
+        mut done: F,
+    ) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        let poll_interval_us = poll_interval_us.max(1);
+        let mut elapsed_us = 0u32;
+        loop {
+            if done(self.is_high()?) {
+                return Ok(true);
+            }
+            if elapsed_us >= timeout_us {
+                return Ok(false);
+            }
+            delay.delay_us(poll_interval_us);
+            elapsed_us = elapsed_us.saturating_add(poll_interval_us);
+        }
+    }
+
+    fn wait_for_edge_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        poll_interval_us: u32,
+        timeout_us: u32,
+        rising: bool,
+        falling: bool,
+    ) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        let mut last = None;
+        self.wait_blocking(delay, poll_interval_us, timeout_us, |high| {
+            let edge = match last {
+                Some(false) if high => rising,
+                Some(true) if !high => falling,
+                _ => false,
+            };
+            last = Some(high);
+            edge
+        })
+    }
+
+    /// Wait until the pin is HIGH.
+    pub fn wait_for_high_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        poll_interval_us: u32,
+        timeout_us: u32,
+    ) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        self.wait_blocking(delay, poll_interval_us, timeout_us, |high| high)
+    }
+
+    /// Wait until the pin is LOW.
+    pub fn wait_for_low_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        poll_interval_us: u32,
+        timeout_us: u32,
+    ) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        self.wait_blocking(delay, poll_interval_us, timeout_us, |high| !high)
+    }
+
+    /// Wait for the pin to go from LOW to HIGH.
+    pub fn wait_for_rising_edge_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        poll_interval_us: u32,
+        timeout_us: u32,
+    ) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        self.wait_for_edge_blocking(delay, poll_interval_us, timeout_us, true, false)
+    }
+
+    /// Wait for the pin to go from HIGH to LOW.
+    pub fn wait_for_falling_edge_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        poll_interval_us: u32,
+        timeout_us: u32,
+    ) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        self.wait_for_edge_blocking(delay, poll_interval_us, timeout_us, false, true)
+    }
+
+    /// Wait for the pin to change its state.
+    pub fn wait_for_any_edge_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        poll_interval_us: u32,
+        timeout_us: u32,
+    ) -> Result<bool, crate::pin::PinError<PD::Error>> {
+        self.wait_for_edge_blocking(delay, poll_interval_us, timeout_us, true, true)
+    }
+}
+
+#[cfg(all(test, feature = "pcf8574"))]
+mod tests {
+    use embedded_hal_mock::eh1::delay as mock_delay;
+    use embedded_hal_mock::eh1::i2c as mock_i2c;
+
+    #[test]
+    fn pcf8574_wait_for_rising_edge() {
+        let expectations = [
+            // HIGH at the start is not an edge
+            mock_i2c::Transaction::read(0x20, vec![0b00000001]),
+            mock_i2c::Transaction::read(0x20, vec![0b00000000]),
+            mock_i2c::Transaction::read(0x20, vec![0b00000001]),
+            // timeout
+            mock_i2c::Transaction::read(0x20, vec![0b00000001]),
+            mock_i2c::Transaction::read(0x20, vec![0b00000001]),
+        ];
+        let mut bus = mock_i2c::Mock::new(&expectations);
+        let delays = [
+            mock_delay::Transaction::blocking_delay_us(100),
+            mock_delay::Transaction::blocking_delay_us(100),
+            mock_delay::Transaction::blocking_delay_us(100),
+        ];
+        let mut delay = mock_delay::CheckedDelay::new(&delays);
+
+        let mut pcf = crate::Pcf8574::new(bus.clone(), false, false, false);
+        let mut pcf_pins = pcf.split();
+
+        assert!(pcf_pins
+            .p0
+            .wait_for_rising_edge_blocking(&mut delay, 100, 1000)
+            .unwrap());
+        assert!(!pcf_pins
+            .p0
+            .wait_for_falling_edge_blocking(&mut delay, 100, 100)
+            .unwrap());
+
+        bus.done();
+        delay.done();
+    }
+}